@@ -0,0 +1,97 @@
+//! Example comparing sequential and parallel collider merging and outline
+//! extraction on a large synthetic map.
+//!
+//! Run with: `cargo run --release --example collider_merge`
+//!
+//! Both merges must cover exactly the filled cells, each once, and both outline
+//! extractions must agree; the example panics otherwise, then prints how long
+//! each took.
+
+use std::time::{Duration, Instant};
+
+use bevy::{
+    math::URect,
+    prelude::*,
+    tasks::{ComputeTaskPool, TaskPool},
+};
+use bevy_spritefusion::prelude::*;
+
+const SIZE: UVec2 = UVec2::new(2048, 2048);
+const RUNS: u32 = 5;
+
+fn main() {
+    ComputeTaskPool::get_or_init(TaskPool::default);
+
+    // Dense walls with rooms carved out and some noise, like a dungeon collision layer
+    let filled = |cell: UVec2| {
+        let room = cell.x % 24 > 2 && cell.y % 16 > 2;
+        let noise =
+            (cell.x.wrapping_mul(73_856_093) ^ cell.y.wrapping_mul(19_349_663)).is_multiple_of(7);
+        !room || noise
+    };
+
+    let (sequential, sequential_time) = time(|| merge_tile_rects(SIZE, filled));
+    let (parallel, parallel_time) = time(|| par_merge_tile_rects(SIZE, filled));
+    check_cover(&sequential, filled);
+    check_cover(&parallel, filled);
+    let (outlines, outlines_time) = time(|| tile_outlines(SIZE, filled));
+    let (par_outlines, par_outlines_time) = time(|| par_tile_outlines(SIZE, filled));
+    assert_eq!(outlines, par_outlines, "parallel outlines differ");
+
+    println!(
+        "{}x{} grid, {} threads",
+        SIZE.x,
+        SIZE.y,
+        ComputeTaskPool::get().thread_num()
+    );
+    println!(
+        "sequential: {:>6} rects in {sequential_time:?}",
+        sequential.len()
+    );
+    println!(
+        "parallel:   {:>6} rects in {parallel_time:?}",
+        parallel.len()
+    );
+    println!(
+        "speedup: {:.2}x",
+        sequential_time.as_secs_f64() / parallel_time.as_secs_f64()
+    );
+    println!(
+        "outlines: {} in {outlines_time:?} sequential, {par_outlines_time:?} parallel",
+        outlines.len()
+    );
+}
+
+/// Run `merge` several times, returning its result and its fastest time.
+fn time<T: Default>(merge: impl Fn() -> T) -> (T, Duration) {
+    let mut best = Duration::MAX;
+    let mut rects = T::default();
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        rects = merge();
+        best = best.min(start.elapsed());
+    }
+    (rects, best)
+}
+
+/// Panic unless `rects` cover every filled cell once and no empty cell.
+fn check_cover(rects: &[URect], filled: impl Fn(UVec2) -> bool) {
+    let mut covered = vec![0u8; (SIZE.x * SIZE.y) as usize];
+    for rect in rects {
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                covered[(y * SIZE.x + x) as usize] += 1;
+            }
+        }
+    }
+    for y in 0..SIZE.y {
+        for x in 0..SIZE.x {
+            let expected = u8::from(filled(UVec2::new(x, y)));
+            assert_eq!(
+                covered[(y * SIZE.x + x) as usize],
+                expected,
+                "cell ({x}, {y}) covered wrongly"
+            );
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Example showing how to ship a Sprite Fusion map inside the executable.
+//!
+//! Run with: `cargo run --example embedded`
+//!
+//! The map and its spritesheet are embedded at compile time from the `assets/` folder,
+//! so the built binary doesn't need them on disk.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    image::{CompressedImageFormats, ImageSampler, ImageType},
+    prelude::*,
+};
+use bevy_spritefusion::prelude::*;
+
+const MAP: &[u8] = include_bytes!("../assets/map.json");
+const SPRITESHEET: &[u8] = include_bytes!("../assets/spritesheet.png");
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(SpriteFusionPlugin)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut maps: ResMut<Assets<SpriteFusionMap>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    commands.spawn((
+        Camera2d,
+        Transform::from_xyz(304.0, 112.0, 0.0),
+        Projection::Orthographic(OrthographicProjection {
+            scale: 0.5,
+            ..OrthographicProjection::default_2d()
+        }),
+    ));
+
+    let map = SpriteFusionMap::from_json_bytes(MAP).expect("embedded map should be valid");
+    let spritesheet = Image::from_buffer(
+        SPRITESHEET,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::nearest(),
+        RenderAssetUsages::default(),
+    )
+    .expect("embedded spritesheet should be a valid PNG");
+
+    // From here on, spawning is the same as for maps loaded from files
+    commands.spawn(SpriteFusionBundle {
+        map: SpriteFusionMapHandle(maps.add(map)),
+        tileset: SpriteFusionTilesetHandle(images.add(spritesheet)),
+        ..default()
+    });
+}
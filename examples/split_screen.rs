@@ -0,0 +1,78 @@
+//! Split-screen example: two cameras, each rendering its own instance of a Sprite Fusion map.
+//!
+//! Run with: `cargo run --example split_screen`
+//!
+//! Make sure you have `map.json` and `spritesheet.png` in the `assets/` folder.
+
+use bevy::{
+    camera::{visibility::RenderLayers, Viewport},
+    prelude::*,
+    window::WindowResized,
+};
+use bevy_spritefusion::prelude::*;
+
+/// Which half of the window a camera renders to (0 = left, 1 = right).
+#[derive(Component)]
+struct CameraPosition(u32);
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
+        .add_plugins(SpriteFusionPlugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, set_camera_viewports)
+        .run();
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    for player in 0..2 {
+        // Each player gets its own render layer
+        let render_layers = RenderLayers::layer(player as usize + 1);
+
+        commands.spawn((
+            Camera2d,
+            Camera {
+                order: player as isize,
+                ..default()
+            },
+            Transform::from_xyz(304.0, 112.0, 0.0),
+            Projection::Orthographic(OrthographicProjection {
+                scale: 0.5,
+                ..OrthographicProjection::default_2d()
+            }),
+            CameraPosition(player),
+            render_layers.clone(),
+        ));
+
+        // The map is only visible to the camera sharing its render layers
+        commands.spawn((
+            SpriteFusionBundle {
+                map: SpriteFusionMapHandle(asset_server.load("map.json")),
+                tileset: SpriteFusionTilesetHandle(asset_server.load("spritesheet.png")),
+                ..default()
+            },
+            render_layers,
+        ));
+    }
+}
+
+fn set_camera_viewports(
+    windows: Query<&Window>,
+    mut resize_events: MessageReader<WindowResized>,
+    mut cameras: Query<(&CameraPosition, &mut Camera)>,
+) {
+    for resize_event in resize_events.read() {
+        let Ok(window) = windows.get(resize_event.window) else {
+            continue;
+        };
+        let size = window.physical_size() / UVec2::new(2, 1);
+
+        for (position, mut camera) in cameras.iter_mut() {
+            camera.viewport = Some(Viewport {
+                physical_position: UVec2::new(position.0 * size.x, 0),
+                physical_size: size,
+                ..default()
+            });
+        }
+    }
+}
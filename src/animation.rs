@@ -0,0 +1,139 @@
+//! Animated tiles driven by attributes.
+//!
+//! Tiles with an `"animFrames"` attribute, either a list like `[4, 5, 6, 7]` or a
+//! string like `"4,5,6,7"`, get a [`TileAnimation`] component and cycle through
+//! those spritesheet indices, at `"animFps"` frames per second. Water, torches
+//! and conveyor belts animate without custom code.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::{AttrKey, StoredTileAttributes},
+    plugin::SpriteFusionSettings,
+    types::{MapPaused, SpriteFusionLayerMarker, TileAttributes},
+};
+
+/// Attribute listing the frames of an animated tile.
+pub const ANIM_FRAMES_ATTRIBUTE: &str = "animFrames";
+/// Attribute giving the frame rate of an animated tile.
+pub const ANIM_FPS_ATTRIBUTE: &str = "animFps";
+
+/// Frame rate of animated tiles without an `"animFps"` attribute.
+pub const DEFAULT_ANIM_FPS: f32 = 8.0;
+
+/// Plugin that animates tiles with animation attributes.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionAnimationPlugin;
+
+impl Plugin for SpriteFusionAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                (tag_animated_tiles, tag_stored_animated_tiles),
+                animate_tiles,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Animation of a tile, cycling its [`TileTextureIndex`].
+///
+/// Tiles with the same frames and frame rate play in sync, so a lake of water
+/// tiles ripples as one.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct TileAnimation {
+    /// Spritesheet indices of the frames, in order.
+    pub frames: Vec<u32>,
+    /// Frames per second.
+    pub fps: f32,
+}
+
+impl TileAnimation {
+    fn from_attributes(attrs: &TileAttributes, frames_key: &str, fps_key: &str) -> Option<Self> {
+        let frames: Vec<u32> = match attrs.0.get(frames_key)? {
+            serde_json::Value::Array(frames) => frames
+                .iter()
+                .filter_map(|frame| u32::try_from(frame.as_u64()?).ok())
+                .collect(),
+            serde_json::Value::String(frames) => frames
+                .split(',')
+                .filter_map(|frame| frame.trim().parse().ok())
+                .collect(),
+            _ => return None,
+        };
+        if frames.is_empty() {
+            return None;
+        }
+        let fps = attrs
+            .get_f64(fps_key)
+            .map_or(DEFAULT_ANIM_FPS, |fps| fps as f32);
+        Some(Self { frames, fps })
+    }
+
+    /// Get the frame shown `elapsed` seconds into the animation.
+    pub fn frame_at(&self, elapsed: f32) -> u32 {
+        let index = (elapsed * self.fps.max(0.0)) as usize % self.frames.len().max(1);
+        self.frames.get(index).copied().unwrap_or_default()
+    }
+}
+
+/// System that keeps [`TileAnimation`] in sync with the attribute components of tiles.
+fn tag_animated_tiles(
+    mut commands: Commands,
+    tiles: Query<(Entity, &TileAttributes), Changed<TileAttributes>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let frames_key = settings.reserved_attribute(ANIM_FRAMES_ATTRIBUTE);
+    let fps_key = settings.reserved_attribute(ANIM_FPS_ATTRIBUTE);
+    for (tile, attrs) in tiles.iter() {
+        match TileAnimation::from_attributes(attrs, &frames_key, &fps_key) {
+            Some(animation) => commands.entity(tile).insert(animation),
+            None => commands.entity(tile).remove::<TileAnimation>(),
+        };
+    }
+}
+
+/// System that inserts [`TileAnimation`] on tiles using an attribute store.
+fn tag_stored_animated_tiles(
+    mut commands: Commands,
+    tiles: Query<Entity, Added<AttrKey>>,
+    attributes: StoredTileAttributes,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let frames_key = settings.reserved_attribute(ANIM_FRAMES_ATTRIBUTE);
+    let fps_key = settings.reserved_attribute(ANIM_FPS_ATTRIBUTE);
+    for tile in tiles.iter() {
+        if let Some(animation) = attributes
+            .get(tile)
+            .and_then(|attrs| TileAnimation::from_attributes(attrs, &frames_key, &fps_key))
+        {
+            commands.entity(tile).insert(animation);
+        }
+    }
+}
+
+/// System that shows the current frame of animated tiles.
+fn animate_tiles(
+    time: Res<Time>,
+    mut tiles: Query<(&TileAnimation, &mut TileTextureIndex, &TilemapId)>,
+    layers: Query<&SpriteFusionLayerMarker>,
+    paused_maps: Query<(), With<MapPaused>>,
+) {
+    let elapsed = time.elapsed_secs();
+    for (animation, mut texture, tilemap_id) in tiles.iter_mut() {
+        let paused = layers
+            .get(tilemap_id.0)
+            .is_ok_and(|layer| paused_maps.contains(layer.map));
+        if paused {
+            continue;
+        }
+        let frame = animation.frame_at(elapsed);
+        if texture.0 != frame {
+            texture.0 = frame;
+        }
+    }
+}
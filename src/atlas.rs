@@ -0,0 +1,141 @@
+//! Packing several tilesets into a single atlas.
+//!
+//! Layers authored in different Sprite Fusion projects each index their own
+//! spritesheet. Packing those spritesheets into one atlas keeps a single
+//! [`TilemapTexture`](bevy_ecs_tilemap::prelude::TilemapTexture) per layer, and the
+//! returned offsets remap tile indices with [`TextureIndexOffsets`].
+
+use bevy::{
+    asset::RenderAssetUsages,
+    image::TextureFormatPixelInfo,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension},
+};
+use thiserror::Error;
+
+use crate::plugin::TextureIndexOffsets;
+
+/// A tileset atlas built by [`pack_tilesets`].
+#[derive(Debug, Clone)]
+pub struct PackedAtlas {
+    /// The packed atlas image.
+    pub image: Image,
+    /// Index of the first tile of each input tileset in the atlas, in input order.
+    pub offsets: Vec<u32>,
+}
+
+impl PackedAtlas {
+    /// Build the texture index offsets of a map, given the input tileset used by
+    /// each of its layers.
+    ///
+    /// Layers that aren't listed use the first tileset.
+    pub fn layer_offsets<'a>(
+        &self,
+        layers: impl IntoIterator<Item = (&'a str, usize)>,
+    ) -> TextureIndexOffsets {
+        layers.into_iter().fold(
+            TextureIndexOffsets::default(),
+            |offsets, (name, tileset)| {
+                offsets.with_layer(name, self.offsets.get(tileset).copied().unwrap_or(0))
+            },
+        )
+    }
+}
+
+/// Errors that can occur when packing tilesets.
+#[derive(Debug, Error)]
+pub enum AtlasPackError {
+    #[error("No tileset to pack")]
+    Empty,
+    #[error("Tileset {0} has no pixel data in the main world")]
+    MissingData(usize),
+    #[error("Tileset {0} has a different texture format than the first one")]
+    FormatMismatch(usize),
+    #[error("Tileset {0} uses a compressed texture format")]
+    UnsupportedFormat(usize),
+}
+
+/// Pack tilesets into one atlas.
+///
+/// Tiles of each tileset keep their row-major order and are appended after the
+/// tiles of the previous tilesets, so a tile index `i` of tileset `n` becomes
+/// `offsets[n] + i` in the atlas. The atlas is as wide as the widest tileset.
+/// Partial tiles at the right or bottom edge of a tileset are dropped.
+///
+/// All tilesets must share the same uncompressed texture format and keep their
+/// data in the main world (the default for loaded images).
+///
+/// ```rust,ignore
+/// let atlas = pack_tilesets(&[terrain, decoration], 16)?;
+/// let offsets = atlas.layer_offsets([("Ground", 0), ("Props", 1)]);
+/// commands.spawn((
+///     SpriteFusionBundle {
+///         tileset: SpriteFusionTilesetHandle(images.add(atlas.image)),
+///         ..default()
+///     },
+///     offsets,
+/// ));
+/// ```
+pub fn pack_tilesets(tilesets: &[&Image], tile_size: u32) -> Result<PackedAtlas, AtlasPackError> {
+    let first = tilesets.first().ok_or(AtlasPackError::Empty)?;
+    let format = first.texture_descriptor.format;
+    let pixel_size = format
+        .pixel_size()
+        .map_err(|_| AtlasPackError::UnsupportedFormat(0))?;
+
+    let grid = |image: &Image| (image.width() / tile_size, image.height() / tile_size);
+    let columns = tilesets
+        .iter()
+        .map(|image| grid(image).0)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let mut offsets = Vec::with_capacity(tilesets.len());
+    let mut tile_count = 0;
+    for image in tilesets {
+        offsets.push(tile_count);
+        let (c, r) = grid(image);
+        tile_count += c * r;
+    }
+    let rows = tile_count.div_ceil(columns).max(1);
+
+    let atlas_width = columns * tile_size;
+    let atlas_height = rows * tile_size;
+    let row_bytes = tile_size as usize * pixel_size;
+    let mut data = vec![0; atlas_width as usize * atlas_height as usize * pixel_size];
+
+    for (n, image) in tilesets.iter().enumerate() {
+        if image.texture_descriptor.format != format {
+            return Err(AtlasPackError::FormatMismatch(n));
+        }
+        let source = image.data.as_ref().ok_or(AtlasPackError::MissingData(n))?;
+        let (c, r) = grid(image);
+        for i in 0..c * r {
+            let (sx, sy) = ((i % c) * tile_size, (i / c) * tile_size);
+            let index = offsets[n] + i;
+            let (dx, dy) = ((index % columns) * tile_size, (index / columns) * tile_size);
+            // Copy the tile one pixel row at a time
+            for row in 0..tile_size {
+                let src = ((sy + row) as usize * image.width() as usize + sx as usize) * pixel_size;
+                let dst = ((dy + row) as usize * atlas_width as usize + dx as usize) * pixel_size;
+                data[dst..dst + row_bytes].copy_from_slice(&source[src..src + row_bytes]);
+            }
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: atlas_width,
+            height: atlas_height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = first.sampler.clone();
+
+    Ok(PackedAtlas { image, offsets })
+}
@@ -0,0 +1,160 @@
+//! Entities attached to tiles.
+//!
+//! [`SpriteFusionCommandsExt::attach_to_tile`](crate::commands::SpriteFusionCommandsExt::attach_to_tile)
+//! spawns an entity that follows a tile: it's placed on the tile, moved when the
+//! tile moves, and despawned with the tile, whether the tile is removed at
+//! runtime, streamed out or its map despawned. Sprites, labels and effects can
+//! hang off tiles without tracking their positions.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    geometry::SpriteFusionMapGeometry,
+    plugin::SpriteFusionSystems,
+    types::{SpriteFusionLayerMarker, TileSpawnIndex},
+};
+
+/// Z of attached entities above the layer of their tile, halfway to the next layer
+/// with the default layer spacing.
+pub const DEFAULT_ATTACHMENT_Z_OFFSET: f32 = 0.05;
+
+pub(crate) struct SpriteFusionAttachmentsPlugin;
+
+impl Plugin for SpriteFusionAttachmentsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, follow_tiles.in_set(SpriteFusionSystems::Place));
+    }
+}
+
+/// A tile to attach an entity to: a tile entity, or a position on a layer of a map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TileTarget {
+    /// A tile entity.
+    Entity(Entity),
+    /// The tile at a position of a layer, found by name.
+    Position {
+        /// The map entity.
+        map: Entity,
+        /// Name of the layer.
+        layer: String,
+        /// Position of the tile.
+        tile_pos: TilePos,
+    },
+}
+
+impl From<Entity> for TileTarget {
+    fn from(tile: Entity) -> Self {
+        Self::Entity(tile)
+    }
+}
+
+impl<S: Into<String>> From<(Entity, S, TilePos)> for TileTarget {
+    fn from((map, layer, tile_pos): (Entity, S, TilePos)) -> Self {
+        Self::Position {
+            map,
+            layer: layer.into(),
+            tile_pos,
+        }
+    }
+}
+
+/// Component of entities attached to a tile, keeping them on the tile.
+///
+/// The entity is a child of the map entity. Its X and Y follow the tile center,
+/// and its Z is the Z of the tile's layer plus `z_offset`. The tile is told
+/// apart from the tile entity by its [`TileSpawnIndex`], so the entity is
+/// despawned when a pooled tile entity is reused for another tile.
+#[derive(Component, Debug, Clone)]
+#[require(Transform, Visibility)]
+pub struct AttachedToTile {
+    /// The tile entity.
+    pub tile: Entity,
+    /// Z above the layer of the tile.
+    pub z_offset: f32,
+    map: Entity,
+    spawn_index: Option<TileSpawnIndex>,
+}
+
+impl AttachedToTile {
+    /// Get the map entity of the tile.
+    pub fn map(&self) -> Entity {
+        self.map
+    }
+}
+
+/// Find a tile, the map it belongs to and its spawn index.
+fn resolve(world: &World, target: &TileTarget) -> Option<(Entity, Entity, Option<TileSpawnIndex>)> {
+    let tile = match target {
+        TileTarget::Entity(tile) => *tile,
+        TileTarget::Position {
+            map,
+            layer,
+            tile_pos,
+        } => world
+            .get::<Children>(*map)?
+            .iter()
+            .filter(|child| {
+                world
+                    .get::<SpriteFusionLayerMarker>(*child)
+                    .is_some_and(|marker| marker.name == *layer)
+            })
+            .find_map(|child| world.get::<TileStorage>(child)?.checked_get(tile_pos))?,
+    };
+    let tilemap_id = world.get::<TilemapId>(tile)?;
+    let layer = world.get::<SpriteFusionLayerMarker>(tilemap_id.0)?;
+    Some((tile, layer.map, world.get::<TileSpawnIndex>(tile).copied()))
+}
+
+/// Attach `entity` to a tile, or despawn it if the tile doesn't exist.
+pub(crate) fn attach(world: &mut World, entity: Entity, target: &TileTarget) {
+    let Some((tile, map, spawn_index)) = resolve(world, target) else {
+        warn!("Can't attach {entity} to {target:?}: no such tile");
+        if let Ok(entity) = world.get_entity_mut(entity) {
+            entity.despawn();
+        }
+        return;
+    };
+    if let Ok(mut entity) = world.get_entity_mut(entity) {
+        entity.insert((
+            AttachedToTile {
+                tile,
+                z_offset: DEFAULT_ATTACHMENT_Z_OFFSET,
+                map,
+                spawn_index,
+            },
+            ChildOf(map),
+        ));
+    }
+}
+
+/// System that moves attached entities onto their tile, and despawns the ones
+/// whose tile is gone.
+fn follow_tiles(
+    mut commands: Commands,
+    mut attached: Query<(Entity, Ref<AttachedToTile>, &mut Transform)>,
+    tiles: Query<(Ref<TilePos>, &TilemapId, Option<&TileSpawnIndex>)>,
+    layers: Query<Ref<SpriteFusionLayerMarker>>,
+    maps: Query<&SpriteFusionMapGeometry>,
+) {
+    for (entity, attachment, mut transform) in attached.iter_mut() {
+        let tile = tiles
+            .get(attachment.tile)
+            .ok()
+            .filter(|(_, _, spawn_index)| spawn_index.copied() == attachment.spawn_index)
+            .and_then(|(pos, tilemap_id, _)| Some((pos, layers.get(tilemap_id.0).ok()?)));
+        // Pooled tile entities may be reused by another tile, or another map
+        let Some((pos, layer)) = tile.filter(|(_, layer)| layer.map == attachment.map) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        if !attachment.is_changed() && !pos.is_changed() && !layer.is_changed() {
+            continue;
+        }
+        let Ok(geometry) = maps.get(attachment.map) else {
+            continue;
+        };
+        let z = geometry.layer_z(&layer.name).unwrap_or_default() + attachment.z_offset;
+        transform.translation = geometry.tile_to_local(*pos).extend(z);
+    }
+}
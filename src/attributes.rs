@@ -0,0 +1,669 @@
+//! Attribute components of spawned tiles.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, VecDeque},
+    marker::PhantomData,
+    mem::size_of,
+    sync::Arc,
+};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    colliders::tile_collides,
+    core::attributes_footprint,
+    editing::remap_tile_index,
+    geometry::SpriteFusionMapGeometry,
+    pathfinding::{MoveCosts, MOVE_COST_ATTRIBUTE},
+    plugin::SpriteFusionSettings,
+    streaming::StreamedChunks,
+    types::{Collider, SpriteFusionLayerMarker, SpriteFusionMapMarker, TileAttributes},
+};
+
+/// Get the key a built-in subsystem reads for one of its attributes with the
+/// given [`SpriteFusionSettings::attribute_prefix`], e.g. `"sf:animFrames"` for
+/// [`ANIM_FRAMES_ATTRIBUTE`](crate::animation::ANIM_FRAMES_ATTRIBUTE) with the
+/// `"sf:"` prefix.
+///
+/// Without a prefix, this is `name` itself.
+pub fn reserved_attribute<'a>(prefix: &str, name: &'a str) -> Cow<'a, str> {
+    if prefix.is_empty() {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("{prefix}{name}"))
+    }
+}
+
+/// Localization hook applied to string attribute values at spawn.
+///
+/// Insert it as a resource to translate sign or NPC text placed in the editor.
+/// The function gets each string value and returns its translation, or `None` to
+/// keep the raw value. Values are translated once, when tiles spawn; to switch
+/// locales at runtime, keep the raw keys and use
+/// [`TileAttributes::localized`](crate::types::TileAttributes::localized) instead.
+///
+/// ```rust,ignore
+/// app.insert_resource(AttributeLocalizer::new(|key| translations.get(key).cloned()));
+/// ```
+#[derive(Resource, Clone)]
+pub struct AttributeLocalizer(Arc<LocalizeFn>);
+
+type LocalizeFn = dyn Fn(&str) -> Option<String> + Send + Sync;
+
+impl AttributeLocalizer {
+    /// Create a localizer from a translation function.
+    pub fn new(localize: impl Fn(&str) -> Option<String> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(localize))
+    }
+
+    /// Translate a value.
+    pub fn localize(&self, value: &str) -> Option<String> {
+        (self.0)(value)
+    }
+}
+
+/// Clone attributes, passing string values through the localizer if any.
+pub(crate) fn clone_attributes(
+    attrs: &HashMap<String, serde_json::Value>,
+    localizer: Option<&AttributeLocalizer>,
+) -> HashMap<String, serde_json::Value> {
+    let Some(localizer) = localizer else {
+        return attrs.clone();
+    };
+    attrs
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(raw) => localizer
+                    .localize(raw)
+                    .map(serde_json::Value::String)
+                    .unwrap_or_else(|| value.clone()),
+                _ => value.clone(),
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+/// How spawned tiles hold their attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AttributeStorage {
+    /// Each tile gets its own [`TileAttributes`] component.
+    #[default]
+    Components,
+    /// Each map gets an [`AttributeStore`] holding every distinct attribute set
+    /// once, and tiles only get an [`AttrKey`] into it.
+    ///
+    /// Much lighter on attribute-heavy maps. Read attributes through
+    /// [`StoredTileAttributes`].
+    Store,
+}
+
+/// Key of a tile's attributes in the [`AttributeStore`] of its map.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AttrKey(pub u32);
+
+/// Attributes of all tiles of a map, stored once per distinct set.
+///
+/// Attached to map entities when
+/// [`SpriteFusionSettings::attribute_storage`](crate::plugin::SpriteFusionSettings::attribute_storage)
+/// is [`AttributeStorage::Store`].
+#[derive(Component, Debug, Default)]
+pub struct AttributeStore {
+    attributes: Vec<TileAttributes>,
+    keys: HashMap<String, AttrKey>,
+}
+
+impl AttributeStore {
+    /// Get the attributes for a key.
+    pub fn get(&self, key: AttrKey) -> Option<&TileAttributes> {
+        self.attributes.get(key.0 as usize)
+    }
+
+    /// Number of distinct attribute sets.
+    pub fn len(&self) -> usize {
+        self.attributes.len()
+    }
+
+    /// Check if the store has no attributes.
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty()
+    }
+
+    /// Estimate the memory used by the store, in bytes.
+    pub fn memory_footprint(&self) -> usize {
+        self.attributes.capacity() * size_of::<TileAttributes>()
+            + self
+                .attributes
+                .iter()
+                .map(|attrs| attributes_footprint(&attrs.0))
+                .sum::<usize>()
+            + self.keys.capacity() * (size_of::<String>() + size_of::<AttrKey>())
+            + self.keys.keys().map(String::capacity).sum::<usize>()
+    }
+
+    /// Create a store from attribute sets, keyed by their index.
+    pub(crate) fn from_sets(sets: Vec<HashMap<String, serde_json::Value>>) -> Self {
+        let mut store = Self::default();
+        for attrs in sets {
+            store.keys.insert(
+                canonical_json(&attrs),
+                AttrKey(store.attributes.len() as u32),
+            );
+            store.attributes.push(TileAttributes(attrs));
+        }
+        store
+    }
+
+    /// Get the attribute sets, indexed by their key.
+    pub(crate) fn into_sets(self) -> Vec<HashMap<String, serde_json::Value>> {
+        self.attributes.into_iter().map(|attrs| attrs.0).collect()
+    }
+
+    /// Add an attribute set, reusing the key of an identical set already stored.
+    pub(crate) fn insert(
+        &mut self,
+        attrs: &HashMap<String, serde_json::Value>,
+        localizer: Option<&AttributeLocalizer>,
+    ) -> AttrKey {
+        *self.keys.entry(canonical_json(attrs)).or_insert_with(|| {
+            self.attributes
+                .push(TileAttributes(clone_attributes(attrs, localizer)));
+            AttrKey(self.attributes.len() as u32 - 1)
+        })
+    }
+}
+
+/// Serialize attributes so that identical sets give the same string.
+fn canonical_json(attrs: &HashMap<String, serde_json::Value>) -> String {
+    // Sorting the keys gives identical sets the same serialization
+    serde_json::to_string(&attrs.iter().collect::<BTreeMap<_, _>>()).unwrap_or_default()
+}
+
+/// System parameter reading tile attributes from the [`AttributeStore`] of their map.
+///
+/// ```rust,ignore
+/// fn find_collectibles(tiles: Query<Entity, With<AttrKey>>, attributes: StoredTileAttributes) {
+///     for tile in tiles.iter() {
+///         if attributes.get(tile).and_then(|a| a.get_bool("isCollectible")) == Some(true) {
+///             // ...
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct StoredTileAttributes<'w, 's> {
+    tiles: Query<'w, 's, (&'static AttrKey, &'static TilemapId)>,
+    layers: Query<'w, 's, &'static SpriteFusionLayerMarker>,
+    stores: Query<'w, 's, &'static AttributeStore>,
+}
+
+impl StoredTileAttributes<'_, '_> {
+    /// Get the attributes of a tile.
+    pub fn get(&self, tile: Entity) -> Option<&TileAttributes> {
+        let (key, tilemap_id) = self.tiles.get(tile).ok()?;
+        let layer = self.layers.get(tilemap_id.0).ok()?;
+        self.stores.get(layer.map).ok()?.get(*key)
+    }
+}
+
+/// Tiles still waiting for their [`TileAttributes`] component.
+///
+/// Only used when
+/// [`SpriteFusionSettings::attribute_budget`](crate::plugin::SpriteFusionSettings::attribute_budget)
+/// is set. Attributes are cloned from the map data when they are inserted, not
+/// while spawning.
+///
+/// ```rust,ignore
+/// fn level_ready(pending: Res<PendingTileAttributes>) -> bool {
+///     pending.is_empty()
+/// }
+/// ```
+#[derive(Resource, Debug, Default)]
+pub struct PendingTileAttributes {
+    queue: VecDeque<PendingAttributes>,
+}
+
+#[derive(Debug)]
+struct PendingAttributes {
+    tile: Entity,
+    map: Entity,
+    layer: usize,
+    index: usize,
+}
+
+impl PendingTileAttributes {
+    /// Number of tiles still waiting for their attributes.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Check if all attributes have been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Queue the attributes of the tile at `index` in layer `layer` of `map`.
+    pub(crate) fn push(&mut self, tile: Entity, map: Entity, layer: usize, index: usize) {
+        self.queue.push_back(PendingAttributes {
+            tile,
+            map,
+            layer,
+            index,
+        });
+    }
+
+    /// Drop the queued attributes of a map's tiles.
+    pub(crate) fn remove_map(&mut self, map: Entity) {
+        self.queue.retain(|entry| entry.map != map);
+    }
+
+    /// Drop the queued attributes of the tiles at the sorted indices `removed`
+    /// of a layer, and point the others at the indices they moved to.
+    pub(crate) fn remove_tiles(&mut self, map: Entity, layer: usize, removed: &[usize]) {
+        self.queue.retain_mut(|entry| {
+            if entry.map != map || entry.layer != layer {
+                return true;
+            }
+            match remap_tile_index(removed, entry.index) {
+                Some(index) => {
+                    entry.index = index;
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+}
+
+/// System that inserts queued attributes, up to the budget each frame.
+pub(crate) fn insert_pending_attributes(
+    mut commands: Commands,
+    settings: Res<SpriteFusionSettings>,
+    mut pending: ResMut<PendingTileAttributes>,
+    maps: Query<&SpriteFusionMapMarker>,
+    localizer: Option<Res<AttributeLocalizer>>,
+) {
+    let budget = settings.attribute_budget.unwrap_or(usize::MAX);
+    for _ in 0..budget {
+        let Some(entry) = pending.queue.pop_front() else {
+            break;
+        };
+        // The map may have been despawned in the meantime
+        let Some(attrs) = maps
+            .get(entry.map)
+            .ok()
+            .and_then(|marker| marker.map.layers.get(entry.layer))
+            .and_then(|layer| layer.tiles.get(entry.index))
+            .and_then(|tile| tile.attributes.as_ref())
+        else {
+            continue;
+        };
+        commands
+            .entity(entry.tile)
+            .try_insert(TileAttributes(clone_attributes(
+                attrs,
+                localizer.as_deref(),
+            )));
+    }
+}
+
+/// An attribute key that tiles can be marked with, see [`AttrMarkerPlugin`].
+pub trait AttributeKey: Send + Sync + 'static {
+    /// Name of the attribute in Sprite Fusion.
+    const KEY: &'static str;
+
+    /// Whether a tile with this attribute value gets the marker.
+    ///
+    /// By default, any value other than `false` and `null` does.
+    fn matches(value: &serde_json::Value) -> bool {
+        !matches!(
+            value,
+            serde_json::Value::Bool(false) | serde_json::Value::Null
+        )
+    }
+}
+
+/// Marker component inserted at spawn on tiles having the attribute `K`.
+///
+/// Allows filtering tiles with `With<AttrMarker<K>>` instead of checking
+/// attribute values every frame.
+#[derive(Component)]
+pub struct AttrMarker<K: AttributeKey>(PhantomData<K>);
+
+impl<K: AttributeKey> Default for AttrMarker<K> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+/// Plugin that inserts [`AttrMarker<K>`] on spawned tiles having the attribute `K`.
+///
+/// Add one instance per attribute key, before maps are spawned.
+///
+/// ```rust,ignore
+/// struct Collectible;
+///
+/// impl AttributeKey for Collectible {
+///     const KEY: &'static str = "isCollectible";
+/// }
+///
+/// app.add_plugins(AttrMarkerPlugin::<Collectible>::default());
+///
+/// fn collectibles(tiles: Query<&TilePos, With<AttrMarker<Collectible>>>) {
+///     // ...
+/// }
+/// ```
+pub struct AttrMarkerPlugin<K> {
+    _marker: PhantomData<fn() -> K>,
+}
+
+impl<K> Default for AttrMarkerPlugin<K> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K: AttributeKey> Plugin for AttrMarkerPlugin<K> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AttrMarkerRegistry>();
+        app.world_mut()
+            .resource_mut::<AttrMarkerRegistry>()
+            .markers
+            .push(RegisteredMarker {
+                key: K::KEY,
+                matches: K::matches,
+                insert: |tile| {
+                    tile.insert(AttrMarker::<K>::default());
+                },
+                sync: |tile, matches| {
+                    if matches {
+                        tile.insert(AttrMarker::<K>::default());
+                    } else {
+                        tile.remove::<AttrMarker<K>>();
+                    }
+                },
+            });
+    }
+}
+
+/// Attribute markers registered with [`AttrMarkerPlugin`].
+#[derive(Resource, Default)]
+pub(crate) struct AttrMarkerRegistry {
+    markers: Vec<RegisteredMarker>,
+}
+
+#[derive(Clone, Copy)]
+struct RegisteredMarker {
+    key: &'static str,
+    matches: fn(&serde_json::Value) -> bool,
+    insert: fn(&mut EntityCommands),
+    sync: fn(&mut EntityWorldMut, bool),
+}
+
+impl AttrMarkerRegistry {
+    /// Insert the markers matching a tile's attributes.
+    pub(crate) fn insert_markers(
+        &self,
+        tile: &mut EntityCommands,
+        attrs: &HashMap<String, serde_json::Value>,
+    ) {
+        for marker in &self.markers {
+            if attrs.get(marker.key).is_some_and(marker.matches) {
+                (marker.insert)(tile);
+            }
+        }
+    }
+}
+
+/// A function inserting components on tiles with an attribute, see
+/// [`AttributeComponentRegistry::register`].
+pub type AttributeComponentFn = dyn Fn(&TileAttributes, &mut EntityCommands) + Send + Sync;
+
+/// Components inserted at spawn on tiles having an attribute.
+///
+/// Each registered function runs on every spawned tile whose attribute has a
+/// value other than `false` or `null`, with all attributes of the tile, so
+/// gameplay components can be built from attributes without querying and
+/// matching them afterwards. Keys are used as is, without the
+/// [`reserved_attribute`] prefix.
+///
+/// Functions only run when tiles spawn: attributes changed at runtime with
+/// [`set_tile_attribute`] don't insert or remove components.
+///
+/// ```rust,ignore
+/// app.register_attribute_component("isCollectible", |attrs, tile| {
+///     tile.insert(Collectible {
+///         name: attrs.get_str("name").unwrap_or("unknown").to_string(),
+///         value: attrs.get_i64("value").unwrap_or(0),
+///     });
+/// });
+/// ```
+#[derive(Resource, Default, Clone)]
+pub struct AttributeComponentRegistry(Vec<(String, Arc<AttributeComponentFn>)>);
+
+impl AttributeComponentRegistry {
+    /// Register a function inserting components on tiles with the attribute `key`.
+    pub fn register(
+        &mut self,
+        key: impl Into<String>,
+        insert: impl Fn(&TileAttributes, &mut EntityCommands) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.0.push((key.into(), Arc::new(insert)));
+        self
+    }
+
+    /// Check if no functions are registered.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Run the functions matching a tile's attributes.
+    pub(crate) fn insert_components(
+        &self,
+        tile: &mut EntityCommands,
+        attrs: &HashMap<String, serde_json::Value>,
+    ) {
+        let mut matching = self.0.iter().filter(|(key, _)| {
+            attrs.get(key).is_some_and(|value| {
+                !matches!(
+                    value,
+                    serde_json::Value::Bool(false) | serde_json::Value::Null
+                )
+            })
+        });
+        let Some(first) = matching.next() else {
+            return;
+        };
+        // Only tiles with a matching attribute pay for the copy
+        let attrs = TileAttributes(attrs.clone());
+        for (_, insert) in std::iter::once(first).chain(matching) {
+            insert(&attrs, tile);
+        }
+    }
+}
+
+/// Extension methods on [`App`] registering attribute components.
+pub trait AttributeComponentAppExt {
+    /// Call `insert` on every spawned tile with the attribute `key`, see
+    /// [`AttributeComponentRegistry`].
+    fn register_attribute_component(
+        &mut self,
+        key: impl Into<String>,
+        insert: impl Fn(&TileAttributes, &mut EntityCommands) + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl AttributeComponentAppExt for App {
+    fn register_attribute_component(
+        &mut self,
+        key: impl Into<String>,
+        insert: impl Fn(&TileAttributes, &mut EntityCommands) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.init_resource::<AttributeComponentRegistry>();
+        self.world_mut()
+            .resource_mut::<AttributeComponentRegistry>()
+            .register(key, insert);
+        self
+    }
+}
+
+/// Sent when an attribute of a tile changes through
+/// [`set_attr`](crate::commands::SpriteFusionCommandsExt::set_attr) or
+/// [`set_tile_attribute`].
+#[derive(Message, Debug, Clone)]
+pub struct TileAttributeChanged {
+    /// The tile entity.
+    pub tile: Entity,
+    /// The attribute key.
+    pub key: String,
+    /// The previous value, `None` if the attribute wasn't set.
+    pub old: Option<serde_json::Value>,
+    /// The new value, `None` if the attribute was removed.
+    pub new: Option<serde_json::Value>,
+}
+
+/// Set an attribute of a spawned tile, or remove it with `None`.
+///
+/// Tiles reading their attributes from an [`AttributeStore`] get their own
+/// [`TileAttributes`] component. Markers of [`AttrMarkerPlugin`] are updated, as
+/// well as the components other plugins derive from attributes, and a
+/// [`TileAttributeChanged`] message is sent if the value changed.
+///
+/// The change is written back to the map data in [`SpriteFusionMapMarker`], so
+/// tiles of streamed chunks keep it when they spawn again and
+/// [`export_map`](crate::export::export_map) saves it. The [`MoveCosts`] of the
+/// map follow changes of its `moveCost` attribute.
+pub fn set_tile_attribute(
+    world: &mut World,
+    tile: Entity,
+    key: &str,
+    value: Option<serde_json::Value>,
+) {
+    if world.get_entity(tile).is_err() {
+        return;
+    }
+    let mut attrs = match world.get::<TileAttributes>(tile) {
+        Some(attrs) => attrs.0.clone(),
+        None => stored_attributes(world, tile).unwrap_or_default(),
+    };
+    let old = match &value {
+        Some(value) => attrs.insert(key.to_string(), value.clone()),
+        None => attrs.remove(key),
+    };
+    if old == value {
+        return;
+    }
+
+    let markers: Vec<RegisteredMarker> = world
+        .get_resource::<AttrMarkerRegistry>()
+        .map(|registry| registry.markers.clone())
+        .unwrap_or_default();
+    let mut entity = world.entity_mut(tile);
+    for marker in markers.iter().filter(|marker| marker.key == key) {
+        (marker.sync)(&mut entity, attrs.get(key).is_some_and(marker.matches));
+    }
+    entity.remove::<AttrKey>();
+    write_back_attribute(world, tile, key, value.as_ref(), &attrs);
+    world.entity_mut(tile).insert(TileAttributes(attrs));
+
+    world.write_message(TileAttributeChanged {
+        tile,
+        key: key.to_string(),
+        old,
+        new: value,
+    });
+}
+
+/// Write an attribute change of a spawned tile into the map data, the streamed
+/// chunks and the move costs of its map, and resync the tile's [`Collider`].
+fn write_back_attribute(
+    world: &mut World,
+    tile: Entity,
+    key: &str,
+    value: Option<&serde_json::Value>,
+    attrs: &HashMap<String, serde_json::Value>,
+) {
+    let (Some(&tile_pos), Some(tilemap_id)) = (
+        world.get::<TilePos>(tile),
+        world.get::<TilemapId>(tile).copied(),
+    ) else {
+        return;
+    };
+    let Some(layer) = world.get::<SpriteFusionLayerMarker>(tilemap_id.0).cloned() else {
+        return;
+    };
+    if let Some(&visible) = world.get::<TileVisible>(tile).filter(|_| layer.collider) {
+        let mut entity = world.entity_mut(tile);
+        if tile_collides(&layer, visible) {
+            entity.insert(Collider);
+        } else {
+            entity.remove::<Collider>();
+        }
+    }
+
+    let prefix = world
+        .get_resource::<SpriteFusionSettings>()
+        .map(|settings| settings.attribute_prefix.clone())
+        .unwrap_or_default();
+    let mut maps = world.query::<(
+        &mut SpriteFusionMapMarker,
+        &SpriteFusionMapGeometry,
+        Option<&mut StreamedChunks>,
+        Option<&mut MoveCosts>,
+    )>();
+    let Ok((mut marker, geometry, streamed, costs)) = maps.get_mut(world, layer.map) else {
+        return;
+    };
+    let pos = geometry.sprite_fusion_pos(tile_pos);
+    let Some(layer_data) = marker.map.layers.get_mut(layer.index) else {
+        return;
+    };
+    for data in layer_data
+        .tiles
+        .iter_mut()
+        .filter(|data| data.x == pos.x && data.y == pos.y)
+    {
+        let data_attrs = data.attributes.get_or_insert_default();
+        match value {
+            Some(value) => {
+                data_attrs.insert(key.to_string(), value.clone());
+            }
+            None => {
+                data_attrs.remove(key);
+            }
+        }
+        if data_attrs.is_empty() {
+            data.attributes = None;
+        }
+    }
+    if let Some(mut streamed) = streamed {
+        streamed.set_attributes(layer.index, tile_pos, attrs);
+    }
+    if key != reserved_attribute(&prefix, MOVE_COST_ATTRIBUTE) {
+        return;
+    }
+    match costs {
+        Some(mut costs) => costs.update_cell(&marker.map, geometry, tile_pos, &prefix),
+        // Maps without any move cost have no MoveCosts until one is set
+        None if value.is_some() => {
+            let costs = MoveCosts::from_map(&marker.map, geometry.tile_origin, &prefix);
+            if !costs.is_empty() {
+                world.entity_mut(layer.map).insert(costs);
+            }
+        }
+        None => {}
+    }
+}
+
+/// Get a copy of the attributes of a tile from the store of its map.
+fn stored_attributes(world: &World, tile: Entity) -> Option<HashMap<String, serde_json::Value>> {
+    let key = world.get::<AttrKey>(tile)?;
+    let layer = world.get::<SpriteFusionLayerMarker>(world.get::<TilemapId>(tile)?.0)?;
+    let store = world.get::<AttributeStore>(layer.map)?;
+    Some(store.get(*key)?.0.clone())
+}
@@ -0,0 +1,128 @@
+//! Integrity checks for spawned tilemaps.
+//!
+//! Runtime editing, despawning and streaming can leave tiles pointing at a
+//! tilemap that no longer exists, or [`TileStorage`] slots pointing at despawned
+//! tiles. The audit finds both, and can fix them.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::common_conditions::on_timer};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::tile_index::index_tile;
+
+/// Plugin that periodically audits all tilemaps and logs what it finds.
+///
+/// Not added by [`SpriteFusionPlugin`](crate::plugin::SpriteFusionPlugin). For a
+/// one-off check, use
+/// [`audit_tilemaps`](crate::commands::SpriteFusionCommandsExt::audit_tilemaps) instead.
+///
+/// ```rust,ignore
+/// app.add_plugins(TilemapAuditPlugin {
+///     interval: Duration::from_secs(5),
+///     fix: true,
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct TilemapAuditPlugin {
+    /// Time between two audits.
+    pub interval: Duration,
+    /// Whether to fix the problems found, or only log them.
+    pub fix: bool,
+}
+
+impl Default for TilemapAuditPlugin {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            fix: false,
+        }
+    }
+}
+
+impl Plugin for TilemapAuditPlugin {
+    fn build(&self, app: &mut App) {
+        let fix = self.fix;
+        app.add_systems(
+            Last,
+            (move |world: &mut World| audit_and_log(world, fix)).run_if(on_timer(self.interval)),
+        );
+    }
+}
+
+/// Problems found by [`audit_tilemaps`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TilemapAuditReport {
+    /// Tiles whose [`TilemapId`] points at a despawned tilemap.
+    pub orphan_tiles: Vec<Entity>,
+    /// Storage slots of a tilemap pointing at a despawned tile.
+    pub dangling_slots: Vec<(Entity, TilePos)>,
+}
+
+impl TilemapAuditReport {
+    /// Check if no problem was found.
+    pub fn is_clean(&self) -> bool {
+        self.orphan_tiles.is_empty() && self.dangling_slots.is_empty()
+    }
+
+    fn log(&self, fixed: bool) {
+        if self.is_clean() {
+            return;
+        }
+        warn!(
+            "Tilemap audit found {} orphan tiles and {} dangling storage slots{}",
+            self.orphan_tiles.len(),
+            self.dangling_slots.len(),
+            if fixed { " (fixed)" } else { "" }
+        );
+    }
+}
+
+/// Check every tilemap of the world for orphan tiles and dangling storage slots.
+///
+/// With `fix`, orphan tiles are despawned and dangling slots are cleared.
+pub fn audit_tilemaps(world: &mut World, fix: bool) -> TilemapAuditReport {
+    let mut report = TilemapAuditReport::default();
+
+    let mut tiles = world.query::<(Entity, &TilemapId)>();
+    for (tile, tilemap_id) in tiles.iter(world) {
+        if world.get_entity(tilemap_id.0).is_err() {
+            report.orphan_tiles.push(tile);
+        }
+    }
+
+    let mut storages = world.query::<(Entity, &TileStorage)>();
+    for (tilemap, storage) in storages.iter(world) {
+        for (index, slot) in storage.iter().enumerate() {
+            let Some(tile) = slot else {
+                continue;
+            };
+            if world.get_entity(*tile).is_err() {
+                let pos = TilePos {
+                    x: index as u32 % storage.size.x,
+                    y: index as u32 / storage.size.x,
+                };
+                report.dangling_slots.push((tilemap, pos));
+            }
+        }
+    }
+
+    if fix {
+        for &tile in &report.orphan_tiles {
+            world.despawn(tile);
+        }
+        for (tilemap, pos) in &report.dangling_slots {
+            if let Some(mut storage) = world.get_mut::<TileStorage>(*tilemap) {
+                storage.remove(pos);
+            }
+            index_tile(world, *tilemap, *pos, None);
+        }
+    }
+
+    report
+}
+
+/// Audit all tilemaps and log the result, used by the commands extension.
+pub(crate) fn audit_and_log(world: &mut World, fix: bool) {
+    audit_tilemaps(world, fix).log(fix);
+}
@@ -0,0 +1,199 @@
+//! Cinematic camera paths drawn in the editor with attribute tiles.
+//!
+//! Tag tiles with `"camPath": "<name>"` and `"order": n` to draw a fly-through,
+//! e.g. for a level intro or the credits. Spawned maps get a [`MapCameraPaths`]
+//! component with a [`CameraPath`] asset per path, and cameras with a
+//! [`FollowCameraPath`] component fly along one of them.
+
+use std::collections::HashMap;
+
+use bevy::{
+    math::curve::{Curve, EaseFunction},
+    prelude::*,
+};
+
+use crate::{
+    patrol::ordered_waypoints,
+    plugin::SpriteFusionSettings,
+    types::{MapPaused, SpriteFusionMapMarker},
+};
+
+/// Attribute naming the camera path a tile belongs to.
+///
+/// Tiles are ordered with [`ORDER_ATTRIBUTE`](crate::patrol::ORDER_ATTRIBUTE), like
+/// patrol routes.
+pub const CAMERA_PATH_ATTRIBUTE: &str = "camPath";
+
+/// Plugin that extracts camera paths from maps and moves [`FollowCameraPath`] cameras.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionCameraPathPlugin;
+
+impl Plugin for SpriteFusionCameraPathPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<CameraPath>()
+            .add_message::<CameraPathFinished>()
+            .add_systems(Update, (insert_camera_paths, follow_camera_paths).chain());
+    }
+}
+
+/// A camera path: waypoints in the local space of the map entity it comes from.
+#[derive(Asset, TypePath, Debug, Clone, Default, PartialEq)]
+pub struct CameraPath {
+    /// Waypoints, in path order.
+    pub waypoints: Vec<Vec2>,
+}
+
+impl CameraPath {
+    /// Total length of the path.
+    pub fn length(&self) -> f32 {
+        self.waypoints.windows(2).map(|w| w[0].distance(w[1])).sum()
+    }
+
+    /// Get the point at `distance` along the path, clamped to its ends.
+    pub fn point_at(&self, distance: f32) -> Option<Vec2> {
+        let mut remaining = distance.max(0.0);
+        for w in self.waypoints.windows(2) {
+            let segment = w[0].distance(w[1]);
+            if remaining <= segment && segment > 0.0 {
+                return Some(w[0].lerp(w[1], remaining / segment));
+            }
+            remaining -= segment;
+        }
+        self.waypoints.last().copied()
+    }
+}
+
+/// Camera paths of a spawned map, by name.
+#[derive(Component, Debug, Clone, Default)]
+pub struct MapCameraPaths(pub HashMap<String, Handle<CameraPath>>);
+
+impl MapCameraPaths {
+    /// Get a path by name.
+    pub fn get(&self, name: &str) -> Option<&Handle<CameraPath>> {
+        self.0.get(name)
+    }
+}
+
+/// Component flying a camera along a [`CameraPath`] of a map.
+///
+/// The camera's [`Transform`] is treated as world space and its Z is left
+/// untouched. The component is removed at the end of the path, and a
+/// [`CameraPathFinished`] message is sent.
+///
+/// ```rust,ignore
+/// fn intro(mut commands: Commands, camera: Single<Entity, With<Camera2d>>, maps: Query<(Entity, &MapCameraPaths)>) {
+///     for (map, paths) in maps.iter() {
+///         if let Some(path) = paths.get("intro") {
+///             commands.entity(*camera).insert(FollowCameraPath::new(map, path.clone(), 8.0));
+///         }
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct FollowCameraPath {
+    /// The map entity the path comes from.
+    pub map: Entity,
+    /// The path to follow.
+    pub path: Handle<CameraPath>,
+    /// Time to go through the whole path, in seconds.
+    pub duration: f32,
+    /// Easing of the progress along the path.
+    pub ease: EaseFunction,
+    elapsed: f32,
+}
+
+impl FollowCameraPath {
+    /// Follow a path in `duration` seconds, easing in and out.
+    pub fn new(map: Entity, path: Handle<CameraPath>, duration: f32) -> Self {
+        Self {
+            map,
+            path,
+            duration,
+            ease: EaseFunction::SmoothStep,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Use another easing.
+    pub fn with_ease(mut self, ease: EaseFunction) -> Self {
+        self.ease = ease;
+        self
+    }
+
+    /// Progress along the path, from 0 to 1, before easing.
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.elapsed / self.duration).min(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Sent when a camera reaches the end of its [`FollowCameraPath`].
+#[derive(Message, Debug, Clone)]
+pub struct CameraPathFinished {
+    /// The camera entity.
+    pub camera: Entity,
+    /// The path it followed.
+    pub path: Handle<CameraPath>,
+}
+
+/// System that adds the camera paths of newly spawned maps.
+fn insert_camera_paths(
+    mut commands: Commands,
+    maps: Query<(Entity, &SpriteFusionMapMarker), Added<SpriteFusionMapMarker>>,
+    mut paths: ResMut<Assets<CameraPath>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (entity, marker) in maps.iter() {
+        let map_paths: HashMap<_, _> = ordered_waypoints(
+            &marker.map,
+            CAMERA_PATH_ATTRIBUTE,
+            &settings.attribute_prefix,
+        )
+        .into_iter()
+        .map(|(name, waypoints)| (name, paths.add(CameraPath { waypoints })))
+        .collect();
+        if !map_paths.is_empty() {
+            commands.entity(entity).insert(MapCameraPaths(map_paths));
+        }
+    }
+}
+
+/// System that moves cameras along their path.
+fn follow_camera_paths(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut cameras: Query<(Entity, &mut FollowCameraPath, &mut Transform)>,
+    maps: Query<&GlobalTransform, Without<MapPaused>>,
+    paths: Res<Assets<CameraPath>>,
+    mut finished: MessageWriter<CameraPathFinished>,
+) {
+    for (camera, mut follow, mut transform) in cameras.iter_mut() {
+        // Cameras on the path of a paused map wait for it to resume
+        let Ok(map_transform) = maps.get(follow.map) else {
+            continue;
+        };
+        // The path may still be loading when added from a file
+        let Some(path) = paths.get(&follow.path) else {
+            continue;
+        };
+
+        follow.elapsed += time.delta_secs();
+        let progress = follow.ease.sample_clamped(follow.progress());
+        if let Some(point) = path.point_at(progress * path.length()) {
+            let target = map_transform.transform_point(point.extend(0.0)).truncate();
+            transform.translation = target.extend(transform.translation.z);
+        }
+
+        if follow.progress() >= 1.0 {
+            commands.entity(camera).remove::<FollowCameraPath>();
+            finished.write(CameraPathFinished {
+                camera,
+                path: follow.path.clone(),
+            });
+        }
+    }
+}
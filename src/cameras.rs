@@ -0,0 +1,73 @@
+//! Assigning spawned maps to specific cameras.
+//!
+//! Bevy doesn't propagate [`RenderLayers`] through the hierarchy, so a map
+//! rendered by only some cameras needs the layers copied onto every tilemap.
+//! Insert [`RenderLayers`] on the map entity and this module keeps its layers
+//! in sync, which is what split-screen views and minimap cameras need.
+
+use bevy::{camera::visibility::RenderLayers, prelude::*};
+
+use crate::types::SpriteFusionLayerMarker;
+
+/// Plugin that propagates [`RenderLayers`] from map entities to their layers.
+///
+/// Added by [`SpriteFusionPlugin`](crate::plugin::SpriteFusionPlugin).
+pub struct SpriteFusionCamerasPlugin;
+
+impl Plugin for SpriteFusionCamerasPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, propagate_map_render_layers);
+    }
+}
+
+/// Copy the render layers of a camera onto a map, so only that camera (and
+/// cameras sharing its layers) renders the map.
+pub(crate) fn assign_map_to_camera(world: &mut World, map: Entity, camera: Entity) {
+    let render_layers = world
+        .get::<RenderLayers>(camera)
+        .cloned()
+        .unwrap_or_default();
+    if let Ok(mut map_entity) = world.get_entity_mut(map) {
+        map_entity.insert(render_layers);
+    }
+}
+
+/// System that keeps layer render layers in sync with their map.
+///
+/// Maps spawning over several frames only get their `SpriteFusionMapMarker`
+/// once every tile is in, so maps are matched through their layers instead.
+fn propagate_map_render_layers(
+    mut commands: Commands,
+    maps: Query<(Ref<RenderLayers>, &Children)>,
+    new_layers: Query<(Entity, &SpriteFusionLayerMarker), Added<SpriteFusionLayerMarker>>,
+    all_layers: Query<(), With<SpriteFusionLayerMarker>>,
+    children: Query<&Children>,
+    mut removed: RemovedComponents<RenderLayers>,
+) {
+    for (render_layers, map_children) in maps.iter() {
+        if !render_layers.is_changed() {
+            continue;
+        }
+        for layer in map_children.iter().filter(|&c| all_layers.contains(c)) {
+            commands.entity(layer).insert(render_layers.clone());
+        }
+    }
+
+    for (layer, marker) in new_layers.iter() {
+        if let Ok((render_layers, _)) = maps.get(marker.map) {
+            commands.entity(layer).insert(render_layers.clone());
+        }
+    }
+
+    for map in removed.read() {
+        if maps.contains(map) {
+            continue;
+        }
+        let Ok(map_children) = children.get(map) else {
+            continue;
+        };
+        for layer in map_children.iter().filter(|&c| all_layers.contains(c)) {
+            commands.entity(layer).remove::<RenderLayers>();
+        }
+    }
+}
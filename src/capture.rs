@@ -0,0 +1,125 @@
+//! Capturing images of map regions.
+//!
+//! [`capture_region`] composites the tiles of a rectangle of a map into an
+//! [`Image`] on the CPU, straight from the map data and its spritesheet: journal
+//! screenshots, a photo mode of discovered areas, or reference images for visual
+//! regression tests of maps, without an offscreen camera.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use thiserror::Error;
+
+use crate::core::{SpriteFusionMap, TileRect};
+
+/// Errors that can occur when capturing a map region.
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("Capture region is empty")]
+    EmptyRegion,
+    #[error("Tileset has no pixel data in the main world")]
+    MissingData,
+    #[error("Tileset format {0:?} isn't 8-bit RGBA")]
+    UnsupportedFormat(TextureFormat),
+}
+
+/// Render the tiles of a map within `rect` into an image, one tile of the
+/// spritesheet per tile of the rectangle.
+///
+/// Layers are drawn bottom to top with alpha blending, over a transparent
+/// background. Tiles whose ID is past the end of the spritesheet are left out.
+/// The image has the format of the spritesheet, which must be 8-bit RGBA and keep
+/// its data in the main world (the default for loaded images).
+///
+/// To capture a spawned map with its runtime edits, capture its
+/// [`export_map`](crate::export::export_map) data. To leave layers out, capture a
+/// copy of the map without them.
+///
+/// ```rust,ignore
+/// fn photo(maps: Res<Assets<SpriteFusionMap>>, images: Res<Assets<Image>>, level: Res<Level>) {
+///     let (Some(map), Some(tileset)) = (maps.get(&level.map), images.get(&level.tileset)) else {
+///         return;
+///     };
+///     let image = capture_region(map, tileset, TileRect::new(10, 4, 16, 9)).unwrap();
+///     image.try_into_dynamic().unwrap().save("photo.png").unwrap();
+/// }
+/// ```
+pub fn capture_region(
+    map: &SpriteFusionMap,
+    tileset: &Image,
+    rect: TileRect,
+) -> Result<Image, CaptureError> {
+    if rect.width == 0 || rect.height == 0 {
+        return Err(CaptureError::EmptyRegion);
+    }
+    let format = tileset.texture_descriptor.format;
+    if !matches!(
+        format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    ) {
+        return Err(CaptureError::UnsupportedFormat(format));
+    }
+    let source = tileset.data.as_ref().ok_or(CaptureError::MissingData)?;
+
+    let tile_size = map.tile_size.max(1);
+    let columns = tileset.width() / tile_size;
+    let rows = tileset.height() / tile_size;
+    let width = rect.width * tile_size;
+    let height = rect.height * tile_size;
+    let mut data = vec![0; width as usize * height as usize * 4];
+
+    // Layers are listed top first
+    for tile in map.layers.iter().rev().flat_map(|layer| &layer.tiles) {
+        if !rect.contains(tile.x, tile.y) || tile.id >= columns * rows {
+            continue;
+        }
+        let (sx, sy) = (
+            (tile.id % columns) * tile_size,
+            (tile.id / columns) * tile_size,
+        );
+        let dx = (tile.x - rect.x) as u32 * tile_size;
+        let dy = (tile.y - rect.y) as u32 * tile_size;
+        for row in 0..tile_size {
+            let src = ((sy + row) as usize * tileset.width() as usize + sx as usize) * 4;
+            let dst = ((dy + row) as usize * width as usize + dx as usize) * 4;
+            let len = tile_size as usize * 4;
+            let pixels = source[src..src + len].chunks_exact(4);
+            for (over, under) in pixels.zip(data[dst..dst + len].chunks_exact_mut(4)) {
+                blend(under, over);
+            }
+        }
+    }
+
+    Ok(Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+        RenderAssetUsages::default(),
+    ))
+}
+
+/// Draw an RGBA pixel over another, in place.
+fn blend(under: &mut [u8], over: &[u8]) {
+    let alpha = over[3] as f32 / 255.0;
+    if alpha >= 1.0 {
+        under.copy_from_slice(over);
+        return;
+    }
+    let under_alpha = under[3] as f32 / 255.0 * (1.0 - alpha);
+    let out_alpha = alpha + under_alpha;
+    if out_alpha <= 0.0 {
+        return;
+    }
+    for channel in 0..3 {
+        let color = over[channel] as f32 * alpha + under[channel] as f32 * under_alpha;
+        under[channel] = (color / out_alpha).round() as u8;
+    }
+    under[3] = (out_alpha * 255.0).round() as u8;
+}
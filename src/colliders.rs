@@ -0,0 +1,617 @@
+//! Merged colliders of collider layers.
+//!
+//! One collider per tile is wasteful on large maps. [`MergedColliders`] covers
+//! the tiles of each collider layer with as few rectangles as possible, merging
+//! adjacent tiles greedily, and keeps them in world space so any physics backend
+//! can build its static bodies from them. [`tile_outlines`] traces the outlines
+//! of the same cells instead, for polyline or chain colliders.
+//!
+//! Hidden tiles don't collide: tiles of collider layers lose their [`Collider`]
+//! while their [`TileVisible`] is off, and get it back when shown again.
+
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use bevy::{math::URect, prelude::*, tasks::ComputeTaskPool};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    plugin::SpriteFusionSystems,
+    types::{Collider, SpriteFusionLayerMarker},
+};
+
+pub(crate) struct SpriteFusionCollidersPlugin;
+
+impl Plugin for SpriteFusionCollidersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (sync_hidden_tile_colliders, update_merged_colliders)
+                .chain()
+                .in_set(SpriteFusionSystems::Sync),
+        );
+    }
+}
+
+/// The tiles of a collider layer merged into rectangles.
+///
+/// Inserted on collider layer entities, and updated when tiles are added,
+/// removed, hidden or shown, or when the layer moves. Only tiles with a
+/// [`Collider`] are covered.
+#[derive(Component, Debug, Clone, Default)]
+pub struct MergedColliders {
+    /// Rectangles covering the tiles, in world space.
+    pub rects: Vec<Rect>,
+    tiles: Vec<URect>,
+}
+
+impl MergedColliders {
+    /// The merged rectangles in tile coordinates of the layer. `max` is exclusive.
+    pub fn tile_rects(&self) -> &[URect] {
+        &self.tiles
+    }
+}
+
+/// Cover the filled cells of a grid with rectangles, merging them greedily.
+///
+/// Rows are scanned bottom to top: each free filled cell starts a rectangle,
+/// which grows right as far as the row allows, then up while the whole span is
+/// filled. The result isn't always minimal, but it's close and fast. `max` is
+/// exclusive.
+pub fn merge_tile_rects(size: UVec2, filled: impl Fn(UVec2) -> bool) -> Vec<URect> {
+    let index = |x: u32, y: u32| (y * size.x + x) as usize;
+    let mut used = vec![false; (size.x * size.y) as usize];
+    let free = |used: &[bool], x: u32, y: u32| !used[index(x, y)] && filled(UVec2::new(x, y));
+    let mut rects = Vec::new();
+
+    for y in 0..size.y {
+        for x in 0..size.x {
+            if !free(&used, x, y) {
+                continue;
+            }
+            let mut end_x = x + 1;
+            while end_x < size.x && free(&used, end_x, y) {
+                end_x += 1;
+            }
+            let mut end_y = y + 1;
+            while end_y < size.y && (x..end_x).all(|cx| free(&used, cx, end_y)) {
+                end_y += 1;
+            }
+            for cy in y..end_y {
+                for cx in x..end_x {
+                    used[index(cx, cy)] = true;
+                }
+            }
+            rects.push(URect::new(x, y, end_x, end_y));
+        }
+    }
+    rects
+}
+
+/// Number of rows merged by each task of [`par_merge_tile_rects`].
+const MERGE_BAND_ROWS: u32 = 64;
+
+/// Like [`merge_tile_rects`], but scans bands of rows in parallel on the
+/// [`ComputeTaskPool`].
+///
+/// Each band is merged on its own, then rectangles spanning the same columns on
+/// both sides of a band edge are joined, so the result covers exactly the same
+/// cells and stays close to the sequential one. Small grids, and apps without a
+/// compute task pool, are merged on the calling thread.
+pub fn par_merge_tile_rects(size: UVec2, filled: impl Fn(UVec2) -> bool + Sync) -> Vec<URect> {
+    let Some(pool) = ComputeTaskPool::try_get().filter(|_| size.y > MERGE_BAND_ROWS) else {
+        return merge_tile_rects(size, filled);
+    };
+    let filled = &filled;
+    let bands = pool.scope(|scope| {
+        for start in (0..size.y).step_by(MERGE_BAND_ROWS as usize) {
+            let rows = MERGE_BAND_ROWS.min(size.y - start);
+            scope.spawn(async move {
+                let offset = UVec2::new(0, start);
+                merge_tile_rects(UVec2::new(size.x, rows), |cell| filled(cell + offset))
+                    .into_iter()
+                    .map(|rect| URect::from_corners(rect.min + offset, rect.max + offset))
+                    .collect::<Vec<_>>()
+            });
+        }
+    });
+
+    // Rectangles reaching the edge of the previous band, by their column span
+    let mut open: HashMap<(u32, u32), usize> = HashMap::new();
+    let mut rects: Vec<URect> = Vec::new();
+    for (band, band_rects) in bands.into_iter().enumerate() {
+        let end = (band as u32 + 1) * MERGE_BAND_ROWS;
+        let mut next = HashMap::new();
+        for rect in band_rects {
+            let span = (rect.min.x, rect.max.x);
+            let index = match open.get(&span) {
+                Some(&index) if rects[index].max.y == rect.min.y => {
+                    rects[index].max.y = rect.max.y;
+                    index
+                }
+                _ => {
+                    rects.push(rect);
+                    rects.len() - 1
+                }
+            };
+            if rect.max.y == end {
+                next.insert(span, index);
+            }
+        }
+        open = next;
+    }
+    rects
+}
+
+/// Trace the outlines of the filled cells of a grid.
+///
+/// Each outline is a closed loop of the corners where it turns, in tile
+/// coordinates like the rectangles of [`merge_tile_rects`], the last corner
+/// joining the first. Outer outlines go counterclockwise and outlines of holes
+/// clockwise, so filled cells are always on the left. Cells touching only by a
+/// corner get separate outlines.
+pub fn tile_outlines(size: UVec2, filled: impl Fn(UVec2) -> bool) -> Vec<Vec<UVec2>> {
+    let mut edges = Vec::new();
+    boundary_edges(size, &filled, 0..size.y, &mut edges);
+    chain_outlines(&edges)
+}
+
+/// Like [`tile_outlines`], but finds the edges of bands of rows in parallel on
+/// the [`ComputeTaskPool`], then joins them into outlines on the calling
+/// thread. The result is the same as the sequential one.
+pub fn par_tile_outlines(size: UVec2, filled: impl Fn(UVec2) -> bool + Sync) -> Vec<Vec<UVec2>> {
+    let Some(pool) = ComputeTaskPool::try_get().filter(|_| size.y > MERGE_BAND_ROWS) else {
+        return tile_outlines(size, filled);
+    };
+    let filled = &filled;
+    let bands = pool.scope(|scope| {
+        for start in (0..size.y).step_by(MERGE_BAND_ROWS as usize) {
+            let end = (start + MERGE_BAND_ROWS).min(size.y);
+            scope.spawn(async move {
+                let mut edges = Vec::new();
+                boundary_edges(size, filled, start..end, &mut edges);
+                edges
+            });
+        }
+    });
+    chain_outlines(&bands.concat())
+}
+
+/// Collect the cell sides of `rows` between a filled and an empty cell, as
+/// `(start, end)` corners with the filled cell on the left.
+fn boundary_edges(
+    size: UVec2,
+    filled: &impl Fn(UVec2) -> bool,
+    rows: Range<u32>,
+    edges: &mut Vec<(UVec2, UVec2)>,
+) {
+    // Cells outside of the grid are empty
+    let empty = |x: u32, y: u32, dx: i32, dy: i32| {
+        let (Some(x), Some(y)) = (x.checked_add_signed(dx), y.checked_add_signed(dy)) else {
+            return true;
+        };
+        x >= size.x || y >= size.y || !filled(UVec2::new(x, y))
+    };
+    for y in rows {
+        for x in 0..size.x {
+            if !filled(UVec2::new(x, y)) {
+                continue;
+            }
+            if empty(x, y, 0, -1) {
+                edges.push((UVec2::new(x, y), UVec2::new(x + 1, y)));
+            }
+            if empty(x, y, 1, 0) {
+                edges.push((UVec2::new(x + 1, y), UVec2::new(x + 1, y + 1)));
+            }
+            if empty(x, y, 0, 1) {
+                edges.push((UVec2::new(x + 1, y + 1), UVec2::new(x, y + 1)));
+            }
+            if empty(x, y, -1, 0) {
+                edges.push((UVec2::new(x, y + 1), UVec2::new(x, y)));
+            }
+        }
+    }
+}
+
+/// Follow boundary edges end to start into closed outlines, keeping only the
+/// corners where they turn.
+fn chain_outlines(edges: &[(UVec2, UVec2)]) -> Vec<Vec<UVec2>> {
+    let mut outgoing: HashMap<UVec2, Vec<usize>> = HashMap::new();
+    for (index, (start, _)) in edges.iter().enumerate() {
+        outgoing.entry(*start).or_default().push(index);
+    }
+    let direction = |index: usize| edges[index].1.as_ivec2() - edges[index].0.as_ivec2();
+    let mut used = vec![false; edges.len()];
+    let mut outlines = Vec::new();
+
+    for first in 0..edges.len() {
+        if used[first] {
+            continue;
+        }
+        let mut path = vec![first];
+        used[first] = true;
+        loop {
+            let current = direction(path[path.len() - 1]);
+            let end = edges[path[path.len() - 1]].1;
+            // Where two outlines touch at a corner, turn left to keep them apart
+            let turn = |index: usize| {
+                let next = direction(index);
+                if next == current.perp() {
+                    0
+                } else if next == current {
+                    1
+                } else {
+                    2
+                }
+            };
+            let Some(next) = outgoing[&end]
+                .iter()
+                .copied()
+                .filter(|&index| !used[index] || index == first)
+                .min_by_key(|&index| turn(index))
+            else {
+                break;
+            };
+            if next == first {
+                break;
+            }
+            used[next] = true;
+            path.push(next);
+        }
+        let corners = (0..path.len())
+            .filter(|&i| direction(path[i]) != direction(path[(i + path.len() - 1) % path.len()]))
+            .map(|i| edges[path[i]].0)
+            .collect();
+        outlines.push(corners);
+    }
+    outlines
+}
+
+/// Convert a rectangle of tiles to the local space of its layer.
+pub fn tile_rect_to_local(rect: URect, grid_size: &TilemapGridSize) -> Rect {
+    let grid = Vec2::new(grid_size.x, grid_size.y);
+    // Tile positions are the tile centers
+    let half = grid / 2.0;
+    Rect::from_corners(
+        rect.min.as_vec2() * grid - half,
+        rect.max.as_vec2() * grid - half,
+    )
+}
+
+/// Convert a corner of [`tile_outlines`] to the local space of its layer.
+pub fn tile_corner_to_local(corner: UVec2, grid_size: &TilemapGridSize) -> Vec2 {
+    let grid = Vec2::new(grid_size.x, grid_size.y);
+    corner.as_vec2() * grid - grid / 2.0
+}
+
+fn merge_storage(storage: &TileStorage, colliders: &Query<(), With<Collider>>) -> Vec<URect> {
+    par_merge_tile_rects(UVec2::new(storage.size.x, storage.size.y), |cell| {
+        storage
+            .checked_get(&TilePos {
+                x: cell.x,
+                y: cell.y,
+            })
+            .is_some_and(|tile| colliders.contains(tile))
+    })
+}
+
+fn to_world(rect: Rect, transform: &GlobalTransform) -> Rect {
+    // Corners may swap when the layer is flipped
+    Rect::from_corners(
+        transform.transform_point(rect.min.extend(0.0)).truncate(),
+        transform.transform_point(rect.max.extend(0.0)).truncate(),
+    )
+}
+
+/// Check if a tile of a layer has a [`Collider`]: tiles of collider layers do
+/// while they're visible.
+pub(crate) fn tile_collides(layer: &SpriteFusionLayerMarker, visible: TileVisible) -> bool {
+    layer.collider && visible.0
+}
+
+/// System that removes the [`Collider`] of hidden tiles of collider layers, and
+/// restores it when they're shown again.
+fn sync_hidden_tile_colliders(
+    mut commands: Commands,
+    tiles: Query<(Entity, &TileVisible, &TilemapId, Has<Collider>), Changed<TileVisible>>,
+    layers: Query<&SpriteFusionLayerMarker>,
+) {
+    for (tile, &visible, tilemap_id, has_collider) in tiles.iter() {
+        let Ok(layer) = layers.get(tilemap_id.0) else {
+            continue;
+        };
+        if !layer.collider {
+            continue;
+        }
+        match (tile_collides(layer, visible), has_collider) {
+            (true, false) => {
+                commands.entity(tile).insert(Collider);
+            }
+            (false, true) => {
+                commands.entity(tile).remove::<Collider>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// System that keeps [`MergedColliders`] in sync with the tiles of collider layers.
+#[allow(clippy::type_complexity)]
+fn update_merged_colliders(
+    mut commands: Commands,
+    mut layers: Query<(
+        Entity,
+        &SpriteFusionLayerMarker,
+        Ref<TileStorage>,
+        &TilemapGridSize,
+        Ref<GlobalTransform>,
+        Option<&mut MergedColliders>,
+    )>,
+    colliders: Query<(), With<Collider>>,
+    added: Query<&TilemapId, Added<Collider>>,
+    tilemap_ids: Query<&TilemapId>,
+    mut removed: RemovedComponents<Collider>,
+) {
+    // Layers whose tiles gained or lost a collider without changing the storage
+    let mut dirty: HashSet<Entity> = added.iter().map(|tilemap_id| tilemap_id.0).collect();
+    dirty.extend(
+        removed
+            .read()
+            .filter_map(|tile| tilemap_ids.get(tile).ok())
+            .map(|tilemap_id| tilemap_id.0),
+    );
+
+    for (entity, layer, storage, grid_size, transform, merged) in layers.iter_mut() {
+        if !layer.collider {
+            continue;
+        }
+        match merged {
+            None => {
+                let tiles = merge_storage(&storage, &colliders);
+                let rects = tiles
+                    .iter()
+                    .map(|&rect| to_world(tile_rect_to_local(rect, grid_size), &transform))
+                    .collect();
+                commands
+                    .entity(entity)
+                    .insert(MergedColliders { rects, tiles });
+            }
+            Some(mut merged) => {
+                let tiles_changed = storage.is_changed() || dirty.contains(&entity);
+                if !tiles_changed && !transform.is_changed() {
+                    continue;
+                }
+                if tiles_changed {
+                    merged.tiles = merge_storage(&storage, &colliders);
+                }
+                let merged = &mut *merged;
+                merged.rects = merged
+                    .tiles
+                    .iter()
+                    .map(|&rect| to_world(tile_rect_to_local(rect, grid_size), &transform))
+                    .collect();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        hint::black_box,
+        time::{Duration, Instant},
+    };
+
+    use bevy::tasks::TaskPool;
+
+    use super::*;
+
+    fn init_pool() {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+    }
+
+    /// Dense walls with rooms carved out and some noise, like a dungeon
+    /// collision layer.
+    fn dungeon(cell: UVec2) -> bool {
+        let room = cell.x % 24 > 2 && cell.y % 16 > 2;
+        let noise =
+            (cell.x.wrapping_mul(73_856_093) ^ cell.y.wrapping_mul(19_349_663)).is_multiple_of(7);
+        !room || noise
+    }
+
+    /// Assert that `rects` cover every filled cell once and no empty cell.
+    fn assert_cover(size: UVec2, rects: &[URect], filled: impl Fn(UVec2) -> bool) {
+        let mut covered = vec![0u8; (size.x * size.y) as usize];
+        for rect in rects {
+            assert!(
+                rect.max.x <= size.x && rect.max.y <= size.y,
+                "{rect:?} out of grid"
+            );
+            for y in rect.min.y..rect.max.y {
+                for x in rect.min.x..rect.max.x {
+                    covered[(y * size.x + x) as usize] += 1;
+                }
+            }
+        }
+        for y in 0..size.y {
+            for x in 0..size.x {
+                assert_eq!(
+                    covered[(y * size.x + x) as usize],
+                    u8::from(filled(UVec2::new(x, y))),
+                    "cell ({x}, {y}) covered wrongly"
+                );
+            }
+        }
+    }
+
+    /// Assert that the sequential and parallel merges both cover the filled cells
+    /// exactly, and return them.
+    fn merge_both(size: UVec2, filled: impl Fn(UVec2) -> bool + Sync) -> (Vec<URect>, Vec<URect>) {
+        init_pool();
+        let sequential = merge_tile_rects(size, &filled);
+        let parallel = par_merge_tile_rects(size, &filled);
+        assert_cover(size, &sequential, &filled);
+        assert_cover(size, &parallel, &filled);
+        (sequential, parallel)
+    }
+
+    /// Twice the signed area of an outline, positive when counterclockwise.
+    fn double_area(outline: &[UVec2]) -> i64 {
+        (0..outline.len())
+            .map(|i| {
+                let (a, b) = (
+                    outline[i].as_i64vec2(),
+                    outline[(i + 1) % outline.len()].as_i64vec2(),
+                );
+                a.x * b.y - b.x * a.y
+            })
+            .sum()
+    }
+
+    #[test]
+    fn merge_empty_grid() {
+        let (sequential, parallel) = merge_both(UVec2::new(40, 300), |_| false);
+        assert!(sequential.is_empty());
+        assert!(parallel.is_empty());
+        let (sequential, parallel) = merge_both(UVec2::ZERO, |_| true);
+        assert!(sequential.is_empty());
+        assert!(parallel.is_empty());
+    }
+
+    #[test]
+    fn merge_full_grid_joins_bands() {
+        let size = UVec2::new(37, 3 * MERGE_BAND_ROWS + 5);
+        let (sequential, parallel) = merge_both(size, |_| true);
+        let whole = vec![URect::from_corners(UVec2::ZERO, size)];
+        assert_eq!(sequential, whole);
+        assert_eq!(parallel, whole);
+    }
+
+    #[test]
+    fn merge_across_band_edges() {
+        let rows = MERGE_BAND_ROWS;
+        // Rectangles ending, starting and crossing exactly at band edges
+        let filled = |cell: UVec2| match cell.x {
+            0..=3 => cell.y < rows,
+            5..=8 => cell.y >= rows,
+            10..=13 => (rows - 1..=rows).contains(&cell.y),
+            15..=18 => (rows - 3..2 * rows + 3).contains(&cell.y),
+            _ => false,
+        };
+        let (sequential, parallel) = merge_both(UVec2::new(20, 3 * rows), filled);
+        assert_eq!(parallel.len(), sequential.len());
+        assert!(parallel.contains(&URect::new(15, rows - 3, 19, 2 * rows + 3)));
+        assert!(parallel.contains(&URect::new(10, rows - 1, 14, rows + 1)));
+    }
+
+    #[test]
+    fn merge_single_column_and_row() {
+        let gaps = |i: u32| !i.is_multiple_of(MERGE_BAND_ROWS + 1) && i % 7 != 3;
+        let (sequential, parallel) = merge_both(UVec2::new(1, 1000), |cell| gaps(cell.y));
+        assert_eq!(parallel, sequential);
+        let (sequential, parallel) = merge_both(UVec2::new(1000, 1), |cell| gaps(cell.x));
+        assert_eq!(parallel, sequential);
+        let (sequential, parallel) = merge_both(UVec2::new(1, 1000), |_| true);
+        assert_eq!(parallel, vec![URect::new(0, 0, 1, 1000)]);
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn merge_large_map() {
+        let (sequential, parallel) = merge_both(UVec2::new(512, 700), dungeon);
+        // Band edges only split rectangles the sequential merge grows across them
+        assert!(parallel.len() <= sequential.len() + sequential.len() / 10);
+    }
+
+    #[test]
+    fn outline_of_rectangle() {
+        let outlines = tile_outlines(UVec2::new(5, 5), |cell| {
+            (1..4).contains(&cell.x) && (2..4).contains(&cell.y)
+        });
+        assert_eq!(
+            outlines,
+            vec![vec![
+                UVec2::new(1, 2),
+                UVec2::new(4, 2),
+                UVec2::new(4, 4),
+                UVec2::new(1, 4)
+            ]]
+        );
+    }
+
+    #[test]
+    fn outlines_of_holes_and_corners() {
+        // A ring, and two cells touching by a corner
+        let filled = |cell: UVec2| {
+            let ring = cell.x < 3 && cell.y < 3 && cell != UVec2::ONE;
+            ring || cell == UVec2::new(5, 5) || cell == UVec2::new(6, 6)
+        };
+        let outlines = tile_outlines(UVec2::new(8, 8), filled);
+        let mut areas: Vec<i64> = outlines.iter().map(|o| double_area(o)).collect();
+        areas.sort();
+        assert_eq!(areas, vec![-2, 2, 2, 18]);
+        assert!(outlines.iter().all(|outline| outline.len() == 4));
+    }
+
+    #[test]
+    fn par_outlines_match_sequential() {
+        init_pool();
+        for size in [
+            UVec2::ZERO,
+            UVec2::new(1, 500),
+            UVec2::new(300, 257),
+            UVec2::new(512, 700),
+        ] {
+            let sequential = tile_outlines(size, dungeon);
+            let parallel = par_tile_outlines(size, dungeon);
+            assert_eq!(parallel, sequential);
+            // Holes take away from the outer outlines
+            let area: i64 = parallel.iter().map(|o| double_area(o)).sum();
+            let cells = (0..size.y)
+                .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+                .filter(|&cell| dungeon(cell))
+                .count();
+            assert_eq!(area, 2 * cells as i64);
+        }
+    }
+
+    /// Get the fastest of a few runs of `run`.
+    fn best_time<T>(run: impl Fn() -> T) -> Duration {
+        (0..5)
+            .map(|_| {
+                let start = Instant::now();
+                black_box(run());
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    #[ignore = "timing test, run with `cargo test --release -- --ignored`"]
+    fn parallel_speedup() {
+        init_pool();
+        let threads = ComputeTaskPool::get().thread_num();
+        if threads < 4 {
+            eprintln!("skipped: the speedup needs at least 4 threads, found {threads}");
+            return;
+        }
+        let size = UVec2::new(2048, 2048);
+
+        let merge = best_time(|| merge_tile_rects(size, dungeon)).as_secs_f64()
+            / best_time(|| par_merge_tile_rects(size, dungeon)).as_secs_f64();
+        assert!(
+            merge >= 1.5,
+            "merge speedup of {merge:.2}x on {threads} threads"
+        );
+        // Outlines are still joined on the calling thread
+        let outlines = best_time(|| tile_outlines(size, dungeon)).as_secs_f64()
+            / best_time(|| par_tile_outlines(size, dungeon)).as_secs_f64();
+        assert!(
+            outlines >= 1.2,
+            "outline speedup of {outlines:.2}x on {threads} threads"
+        );
+    }
+}
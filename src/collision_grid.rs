@@ -0,0 +1,292 @@
+//! Blocked cells of whole maps, for movement and AI code.
+//!
+//! Checking walls through ECS queries at every step of a movement or AI routine
+//! is slow and awkward. Spawned maps get a [`CollisionGrid`], one bit per cell,
+//! set where a collider layer has a tile, with [`is_blocked`](CollisionGrid::is_blocked)
+//! and [`raycast`](CollisionGrid::raycast) helpers. Paths are found over it with
+//! [`pathfind`](CollisionGrid::pathfind), see the
+//! [`pathfinding`](crate::pathfinding) module.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::{math::URect, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    colliders::MergedColliders,
+    convert::{layer_tiles, tilemap_size, TileOrigin},
+    geometry::SpriteFusionMapGeometry,
+    plugin::SpriteFusionSystems,
+    types::{SpriteFusionLayerMarker, SpriteFusionMap},
+};
+
+pub(crate) struct SpriteFusionCollisionGridPlugin;
+
+impl Plugin for SpriteFusionCollisionGridPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            update_collision_grids.after(SpriteFusionSystems::Sync),
+        );
+    }
+}
+
+/// Component of spawned maps telling which cells hold a collider tile, on any
+/// collider layer.
+///
+/// Cells are indexed by spawned [`TilePos`]. The grid follows the
+/// [`MergedColliders`] of the map's layers, so it's updated when tiles are
+/// edited, hidden or streamed; cells of streamed chunks that aren't loaded are
+/// free.
+///
+/// ```rust,ignore
+/// fn can_see_player(
+///     maps: Query<(&CollisionGrid, &SpriteFusionMapGeometry)>,
+///     guard: Single<&Transform, With<Guard>>,
+///     player: Single<&Transform, With<Player>>,
+/// ) -> bool {
+///     let Ok((grid, geometry)) = maps.single() else {
+///         return false;
+///     };
+///     let from = geometry.world_to_tile(guard.translation.truncate());
+///     let to = geometry.world_to_tile(player.translation.truncate());
+///     from.zip(to).is_some_and(|(from, to)| grid.line_of_sight(from, to))
+/// }
+/// ```
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct CollisionGrid {
+    size: TilemapSize,
+    bits: Vec<u64>,
+}
+
+impl CollisionGrid {
+    /// An empty grid of `size` cells.
+    pub fn new(size: TilemapSize) -> Self {
+        Self {
+            size,
+            bits: vec![0; size.count().div_ceil(64)],
+        }
+    }
+
+    /// Build the grid of a map from its data, blocking the cells of every tile
+    /// of its collider layers.
+    pub fn from_map(map: &SpriteFusionMap, origin: TileOrigin) -> Self {
+        let mut grid = Self::new(tilemap_size(map));
+        for layer in map.layers.iter().filter(|layer| layer.collider) {
+            for tile in layer_tiles(map, layer, origin) {
+                grid.set_blocked(tile.position, true);
+            }
+        }
+        grid
+    }
+
+    /// Size of the grid, the tilemap size of the map.
+    pub fn size(&self) -> TilemapSize {
+        self.size
+    }
+
+    /// Check if a cell is blocked. Cells outside of the grid are blocked.
+    pub fn is_blocked(&self, tile_pos: TilePos) -> bool {
+        let Some(index) = self.index(tile_pos) else {
+            return true;
+        };
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Block or free a cell. Cells outside of the grid are ignored.
+    ///
+    /// Spawned maps rebuild their grid when their colliders change, dropping
+    /// cells set by hand.
+    pub fn set_blocked(&mut self, tile_pos: TilePos, blocked: bool) {
+        let Some(index) = self.index(tile_pos) else {
+            return;
+        };
+        let bit = 1 << (index % 64);
+        if blocked {
+            self.bits[index / 64] |= bit;
+        } else {
+            self.bits[index / 64] &= !bit;
+        }
+    }
+
+    /// Follow the line from `from` to `to`, and get the first blocked cell on
+    /// it, if any.
+    ///
+    /// Every cell the line between the tile centers touches is checked, both
+    /// ends included. A line passing exactly through the corner of two cells is
+    /// stopped by either of them, so rays don't slip between diagonal walls.
+    pub fn raycast(&self, from: TilePos, to: TilePos) -> Option<TilePos> {
+        let delta = IVec2::new(to.x as i32 - from.x as i32, to.y as i32 - from.y as i32);
+        let (nx, ny) = (delta.x.unsigned_abs() as i64, delta.y.unsigned_abs() as i64);
+        let step = delta.signum();
+        let mut cell = IVec2::new(from.x as i32, from.y as i32);
+        let blocked = |cell: IVec2| {
+            let tile_pos = TilePos {
+                x: cell.x as u32,
+                y: cell.y as u32,
+            };
+            self.is_blocked(tile_pos).then_some(tile_pos)
+        };
+
+        if let Some(hit) = blocked(cell) {
+            return Some(hit);
+        }
+        let (mut ix, mut iy) = (0, 0);
+        while ix < nx || iy < ny {
+            // Compare where the line crosses the next vertical and horizontal edges
+            let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+            if decision == 0 {
+                let corner = blocked(cell + IVec2::new(step.x, 0))
+                    .or_else(|| blocked(cell + IVec2::new(0, step.y)));
+                if corner.is_some() {
+                    return corner;
+                }
+                cell += step;
+                ix += 1;
+                iy += 1;
+            } else if decision < 0 {
+                cell.x += step.x;
+                ix += 1;
+            } else {
+                cell.y += step.y;
+                iy += 1;
+            }
+            if let Some(hit) = blocked(cell) {
+                return Some(hit);
+            }
+        }
+        None
+    }
+
+    /// Check if no blocked cell lies on the line from `from` to `to`, see
+    /// [`raycast`](Self::raycast).
+    pub fn line_of_sight(&self, from: TilePos, to: TilePos) -> bool {
+        self.raycast(from, to).is_none()
+    }
+
+    fn index(&self, tile_pos: TilePos) -> Option<usize> {
+        tile_pos
+            .within_map_bounds(&self.size)
+            .then(|| (tile_pos.y * self.size.x + tile_pos.x) as usize)
+    }
+
+    fn block_rect(&mut self, rect: URect) {
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                self.set_blocked(TilePos { x, y }, true);
+            }
+        }
+    }
+}
+
+/// System that builds the collision grid of newly spawned maps, and rebuilds it
+/// when the colliders of one of their layers cover other cells.
+fn update_collision_grids(
+    mut commands: Commands,
+    maps: Query<(Entity, Ref<SpriteFusionMapGeometry>)>,
+    layers: Query<(Entity, &SpriteFusionLayerMarker, Ref<MergedColliders>)>,
+    mut built: Local<HashMap<Entity, Vec<URect>>>,
+) {
+    // Merged colliders also change when their layer moves, keeping their cells
+    let mut dirty = HashSet::new();
+    for (entity, layer, merged) in layers.iter() {
+        if !merged.is_changed()
+            || built
+                .get(&entity)
+                .is_some_and(|rects| rects.as_slice() == merged.tile_rects())
+        {
+            continue;
+        }
+        built.insert(entity, merged.tile_rects().to_vec());
+        dirty.insert(layer.map);
+    }
+    built.retain(|&layer, _| layers.contains(layer));
+    dirty.extend(
+        maps.iter()
+            .filter(|(_, geometry)| geometry.is_added())
+            .map(|(map, _)| map),
+    );
+
+    for map in dirty {
+        let Ok((_, geometry)) = maps.get(map) else {
+            continue;
+        };
+        let mut grid = CollisionGrid::new(geometry.size);
+        for (_, _, merged) in layers.iter().filter(|(_, layer, _)| layer.map == map) {
+            for &rect in merged.tile_rects() {
+                grid.block_rect(rect);
+            }
+        }
+        commands.entity(map).insert(grid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked_grid(size: (u32, u32), blocked: &[(u32, u32)]) -> CollisionGrid {
+        let mut grid = CollisionGrid::new(TilemapSize {
+            x: size.0,
+            y: size.1,
+        });
+        for &(x, y) in blocked {
+            grid.set_blocked(TilePos { x, y }, true);
+        }
+        grid
+    }
+
+    fn pos(x: u32, y: u32) -> TilePos {
+        TilePos { x, y }
+    }
+
+    #[test]
+    fn blocked_cells() {
+        let mut grid = blocked_grid((70, 2), &[(65, 1)]);
+        assert!(grid.is_blocked(pos(65, 1)));
+        assert!(!grid.is_blocked(pos(65, 0)));
+        assert!(grid.is_blocked(pos(70, 0)));
+        assert!(grid.is_blocked(pos(0, 2)));
+        grid.set_blocked(pos(65, 1), false);
+        grid.set_blocked(pos(70, 0), true);
+        assert_eq!(grid, CollisionGrid::new(TilemapSize { x: 70, y: 2 }));
+    }
+
+    #[test]
+    fn raycast_clear_lines() {
+        let grid = blocked_grid((8, 8), &[(7, 0), (0, 7)]);
+        assert_eq!(grid.raycast(pos(0, 0), pos(6, 6)), None);
+        assert_eq!(grid.raycast(pos(1, 1), pos(6, 3)), None);
+        assert_eq!(grid.raycast(pos(3, 3), pos(3, 3)), None);
+        assert!(grid.line_of_sight(pos(6, 5), pos(1, 0)));
+    }
+
+    #[test]
+    fn raycast_hits_first_blocked_cell() {
+        let grid = blocked_grid((8, 3), &[(3, 1), (5, 1)]);
+        assert_eq!(grid.raycast(pos(0, 1), pos(7, 1)), Some(pos(3, 1)));
+        assert_eq!(grid.raycast(pos(7, 1), pos(0, 1)), Some(pos(5, 1)));
+        // A shallow line passing through the wall's row
+        assert_eq!(grid.raycast(pos(0, 0), pos(7, 2)), Some(pos(3, 1)));
+        assert!(!grid.line_of_sight(pos(0, 1), pos(7, 1)));
+    }
+
+    #[test]
+    fn raycast_blocked_by_corners() {
+        // Diagonal walls: a ray through their shared corner doesn't slip between them
+        let grid = blocked_grid((2, 2), &[(1, 0), (0, 1)]);
+        assert_eq!(grid.raycast(pos(0, 0), pos(1, 1)), Some(pos(1, 0)));
+        let grid = blocked_grid((3, 3), &[(1, 0)]);
+        assert_eq!(grid.raycast(pos(0, 0), pos(2, 2)), Some(pos(1, 0)));
+        assert_eq!(grid.raycast(pos(0, 1), pos(2, 1)), None);
+    }
+
+    #[test]
+    fn raycast_blocked_ends() {
+        let grid = blocked_grid((4, 4), &[(0, 0), (3, 3)]);
+        assert_eq!(grid.raycast(pos(0, 0), pos(2, 0)), Some(pos(0, 0)));
+        assert_eq!(grid.raycast(pos(1, 1), pos(3, 3)), Some(pos(3, 3)));
+        // Cells outside of the grid are blocked
+        assert_eq!(grid.raycast(pos(1, 1), pos(1, 5)), Some(pos(1, 4)));
+    }
+}
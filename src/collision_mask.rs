@@ -0,0 +1,100 @@
+//! Collision painted in an image editor.
+//!
+//! Some teams paint collision as a black and white mask rather than as a Sprite
+//! Fusion layer. [`inject_collision_mask`] turns such a mask, one pixel per tile,
+//! into a collider layer of a loaded [`SpriteFusionMap`], which then spawns like
+//! any other collider layer.
+
+use bevy::{image::TextureAccessError, prelude::*};
+use thiserror::Error;
+
+use crate::core::{SpriteFusionLayer, SpriteFusionMap, SpriteFusionTile};
+
+/// Errors reading a collision mask.
+#[derive(Debug, Error)]
+pub enum CollisionMaskError {
+    /// The mask doesn't have one pixel per tile of the map.
+    #[error("collision mask is {found}, but the map is {expected} tiles")]
+    SizeMismatch {
+        /// Size of the map, in tiles.
+        expected: UVec2,
+        /// Size of the mask, in pixels.
+        found: UVec2,
+    },
+    /// The pixels of the mask can't be read.
+    #[error("can't read collision mask pixels: {0}")]
+    Pixels(#[from] TextureAccessError),
+}
+
+/// Build a collider layer from a collision mask.
+///
+/// The mask must have one pixel per tile, the top-left pixel for the top-left
+/// tile. Bright, opaque pixels are solid; dark or transparent pixels are free.
+/// Solid tiles show the spritesheet tile `tile_id`.
+pub fn collision_layer_from_mask(
+    map: &SpriteFusionMap,
+    mask: &Image,
+    name: impl Into<String>,
+    tile_id: u32,
+) -> Result<SpriteFusionLayer, CollisionMaskError> {
+    let expected = UVec2::new(map.map_width, map.map_height);
+    let found = UVec2::new(mask.width(), mask.height());
+    if found != expected {
+        return Err(CollisionMaskError::SizeMismatch { expected, found });
+    }
+
+    let mut tiles = Vec::new();
+    for y in 0..found.y {
+        for x in 0..found.x {
+            let color = mask.get_color_at(x, y)?.to_srgba();
+            let brightness = (color.red + color.green + color.blue) / 3.0;
+            if color.alpha >= 0.5 && brightness >= 0.5 {
+                tiles.push(SpriteFusionTile {
+                    id: tile_id,
+                    x: x as i32,
+                    y: y as i32,
+                    attributes: None,
+                });
+            }
+        }
+    }
+    Ok(SpriteFusionLayer {
+        name: name.into(),
+        collider: true,
+        tiles,
+    })
+}
+
+/// Add a collider layer built from a collision mask to a map, see
+/// [`collision_layer_from_mask`].
+///
+/// The layer is added last, below every other layer. Its tiles are drawn like
+/// any other, so pick a transparent `tile_id`, or hide the spawned layer with
+/// [`SpriteFusionLayers::hide_layer`](crate::layers::SpriteFusionLayers::hide_layer).
+///
+/// ```rust,ignore
+/// fn add_painted_collision(
+///     level: Res<Level>,
+///     mut maps: ResMut<Assets<SpriteFusionMap>>,
+///     images: Res<Assets<Image>>,
+///     mut done: Local<bool>,
+/// ) {
+///     let (Some(map), Some(mask)) = (maps.get_mut(&level.map), images.get(&level.collision)) else {
+///         return;
+///     };
+///     if !*done {
+///         inject_collision_mask(map, mask, "Collision", 0).unwrap();
+///         *done = true;
+///     }
+/// }
+/// ```
+pub fn inject_collision_mask(
+    map: &mut SpriteFusionMap,
+    mask: &Image,
+    name: impl Into<String>,
+    tile_id: u32,
+) -> Result<(), CollisionMaskError> {
+    let layer = collision_layer_from_mask(map, mask, name, tile_id)?;
+    map.layers.push(layer);
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! Color-key transparency for legacy tilesets.
+//!
+//! Old tilesets often have no alpha channel and mark transparent pixels with a
+//! key color instead, usually magenta. With
+//! [`SpriteFusionSettings::color_key`] set, pixels of that color in map
+//! spritesheets are made transparent once the image is loaded, and again when
+//! it's hot reloaded.
+
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+
+use crate::plugin::{SpriteFusionSettings, SpriteFusionTilesetHandle};
+
+pub(crate) struct SpriteFusionColorKeyPlugin;
+
+impl Plugin for SpriteFusionColorKeyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, apply_color_key);
+    }
+}
+
+/// Make the pixels matching `key` transparent.
+///
+/// Only 8-bit RGBA images are supported, which is what PNG and other common
+/// formats load as. Returns `false` if the image has another format.
+pub fn apply_color_key_to_image(image: &mut Image, key: Color) -> bool {
+    if !is_rgba8(image) {
+        return false;
+    }
+    let [r, g, b, _] = key.to_srgba().to_u8_array();
+    for pixel in image
+        .data
+        .iter_mut()
+        .flat_map(|data| data.chunks_exact_mut(4))
+    {
+        if pixel[..3] == [r, g, b] {
+            pixel.copy_from_slice(&[0, 0, 0, 0]);
+        }
+    }
+    true
+}
+
+fn is_rgba8(image: &Image) -> bool {
+    matches!(
+        image.texture_descriptor.format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    )
+}
+
+fn has_color_key(image: &Image, key: [u8; 3]) -> bool {
+    image
+        .data
+        .iter()
+        .flat_map(|data| data.chunks_exact(4))
+        .any(|pixel| pixel[..3] == key && pixel[3] != 0)
+}
+
+/// System that applies the color key to map spritesheets when they're loaded or
+/// modified.
+fn apply_color_key(
+    mut image_events: MessageReader<AssetEvent<Image>>,
+    tilesets: Query<&SpriteFusionTilesetHandle>,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let Some(key) = settings.color_key else {
+        image_events.clear();
+        return;
+    };
+    let [r, g, b, _] = key.to_srgba().to_u8_array();
+
+    for event in image_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if !tilesets.iter().any(|tileset| tileset.id() == *id) {
+            continue;
+        }
+        // Check first, so keying the image doesn't trigger another `Modified` event
+        let Some(image) = images.get(*id) else {
+            continue;
+        };
+        if !is_rgba8(image) {
+            warn!(
+                "Can't apply color key to tileset with format {:?}",
+                image.texture_descriptor.format
+            );
+            continue;
+        }
+        if !has_color_key(image, [r, g, b]) {
+            continue;
+        }
+        if let Some(image) = images.get_mut(*id) {
+            apply_color_key_to_image(image, key);
+        }
+    }
+}
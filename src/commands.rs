@@ -0,0 +1,208 @@
+//! Command helpers for spawned Sprite Fusion maps.
+
+use std::path::PathBuf;
+
+use bevy::{camera::visibility::RenderLayers, prelude::*};
+
+use crate::{
+    attachments::{self, TileTarget},
+    attributes, audit, cameras, export, floors, plugin, pool,
+    prepare::{self, PreparedMap},
+    state::{self, MapRuntimeState},
+};
+
+/// Extension methods on [`Commands`] for working with Sprite Fusion maps.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use bevy::prelude::*;
+/// use bevy_spritefusion::prelude::*;
+///
+/// fn toggle_map(mut commands: Commands, maps: Query<Entity, With<SpriteFusionMapMarker>>) {
+///     for map in maps.iter() {
+///         commands.hide_map(map);
+///     }
+/// }
+/// ```
+pub trait SpriteFusionCommandsExt {
+    /// Hide a map with all of its layers and tiles.
+    fn hide_map(&mut self, map: Entity);
+
+    /// Show a map previously hidden with [`hide_map`](Self::hide_map).
+    fn show_map(&mut self, map: Entity);
+
+    /// Despawn a map, keeping its tile entities in the
+    /// [`TileEntityPool`](crate::pool::TileEntityPool) for reuse by the next spawned map.
+    fn recycle_map(&mut self, map: Entity);
+
+    /// Despawn the layers, tiles and zones of a map, keeping the map entity with
+    /// its handles, so it can be spawned again with
+    /// [`respawn_spritefusion_map`](Self::respawn_spritefusion_map).
+    fn despawn_spritefusion_map(&mut self, map: Entity);
+
+    /// Despawn the contents of a map and spawn it again from its handles, e.g. after
+    /// swapping its [`SpriteFusionMapHandle`](crate::plugin::SpriteFusionMapHandle).
+    fn respawn_spritefusion_map(&mut self, map: Entity);
+
+    /// Set the render layers of a map and all of its layers.
+    fn set_map_render_layers(&mut self, map: Entity, render_layers: RenderLayers);
+
+    /// Render a map only with `camera` (and cameras sharing its render layers).
+    ///
+    /// Give each camera its own [`RenderLayers`] first, e.g. one layer per player
+    /// in split-screen.
+    fn assign_map_to_camera(&mut self, map: Entity, camera: Entity);
+
+    /// Check all tilemaps for orphan tiles and dangling storage slots, and log
+    /// what was found. With `fix`, the problems are also repaired.
+    ///
+    /// See [`audit_tilemaps`](crate::audit::audit_tilemaps) to get the report instead.
+    fn audit_tilemaps(&mut self, fix: bool);
+
+    /// Show the layers of one floor of a map, see [`Floor`](crate::floors::Floor).
+    fn show_floor(&mut self, map: Entity, floor: i32);
+
+    /// Hide the layers of one floor of a map.
+    fn hide_floor(&mut self, map: Entity, floor: i32);
+
+    /// Show the layers of one floor of a map and hide all the others, e.g. when the
+    /// player goes upstairs.
+    fn show_only_floor(&mut self, map: Entity, floor: i32);
+
+    /// Restore a savegame captured with
+    /// [`serialize_runtime_state`](crate::state::serialize_runtime_state) on a spawned map.
+    fn apply_runtime_state(&mut self, map: Entity, state: MapRuntimeState);
+
+    /// Set an attribute of a spawned tile, keeping markers and derived components
+    /// in sync and sending a [`TileAttributeChanged`](crate::attributes::TileAttributeChanged).
+    ///
+    /// ```rust,ignore
+    /// commands.set_attr(door, "locked", false);
+    /// ```
+    fn set_attr(
+        &mut self,
+        tile: Entity,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    );
+
+    /// Remove an attribute of a spawned tile, see [`set_attr`](Self::set_attr).
+    fn remove_attr(&mut self, tile: Entity, key: impl Into<String>);
+
+    /// Spawn a map prepared with [`prepare_map`](crate::prepare::prepare_map), and
+    /// return its map entity.
+    ///
+    /// The map spawns right away, without waiting for the tileset to load.
+    fn spawn_prepared_map(&mut self, prepared: PreparedMap, tileset: Handle<Image>) -> Entity;
+
+    /// Save the live state of a spawned map to a Sprite Fusion JSON file, see
+    /// [`export_map`](crate::export::export_map). Failures are logged.
+    fn save_spritefusion_map(&mut self, map: Entity, path: impl Into<PathBuf>);
+
+    /// Spawn `bundle` on a tile and keep it there, see
+    /// [`AttachedToTile`](crate::attachments::AttachedToTile). Returns the
+    /// attached entity, despawned with a warning if there's no such tile.
+    ///
+    /// ```rust,ignore
+    /// commands.attach_to_tile(chest_tile, Sprite::from_image(sparkles));
+    /// commands.attach_to_tile((map, "Ground", TilePos { x: 3, y: 7 }), Text2d::new("Shop"));
+    /// ```
+    fn attach_to_tile(&mut self, tile: impl Into<TileTarget>, bundle: impl Bundle) -> Entity;
+}
+
+impl SpriteFusionCommandsExt for Commands<'_, '_> {
+    fn hide_map(&mut self, map: Entity) {
+        self.entity(map).insert(Visibility::Hidden);
+    }
+
+    fn show_map(&mut self, map: Entity) {
+        // Inherited keeps the map hidden if one of its own ancestors is hidden
+        self.entity(map).insert(Visibility::Inherited);
+    }
+
+    fn recycle_map(&mut self, map: Entity) {
+        self.queue(move |world: &mut World| pool::recycle_map(world, map));
+    }
+
+    fn despawn_spritefusion_map(&mut self, map: Entity) {
+        self.queue(move |world: &mut World| plugin::despawn_map(world, map, false));
+    }
+
+    fn respawn_spritefusion_map(&mut self, map: Entity) {
+        self.queue(move |world: &mut World| plugin::despawn_map(world, map, true));
+    }
+
+    fn set_map_render_layers(&mut self, map: Entity, render_layers: RenderLayers) {
+        self.entity(map).insert(render_layers);
+    }
+
+    fn assign_map_to_camera(&mut self, map: Entity, camera: Entity) {
+        self.queue(move |world: &mut World| cameras::assign_map_to_camera(world, map, camera));
+    }
+
+    fn audit_tilemaps(&mut self, fix: bool) {
+        self.queue(move |world: &mut World| audit::audit_and_log(world, fix));
+    }
+
+    fn show_floor(&mut self, map: Entity, floor: i32) {
+        self.queue(move |world: &mut World| {
+            floors::set_floor_visibility(world, map, |f| (f == floor).then_some(true))
+        });
+    }
+
+    fn hide_floor(&mut self, map: Entity, floor: i32) {
+        self.queue(move |world: &mut World| {
+            floors::set_floor_visibility(world, map, |f| (f == floor).then_some(false))
+        });
+    }
+
+    fn show_only_floor(&mut self, map: Entity, floor: i32) {
+        self.queue(move |world: &mut World| {
+            floors::set_floor_visibility(world, map, |f| Some(f == floor))
+        });
+    }
+
+    fn apply_runtime_state(&mut self, map: Entity, runtime_state: MapRuntimeState) {
+        self.queue(move |world: &mut World| state::apply_runtime_state(world, map, &runtime_state));
+    }
+
+    fn set_attr(
+        &mut self,
+        tile: Entity,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) {
+        let (key, value) = (key.into(), value.into());
+        self.queue(move |world: &mut World| {
+            attributes::set_tile_attribute(world, tile, &key, Some(value))
+        });
+    }
+
+    fn remove_attr(&mut self, tile: Entity, key: impl Into<String>) {
+        let key = key.into();
+        self.queue(move |world: &mut World| {
+            attributes::set_tile_attribute(world, tile, &key, None)
+        });
+    }
+
+    fn save_spritefusion_map(&mut self, map: Entity, path: impl Into<PathBuf>) {
+        let path = path.into();
+        self.queue(move |world: &mut World| export::save_and_log(world, map, &path));
+    }
+
+    fn attach_to_tile(&mut self, tile: impl Into<TileTarget>, bundle: impl Bundle) -> Entity {
+        let target = tile.into();
+        let entity = self.spawn(bundle).id();
+        self.queue(move |world: &mut World| attachments::attach(world, entity, &target));
+        entity
+    }
+
+    fn spawn_prepared_map(&mut self, prepared: PreparedMap, tileset: Handle<Image>) -> Entity {
+        let map = self
+            .spawn((Transform::default(), Visibility::default()))
+            .id();
+        self.run_system_cached_with(prepare::spawn_prepared_map, (map, tileset, prepared));
+        map
+    }
+}
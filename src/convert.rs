@@ -0,0 +1,309 @@
+//! Conversion from Sprite Fusion map data to bevy_ecs_tilemap data.
+//!
+//! These functions hold the coordinate and texture-index math used when spawning
+//! maps, for users who want to spawn tilemaps themselves (custom bundles, custom
+//! scheduling) without reimplementing it.
+
+use std::{collections::HashMap, sync::Arc};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{SpriteFusionLayer, SpriteFusionMap, SpriteFusionTile};
+
+/// Get the tilemap size of a map.
+///
+/// The size grows to also cover tiles at negative coordinates, see [`tile_offset`].
+pub fn tilemap_size(map: &SpriteFusionMap) -> TilemapSize {
+    let offset = tile_offset(map);
+    TilemapSize {
+        x: map.map_width + offset.x,
+        y: map.map_height + offset.y,
+    }
+}
+
+/// Get the offset moving every tile of a map to non-negative coordinates.
+///
+/// Hand-edited or composed maps can have tiles at negative x/y. Those are shifted
+/// into tilemap space by this offset, which is zero for regular exports.
+pub fn tile_offset(map: &SpriteFusionMap) -> UVec2 {
+    let (min_x, min_y) = map
+        .layers
+        .iter()
+        .flat_map(|layer| layer.tiles.iter())
+        .fold((0, 0), |(x, y), tile| (tile.x.min(x), tile.y.min(y)));
+    UVec2::new(min_x.unsigned_abs(), min_y.unsigned_abs())
+}
+
+/// Where row 0 of spawned [`TilePos`] coordinates lies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum TileOrigin {
+    /// Row 0 is the bottom row, following bevy_ecs_tilemap. Sprite Fusion rows are flipped.
+    #[default]
+    BottomLeft,
+    /// Row 0 is the top row, keeping Sprite Fusion coordinates as they are.
+    ///
+    /// Layers are then rendered through a transform mirroring Y, so maps look the same
+    /// as with [`TileOrigin::BottomLeft`].
+    TopLeft,
+}
+
+/// What to do when a layer has several tiles at the same position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DuplicateTilePolicy {
+    /// Keep the tile appearing first in the export.
+    KeepFirst,
+    /// Keep the tile appearing last in the export.
+    #[default]
+    KeepLast,
+    /// Refuse to spawn the map and log an error.
+    Error,
+}
+
+/// How spawned layers get their Z coordinate, relative to the map entity.
+///
+/// Use it as [`SpriteFusionSettings::layer_z`](crate::plugin::SpriteFusionSettings::layer_z),
+/// or insert it next to a [`SpriteFusionBundle`](crate::plugin::SpriteFusionBundle)
+/// to override the settings for one map.
+///
+/// ```rust,ignore
+/// // Layers at 10.0, 9.0, 8.0... so sprites at Z 9.5 go between the first two
+/// settings.layer_z = LayerZPolicy::Spacing { base: 10.0, step: -1.0 };
+///
+/// // Per layer, by name
+/// settings.layer_z = LayerZPolicy::Explicit(HashMap::from([
+///     ("Roofs".to_string(), 50.0),
+///     ("Ground".to_string(), 0.0),
+/// ]));
+/// ```
+#[derive(Component, Clone)]
+pub enum LayerZPolicy {
+    /// `base + step * index`, with layer 0 the topmost layer in Sprite Fusion.
+    Spacing {
+        /// Z of the topmost layer.
+        base: f32,
+        /// Z difference between consecutive layers, negative to draw later layers
+        /// below earlier ones.
+        step: f32,
+    },
+    /// Z by layer name. Layers missing from the map fall back to the default spacing.
+    Explicit(HashMap<String, f32>),
+    /// Z computed from the layer index and data.
+    Custom(Arc<LayerZFn>),
+}
+
+/// A function computing the Z of a layer, see [`LayerZPolicy::Custom`].
+pub type LayerZFn = dyn Fn(usize, &SpriteFusionLayer) -> f32 + Send + Sync;
+
+impl LayerZPolicy {
+    /// Compute the Z of a layer with a closure.
+    pub fn custom(f: impl Fn(usize, &SpriteFusionLayer) -> f32 + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(f))
+    }
+
+    /// Get the Z of the layer at `index` in the map.
+    pub fn z(&self, index: usize, layer: &SpriteFusionLayer) -> f32 {
+        match self {
+            Self::Spacing { base, step } => base + step * index as f32,
+            Self::Explicit(z) => z
+                .get(&layer.name)
+                .copied()
+                .unwrap_or_else(|| Self::default().z(index, layer)),
+            Self::Custom(f) => f(index, layer),
+        }
+    }
+}
+
+impl Default for LayerZPolicy {
+    /// In Sprite Fusion, layer 0 is on top and the last layer is the background,
+    /// so each layer goes 0.1 below the previous one.
+    fn default() -> Self {
+        Self::Spacing {
+            base: 0.0,
+            step: -0.1,
+        }
+    }
+}
+
+impl std::fmt::Debug for LayerZPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Spacing { base, step } => f
+                .debug_struct("Spacing")
+                .field("base", base)
+                .field("step", step)
+                .finish(),
+            Self::Explicit(z) => f.debug_tuple("Explicit").field(z).finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// Convert a Sprite Fusion tile position (top-left origin) to a [`TilePos`]
+/// (bottom-left origin).
+///
+/// Returns `None` if the tile lies outside of the map.
+pub fn tile_pos(map: &SpriteFusionMap, x: i32, y: i32) -> Option<TilePos> {
+    tile_pos_with_origin(map, x, y, TileOrigin::BottomLeft)
+}
+
+/// Convert a Sprite Fusion tile position (top-left origin) to a [`TilePos`] using
+/// the given origin.
+///
+/// The [`tile_offset`] of the map is applied, which scans all of its tiles. Returns
+/// `None` if the tile lies outside of the map.
+pub fn tile_pos_with_origin(
+    map: &SpriteFusionMap,
+    x: i32,
+    y: i32,
+    origin: TileOrigin,
+) -> Option<TilePos> {
+    to_tile_pos(x, y, tile_offset(map), &tilemap_size(map), origin)
+}
+
+pub(crate) fn to_tile_pos(
+    x: i32,
+    y: i32,
+    offset: UVec2,
+    map_size: &TilemapSize,
+    origin: TileOrigin,
+) -> Option<TilePos> {
+    let x = u32::try_from(i64::from(x) + i64::from(offset.x)).ok()?;
+    let y = u32::try_from(i64::from(y) + i64::from(offset.y)).ok()?;
+    if x >= map_size.x || y >= map_size.y {
+        return None;
+    }
+    let pos = TilePos { x, y };
+    Some(match origin {
+        TileOrigin::BottomLeft => top_left_to_bottom_left(pos, map_size),
+        TileOrigin::TopLeft => pos,
+    })
+}
+
+/// Get the center of a Sprite Fusion tile in the local space of its map entity.
+///
+/// This doesn't depend on [`TileOrigin`] nor on the [`tile_offset`]: spawned layers
+/// are placed so that tiles end up at the same place in every case.
+pub fn map_local_position(map: &SpriteFusionMap, x: i32, y: i32) -> Vec2 {
+    let tile_size = map.tile_size as f32;
+    Vec2::new(
+        x as f32 * tile_size,
+        (map.map_height as i32 - 1 - y) as f32 * tile_size,
+    )
+}
+
+/// Convert a top-left origin tile position to a bottom-left origin one.
+pub fn top_left_to_bottom_left(pos: TilePos, map_size: &TilemapSize) -> TilePos {
+    TilePos {
+        x: pos.x,
+        y: (map_size.y - 1) - pos.y,
+    }
+}
+
+/// Convert a bottom-left origin tile position to a top-left origin one.
+pub fn bottom_left_to_top_left(pos: TilePos, map_size: &TilemapSize) -> TilePos {
+    // Mirroring rows is its own inverse
+    top_left_to_bottom_left(pos, map_size)
+}
+
+/// Compute the tilemap data of one layer without spawning anything.
+///
+/// Tiles are returned in the deterministic spawn order (row by row, top to bottom).
+/// Tiles outside of the map are skipped. An out of range `layer_index` gives no tiles.
+/// Positions use [`TileOrigin::BottomLeft`], and duplicate tiles are resolved with
+/// [`DuplicateTilePolicy::KeepLast`].
+///
+/// ```rust,ignore
+/// let (size, tiles) = build_tilemap_data(&map, 0);
+/// let mut storage = TileStorage::empty(size);
+/// for (position, texture_index, flip) in tiles {
+///     let tile = commands.spawn((TileBundle { position, texture_index, flip, ..default() }, MyTile)).id();
+///     storage.set(&position, tile);
+/// }
+/// ```
+pub fn build_tilemap_data(
+    map: &SpriteFusionMap,
+    layer_index: usize,
+) -> (TilemapSize, Vec<(TilePos, TileTextureIndex, TileFlip)>) {
+    let tiles = map
+        .layers
+        .get(layer_index)
+        .map(|layer| {
+            let mut tiles = layer_tiles(map, layer, TileOrigin::BottomLeft);
+            // Can't fail with this policy
+            let _ = resolve_duplicates(&mut tiles, DuplicateTilePolicy::KeepLast);
+            tiles
+                .into_iter()
+                .map(|tile| (tile.position, tile.texture_index, tile.flip))
+                .collect()
+        })
+        .unwrap_or_default();
+    (tilemap_size(map), tiles)
+}
+
+/// A tile of a layer with its computed tilemap data.
+pub(crate) struct LayerTile<'a> {
+    pub tile: &'a SpriteFusionTile,
+    /// Index of the tile in its layer's `tiles`.
+    pub index: usize,
+    pub position: TilePos,
+    pub texture_index: TileTextureIndex,
+    pub flip: TileFlip,
+}
+
+/// Compute the tilemap data of a layer, in deterministic spawn order.
+///
+/// With [`TileOrigin::TopLeft`], tiles are flipped vertically to make up for the
+/// mirrored layer transform.
+pub(crate) fn layer_tiles<'a>(
+    map: &SpriteFusionMap,
+    layer: &'a SpriteFusionLayer,
+    origin: TileOrigin,
+) -> Vec<LayerTile<'a>> {
+    let mut tiles: Vec<_> = layer.tiles.iter().enumerate().collect();
+    tiles.sort_by_key(|(_, tile)| (tile.y, tile.x));
+    let offset = tile_offset(map);
+    let map_size = tilemap_size(map);
+
+    tiles
+        .into_iter()
+        .filter_map(|(index, tile)| {
+            Some(LayerTile {
+                tile,
+                index,
+                position: to_tile_pos(tile.x, tile.y, offset, &map_size, origin)?,
+                texture_index: TileTextureIndex(tile.tile_id()),
+                flip: TileFlip {
+                    y: origin == TileOrigin::TopLeft,
+                    ..default()
+                },
+            })
+        })
+        .collect()
+}
+
+/// Drop tiles sharing a position with another tile of the layer, following `policy`.
+///
+/// Expects tiles in the order of [`layer_tiles`]. With [`DuplicateTilePolicy::Error`],
+/// returns the first duplicated tile.
+pub(crate) fn resolve_duplicates<'a>(
+    tiles: &mut Vec<LayerTile<'a>>,
+    policy: DuplicateTilePolicy,
+) -> Result<(), &'a SpriteFusionTile> {
+    // The sort in layer_tiles is stable, so duplicates are adjacent and in export order
+    match policy {
+        DuplicateTilePolicy::KeepFirst => tiles.dedup_by(|a, b| a.position == b.position),
+        DuplicateTilePolicy::KeepLast => {
+            tiles.reverse();
+            tiles.dedup_by(|a, b| a.position == b.position);
+            tiles.reverse();
+        }
+        DuplicateTilePolicy::Error => {
+            if let Some(pair) = tiles.windows(2).find(|w| w[0].position == w[1].position) {
+                return Err(pair[1].tile);
+            }
+        }
+    }
+    Ok(())
+}
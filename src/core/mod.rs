@@ -0,0 +1,229 @@
+//! The Sprite Fusion map data model.
+//!
+//! These types match the JSON export format from Sprite Fusion. They only depend on
+//! `serde`, so map-generation CLIs and build scripts can use them with
+//! `default-features = false`, without pulling in Bevy.
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+};
+
+#[cfg(feature = "bevy")]
+use bevy::{
+    asset::Asset,
+    reflect::{
+        std_traits::ReflectDefault, Reflect, ReflectDeserialize, ReflectSerialize, TypePath,
+    },
+};
+
+mod ops;
+mod validate;
+
+#[cfg(feature = "render")]
+pub(crate) use ops::attributes_footprint;
+pub use ops::{ResizeAnchor, TileRect};
+pub use validate::ValidationError;
+
+/// Parse a SpriteFusion JSON map.
+pub fn parse_map(bytes: &[u8]) -> Result<SpriteFusionMap, serde_json::Error> {
+    serde_json::from_slice(bytes)
+}
+
+/// A complete SpriteFusion map export.
+///
+/// This is the root type that gets deserialized from the SpriteFusion JSON export.
+///
+/// With the `bevy` feature, it implements `Reflect`. JSON values, like
+/// [`properties`](Self::properties) and tile attributes, are reflected as opaque
+/// [`SpriteFusionAttributes`] values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Asset, Reflect))]
+#[serde(rename_all = "camelCase")]
+pub struct SpriteFusionMap {
+    /// Size of each tile in pixels.
+    pub tile_size: u32,
+    /// Width of the map in tiles.
+    pub map_width: u32,
+    /// Height of the map in tiles.
+    pub map_height: u32,
+    /// All layers in the map, ordered from top to bottom (first layer is on top, last is background).
+    pub layers: Vec<SpriteFusionLayer>,
+    /// Optional map-level properties, such as `"music"`.
+    ///
+    /// Sprite Fusion doesn't export these; add them to the JSON by hand or from tooling.
+    #[serde(default, skip_serializing_if = "no_attributes")]
+    pub properties: SpriteFusionAttributes,
+}
+
+impl SpriteFusionMap {
+    /// Parse a map from the bytes of a Sprite Fusion JSON export.
+    ///
+    /// Useful for maps embedded in the executable with `include_bytes!`; add the
+    /// result to `Assets<SpriteFusionMap>` to spawn it like a loaded map.
+    pub fn from_json_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        parse_map(bytes)
+    }
+
+    /// Serialize the map to the Sprite Fusion JSON format, which the Sprite Fusion
+    /// editor can open again.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// A single layer in a SpriteFusion map.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+pub struct SpriteFusionLayer {
+    /// Name of the layer.
+    pub name: String,
+    /// Whether this layer should have collision enabled.
+    #[serde(default)]
+    pub collider: bool,
+    /// All tiles in this layer.
+    pub tiles: Vec<SpriteFusionTile>,
+}
+
+/// A single tile in a SpriteFusion layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+pub struct SpriteFusionTile {
+    /// Tile ID referencing the index in the spritesheet.
+    ///
+    /// Sprite Fusion exports it as a string (e.g. `"0"`, `"1"`); it's parsed when
+    /// the map is deserialized, and a malformed ID fails the whole map. Numbers
+    /// are accepted too, and it's serialized back as a string.
+    #[serde(
+        deserialize_with = "deserialize_tile_id",
+        serialize_with = "serialize_tile_id"
+    )]
+    pub id: u32,
+    /// X position in tile coordinates.
+    pub x: i32,
+    /// Y position in tile coordinates.
+    pub y: i32,
+    /// Optional custom attributes attached to this tile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<SpriteFusionAttributes>,
+}
+
+/// Custom JSON values by name, like map properties and tile attributes.
+///
+/// Derefs to the underlying `HashMap`. With the `bevy` feature, it's reflected
+/// as an opaque value, serialized like in the Sprite Fusion export.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+#[cfg_attr(
+    feature = "bevy",
+    reflect(opaque, Debug, Clone, Default, PartialEq, Serialize, Deserialize)
+)]
+#[serde(transparent)]
+pub struct SpriteFusionAttributes(pub HashMap<String, serde_json::Value>);
+
+impl Deref for SpriteFusionAttributes {
+    type Target = HashMap<String, serde_json::Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for SpriteFusionAttributes {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<HashMap<String, serde_json::Value>> for SpriteFusionAttributes {
+    fn from(values: HashMap<String, serde_json::Value>) -> Self {
+        Self(values)
+    }
+}
+
+impl IntoIterator for SpriteFusionAttributes {
+    type Item = (String, serde_json::Value);
+    type IntoIter = std::collections::hash_map::IntoIter<String, serde_json::Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a SpriteFusionAttributes {
+    type Item = (&'a String, &'a serde_json::Value);
+    type IntoIter = std::collections::hash_map::Iter<'a, String, serde_json::Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+fn no_attributes(attributes: &SpriteFusionAttributes) -> bool {
+    attributes.is_empty()
+}
+
+impl FromIterator<(String, serde_json::Value)> for SpriteFusionAttributes {
+    fn from_iter<I: IntoIterator<Item = (String, serde_json::Value)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl SpriteFusionTile {
+    /// Get the tile ID.
+    pub fn tile_id(&self) -> u32 {
+        self.id
+    }
+}
+
+fn deserialize_tile_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TileId {
+        Number(u32),
+        String(String),
+    }
+
+    match TileId::deserialize(deserializer)? {
+        TileId::Number(id) => Ok(id),
+        TileId::String(id) => id
+            .trim()
+            .parse()
+            .map_err(|_| de::Error::custom(format!("invalid tile ID '{id}'"))),
+    }
+}
+
+fn serialize_tile_id<S: Serializer>(id: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(id)
+}
+
+/// Default attributes of tiles by tile ID, from a sidecar tileset JSON.
+///
+/// Gameplay data shared by every instance of a tile (e.g. every tile 42 is
+/// water) lives here instead of being painted onto each tile in the editor:
+///
+/// ```json
+/// { "42": { "surface": "water" }, "43": { "surface": "sand" } }
+/// ```
+///
+/// See [`SpriteFusionMap::apply_tile_defaults`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Asset, TypePath))]
+#[serde(transparent)]
+pub struct TilesetDefaults {
+    /// Default attributes, by tile ID.
+    pub tiles: HashMap<u32, HashMap<String, serde_json::Value>>,
+}
+
+impl TilesetDefaults {
+    /// Parse tileset defaults from JSON bytes.
+    pub fn from_json_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    /// Get the default attributes of a tile ID.
+    pub fn get(&self, id: u32) -> Option<&HashMap<String, serde_json::Value>> {
+        self.tiles.get(&id)
+    }
+}
@@ -0,0 +1,323 @@
+//! Data operations on Sprite Fusion maps.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem::size_of,
+};
+
+use serde_json::Value;
+
+use super::{SpriteFusionLayer, SpriteFusionMap, SpriteFusionTile, TilesetDefaults};
+
+/// A rectangle of tiles, in Sprite Fusion coordinates (top-left origin).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TileRect {
+    /// X of the left column.
+    pub x: i32,
+    /// Y of the top row.
+    pub y: i32,
+    /// Width in tiles.
+    pub width: u32,
+    /// Height in tiles.
+    pub height: u32,
+}
+
+impl TileRect {
+    /// Create a rectangle from its top-left corner and size.
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Check if a tile position lies inside the rectangle.
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && ((x - self.x) as u32) < self.width
+            && ((y - self.y) as u32) < self.height
+    }
+}
+
+/// Where the existing content stays when resizing a map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ResizeAnchor {
+    /// Keep the top-left corner in place.
+    #[default]
+    TopLeft,
+    /// Keep the top edge in place, centered horizontally.
+    Top,
+    /// Keep the top-right corner in place.
+    TopRight,
+    /// Keep the left edge in place, centered vertically.
+    Left,
+    /// Keep the content centered.
+    Center,
+    /// Keep the right edge in place, centered vertically.
+    Right,
+    /// Keep the bottom-left corner in place.
+    BottomLeft,
+    /// Keep the bottom edge in place, centered horizontally.
+    Bottom,
+    /// Keep the bottom-right corner in place.
+    BottomRight,
+}
+
+impl SpriteFusionMap {
+    /// Flatten several layers into a single new layer.
+    ///
+    /// Where several of the layers have a tile at the same position, the topmost
+    /// layer (lowest index in the map) wins. The merged layer is a collider if any
+    /// of the merged layers is. Names that don't match a layer are ignored.
+    ///
+    /// The map itself is left untouched; push or swap in the returned layer as needed.
+    pub fn merge_layers(&self, names: &[&str], new_name: &str) -> SpriteFusionLayer {
+        let mut occupied = HashSet::new();
+        let mut merged = SpriteFusionLayer {
+            name: new_name.to_string(),
+            collider: false,
+            tiles: Vec::new(),
+        };
+
+        // Layers are ordered top to bottom, so the first tile seen at a position wins
+        for layer in self
+            .layers
+            .iter()
+            .filter(|l| names.contains(&l.name.as_str()))
+        {
+            merged.collider |= layer.collider;
+            for tile in &layer.tiles {
+                if occupied.insert((tile.x, tile.y)) {
+                    merged.tiles.push(tile.clone());
+                }
+            }
+        }
+
+        merged
+    }
+
+    /// Merge default attributes under the attributes of every tile.
+    ///
+    /// Attributes set on a tile win over the defaults of its tile ID.
+    pub fn apply_tile_defaults(&mut self, defaults: &TilesetDefaults) {
+        for tile in self.layers.iter_mut().flat_map(|l| l.tiles.iter_mut()) {
+            let Some(tile_defaults) = defaults.get(tile.tile_id()) else {
+                continue;
+            };
+            let mut attributes = tile_defaults.clone();
+            attributes.extend(tile.attributes.take().unwrap_or_default());
+            tile.attributes = Some(attributes.into());
+        }
+    }
+
+    /// Count how many times each tile id is used in a layer.
+    ///
+    /// Returns `None` if no layer has this name. Ids are sorted, which keeps reports
+    /// built from the histogram stable.
+    ///
+    /// ```rust,ignore
+    /// for (id, count) in map.tile_histogram("Ground").unwrap_or_default() {
+    ///     println!("tile {id}: {count}");
+    /// }
+    /// ```
+    pub fn tile_histogram(&self, layer: &str) -> Option<BTreeMap<u32, usize>> {
+        let layer = self.layers.iter().find(|l| l.name == layer)?;
+        let mut histogram = BTreeMap::new();
+        for tile in &layer.tiles {
+            *histogram.entry(tile.tile_id()).or_insert(0) += 1;
+        }
+        Some(histogram)
+    }
+
+    /// Estimate the memory used by the map data, in bytes.
+    ///
+    /// Counts the structs and their heap allocations, but not allocator overhead.
+    /// Spawned maps keep a copy of this data in their
+    /// `SpriteFusionMapMarker`.
+    pub fn memory_footprint(&self) -> usize {
+        size_of::<Self>()
+            + self.layers.capacity() * size_of::<SpriteFusionLayer>()
+            + self
+                .layers
+                .iter()
+                .map(|layer| {
+                    layer.name.capacity()
+                        + layer.tiles.capacity() * size_of::<SpriteFusionTile>()
+                        + layer
+                            .tiles
+                            .iter()
+                            .map(|tile| {
+                                tile.attributes
+                                    .as_ref()
+                                    .map_or(0, |attrs| attributes_footprint(attrs))
+                            })
+                            .sum::<usize>()
+                })
+                .sum::<usize>()
+    }
+
+    /// Hash the content of the map: its size, layers, tiles and attributes.
+    ///
+    /// The hash is stable across runs and platforms, so it can seed reproducible
+    /// generation per level. Map properties aren't hashed.
+    pub fn content_hash(&self) -> u64 {
+        let mut hash = Fnv1a::default();
+        hash.write_u64(self.tile_size.into());
+        hash.write_u64(self.map_width.into());
+        hash.write_u64(self.map_height.into());
+        for layer in &self.layers {
+            hash.write(layer.name.as_bytes());
+            hash.write_u64(layer.collider.into());
+            for tile in &layer.tiles {
+                hash.write_u64(tile.id.into());
+                hash.write_u64(tile.x as u64);
+                hash.write_u64(tile.y as u64);
+                // Attributes are hashed in key order, as map iteration order varies
+                let mut attrs: Vec<_> = tile.attributes.iter().flatten().collect();
+                attrs.sort_unstable_by_key(|(key, _)| key.as_str());
+                for (key, value) in attrs {
+                    hash.write(key.as_bytes());
+                    hash.write(value.to_string().as_bytes());
+                }
+            }
+        }
+        hash.0
+    }
+
+    /// Crop the map to a rectangle.
+    ///
+    /// The top-left corner of `rect` becomes the new origin and tiles outside of it
+    /// are dropped.
+    pub fn crop(&mut self, rect: TileRect) {
+        for layer in &mut self.layers {
+            layer.tiles.retain(|tile| rect.contains(tile.x, tile.y));
+        }
+        self.map_width = rect.width;
+        self.map_height = rect.height;
+        self.shift(-rect.x, -rect.y);
+    }
+
+    /// Resize the map, placing the existing content according to `anchor`.
+    ///
+    /// Tiles that end up outside of the new size are dropped.
+    pub fn resize(&mut self, new_width: u32, new_height: u32, anchor: ResizeAnchor) {
+        let dw = new_width as i32 - self.map_width as i32;
+        let dh = new_height as i32 - self.map_height as i32;
+        let (dx, dy) = match anchor {
+            ResizeAnchor::TopLeft => (0, 0),
+            ResizeAnchor::Top => (dw / 2, 0),
+            ResizeAnchor::TopRight => (dw, 0),
+            ResizeAnchor::Left => (0, dh / 2),
+            ResizeAnchor::Center => (dw / 2, dh / 2),
+            ResizeAnchor::Right => (dw, dh / 2),
+            ResizeAnchor::BottomLeft => (0, dh),
+            ResizeAnchor::Bottom => (dw / 2, dh),
+            ResizeAnchor::BottomRight => (dw, dh),
+        };
+        self.map_width = new_width;
+        self.map_height = new_height;
+        self.shift(dx, dy);
+    }
+
+    /// Move every tile by (`dx`, `dy`), dropping tiles that leave the map.
+    ///
+    /// Positive `dy` moves tiles down, following Sprite Fusion's top-left origin.
+    pub fn shift(&mut self, dx: i32, dy: i32) {
+        let bounds = TileRect::new(0, 0, self.map_width, self.map_height);
+        for layer in &mut self.layers {
+            for tile in &mut layer.tiles {
+                tile.x += dx;
+                tile.y += dy;
+            }
+            layer.tiles.retain(|tile| bounds.contains(tile.x, tile.y));
+        }
+    }
+
+    /// Get a copy of the map mirrored left to right.
+    ///
+    /// Only tile positions are remapped: the format has no per-tile flip flags yet,
+    /// so the tile graphics themselves are not mirrored.
+    pub fn flip_horizontal(&self) -> Self {
+        let width = self.map_width as i32;
+        self.remapped(self.map_width, self.map_height, |x, y| (width - 1 - x, y))
+    }
+
+    /// Get a copy of the map mirrored top to bottom.
+    ///
+    /// Only tile positions are remapped, see [`flip_horizontal`](Self::flip_horizontal).
+    pub fn flip_vertical(&self) -> Self {
+        let height = self.map_height as i32;
+        self.remapped(self.map_width, self.map_height, |x, y| (x, height - 1 - y))
+    }
+
+    /// Get a copy of the map rotated 90 degrees clockwise.
+    ///
+    /// Width and height are swapped. Only tile positions are remapped, see
+    /// [`flip_horizontal`](Self::flip_horizontal).
+    pub fn rotate_90(&self) -> Self {
+        let height = self.map_height as i32;
+        self.remapped(self.map_height, self.map_width, |x, y| (height - 1 - y, x))
+    }
+
+    fn remapped(&self, width: u32, height: u32, f: impl Fn(i32, i32) -> (i32, i32)) -> Self {
+        let mut map = self.clone();
+        map.map_width = width;
+        map.map_height = height;
+        for tile in map.layers.iter_mut().flat_map(|l| l.tiles.iter_mut()) {
+            (tile.x, tile.y) = f(tile.x, tile.y);
+        }
+        map
+    }
+}
+
+/// 64-bit FNV-1a, a simple hash with the same output on every platform.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        // Separate fields, so "ab" + "c" and "a" + "bc" differ
+        self.0 = (self.0 ^ 0xff).wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+}
+
+/// Estimate the heap memory used by a tile's attributes, in bytes.
+pub(crate) fn attributes_footprint(attrs: &HashMap<String, Value>) -> usize {
+    attrs.capacity() * (size_of::<String>() + size_of::<Value>())
+        + attrs
+            .iter()
+            .map(|(key, value)| key.capacity() + value_footprint(value))
+            .sum::<usize>()
+}
+
+fn value_footprint(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.capacity(),
+        Value::Array(values) => {
+            values.capacity() * size_of::<Value>()
+                + values.iter().map(value_footprint).sum::<usize>()
+        }
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| {
+                size_of::<String>() + size_of::<Value>() + key.capacity() + value_footprint(value)
+            })
+            .sum(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,102 @@
+//! Semantic checks of map data.
+
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use super::SpriteFusionMap;
+
+/// A problem found in a map by [`SpriteFusionMap::validate`].
+///
+/// Positions are in Sprite Fusion coordinates.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationError {
+    /// A tile lies right of or below the map.
+    ///
+    /// Tiles at negative coordinates aren't reported: they're shifted into the
+    /// tilemap when spawned.
+    #[error("layer '{layer}' has a tile at ({x}, {y}), outside of the map")]
+    OutOfBounds {
+        /// Name of the layer.
+        layer: String,
+        /// X position of the tile.
+        x: i32,
+        /// Y position of the tile.
+        y: i32,
+    },
+    /// A layer has several tiles at the same position.
+    #[error("layer '{layer}' has several tiles at ({x}, {y})")]
+    DuplicateTile {
+        /// Name of the layer.
+        layer: String,
+        /// X position of the tiles.
+        x: i32,
+        /// Y position of the tiles.
+        y: i32,
+    },
+    /// A tile ID is past the last tile of the spritesheet.
+    #[error("layer '{layer}' has a tile at ({x}, {y}) with ID {id}, but the spritesheet only has {capacity} tiles")]
+    TileIdOutOfRange {
+        /// Name of the layer.
+        layer: String,
+        /// X position of the tile.
+        x: i32,
+        /// Y position of the tile.
+        y: i32,
+        /// The tile ID.
+        id: u32,
+        /// Number of tiles in the spritesheet.
+        capacity: u32,
+    },
+}
+
+impl SpriteFusionMap {
+    /// Check the map for tiles outside of the map and duplicate tiles.
+    ///
+    /// Malformed tile IDs are already rejected when the map is deserialized.
+    ///
+    /// Returns every problem found, in layer order.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for layer in &self.layers {
+            let mut positions = HashSet::new();
+            for tile in &layer.tiles {
+                if tile.x >= self.map_width as i32 || tile.y >= self.map_height as i32 {
+                    errors.push(ValidationError::OutOfBounds {
+                        layer: layer.name.clone(),
+                        x: tile.x,
+                        y: tile.y,
+                    });
+                }
+                if !positions.insert((tile.x, tile.y)) {
+                    errors.push(ValidationError::DuplicateTile {
+                        layer: layer.name.clone(),
+                        x: tile.x,
+                        y: tile.y,
+                    });
+                }
+            }
+        }
+        errors
+    }
+
+    /// Check that every tile ID fits in a spritesheet of `capacity` tiles.
+    pub fn validate_tile_capacity(&self, capacity: u32) -> Vec<ValidationError> {
+        self.layers
+            .iter()
+            .flat_map(|layer| {
+                layer
+                    .tiles
+                    .iter()
+                    .filter(|tile| tile.tile_id() >= capacity)
+                    .map(|tile| ValidationError::TileIdOutOfRange {
+                        layer: layer.name.clone(),
+                        x: tile.x,
+                        y: tile.y,
+                        id: tile.tile_id(),
+                        capacity,
+                    })
+            })
+            .collect()
+    }
+}
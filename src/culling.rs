@@ -0,0 +1,59 @@
+//! Culling margins around camera views.
+//!
+//! Tilemap chunks and sprites are culled against the camera frustum computed from
+//! its transform, so camera shake applied after that, or a fast pan, can show
+//! chunk edges that weren't rendered yet. A [`CullingMargin`] on a camera grows
+//! its frustum on every side, so everything within the margin is rendered too.
+
+use bevy::{
+    camera::{
+        primitives::{Frustum, HalfSpace},
+        visibility::VisibilitySystems,
+    },
+    prelude::*,
+};
+
+pub(crate) struct SpriteFusionCullingPlugin;
+
+impl Plugin for SpriteFusionCullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            expand_frusta
+                .after(VisibilitySystems::UpdateFrusta)
+                .before(VisibilitySystems::CheckVisibility),
+        );
+    }
+}
+
+/// Component rendering an extra margin around the view of a camera, in world
+/// units.
+///
+/// ```rust,ignore
+/// // Two 16px tiles beyond each edge of the viewport
+/// commands.spawn((Camera2d, CullingMargin::tiles(2, 16.0)));
+/// ```
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct CullingMargin(pub f32);
+
+impl CullingMargin {
+    /// A margin of `count` tiles of `tile_size` world units.
+    pub fn tiles(count: u32, tile_size: f32) -> Self {
+        Self(count as f32 * tile_size)
+    }
+}
+
+/// System that grows the frustum of cameras with a [`CullingMargin`].
+fn expand_frusta(
+    mut cameras: Query<(&CullingMargin, &GlobalTransform, &Projection, &mut Frustum)>,
+) {
+    for (margin, transform, projection, mut frustum) in cameras.iter_mut() {
+        // Start over from the camera's own frustum: it's only recomputed when the
+        // camera moves, and the margin mustn't add up frame after frame
+        let mut expanded = projection.compute_frustum(transform);
+        for half_space in &mut expanded.half_spaces[..Frustum::NEAR_PLANE_IDX] {
+            *half_space = HalfSpace::new(half_space.normal_d() + Vec4::W * margin.0);
+        }
+        *frustum = expanded;
+    }
+}
@@ -0,0 +1,435 @@
+//! Decals splatted on maps: blood, scorch marks, footprints.
+//!
+//! Insert [`MapDecals`] on a map entity, then send [`SpawnDecal`] messages. The
+//! decals of a map are batched into a single mesh drawn over its layers and
+//! clipped to the map, so thousands of decals cost one entity instead of one
+//! sprite each. The number of decals is capped, and they can fade out over time.
+
+use std::collections::{HashMap, VecDeque};
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    geometry::SpriteFusionMapGeometry,
+    types::{MapPaused, SpriteFusionMapMarker},
+};
+
+/// Z of decals above the layer they're drawn on.
+const DECAL_Z_OFFSET: f32 = 0.0005;
+
+/// Plugin that draws the decals of [`SpawnDecal`] messages.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionDecalsPlugin;
+
+impl Plugin for SpriteFusionDecalsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<SpawnDecal>().add_systems(
+            Update,
+            (spawn_decals, fade_decals, update_decal_meshes).chain(),
+        );
+    }
+}
+
+/// Component enabling decals on a map entity.
+///
+/// ```rust,ignore
+/// commands.entity(map).insert(
+///     MapDecals::new(asset_server.load("decals.png"), UVec2::splat(16))
+///         .on_layer("Ground")
+///         .with_max_decals(500)
+///         .with_fade(DecalFade::After { delay: 30.0, duration: 2.0 }),
+/// );
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct MapDecals {
+    /// Spritesheet of the decals, a grid of `frame_size` cells numbered row by row.
+    pub texture: Handle<Image>,
+    /// Size of a cell of the spritesheet, in pixels.
+    pub frame_size: UVec2,
+    /// Layer the decals are drawn just above. `None` draws them above every layer.
+    pub layer: Option<String>,
+    /// Maximum number of decals. Past it, the oldest decal is removed for each new one.
+    pub max_decals: usize,
+    /// How decals fade out.
+    pub fade: DecalFade,
+    decals: VecDeque<Decal>,
+    dirty: bool,
+}
+
+impl MapDecals {
+    /// Decals cut from a spritesheet of `frame_size` cells, above every layer, at
+    /// most 1024 of them, never fading.
+    pub fn new(texture: Handle<Image>, frame_size: UVec2) -> Self {
+        Self {
+            texture,
+            frame_size,
+            layer: None,
+            max_decals: 1024,
+            fade: DecalFade::Never,
+            decals: VecDeque::new(),
+            dirty: true,
+        }
+    }
+
+    /// Draw the decals just above a layer, so upper layers cover them.
+    pub fn on_layer(mut self, layer: impl Into<String>) -> Self {
+        self.layer = Some(layer.into());
+        self
+    }
+
+    /// Set the maximum number of decals.
+    pub fn with_max_decals(mut self, max_decals: usize) -> Self {
+        self.max_decals = max_decals;
+        self
+    }
+
+    /// Set how decals fade out.
+    pub fn with_fade(mut self, fade: DecalFade) -> Self {
+        self.fade = fade;
+        self
+    }
+
+    /// Get the number of decals on the map.
+    pub fn len(&self) -> usize {
+        self.decals.len()
+    }
+
+    /// Check if the map has no decals.
+    pub fn is_empty(&self) -> bool {
+        self.decals.is_empty()
+    }
+
+    /// Remove every decal.
+    pub fn clear(&mut self) {
+        self.decals.clear();
+        self.dirty = true;
+    }
+}
+
+/// How the decals of a map fade out, see [`MapDecals::fade`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DecalFade {
+    /// Decals stay until removed by [`MapDecals::max_decals`].
+    #[default]
+    Never,
+    /// Decals start fading `delay` seconds after they're splatted, and are
+    /// removed `duration` seconds later.
+    After {
+        /// Seconds before a decal starts fading.
+        delay: f32,
+        /// Seconds a decal takes to fade out.
+        duration: f32,
+    },
+}
+
+impl DecalFade {
+    /// Get the opacity of a decal `age` seconds old.
+    fn opacity(self, age: f32) -> f32 {
+        match self {
+            Self::Never => 1.0,
+            Self::After { delay, duration } if duration > 0.0 => {
+                1.0 - ((age - delay) / duration).clamp(0.0, 1.0)
+            }
+            Self::After { delay, .. } => f32::from(age < delay),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Decal {
+    /// Center in the map's local space.
+    position: Vec2,
+    size: Vec2,
+    rotation: f32,
+    frame: u32,
+    color: Color,
+    age: f32,
+}
+
+/// Message splatting a decal on a map with [`MapDecals`].
+///
+/// Decals are clipped to the map; decals sent to maps without [`MapDecals`], or
+/// not spawned yet, are dropped.
+///
+/// ```rust,ignore
+/// fn splat_blood(mut decals: MessageWriter<SpawnDecal>, hits: MessageReader<Hit>) {
+///     for hit in hits.read() {
+///         decals.write(SpawnDecal::new(hit.map, hit.position, 3).with_rotation(hit.angle));
+///     }
+/// }
+/// ```
+#[derive(Message, Debug, Clone)]
+pub struct SpawnDecal {
+    /// The map entity.
+    pub map: Entity,
+    /// World-space center of the decal.
+    pub position: Vec2,
+    /// Cell of the [`MapDecals::texture`] spritesheet.
+    pub frame: u32,
+    /// Size in the map's local space. `None` uses the frame size in pixels.
+    pub size: Option<Vec2>,
+    /// Rotation in radians, counterclockwise.
+    pub rotation: f32,
+    /// Color multiplied with the texture.
+    pub color: Color,
+}
+
+impl SpawnDecal {
+    /// A decal showing `frame` at a world position, unrotated and untinted.
+    pub fn new(map: Entity, position: Vec2, frame: u32) -> Self {
+        Self {
+            map,
+            position,
+            frame,
+            size: None,
+            rotation: 0.0,
+            color: Color::WHITE,
+        }
+    }
+
+    /// Set the size of the decal, in the map's local space.
+    pub fn with_size(mut self, size: Vec2) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Set the rotation of the decal, in radians.
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Tint the decal.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// Entity drawing the decals of a map, a child of the map entity.
+#[derive(Component)]
+struct DecalMesh {
+    map: Entity,
+}
+
+/// System that adds the decals of [`SpawnDecal`] messages to their map.
+fn spawn_decals(
+    mut messages: MessageReader<SpawnDecal>,
+    mut maps: Query<(&mut MapDecals, &SpriteFusionMapGeometry)>,
+) {
+    for message in messages.read() {
+        let Ok((mut decals, geometry)) = maps.get_mut(message.map) else {
+            continue;
+        };
+        let decals = &mut *decals;
+        if decals.max_decals == 0 {
+            continue;
+        }
+        decals.decals.push_back(Decal {
+            position: geometry.world_to_local(message.position),
+            size: message.size.unwrap_or(decals.frame_size.as_vec2()),
+            rotation: message.rotation,
+            frame: message.frame,
+            color: message.color,
+            age: 0.0,
+        });
+        while decals.decals.len() > decals.max_decals {
+            decals.decals.pop_front();
+        }
+        decals.dirty = true;
+    }
+}
+
+/// System that ages decals and removes the ones that faded out.
+fn fade_decals(time: Res<Time>, mut maps: Query<&mut MapDecals, Without<MapPaused>>) {
+    let delta = time.delta_secs();
+    for mut decals in maps.iter_mut() {
+        let DecalFade::After { delay, duration } = decals.fade else {
+            continue;
+        };
+        if decals.decals.is_empty() {
+            continue;
+        }
+        // Aging alone doesn't change the mesh, `dirty` flags the frames that do
+        let decals = decals.bypass_change_detection();
+        let count = decals.decals.len();
+        for decal in decals.decals.iter_mut() {
+            decal.age += delta;
+        }
+        decals.decals.retain(|decal| decal.age < delay + duration);
+        // Fading decals change opacity every frame
+        decals.dirty |=
+            decals.decals.len() != count || decals.decals.iter().any(|decal| decal.age > delay);
+    }
+}
+
+/// System that rebuilds the decal mesh of maps whose decals changed.
+#[allow(clippy::type_complexity)]
+fn update_decal_meshes(
+    mut commands: Commands,
+    mut maps: Query<(
+        Entity,
+        &mut MapDecals,
+        &SpriteFusionMapGeometry,
+        &SpriteFusionMapMarker,
+    )>,
+    decal_meshes: Query<(Entity, &DecalMesh, &Mesh2d, &MeshMaterial2d<ColorMaterial>)>,
+    images: Res<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let existing: HashMap<Entity, _> = decal_meshes
+        .iter()
+        .map(|(entity, decal_mesh, mesh, material)| (decal_mesh.map, (entity, mesh, material)))
+        .collect();
+
+    for (map, mut decals, geometry, marker) in maps.iter_mut() {
+        if !decals.dirty && !decals.is_changed() {
+            continue;
+        }
+        // UVs need the spritesheet size
+        let Some(image) = images.get(&decals.texture) else {
+            continue;
+        };
+        // Keep the next frame from seeing this as a user change
+        decals.bypass_change_detection().dirty = false;
+
+        let mesh = decal_mesh(&decals, geometry, image.size());
+        let z = decal_z(&decals, geometry, marker);
+        match existing.get(&map) {
+            Some(&(entity, mesh_handle, material)) => {
+                if let Some(target) = meshes.get_mut(&mesh_handle.0) {
+                    *target = mesh;
+                }
+                if let Some(material) = materials.get_mut(&material.0) {
+                    material.texture = Some(decals.texture.clone());
+                }
+                commands
+                    .entity(entity)
+                    .insert(Transform::from_xyz(0.0, 0.0, z));
+            }
+            None => {
+                commands.spawn((
+                    DecalMesh { map },
+                    Mesh2d(meshes.add(mesh)),
+                    MeshMaterial2d(materials.add(ColorMaterial::from(decals.texture.clone()))),
+                    Transform::from_xyz(0.0, 0.0, z),
+                    ChildOf(map),
+                ));
+            }
+        }
+    }
+}
+
+/// Get the Z of the decals of a map, relative to the map entity.
+fn decal_z(
+    decals: &MapDecals,
+    geometry: &SpriteFusionMapGeometry,
+    marker: &SpriteFusionMapMarker,
+) -> f32 {
+    let layer_z = match &decals.layer {
+        Some(layer) => geometry.layer_z(layer),
+        None => marker
+            .map
+            .layers
+            .iter()
+            .filter_map(|layer| geometry.layer_z(&layer.name))
+            .reduce(f32::max),
+    };
+    layer_z.unwrap_or_default() + DECAL_Z_OFFSET
+}
+
+/// Build the mesh of the decals of a map, each clipped to the map bounds.
+fn decal_mesh(decals: &MapDecals, geometry: &SpriteFusionMapGeometry, image_size: UVec2) -> Mesh {
+    let last = TilePos {
+        x: geometry.size.x.saturating_sub(1),
+        y: geometry.size.y.saturating_sub(1),
+    };
+    let bounds = Rect::from_corners(
+        geometry.tile_to_local(TilePos { x: 0, y: 0 }),
+        geometry.tile_to_local(last),
+    )
+    .inflate(geometry.tile_size / 2.0);
+
+    let frame_size = decals.frame_size.max(UVec2::ONE);
+    let columns = (image_size.x / frame_size.x).max(1);
+    let uv_size = frame_size.as_vec2() / image_size.max(UVec2::ONE).as_vec2();
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for decal in &decals.decals {
+        let uv_min = UVec2::new(decal.frame % columns, decal.frame / columns).as_vec2() * uv_size;
+        let uv_max = uv_min + uv_size;
+        let rotation = Vec2::from_angle(decal.rotation);
+        let half = decal.size / 2.0;
+        // Image rows go down, so the top of the quad samples the top of the cell
+        let quad = [
+            (Vec2::new(-half.x, half.y), uv_min),
+            (Vec2::new(half.x, half.y), Vec2::new(uv_max.x, uv_min.y)),
+            (Vec2::new(half.x, -half.y), uv_max),
+            (Vec2::new(-half.x, -half.y), Vec2::new(uv_min.x, uv_max.y)),
+        ]
+        .map(|(corner, uv)| (decal.position + rotation.rotate(corner), uv));
+
+        let polygon = clip_polygon(quad.to_vec(), bounds);
+        if polygon.len() < 3 {
+            continue;
+        }
+        let mut color = decal.color.to_linear();
+        color.alpha *= decals.fade.opacity(decal.age);
+        let first = positions.len() as u32;
+        for (position, uv) in &polygon {
+            positions.push(position.extend(0.0).to_array());
+            uvs.push(uv.to_array());
+            colors.push(color.to_f32_array());
+        }
+        // Clipped quads stay convex, so a fan covers them
+        for i in 1..polygon.len() as u32 - 1 {
+            indices.extend_from_slice(&[first, first + i, first + i + 1]);
+        }
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// Clip a convex polygon of positions and UVs to a rectangle, with the
+/// Sutherland-Hodgman algorithm.
+fn clip_polygon(mut polygon: Vec<(Vec2, Vec2)>, bounds: Rect) -> Vec<(Vec2, Vec2)> {
+    let planes = [
+        (Vec2::X, bounds.min.x),
+        (Vec2::NEG_X, -bounds.max.x),
+        (Vec2::Y, bounds.min.y),
+        (Vec2::NEG_Y, -bounds.max.y),
+    ];
+    for (normal, offset) in planes {
+        let input = std::mem::take(&mut polygon);
+        for (i, &(a, a_uv)) in input.iter().enumerate() {
+            let (b, b_uv) = input[(i + 1) % input.len()];
+            let a_dist = normal.dot(a) - offset;
+            let b_dist = normal.dot(b) - offset;
+            if a_dist >= 0.0 {
+                polygon.push((a, a_uv));
+            }
+            if (a_dist >= 0.0) != (b_dist >= 0.0) {
+                let t = a_dist / (a_dist - b_dist);
+                polygon.push((a.lerp(b, t), a_uv.lerp(b_uv, t)));
+            }
+        }
+    }
+    polygon
+}
@@ -0,0 +1,144 @@
+//! Memory usage estimates of spawned maps.
+
+use std::mem::size_of;
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    image::TextureFormatPixelInfo,
+    prelude::*,
+};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::AttributeStore,
+    core::attributes_footprint,
+    plugin::SpriteFusionTilesetHandle,
+    types::{SpriteFusionLayerMarker, SpriteFusionMapMarker, TileSpawnIndex},
+};
+
+/// Plugin that estimates the memory used by each spawned map.
+///
+/// Each map gets a [`MapMemoryEstimate`], and the totals over all maps are
+/// reported as diagnostics, e.g. through `LogDiagnosticsPlugin`. Not added by
+/// [`SpriteFusionPlugin`](crate::plugin::SpriteFusionPlugin).
+///
+/// ```rust,ignore
+/// app.add_plugins((SpriteFusionDiagnosticsPlugin, LogDiagnosticsPlugin::default()));
+/// ```
+pub struct SpriteFusionDiagnosticsPlugin;
+
+impl SpriteFusionDiagnosticsPlugin {
+    /// Estimated memory used by all spawned maps, in bytes.
+    pub const MAP_MEMORY: DiagnosticPath = DiagnosticPath::const_new("spritefusion/map_memory");
+    /// Number of entities of all spawned maps.
+    pub const MAP_ENTITIES: DiagnosticPath = DiagnosticPath::const_new("spritefusion/map_entities");
+}
+
+impl Plugin for SpriteFusionDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::MAP_MEMORY).with_suffix(" B"))
+            .register_diagnostic(Diagnostic::new(Self::MAP_ENTITIES))
+            .add_systems(
+                PostUpdate,
+                (estimate_map_memory, measure_map_memory).chain(),
+            );
+    }
+}
+
+/// Estimated memory used by a spawned map.
+///
+/// Computed once when the map spawns, so later runtime edits aren't reflected.
+/// Figures leave out allocator and ECS bookkeeping overhead; use them to compare
+/// maps and spawn options rather than as exact numbers.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MapMemoryEstimate {
+    /// Entities of the map: the map itself, its layers and its tiles.
+    pub entities: usize,
+    /// Components of those entities and tile storages, attributes included.
+    pub component_bytes: usize,
+    /// Tileset texture, once uploaded to the GPU.
+    pub texture_bytes: usize,
+    /// Copy of the map data kept in [`SpriteFusionMapMarker`].
+    pub data_bytes: usize,
+}
+
+impl MapMemoryEstimate {
+    /// Total estimated bytes.
+    pub fn total(&self) -> usize {
+        self.component_bytes + self.texture_bytes + self.data_bytes
+    }
+}
+
+/// Estimated size of the components every spawned tile has.
+const TILE_BYTES: usize = size_of::<TileBundle>()
+    + size_of::<ChildOf>()
+    + size_of::<TileSpawnIndex>()
+    // Entry in the layer's `Children`
+    + size_of::<Entity>();
+
+/// System that computes the estimate of newly spawned maps.
+#[allow(clippy::type_complexity)]
+fn estimate_map_memory(
+    mut commands: Commands,
+    maps: Query<
+        (
+            Entity,
+            &SpriteFusionMapMarker,
+            &SpriteFusionTilesetHandle,
+            Option<&Children>,
+            Option<&AttributeStore>,
+        ),
+        Without<MapMemoryEstimate>,
+    >,
+    layers: Query<&TileStorage, With<SpriteFusionLayerMarker>>,
+    images: Res<Assets<Image>>,
+) {
+    for (entity, marker, tileset, children, attribute_store) in maps.iter() {
+        let mut estimate = MapMemoryEstimate {
+            entities: 1,
+            data_bytes: marker.map.memory_footprint(),
+            ..default()
+        };
+
+        for storage in children
+            .into_iter()
+            .flatten()
+            .filter_map(|c| layers.get(*c).ok())
+        {
+            let tiles = storage.iter().flatten().count();
+            estimate.entities += 1 + tiles;
+            estimate.component_bytes +=
+                tiles * TILE_BYTES + storage.size.count() * size_of::<Option<Entity>>();
+        }
+
+        estimate.component_bytes += match attribute_store {
+            Some(store) => store.memory_footprint(),
+            None => marker
+                .map
+                .layers
+                .iter()
+                .flat_map(|layer| layer.tiles.iter())
+                .filter_map(|tile| tile.attributes.as_ref())
+                .filter(|attrs| !attrs.is_empty())
+                .map(|attrs| attributes_footprint(attrs))
+                .sum(),
+        };
+
+        if let Some(image) = images.get(&tileset.0) {
+            let pixel_size = image.texture_descriptor.format.pixel_size().unwrap_or(4);
+            estimate.texture_bytes = image.width() as usize * image.height() as usize * pixel_size;
+        }
+
+        commands.entity(entity).insert(estimate);
+    }
+}
+
+/// System that reports the totals over all maps.
+fn measure_map_memory(mut diagnostics: Diagnostics, estimates: Query<&MapMemoryEstimate>) {
+    diagnostics.add_measurement(&SpriteFusionDiagnosticsPlugin::MAP_MEMORY, || {
+        estimates.iter().map(|e| e.total()).sum::<usize>() as f64
+    });
+    diagnostics.add_measurement(&SpriteFusionDiagnosticsPlugin::MAP_ENTITIES, || {
+        estimates.iter().map(|e| e.entities).sum::<usize>() as f64
+    });
+}
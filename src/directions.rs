@@ -0,0 +1,188 @@
+//! Direction tiles: conveyor belts, currents, wind and one-way corridors.
+//!
+//! Tiles with a `"dir"` attribute set to `"N"`, `"S"`, `"E"` or `"W"` make up
+//! the [`DirectionGrid`] of their map. Entities with a [`Pushable`] component
+//! standing on one are pushed along its direction; one-way corridors can read
+//! [`DirectionGrid::direction_at`] to reject moves against the flow.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    attributes::reserved_attribute,
+    plugin::SpriteFusionSettings,
+    types::{MapPaused, SpriteFusionMap, SpriteFusionMapMarker},
+};
+
+/// Attribute giving the direction of a tile.
+pub const DIRECTION_ATTRIBUTE: &str = "dir";
+
+/// Plugin that adds the [`DirectionGrid`] of spawned maps and moves
+/// [`Pushable`] entities along it.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionDirectionsPlugin;
+
+impl Plugin for SpriteFusionDirectionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (insert_direction_grids, push_entities).chain());
+    }
+}
+
+/// A cardinal direction, as in Sprite Fusion where north is up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileDirection {
+    /// Up, `"N"`.
+    North,
+    /// Down, `"S"`.
+    South,
+    /// Right, `"E"`.
+    East,
+    /// Left, `"W"`.
+    West,
+}
+
+impl TileDirection {
+    /// Parse a `"dir"` attribute value.
+    pub fn from_attribute(value: &str) -> Option<Self> {
+        match value {
+            "N" => Some(Self::North),
+            "S" => Some(Self::South),
+            "E" => Some(Self::East),
+            "W" => Some(Self::West),
+            _ => None,
+        }
+    }
+
+    /// Unit vector of the direction in the local space of the map entity.
+    pub fn vector(self) -> Vec2 {
+        match self {
+            Self::North => Vec2::Y,
+            Self::South => Vec2::NEG_Y,
+            Self::East => Vec2::X,
+            Self::West => Vec2::NEG_X,
+        }
+    }
+
+    /// The opposite direction.
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::North => Self::South,
+            Self::South => Self::North,
+            Self::East => Self::West,
+            Self::West => Self::East,
+        }
+    }
+}
+
+/// Direction tiles of a spawned map.
+#[derive(Component, Debug, Clone, Default)]
+pub struct DirectionGrid {
+    tiles: HashMap<(i32, i32), TileDirection>,
+    tile_size: f32,
+    map_height: i32,
+}
+
+impl DirectionGrid {
+    /// Collect the direction tiles of a map.
+    ///
+    /// Where several layers have a direction tile at the same position, the
+    /// topmost one wins. Attributes are read with the given
+    /// [`SpriteFusionSettings::attribute_prefix`].
+    pub fn from_map(map: &SpriteFusionMap, prefix: &str) -> Self {
+        let key = reserved_attribute(prefix, DIRECTION_ATTRIBUTE);
+        let mut tiles = HashMap::new();
+        // Walk layers bottom to top, so upper layers overwrite lower ones
+        for tile in map.layers.iter().rev().flat_map(|layer| layer.tiles.iter()) {
+            let direction = tile
+                .attributes
+                .as_ref()
+                .and_then(|attrs| attrs.get(&*key))
+                .and_then(|v| v.as_str())
+                .and_then(TileDirection::from_attribute);
+            if let Some(direction) = direction {
+                tiles.insert((tile.x, tile.y), direction);
+            }
+        }
+        Self {
+            tiles,
+            tile_size: map.tile_size as f32,
+            map_height: map.map_height as i32,
+        }
+    }
+
+    /// Check if the map has no direction tiles.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Get the direction at a position, in Sprite Fusion coordinates.
+    pub fn direction_at(&self, x: i32, y: i32) -> Option<TileDirection> {
+        self.tiles.get(&(x, y)).copied()
+    }
+
+    /// Get the direction at a position in the local space of the map entity.
+    pub fn direction_at_local(&self, local: Vec2) -> Option<TileDirection> {
+        let x = (local.x / self.tile_size).round() as i32;
+        let y = self.map_height - 1 - (local.y / self.tile_size).round() as i32;
+        self.direction_at(x, y)
+    }
+
+    /// Iterate over the direction tiles, in Sprite Fusion coordinates.
+    pub fn iter(&self) -> impl Iterator<Item = ((i32, i32), TileDirection)> + '_ {
+        self.tiles.iter().map(|(&pos, &direction)| (pos, direction))
+    }
+}
+
+/// Component for entities pushed along the direction tiles they stand on.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Pushable {
+    /// Push speed, in tiles per second.
+    pub speed: f32,
+}
+
+impl Default for Pushable {
+    fn default() -> Self {
+        Self { speed: 2.0 }
+    }
+}
+
+/// System that adds the direction grid of newly spawned maps.
+fn insert_direction_grids(
+    mut commands: Commands,
+    maps: Query<(Entity, &SpriteFusionMapMarker), Added<SpriteFusionMapMarker>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (entity, marker) in maps.iter() {
+        let grid = DirectionGrid::from_map(&marker.map, &settings.attribute_prefix);
+        if !grid.is_empty() {
+            commands.entity(entity).insert(grid);
+        }
+    }
+}
+
+/// System that moves [`Pushable`] entities along the direction tiles under them.
+fn push_entities(
+    time: Res<Time>,
+    mut entities: Query<(&Pushable, &mut Transform, &GlobalTransform)>,
+    maps: Query<(&DirectionGrid, &GlobalTransform), Without<MapPaused>>,
+) {
+    for (pushable, mut transform, global) in entities.iter_mut() {
+        let position = global.translation();
+        for (grid, map_transform) in maps.iter() {
+            let local = map_transform
+                .affine()
+                .inverse()
+                .transform_point3(position)
+                .truncate();
+            let Some(direction) = grid.direction_at_local(local) else {
+                continue;
+            };
+            let step = direction.vector() * pushable.speed * grid.tile_size * time.delta_secs();
+            let delta = map_transform.affine().transform_vector3(step.extend(0.0));
+            transform.translation += delta.with_z(0.0);
+            break;
+        }
+    }
+}
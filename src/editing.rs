@@ -0,0 +1,252 @@
+//! Editing the tiles of spawned maps at runtime.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::PendingTileAttributes,
+    convert::TileOrigin,
+    core::SpriteFusionTile,
+    geometry::SpriteFusionMapGeometry,
+    hooks::SpriteFusionHookRegistry,
+    pathfinding::{tile_move_cost, MoveCosts, MOVE_COST_ATTRIBUTE},
+    plugin::{SpriteFusionSettings, TextureIndexOffsets},
+    prepare::{NextTileSpawnIndex, PreparedAttributes, PreparedTile},
+    streaming::StreamedChunks,
+    tile_index::SpriteFusionTileIndex,
+    types::{Collider, SpriteFusionLayerMarker, SpriteFusionMapMarker, TileSpawnIndex},
+};
+
+/// A [`SystemParam`] placing, changing and removing tiles of spawned maps.
+///
+/// Edits keep the [`TileStorage`] of the layer, the [`TileTextureIndex`] of the
+/// tile and the map data in [`SpriteFusionMapMarker`] in sync, so systems reading
+/// any of them see the same map. Layers are found by name, and positions are the
+/// [`TilePos`] of the spawned tiles.
+///
+/// On maps with [`MapStreaming`](crate::streaming::MapStreaming), edits in
+/// chunks that aren't spawned only change the map data: the tiles show up
+/// edited when their chunk spawns.
+///
+/// ```rust,ignore
+/// fn break_wall(mut map_commands: SpriteFusionMapCommands, hits: MessageReader<WallHit>) {
+///     for hit in hits.read() {
+///         map_commands.remove_tile(hit.map, "Walls", hit.tile_pos);
+///         map_commands.set_tile(hit.map, "Ground", hit.tile_pos, RUBBLE_TILE);
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct SpriteFusionMapCommands<'w, 's> {
+    commands: Commands<'w, 's>,
+    maps: Query<
+        'w,
+        's,
+        (
+            &'static mut SpriteFusionMapMarker,
+            &'static SpriteFusionMapGeometry,
+            Option<&'static TextureIndexOffsets>,
+            &'static mut NextTileSpawnIndex,
+        ),
+    >,
+    layers: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static SpriteFusionLayerMarker,
+            &'static mut TileStorage,
+        ),
+    >,
+    textures: Query<'w, 's, &'static mut TileTextureIndex>,
+    streamed: Query<'w, 's, &'static mut StreamedChunks>,
+    move_costs: Query<'w, 's, &'static mut MoveCosts>,
+    tile_indices: Query<'w, 's, &'static mut SpriteFusionTileIndex>,
+    pending_attributes: ResMut<'w, PendingTileAttributes>,
+    hooks: Res<'w, SpriteFusionHookRegistry>,
+    settings: Res<'w, SpriteFusionSettings>,
+}
+
+impl SpriteFusionMapCommands<'_, '_> {
+    /// Get the tile entity at a position of a layer.
+    pub fn get_tile_entity(&self, map: Entity, layer: &str, tile_pos: TilePos) -> Option<Entity> {
+        let (_, _, storage) = self.find_layer(map, layer)?;
+        storage.checked_get(&tile_pos)
+    }
+
+    /// Show the spritesheet tile `tile_id` at a position of a layer, spawning a
+    /// tile there if there's none.
+    ///
+    /// Returns the tile entity, or `None` if the map has no such layer, the
+    /// position is outside of it or its streamed chunk isn't spawned. New tiles
+    /// get the next [`TileSpawnIndex`] of the map and run the
+    /// [`on_tile_spawn`](crate::hooks::SpriteFusionHooks::on_tile_spawn) hooks.
+    pub fn set_tile(
+        &mut self,
+        map: Entity,
+        layer: &str,
+        tile_pos: TilePos,
+        tile_id: u32,
+    ) -> Option<Entity> {
+        let (layer_entity, layer_marker, _) = self.find_layer(map, layer)?;
+        let (layer_index, collider) = (layer_marker.index, layer_marker.collider);
+        let (mut marker, geometry, offsets, mut next_spawn_index) = self.maps.get_mut(map).ok()?;
+        if !in_bounds(&geometry.size, tile_pos) {
+            return None;
+        }
+        let texture_index = TileTextureIndex(tile_id + offsets.map_or(0, |o| o.get(layer)));
+        let flip_y = geometry.tile_origin == TileOrigin::TopLeft;
+        let pos = geometry.sprite_fusion_pos(tile_pos);
+
+        // Update the map data first, the spawn hooks read it
+        let layer_data = marker.map.layers.get_mut(layer_index)?;
+        let mut data_index = None;
+        for (index, tile) in layer_data.tiles.iter_mut().enumerate() {
+            if tile.x == pos.x && tile.y == pos.y {
+                tile.id = tile_id;
+                data_index.get_or_insert(index);
+            }
+        }
+        let data_index = data_index.unwrap_or_else(|| {
+            layer_data.tiles.push(SpriteFusionTile {
+                id: tile_id,
+                x: pos.x,
+                y: pos.y,
+                attributes: None,
+            });
+            layer_data.tiles.len() - 1
+        });
+
+        // Tiles of streamed chunks keep the spawn index of their chunk data
+        let mut spawn_index = None;
+        if let Ok(mut streamed) = self.streamed.get_mut(map) {
+            spawn_index = streamed.set_tile(
+                layer_index,
+                PreparedTile {
+                    index: data_index,
+                    x: tile_pos.x,
+                    y: tile_pos.y,
+                    texture_index: texture_index.0,
+                    flip_y,
+                    attributes: PreparedAttributes::None,
+                },
+                &mut next_spawn_index,
+            );
+            if !streamed.is_loaded(tile_pos) {
+                return None;
+            }
+        }
+
+        let (_, _, mut storage) = self.layers.get_mut(layer_entity).ok()?;
+        if let Some(tile) = storage.checked_get(&tile_pos) {
+            if let Ok(mut texture) = self.textures.get_mut(tile) {
+                texture.set_if_neq(texture_index);
+                return Some(tile);
+            }
+        }
+
+        let mut tile_commands = self.commands.spawn((
+            TileBundle {
+                position: tile_pos,
+                tilemap_id: TilemapId(layer_entity),
+                texture_index,
+                flip: TileFlip {
+                    y: flip_y,
+                    ..default()
+                },
+                ..default()
+            },
+            ChildOf(layer_entity),
+            TileSpawnIndex(spawn_index.unwrap_or_else(|| next_spawn_index.take())),
+        ));
+        if collider {
+            tile_commands.insert(Collider);
+        }
+        let layer_data = &marker.map.layers[layer_index];
+        self.hooks.tile_spawned(
+            &mut tile_commands,
+            &layer_data.tiles[data_index],
+            layer_data,
+        );
+        let tile = tile_commands.id();
+        storage.set(&tile_pos, tile);
+        if let Ok(mut index) = self.tile_indices.get_mut(map) {
+            index.set_tile(layer_entity, tile_pos, Some(tile));
+        }
+        Some(tile)
+    }
+
+    /// Despawn the tile at a position of a layer.
+    ///
+    /// Returns the despawned tile entity, or `None` if there was no tile
+    /// entity.
+    pub fn remove_tile(&mut self, map: Entity, layer: &str, tile_pos: TilePos) -> Option<Entity> {
+        let (layer_entity, layer_marker, _) = self.find_layer(map, layer)?;
+        let layer_index = layer_marker.index;
+        if let Ok((mut marker, geometry, _, _)) = self.maps.get_mut(map) {
+            let pos = geometry.sprite_fusion_pos(tile_pos);
+            if let Some(layer_data) = marker.map.layers.get_mut(layer_index) {
+                let removed: Vec<usize> = layer_data
+                    .tiles
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, tile)| tile.x == pos.x && tile.y == pos.y)
+                    .map(|(index, _)| index)
+                    .collect();
+                let key = self.settings.reserved_attribute(MOVE_COST_ATTRIBUTE);
+                let had_cost = removed
+                    .iter()
+                    .any(|&index| tile_move_cost(&layer_data.tiles[index], &key).is_some());
+                layer_data
+                    .tiles
+                    .retain(|tile| tile.x != pos.x || tile.y != pos.y);
+                if had_cost {
+                    if let Ok(mut costs) = self.move_costs.get_mut(map) {
+                        costs.update_cell(
+                            &marker.map,
+                            geometry,
+                            tile_pos,
+                            &self.settings.attribute_prefix,
+                        );
+                    }
+                }
+                // Queued attributes and streamed chunks refer to tiles by index
+                self.pending_attributes
+                    .remove_tiles(map, layer_index, &removed);
+                if let Ok(mut streamed) = self.streamed.get_mut(map) {
+                    streamed.remove_tiles(layer_index, tile_pos, &removed);
+                }
+            }
+        }
+
+        let (_, _, mut storage) = self.layers.get_mut(layer_entity).ok()?;
+        let tile = storage.checked_get(&tile_pos)?;
+        storage.remove(&tile_pos);
+        if let Ok(mut index) = self.tile_indices.get_mut(map) {
+            index.set_tile(layer_entity, tile_pos, None);
+        }
+        self.commands.entity(tile).despawn();
+        Some(tile)
+    }
+
+    fn find_layer(
+        &self,
+        map: Entity,
+        layer: &str,
+    ) -> Option<(Entity, &SpriteFusionLayerMarker, &TileStorage)> {
+        self.layers
+            .iter()
+            .find(|(_, marker, _)| marker.map == map && marker.name == layer)
+    }
+}
+
+/// Get the index a map data tile moved to once the tiles at the sorted indices
+/// `removed` were removed from its layer, or `None` if it was one of them.
+pub(crate) fn remap_tile_index(removed: &[usize], index: usize) -> Option<usize> {
+    let before = removed.partition_point(|&r| r < index);
+    (removed.get(before) != Some(&index)).then_some(index - before)
+}
+
+fn in_bounds(size: &TilemapSize, tile_pos: TilePos) -> bool {
+    tile_pos.x < size.x && tile_pos.y < size.y
+}
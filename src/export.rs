@@ -0,0 +1,131 @@
+//! Exporting spawned maps back to Sprite Fusion data.
+//!
+//! [`export_map`] rebuilds a [`SpriteFusionMap`] from the live state of a spawned
+//! map, so in-game editors can save their edits as JSON with
+//! [`SpriteFusionMap::to_json`] and open them again in the Sprite Fusion editor.
+
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::{AttrKey, AttributeStore},
+    core::{SpriteFusionMap, SpriteFusionTile},
+    geometry::SpriteFusionMapGeometry,
+    plugin::TextureIndexOffsets,
+    streaming::StreamedChunks,
+    types::{SpriteFusionLayerMarker, SpriteFusionMapMarker, TileAttributes},
+};
+
+/// Rebuild the map data of a spawned map from its tile storages, tile textures
+/// and tile attributes.
+///
+/// Tiles placed, changed or removed at runtime are included. Layer order,
+/// collider flags, map properties and layers skipped when spawning come from the
+/// map data in [`SpriteFusionMapMarker`]. Tile IDs are the
+/// [`TileTextureIndex`] minus the [`TextureIndexOffsets`] of the map. Attributes
+/// are exported as the tiles hold them, so localized values and merged tileset
+/// defaults are saved as is, and tiles whose attributes are still pending (see
+/// [`SpriteFusionSettings::attribute_budget`](crate::plugin::SpriteFusionSettings::attribute_budget))
+/// are saved without any.
+///
+/// On maps with [`MapStreaming`](crate::streaming::MapStreaming), tiles of
+/// chunks that aren't spawned are taken from the map data, which tile edits
+/// keep up to date.
+///
+/// Returns `None` if `map` isn't a spawned map.
+///
+/// ```rust,ignore
+/// fn save_level(world: &mut World) {
+///     let map = world.query_filtered::<Entity, With<SpriteFusionMapMarker>>().single(world).unwrap();
+///     if let Some(data) = export_map(world, map) {
+///         std::fs::write("assets/level.sf.json", data.to_json().unwrap()).unwrap();
+///     }
+/// }
+/// ```
+pub fn export_map(world: &World, map: Entity) -> Option<SpriteFusionMap> {
+    let marker = world.get::<SpriteFusionMapMarker>(map)?;
+    let geometry = world.get::<SpriteFusionMapGeometry>(map)?;
+    let offsets = world.get::<TextureIndexOffsets>(map);
+    let store = world.get::<AttributeStore>(map);
+    let streamed = world.get::<StreamedChunks>(map);
+    let mut data = marker.map.clone();
+
+    for child in world.get::<Children>(map).into_iter().flatten() {
+        let (Some(layer), Some(storage)) = (
+            world.get::<SpriteFusionLayerMarker>(*child),
+            world.get::<TileStorage>(*child),
+        ) else {
+            continue;
+        };
+        let Some(layer_data) = data.layers.get_mut(layer.index) else {
+            continue;
+        };
+        let texture_offset = offsets.map_or(0, |offsets| offsets.get(&layer.name));
+
+        // Tiles of streamed chunks that aren't spawned only live in the map data
+        let mut tiles: Vec<SpriteFusionTile> = streamed
+            .into_iter()
+            .flat_map(|streamed| {
+                layer_data.tiles.iter().filter(move |tile| {
+                    geometry
+                        .tile_pos(tile.x, tile.y)
+                        .is_some_and(|tile_pos| !streamed.is_loaded(tile_pos))
+                })
+            })
+            .cloned()
+            .collect();
+        for y in 0..storage.size.y {
+            for x in 0..storage.size.x {
+                let tile_pos = TilePos { x, y };
+                let Some(tile) = storage.checked_get(&tile_pos) else {
+                    continue;
+                };
+                let Some(texture) = world.get::<TileTextureIndex>(tile) else {
+                    continue;
+                };
+                let attributes = world.get::<TileAttributes>(tile).or_else(|| {
+                    let key = world.get::<AttrKey>(tile)?;
+                    store?.get(*key)
+                });
+                let pos = geometry.sprite_fusion_pos(tile_pos);
+                tiles.push(SpriteFusionTile {
+                    id: texture.0.saturating_sub(texture_offset),
+                    x: pos.x,
+                    y: pos.y,
+                    attributes: attributes
+                        .filter(|attrs| !attrs.0.is_empty())
+                        .map(|attrs| attrs.0.clone().into()),
+                });
+            }
+        }
+        // Sprite Fusion lists tiles row by row
+        tiles.sort_by_key(|tile| (tile.y, tile.x));
+        layer_data.tiles = tiles;
+        layer_data.collider = layer.collider;
+    }
+    Some(data)
+}
+
+/// Export a spawned map and save it to a file, logging failures.
+pub(crate) fn save_and_log(world: &World, map: Entity, path: &Path) {
+    let Some(data) = export_map(world, map) else {
+        warn!("Can't save {map}: not a spawned Sprite Fusion map");
+        return;
+    };
+    let json = match data.to_json() {
+        Ok(json) => json,
+        Err(err) => {
+            error!("Failed to serialize Sprite Fusion map {map}: {err}");
+            return;
+        }
+    };
+    match std::fs::write(path, json) {
+        Ok(()) => info!("Saved Sprite Fusion map to {}", path.display()),
+        Err(err) => error!(
+            "Failed to save Sprite Fusion map to {}: {err}",
+            path.display()
+        ),
+    }
+}
@@ -0,0 +1,71 @@
+//! Placeholder tilesets for missing art.
+//!
+//! During a jam, a map often lands before its spritesheet, or the spritesheet is
+//! renamed and the map no longer finds it. With
+//! [`SpriteFusionSettings::fallback_tileset`](crate::plugin::SpriteFusionSettings::fallback_tileset),
+//! such maps spawn anyway with a checkerboard tileset from [`checkerboard_tileset`],
+//! so layout, collision and gameplay can still be tested.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::core::SpriteFusionMap;
+
+/// Tiles per row of generated tilesets.
+const FALLBACK_COLUMNS: u32 = 16;
+
+/// Build a checkerboard tileset of `tile_count` tiles of `tile_size` pixels.
+///
+/// Each tile is a 2×2 checkerboard of black and a color picked from the tile
+/// index, so tiles with different IDs can still be told apart.
+pub fn checkerboard_tileset(tile_size: u32, tile_count: u32) -> Image {
+    let tile_size = tile_size.max(1);
+    let columns = FALLBACK_COLUMNS.min(tile_count).max(1);
+    let rows = tile_count.div_ceil(columns).max(1);
+    let width = columns * tile_size;
+    let height = rows * tile_size;
+    let half = tile_size.div_ceil(2);
+
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let index = (y / tile_size) * columns + x / tile_size;
+            let dark = ((x % tile_size) / half + (y % tile_size) / half) % 2 == 1;
+            let color = if dark {
+                Color::BLACK
+            } else {
+                // Golden angle steps keep neighboring IDs far apart on the color wheel
+                Color::hsl((index as f32 * 137.5) % 360.0, 0.8, 0.6)
+            };
+            data.extend_from_slice(&color.to_srgba().to_u8_array());
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// Build a checkerboard tileset with a tile for every tile ID of a map, after
+/// `offset` is added to them.
+pub(crate) fn map_fallback_tileset(map: &SpriteFusionMap, offset: u32) -> Image {
+    let tile_count = map
+        .layers
+        .iter()
+        .flat_map(|layer| &layer.tiles)
+        .map(|tile| tile.tile_id() + 1)
+        .max()
+        .unwrap_or(1);
+    checkerboard_tileset(map.tile_size, tile_count + offset)
+}
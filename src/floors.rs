@@ -0,0 +1,372 @@
+//! Floors of multi-story maps authored as stacked layers.
+//!
+//! A layer belongs to a floor when its name starts with `floor<n>` (e.g.
+//! `"floor1/walls"`, `"Floor 2 - props"`), or when its tiles have a `"floor": n`
+//! attribute. Other layers are on floor 0. Layers get a [`Floor`] component, whole
+//! floors can be shown and hidden with
+//! [`SpriteFusionCommandsExt`](crate::commands::SpriteFusionCommandsExt), and maps get
+//! a [`NavGrid`] per floor in [`MapNavGrids`].
+//!
+//! Entities with an [`OnFloor`] component change floor when they step on a tile
+//! with a `"stairsTo": n` attribute, and only collide with tiles of their floor in
+//! [`TileOverlapPlugin`](crate::overlap::TileOverlapPlugin).
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::{reserved_attribute, AttrKey, StoredTileAttributes},
+    convert::{layer_tiles, tilemap_size, TileOrigin},
+    core::SpriteFusionLayer,
+    geometry::world_to_tile,
+    plugin::SpriteFusionSettings,
+    types::{
+        MapPaused, SpriteFusionLayerMarker, SpriteFusionMap, SpriteFusionMapMarker, TileAttributes,
+    },
+};
+
+/// Attribute giving the floor of the layer holding the tile.
+pub const FLOOR_ATTRIBUTE: &str = "floor";
+/// Attribute of stairs and elevator tiles, giving the floor they lead to.
+pub const STAIRS_ATTRIBUTE: &str = "stairsTo";
+
+/// Plugin that tags layers with their [`Floor`] and builds per-floor [`NavGrid`]s.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionFloorsPlugin;
+
+impl Plugin for SpriteFusionFloorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<FloorChanged>().add_systems(
+            Update,
+            (
+                (
+                    tag_layer_floors,
+                    insert_nav_grids,
+                    tag_stairs,
+                    tag_stored_stairs,
+                ),
+                take_stairs,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// The floor of a layer entity.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Floor(pub i32);
+
+/// The floor an entity is on.
+///
+/// The entity changes floor when it steps on [`Stairs`] of its floor. Entities
+/// without this component collide with the tiles of every floor.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnFloor {
+    /// The current floor.
+    pub floor: i32,
+    // Stairs are only taken again once the entity left them, so it doesn't bounce
+    // between the two ends of a staircase
+    on_stairs: bool,
+}
+
+impl OnFloor {
+    /// Start on a floor.
+    pub fn new(floor: i32) -> Self {
+        Self {
+            floor,
+            on_stairs: false,
+        }
+    }
+}
+
+/// Component attached to tiles with a `"stairsTo"` attribute.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stairs {
+    /// The floor the stairs lead to.
+    pub to: i32,
+}
+
+impl Stairs {
+    fn from_attributes(attrs: &TileAttributes, key: &str) -> Option<Self> {
+        let to = attrs.get_i64(key)?;
+        Some(Self {
+            to: i32::try_from(to).ok()?,
+        })
+    }
+}
+
+/// Sent when an entity with [`OnFloor`] takes stairs to another floor.
+#[derive(Message, Debug, Clone)]
+pub struct FloorChanged {
+    /// The entity changing floor.
+    pub entity: Entity,
+    /// The map holding the stairs.
+    pub map: Entity,
+    /// The previous floor.
+    pub from: i32,
+    /// The new floor.
+    pub to: i32,
+}
+
+/// Get the floor of a layer, from its name or the `"floor"` attribute of its
+/// tiles, read with the given
+/// [`SpriteFusionSettings::attribute_prefix`].
+pub fn layer_floor(layer: &SpriteFusionLayer, prefix: &str) -> i32 {
+    let key = reserved_attribute(prefix, FLOOR_ATTRIBUTE);
+    floor_from_name(&layer.name)
+        .or_else(|| {
+            layer.tiles.iter().find_map(|tile| {
+                let floor = tile.attributes.as_ref()?.get(&*key)?.as_i64()?;
+                i32::try_from(floor).ok()
+            })
+        })
+        .unwrap_or(0)
+}
+
+fn floor_from_name(name: &str) -> Option<i32> {
+    let prefix = name.get(..5)?;
+    if !prefix.eq_ignore_ascii_case("floor") {
+        return None;
+    }
+    let rest = name[5..].trim_start();
+    let end = rest
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+        .map_or(rest.len(), |(i, _)| i);
+    rest[..end].parse().ok()
+}
+
+/// Walkable cells of one floor, indexed by spawned [`TilePos`].
+///
+/// A cell is blocked when a collider layer of the floor has a tile there, or when
+/// it's stamped by an entity standing on it, see
+/// [`BakeFootprint`](crate::footprints::BakeFootprint).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NavGrid {
+    /// Size of the grid, the tilemap size of the map.
+    pub size: TilemapSize,
+    blocked: Vec<bool>,
+    stamps: Vec<u32>,
+}
+
+impl NavGrid {
+    /// Build the grid of one floor of a map, finding the floor of layers with
+    /// [`layer_floor`].
+    pub fn from_floor(map: &SpriteFusionMap, floor: i32, origin: TileOrigin, prefix: &str) -> Self {
+        let size = tilemap_size(map);
+        let mut blocked = vec![false; size.count()];
+        for layer in &map.layers {
+            if !layer.collider || layer_floor(layer, prefix) != floor {
+                continue;
+            }
+            for tile in layer_tiles(map, layer, origin) {
+                blocked[grid_index(&tile.position, &size)] = true;
+            }
+        }
+        let stamps = vec![0; size.count()];
+        Self {
+            size,
+            blocked,
+            stamps,
+        }
+    }
+
+    /// Check if a cell is blocked. Cells outside of the grid are blocked.
+    pub fn is_blocked(&self, pos: &TilePos) -> bool {
+        if !pos.within_map_bounds(&self.size) {
+            return true;
+        }
+        let index = grid_index(pos, &self.size);
+        self.blocked[index] || self.stamps[index] > 0
+    }
+
+    /// Block a cell until it's unstamped. Cells stamped several times stay blocked
+    /// until they're unstamped as many times.
+    pub fn stamp(&mut self, pos: &TilePos) {
+        if pos.within_map_bounds(&self.size) {
+            self.stamps[grid_index(pos, &self.size)] += 1;
+        }
+    }
+
+    /// Remove a stamp from a cell.
+    pub fn unstamp(&mut self, pos: &TilePos) {
+        if pos.within_map_bounds(&self.size) {
+            let stamps = &mut self.stamps[grid_index(pos, &self.size)];
+            *stamps = stamps.saturating_sub(1);
+        }
+    }
+
+    /// Check if a cell can be walked on.
+    pub fn is_walkable(&self, pos: &TilePos) -> bool {
+        !self.is_blocked(pos)
+    }
+}
+
+fn grid_index(pos: &TilePos, size: &TilemapSize) -> usize {
+    (pos.y * size.x + pos.x) as usize
+}
+
+/// The [`NavGrid`] of every floor of a spawned map.
+#[derive(Component, Debug, Clone, Default)]
+pub struct MapNavGrids(pub BTreeMap<i32, NavGrid>);
+
+impl MapNavGrids {
+    /// Get the grid of a floor.
+    pub fn get(&self, floor: i32) -> Option<&NavGrid> {
+        self.0.get(&floor)
+    }
+
+    /// Iterate over the floors of the map, from the lowest.
+    pub fn floors(&self) -> impl Iterator<Item = i32> + '_ {
+        self.0.keys().copied()
+    }
+}
+
+/// System that inserts [`Floor`] on newly spawned layers.
+fn tag_layer_floors(
+    mut commands: Commands,
+    layers: Query<(Entity, &SpriteFusionLayerMarker), Added<SpriteFusionLayerMarker>>,
+    maps: Query<&SpriteFusionMapMarker>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (entity, layer) in layers.iter() {
+        let Some(data) = maps
+            .get(layer.map)
+            .ok()
+            .and_then(|marker| marker.map.layers.get(layer.index))
+        else {
+            continue;
+        };
+        let floor = layer_floor(data, &settings.attribute_prefix);
+        commands.entity(entity).insert(Floor(floor));
+    }
+}
+
+/// System that builds the nav grids of newly spawned maps.
+fn insert_nav_grids(
+    mut commands: Commands,
+    maps: Query<(Entity, &SpriteFusionMapMarker), Added<SpriteFusionMapMarker>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (entity, marker) in maps.iter() {
+        let mut grids = BTreeMap::new();
+        let prefix = &settings.attribute_prefix;
+        for layer in &marker.map.layers {
+            let floor = layer_floor(layer, prefix);
+            grids.entry(floor).or_insert_with(|| {
+                NavGrid::from_floor(&marker.map, floor, settings.tile_origin, prefix)
+            });
+        }
+        commands.entity(entity).insert(MapNavGrids(grids));
+    }
+}
+
+/// System that keeps [`Stairs`] in sync with the attribute components of tiles.
+fn tag_stairs(
+    mut commands: Commands,
+    tiles: Query<(Entity, &TileAttributes), Changed<TileAttributes>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let key = settings.reserved_attribute(STAIRS_ATTRIBUTE);
+    for (tile, attrs) in tiles.iter() {
+        match Stairs::from_attributes(attrs, &key) {
+            Some(stairs) => commands.entity(tile).insert(stairs),
+            None => commands.entity(tile).remove::<Stairs>(),
+        };
+    }
+}
+
+/// System that inserts [`Stairs`] on tiles using an attribute store.
+fn tag_stored_stairs(
+    mut commands: Commands,
+    tiles: Query<Entity, Added<AttrKey>>,
+    attributes: StoredTileAttributes,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let key = settings.reserved_attribute(STAIRS_ATTRIBUTE);
+    for tile in tiles.iter() {
+        let stairs = attributes
+            .get(tile)
+            .and_then(|attrs| Stairs::from_attributes(attrs, &key));
+        if let Some(stairs) = stairs {
+            commands.entity(tile).insert(stairs);
+        }
+    }
+}
+
+/// System that moves entities to another floor when they step on stairs.
+fn take_stairs(
+    mut entities: Query<(Entity, &mut OnFloor, &GlobalTransform)>,
+    layers: Query<(
+        &SpriteFusionLayerMarker,
+        &Floor,
+        &TileStorage,
+        &TilemapSize,
+        &TilemapGridSize,
+        &GlobalTransform,
+    )>,
+    paused_maps: Query<(), With<MapPaused>>,
+    stairs: Query<&Stairs>,
+    mut changed: MessageWriter<FloorChanged>,
+) {
+    for (entity, mut on_floor, transform) in entities.iter_mut() {
+        let position = transform.translation().truncate();
+        let found = layers
+            .iter()
+            .filter(|(layer, floor, ..)| {
+                floor.0 == on_floor.floor && !paused_maps.contains(layer.map)
+            })
+            .find_map(
+                |(layer, _, storage, map_size, grid_size, layer_transform)| {
+                    let pos = world_to_tile(position, layer_transform, grid_size, map_size)?;
+                    let tile = storage.get(&pos)?;
+                    Some((layer.map, *stairs.get(tile).ok()?))
+                },
+            );
+
+        let Some((map, stairs)) = found else {
+            if on_floor.on_stairs {
+                on_floor.on_stairs = false;
+            }
+            continue;
+        };
+        if on_floor.on_stairs || stairs.to == on_floor.floor {
+            continue;
+        }
+        let from = on_floor.floor;
+        *on_floor = OnFloor {
+            floor: stairs.to,
+            on_stairs: true,
+        };
+        changed.write(FloorChanged {
+            entity,
+            map,
+            from,
+            to: stairs.to,
+        });
+    }
+}
+
+/// Set the visibility of the layers of a map, per floor. `None` leaves a floor as is.
+pub(crate) fn set_floor_visibility(
+    world: &mut World,
+    map: Entity,
+    visible: impl Fn(i32) -> Option<bool>,
+) {
+    let mut layers = world.query::<(Entity, &SpriteFusionLayerMarker, &Floor)>();
+    let changes: Vec<_> = layers
+        .iter(world)
+        .filter(|(_, layer, _)| layer.map == map)
+        .filter_map(|(entity, _, floor)| Some((entity, visible(floor.0)?)))
+        .collect();
+    for (entity, visible) in changes {
+        world.entity_mut(entity).insert(if visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
@@ -0,0 +1,162 @@
+//! Footprints of entities placed in the editor, baked into nav grids.
+//!
+//! Chests, statues and buildings spawned from tiles (see
+//! [`SpawnedFromTile`]) don't block pathfinding on their own, as they aren't on a
+//! collider layer. Insert a [`BakeFootprint`] on them to stamp the tiles they
+//! cover into the [`NavGrid`](crate::floors::NavGrid) of their floor; the tiles
+//! are unstamped when the component is removed or the entity despawned.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    floors::{layer_floor, MapNavGrids},
+    geometry::SpriteFusionMapGeometry,
+    plugin::SpriteFusionSettings,
+    spawners::SpawnedFromTile,
+    types::SpriteFusionMapMarker,
+};
+
+/// Plugin that bakes the footprints of entities spawned from tiles into the
+/// nav grids of their floor.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionFootprintsPlugin;
+
+impl Plugin for SpriteFusionFootprintsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BakedFootprints>()
+            .add_systems(Update, (unbake_footprints, bake_footprints).chain());
+    }
+}
+
+/// Component stamping the tiles covered by an entity spawned from a tile into the
+/// nav grid of its map, as blocked.
+///
+/// The footprint extends right and down from the tile the entity was spawned
+/// from, like a multi-tile object drawn from its top-left corner in the editor.
+///
+/// ```rust,ignore
+/// fn spawn_prefabs(mut commands: Commands, spawned: Query<(Entity, &SpawnedFromTile), Added<SpawnedFromTile>>) {
+///     for (entity, spawned) in spawned.iter() {
+///         match spawned.kind.as_str() {
+///             "chest" => commands.entity(entity).insert(BakeFootprint::default()),
+///             "house" => commands.entity(entity).insert(BakeFootprint::new(3, 2)),
+///             _ => {}
+///         }
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BakeFootprint {
+    /// Width of the footprint, in tiles.
+    pub width: u32,
+    /// Height of the footprint, in tiles.
+    pub height: u32,
+}
+
+impl BakeFootprint {
+    /// A footprint of `width` by `height` tiles.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+}
+
+impl Default for BakeFootprint {
+    fn default() -> Self {
+        Self::new(1, 1)
+    }
+}
+
+/// Tiles stamped for each entity, to unstamp them once the entity is gone.
+#[derive(Resource, Default)]
+struct BakedFootprints(HashMap<Entity, BakedFootprint>);
+
+struct BakedFootprint {
+    map: Entity,
+    floor: i32,
+    tiles: Vec<TilePos>,
+}
+
+impl BakedFootprint {
+    fn unstamp(&self, grids: &mut MapNavGrids) {
+        if let Some(grid) = grids.0.get_mut(&self.floor) {
+            for pos in &self.tiles {
+                grid.unstamp(pos);
+            }
+        }
+    }
+}
+
+/// System that stamps the footprints of entities into the nav grids of their map.
+fn bake_footprints(
+    entities: Query<(Entity, Ref<BakeFootprint>, &SpawnedFromTile)>,
+    mut maps: Query<(
+        &SpriteFusionMapMarker,
+        &SpriteFusionMapGeometry,
+        &mut MapNavGrids,
+    )>,
+    mut baked: ResMut<BakedFootprints>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (entity, footprint, spawned) in entities.iter() {
+        if baked.0.contains_key(&entity) {
+            if !footprint.is_changed() {
+                continue;
+            }
+            // Resized footprints are stamped again
+            if let Some(old) = baked.0.remove(&entity) {
+                if let Ok((_, _, mut grids)) = maps.get_mut(old.map) {
+                    old.unstamp(&mut grids);
+                }
+            }
+        }
+        // The nav grids may not be inserted yet on maps spawned this frame
+        let Ok((marker, geometry, mut grids)) = maps.get_mut(spawned.map) else {
+            continue;
+        };
+        let floor = marker
+            .map
+            .layers
+            .get(spawned.layer)
+            .map_or(0, |layer| layer_floor(layer, &settings.attribute_prefix));
+        let Some(grid) = grids.0.get_mut(&floor) else {
+            continue;
+        };
+
+        let tiles: Vec<TilePos> = (0..footprint.height as i32)
+            .flat_map(|dy| (0..footprint.width as i32).map(move |dx| (dx, dy)))
+            .filter_map(|(dx, dy)| geometry.tile_pos(spawned.x + dx, spawned.y + dy))
+            .collect();
+        for pos in &tiles {
+            grid.stamp(pos);
+        }
+        baked.0.insert(
+            entity,
+            BakedFootprint {
+                map: spawned.map,
+                floor,
+                tiles,
+            },
+        );
+    }
+}
+
+/// System that unstamps the footprints of entities that lost their
+/// [`BakeFootprint`] or were despawned.
+fn unbake_footprints(
+    mut removed: RemovedComponents<BakeFootprint>,
+    mut maps: Query<&mut MapNavGrids>,
+    mut baked: ResMut<BakedFootprints>,
+) {
+    for entity in removed.read() {
+        let Some(footprint) = baked.0.remove(&entity) else {
+            continue;
+        };
+        if let Ok(mut grids) = maps.get_mut(footprint.map) {
+            footprint.unstamp(&mut grids);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Conversions between world space and Sprite Fusion tile coordinates.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    convert::{to_tile_pos, TileOrigin},
+    plugin::SpriteFusionSystems,
+};
+
+pub(crate) struct SpriteFusionGeometryPlugin;
+
+impl Plugin for SpriteFusionGeometryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            sync_map_geometry.in_set(SpriteFusionSystems::Sync),
+        );
+    }
+}
+
+/// Geometry of a spawned map, for conversions between world space and the
+/// [`TilePos`] of its tiles.
+///
+/// Inserted on map entities when they spawn. Accounts for the map transform, the
+/// tile size, tiles at negative coordinates and the [`TileOrigin`] the map was
+/// spawned with, so unlike [`tile_to_world`] it doesn't need a layer. The map
+/// transform is copied after transform propagation, so conversions lag a frame
+/// behind a map that's moving.
+///
+/// ```rust,ignore
+/// fn click_tile(maps: Query<&SpriteFusionMapGeometry>, cursor: Res<CursorWorldPosition>) {
+///     for geometry in maps.iter() {
+///         if let Some(pos) = geometry.world_to_tile(cursor.0) {
+///             info!("Clicked tile ({}, {})", pos.x, pos.y);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct SpriteFusionMapGeometry {
+    /// Size of the tilemaps, in tiles.
+    pub size: TilemapSize,
+    /// Size of a tile, in world units before the map transform.
+    pub tile_size: f32,
+    /// Offset added to Sprite Fusion coordinates to get tilemap coordinates.
+    pub tile_offset: UVec2,
+    /// Coordinate convention of the spawned [`TilePos`] components.
+    pub tile_origin: TileOrigin,
+    layer_z: Vec<(String, f32)>,
+    transform: GlobalTransform,
+}
+
+impl SpriteFusionMapGeometry {
+    pub(crate) fn new(
+        size: TilemapSize,
+        tile_size: f32,
+        tile_offset: UVec2,
+        tile_origin: TileOrigin,
+        layer_z: Vec<(String, f32)>,
+    ) -> Self {
+        Self {
+            size,
+            tile_size,
+            tile_offset,
+            tile_origin,
+            layer_z,
+            transform: GlobalTransform::IDENTITY,
+        }
+    }
+
+    /// Set the map transform used before the next transform propagation.
+    pub(crate) fn with_transform(mut self, transform: GlobalTransform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Get the Z of a layer relative to the map entity, as set by
+    /// [`SpriteFusionSettings::layer_z`](crate::plugin::SpriteFusionSettings::layer_z).
+    ///
+    /// Returns `None` if the map has no spawned layer with this name. With
+    /// [`layer_z_jitter`](crate::plugin::SpriteFusionSettings::layer_z_jitter), the
+    /// layer is drawn slightly below this Z, so sprites placed right at it draw
+    /// above the layer without flickering.
+    ///
+    /// ```rust,ignore
+    /// let z = geometry.layer_z("Ground").unwrap_or_default();
+    /// commands.spawn((Sprite::from_image(player), Transform::from_xyz(x, y, z)));
+    /// ```
+    pub fn layer_z(&self, layer_name: &str) -> Option<f32> {
+        self.layer_z
+            .iter()
+            .find(|(name, _)| name == layer_name)
+            .map(|&(_, z)| z)
+    }
+
+    /// Convert a Sprite Fusion tile position (top-left origin) to the [`TilePos`]
+    /// of the spawned tile.
+    ///
+    /// Returns `None` if the tile lies outside of the map.
+    pub fn tile_pos(&self, x: i32, y: i32) -> Option<TilePos> {
+        to_tile_pos(x, y, self.tile_offset, &self.size, self.tile_origin)
+    }
+
+    /// Convert the [`TilePos`] of a spawned tile back to a Sprite Fusion tile
+    /// position (top-left origin).
+    pub fn sprite_fusion_pos(&self, tile_pos: TilePos) -> IVec2 {
+        let row = match self.tile_origin {
+            TileOrigin::BottomLeft => (self.size.y - 1) as i32 - tile_pos.y as i32,
+            TileOrigin::TopLeft => tile_pos.y as i32,
+        };
+        IVec2::new(tile_pos.x as i32, row) - self.tile_offset.as_ivec2()
+    }
+
+    /// Get the position of a tile center in the map's local space.
+    pub fn tile_to_local(&self, tile_pos: TilePos) -> Vec2 {
+        let x = (tile_pos.x as f32 - self.tile_offset.x as f32) * self.tile_size;
+        let y = match self.tile_origin {
+            TileOrigin::BottomLeft => tile_pos.y as f32,
+            // Layers are mirrored around the map to draw row 0 at the top
+            TileOrigin::TopLeft => (self.size.y - 1) as f32 - tile_pos.y as f32,
+        } * self.tile_size;
+        Vec2::new(x, y)
+    }
+
+    /// Get the tile under a position in the map's local space.
+    ///
+    /// Returns `None` if the position falls outside of the map.
+    pub fn local_to_tile(&self, local: Vec2) -> Option<TilePos> {
+        let x = (local.x / self.tile_size + 0.5).floor() + self.tile_offset.x as f32;
+        let row = (local.y / self.tile_size + 0.5).floor();
+        let y = match self.tile_origin {
+            TileOrigin::BottomLeft => row,
+            TileOrigin::TopLeft => (self.size.y - 1) as f32 - row,
+        };
+        if x < 0.0 || y < 0.0 || x >= self.size.x as f32 || y >= self.size.y as f32 {
+            return None;
+        }
+        Some(TilePos {
+            x: x as u32,
+            y: y as u32,
+        })
+    }
+
+    /// Get the world-space center of a tile.
+    pub fn tile_to_world(&self, tile_pos: TilePos) -> Vec2 {
+        self.transform
+            .transform_point(self.tile_to_local(tile_pos).extend(0.0))
+            .truncate()
+    }
+
+    /// Get the tile under a world-space position.
+    ///
+    /// Returns `None` if the position falls outside of the map.
+    pub fn world_to_tile(&self, world_pos: Vec2) -> Option<TilePos> {
+        self.local_to_tile(self.world_to_local(world_pos))
+    }
+
+    /// Convert a world-space position to the map's local space.
+    pub fn world_to_local(&self, world_pos: Vec2) -> Vec2 {
+        self.transform
+            .affine()
+            .inverse()
+            .transform_point3(world_pos.extend(0.0))
+            .truncate()
+    }
+}
+
+/// System that copies the map transform into [`SpriteFusionMapGeometry`].
+#[allow(clippy::type_complexity)]
+fn sync_map_geometry(
+    mut maps: Query<
+        (&mut SpriteFusionMapGeometry, &GlobalTransform),
+        Or<(Changed<GlobalTransform>, Added<SpriteFusionMapGeometry>)>,
+    >,
+) {
+    for (mut geometry, transform) in maps.iter_mut() {
+        geometry.transform = *transform;
+    }
+}
+
+/// Get the world-space center of a tile on a spawned layer.
+///
+/// Tiles are centered on multiples of the grid size in the layer's local space.
+pub fn tile_to_world(
+    tile_pos: &TilePos,
+    layer_transform: &GlobalTransform,
+    grid_size: &TilemapGridSize,
+) -> Vec2 {
+    let local = Vec3::new(
+        tile_pos.x as f32 * grid_size.x,
+        tile_pos.y as f32 * grid_size.y,
+        0.0,
+    );
+    layer_transform.transform_point(local).truncate()
+}
+
+/// Get the tile under a world-space position on a spawned layer.
+///
+/// Returns `None` if the position falls outside of the layer.
+pub fn world_to_tile(
+    world_pos: Vec2,
+    layer_transform: &GlobalTransform,
+    grid_size: &TilemapGridSize,
+    map_size: &TilemapSize,
+) -> Option<TilePos> {
+    let local = layer_transform
+        .affine()
+        .inverse()
+        .transform_point3(world_pos.extend(0.0));
+    let x = (local.x / grid_size.x + 0.5).floor();
+    let y = (local.y / grid_size.y + 0.5).floor();
+    if x < 0.0 || y < 0.0 || x >= map_size.x as f32 || y >= map_size.y as f32 {
+        return None;
+    }
+    Some(TilePos {
+        x: x as u32,
+        y: y as u32,
+    })
+}
+
+/// Get the inclusive range of tiles covered by a world-space rectangle on a spawned layer.
+///
+/// Returns `None` if the rectangle lies entirely outside of the layer.
+pub fn world_rect_to_tiles(
+    center: Vec2,
+    half_size: Vec2,
+    layer_transform: &GlobalTransform,
+    grid_size: &TilemapGridSize,
+    map_size: &TilemapSize,
+) -> Option<(TilePos, TilePos)> {
+    let inverse = layer_transform.affine().inverse();
+    let a = inverse.transform_point3((center - half_size).extend(0.0)).truncate();
+    let b = inverse.transform_point3((center + half_size).extend(0.0)).truncate();
+    let grid = Vec2::new(grid_size.x, grid_size.y);
+    let min = (a.min(b) / grid + 0.5).floor();
+    let max = (a.max(b) / grid + 0.5).floor();
+    let bounds = Vec2::new(map_size.x as f32, map_size.y as f32);
+    if max.x < 0.0 || max.y < 0.0 || min.x >= bounds.x || min.y >= bounds.y {
+        return None;
+    }
+    let min = min.max(Vec2::ZERO);
+    let max = max.min(bounds - 1.0);
+    Some((
+        TilePos {
+            x: min.x as u32,
+            y: min.y as u32,
+        },
+        TilePos {
+            x: max.x as u32,
+            y: max.y as u32,
+        },
+    ))
+}
@@ -0,0 +1,292 @@
+//! Heatmap overlays of per-tile values.
+//!
+//! Insert a [`MapHeatmap`] on a map entity to draw a scalar field over the map,
+//! one translucent colored quad per tile: path costs, distance fields, danger
+//! maps or anything else an AI computes on the tile grid. The overlay is a
+//! single mesh, rebuilt when the heatmap changes, and can be toggled at runtime.
+
+use std::collections::HashMap;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{geometry::SpriteFusionMapGeometry, types::SpriteFusionMapMarker};
+
+/// Z of heatmaps above the top layer of their map, above decals.
+const HEATMAP_Z_OFFSET: f32 = 0.001;
+
+/// Plugin that draws the [`MapHeatmap`] overlays of maps.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionHeatmapPlugin;
+
+impl Plugin for SpriteFusionHeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_heatmap_meshes);
+    }
+}
+
+/// Component drawing a heatmap over a map entity.
+///
+/// Values are stored per [`TilePos`] of the map. Tiles without a value aren't
+/// drawn; the others get a color between [`low`](Self::low) and
+/// [`high`](Self::high), by where their value falls in the heatmap's range.
+///
+/// ```rust,ignore
+/// fn show_nav_costs(mut commands: Commands, maps: Query<(Entity, &MapNavGrids)>) {
+///     for (map, grids) in maps.iter() {
+///         let Some(grid) = grids.get(0) else { continue };
+///         commands.entity(map).insert(MapHeatmap::from_fn(grid.size, |pos| {
+///             Some(if grid.is_walkable(&pos) { 0.0 } else { 1.0 })
+///         }));
+///     }
+/// }
+///
+/// fn toggle_heatmap(keys: Res<ButtonInput<KeyCode>>, mut heatmaps: Query<&mut MapHeatmap>) {
+///     if keys.just_pressed(KeyCode::F3) {
+///         heatmaps.iter_mut().for_each(|mut heatmap| heatmap.toggle());
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct MapHeatmap {
+    /// Color of the lowest values.
+    pub low: Color,
+    /// Color of the highest values.
+    pub high: Color,
+    /// Values mapped to `low` and `high`. `None` uses the smallest and largest
+    /// values of the heatmap.
+    pub range: Option<(f32, f32)>,
+    /// Opacity of the overlay, multiplied with the alpha of the colors.
+    pub opacity: f32,
+    /// Whether the overlay is drawn.
+    pub visible: bool,
+    size: TilemapSize,
+    values: Vec<Option<f32>>,
+}
+
+impl MapHeatmap {
+    /// An empty heatmap for a map of `size` tiles, from green to red, half opaque.
+    pub fn new(size: TilemapSize) -> Self {
+        Self {
+            low: Color::srgb(0.0, 1.0, 0.0),
+            high: Color::srgb(1.0, 0.0, 0.0),
+            range: None,
+            opacity: 0.5,
+            visible: true,
+            size,
+            values: vec![None; size.count()],
+        }
+    }
+
+    /// A heatmap with the value of `f` at each tile.
+    pub fn from_fn(size: TilemapSize, f: impl Fn(TilePos) -> Option<f32>) -> Self {
+        let mut heatmap = Self::new(size);
+        heatmap.fill(f);
+        heatmap
+    }
+
+    /// Set the colors of the lowest and highest values.
+    pub fn with_colors(mut self, low: Color, high: Color) -> Self {
+        self.low = low;
+        self.high = high;
+        self
+    }
+
+    /// Set the values mapped to the low and high colors.
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    /// Set the opacity of the overlay.
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Get the size of the heatmap, in tiles.
+    pub fn size(&self) -> TilemapSize {
+        self.size
+    }
+
+    /// Get the value at a tile.
+    pub fn get(&self, tile_pos: TilePos) -> Option<f32> {
+        self.index(tile_pos).and_then(|index| self.values[index])
+    }
+
+    /// Set the value at a tile, `None` leaving it undrawn. Positions outside the
+    /// heatmap are ignored.
+    pub fn set(&mut self, tile_pos: TilePos, value: Option<f32>) {
+        if let Some(index) = self.index(tile_pos) {
+            self.values[index] = value;
+        }
+    }
+
+    /// Replace every value with the value of `f` at each tile.
+    pub fn fill(&mut self, f: impl Fn(TilePos) -> Option<f32>) {
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                self.values[(y * self.size.x + x) as usize] = f(TilePos { x, y });
+            }
+        }
+    }
+
+    /// Remove every value.
+    pub fn clear(&mut self) {
+        self.values.fill(None);
+    }
+
+    /// Show the overlay if it's hidden, hide it otherwise.
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Get the color of a value.
+    pub fn color(&self, value: f32) -> Color {
+        self.color_in(value, self.color_range())
+    }
+
+    fn color_in(&self, value: f32, (min, max): (f32, f32)) -> Color {
+        let t = if max > min {
+            ((value - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let color = Oklaba::from(self.low).mix(&Oklaba::from(self.high), t);
+        Color::from(color).with_alpha(color.alpha * self.opacity)
+    }
+
+    fn index(&self, tile_pos: TilePos) -> Option<usize> {
+        tile_pos
+            .within_map_bounds(&self.size)
+            .then(|| (tile_pos.y * self.size.x + tile_pos.x) as usize)
+    }
+
+    /// Get the values mapped to the low and high colors.
+    fn color_range(&self) -> (f32, f32) {
+        if let Some(range) = self.range {
+            return range;
+        }
+        self.values
+            .iter()
+            .flatten()
+            .filter(|value| value.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+                (min.min(*value), max.max(*value))
+            })
+    }
+}
+
+/// Entity drawing the heatmap of a map, a child of the map entity.
+#[derive(Component)]
+struct HeatmapMesh {
+    map: Entity,
+}
+
+/// System that rebuilds the overlay of maps whose heatmap changed, and despawns
+/// the overlays of maps that lost it.
+fn update_heatmap_meshes(
+    mut commands: Commands,
+    maps: Query<(
+        Entity,
+        Ref<MapHeatmap>,
+        Ref<SpriteFusionMapGeometry>,
+        &SpriteFusionMapMarker,
+    )>,
+    heatmap_meshes: Query<(Entity, &HeatmapMesh, &Mesh2d)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut existing: HashMap<Entity, _> = heatmap_meshes
+        .iter()
+        .map(|(entity, heatmap_mesh, mesh)| (heatmap_mesh.map, (entity, mesh)))
+        .collect();
+
+    for (map, heatmap, geometry, marker) in maps.iter() {
+        let current = existing.remove(&map);
+        if current.is_some() && !heatmap.is_changed() && !geometry.is_changed() {
+            continue;
+        }
+        let visibility = if heatmap.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        let z = marker
+            .map
+            .layers
+            .iter()
+            .filter_map(|layer| geometry.layer_z(&layer.name))
+            .reduce(f32::max)
+            .unwrap_or_default()
+            + HEATMAP_Z_OFFSET;
+        let mesh = heatmap_mesh(&heatmap, &geometry);
+        match current {
+            Some((entity, mesh_handle)) => {
+                if let Some(target) = meshes.get_mut(&mesh_handle.0) {
+                    *target = mesh;
+                }
+                commands
+                    .entity(entity)
+                    .insert((Transform::from_xyz(0.0, 0.0, z), visibility));
+            }
+            None => {
+                commands.spawn((
+                    HeatmapMesh { map },
+                    Mesh2d(meshes.add(mesh)),
+                    MeshMaterial2d(materials.add(ColorMaterial::default())),
+                    Transform::from_xyz(0.0, 0.0, z),
+                    visibility,
+                    ChildOf(map),
+                ));
+            }
+        }
+    }
+
+    for (entity, _) in existing.into_values() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Build the overlay mesh of a heatmap, one quad per tile with a value.
+fn heatmap_mesh(heatmap: &MapHeatmap, geometry: &SpriteFusionMapGeometry) -> Mesh {
+    let half = geometry.tile_size / 2.0;
+    let range = heatmap.color_range();
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut colors: Vec<[f32; 4]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for y in 0..heatmap.size.y {
+        for x in 0..heatmap.size.x {
+            let tile_pos = TilePos { x, y };
+            let Some(value) = heatmap.get(tile_pos).filter(|value| value.is_finite()) else {
+                continue;
+            };
+            let center = geometry.tile_to_local(tile_pos);
+            let color = heatmap.color_in(value, range).to_linear().to_f32_array();
+            let first = positions.len() as u32;
+            for corner in [
+                Vec2::new(-half, -half),
+                Vec2::new(half, -half),
+                Vec2::new(half, half),
+                Vec2::new(-half, half),
+            ] {
+                positions.push((center + corner).extend(0.0).to_array());
+                colors.push(color);
+            }
+            indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(indices))
+}
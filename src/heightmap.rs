@@ -0,0 +1,169 @@
+//! Heightmaps driven by tile attributes, for hybrid 2D/3D projects.
+
+#[cfg(feature = "render")]
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+};
+
+use crate::core::SpriteFusionMap;
+
+/// A grid of heights, one per tile, in Sprite Fusion coordinates (top-left origin).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    width: u32,
+    height: u32,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Create a heightmap from heights stored row by row, starting from the top
+    /// row.
+    ///
+    /// Returns `None` unless there's exactly one height per tile.
+    pub fn new(width: u32, height: u32, heights: Vec<f32>) -> Option<Self> {
+        let tiles = (width as usize).checked_mul(height as usize)?;
+        (heights.len() == tiles).then_some(Self {
+            width,
+            height,
+            heights,
+        })
+    }
+
+    /// Get the width in tiles.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height in tiles.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the heights, row by row, starting from the top row.
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    /// Get the heights mutably, row by row, starting from the top row.
+    pub fn heights_mut(&mut self) -> &mut [f32] {
+        &mut self.heights
+    }
+
+    /// Get the height of a tile.
+    pub fn get(&self, x: u32, y: u32) -> Option<f32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.heights.get((y * self.width + x) as usize).copied()
+    }
+
+    /// Build a mesh with one vertex per tile center, lying on the XZ plane.
+    ///
+    /// X follows the map columns and +Z follows the map rows (downwards in
+    /// Sprite Fusion), so the mesh matches a map placed with
+    /// [`MapPlacement::Ground`](crate::placement::MapPlacement::Ground).
+    /// Heights are multiplied by `height_scale` along +Y.
+    #[cfg(feature = "render")]
+    pub fn to_mesh(&self, tile_size: f32, height_scale: f32) -> Mesh {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let height_at = |x: usize, y: usize| self.heights[y * w + x] * height_scale;
+
+        let mut positions = Vec::with_capacity(w * h);
+        let mut normals = Vec::with_capacity(w * h);
+        let mut uvs = Vec::with_capacity(w * h);
+        for y in 0..h {
+            for x in 0..w {
+                positions.push([x as f32 * tile_size, height_at(x, y), y as f32 * tile_size]);
+
+                // Central differences, clamped at the borders
+                let dx = (height_at((x + 1).min(w - 1), y) - height_at(x.saturating_sub(1), y))
+                    / (2.0 * tile_size);
+                let dz = (height_at(x, (y + 1).min(h - 1)) - height_at(x, y.saturating_sub(1)))
+                    / (2.0 * tile_size);
+                normals.push(Vec3::new(-dx, 1.0, -dz).normalize().to_array());
+
+                uvs.push([
+                    x as f32 / (w.max(2) - 1) as f32,
+                    y as f32 / (h.max(2) - 1) as f32,
+                ]);
+            }
+        }
+
+        let mut indices = Vec::with_capacity(w.saturating_sub(1) * h.saturating_sub(1) * 6);
+        for y in 0..h.saturating_sub(1) {
+            for x in 0..w.saturating_sub(1) {
+                let a = (y * w + x) as u32;
+                let b = a + 1;
+                let c = a + w as u32;
+                let d = c + 1;
+                indices.extend_from_slice(&[a, c, b, b, c, d]);
+            }
+        }
+
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
+    }
+}
+
+/// Build a heightmap from a numeric tile attribute.
+///
+/// When several layers have a tile with the attribute at the same position, the
+/// topmost layer wins. Tiles without the attribute have a height of 0.
+///
+/// ```rust,ignore
+/// let heightmap = extract_heightmap(&map, "height");
+/// let mesh = meshes.add(heightmap.to_mesh(map.tile_size as f32, 8.0));
+/// ```
+pub fn extract_heightmap(map: &SpriteFusionMap, attribute_key: &str) -> Heightmap {
+    let (width, height) = (map.map_width, map.map_height);
+    let mut heights = vec![0.0; (width * height) as usize];
+
+    // Layer 0 is on top, so walk from the background up and let upper layers overwrite
+    for layer in map.layers.iter().rev() {
+        for tile in &layer.tiles {
+            if tile.x < 0 || tile.y < 0 || tile.x as u32 >= width || tile.y as u32 >= height {
+                continue;
+            }
+            let Some(value) = tile
+                .attributes
+                .as_ref()
+                .and_then(|attrs| attrs.get(attribute_key))
+                .and_then(|v| v.as_f64())
+            else {
+                continue;
+            };
+            heights[(tile.y as u32 * width + tile.x as u32) as usize] = value as f32;
+        }
+    }
+
+    Heightmap {
+        width,
+        height,
+        heights,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_height_per_tile() {
+        assert!(Heightmap::new(3, 2, vec![0.0; 5]).is_none());
+        assert!(Heightmap::new(3, 2, vec![0.0; 7]).is_none());
+        assert!(Heightmap::new(u32::MAX, u32::MAX, Vec::new()).is_none());
+        assert!(Heightmap::new(0, 4, Vec::new()).is_some());
+
+        let heightmap = Heightmap::new(3, 2, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]).unwrap();
+        assert_eq!(heightmap.get(2, 1), Some(5.0));
+        assert_eq!(heightmap.get(3, 0), None);
+    }
+}
@@ -0,0 +1,210 @@
+//! Lifecycle hooks for customizing how maps are spawned.
+//!
+//! Implement [`SpriteFusionHooks`] and register it with
+//! [`SpriteFusionHooksAppExt::add_spritefusion_hooks`] to edit map data before it's
+//! prepared, and add components to layers, tiles and maps as they spawn, without
+//! forking the spawn systems.
+
+use std::sync::Arc;
+
+use bevy::prelude::*;
+
+use crate::core::{SpriteFusionLayer, SpriteFusionMap, SpriteFusionTile};
+
+/// Callbacks run at each step of the life of a map.
+///
+/// Every method does nothing by default; implement the ones you need.
+///
+/// ```rust,ignore
+/// struct LevelHooks;
+///
+/// impl SpriteFusionHooks for LevelHooks {
+///     fn on_map_loaded(&self, _map: Entity, data: &mut SpriteFusionMap) {
+///         data.layers.retain(|layer| layer.name != "Editor notes");
+///     }
+///
+///     fn on_layer_spawn(&self, layer: &mut EntityCommands, data: &SpriteFusionLayer) {
+///         if data.name == "Water" {
+///             layer.insert(WaterLayer);
+///         }
+///     }
+///
+///     fn on_map_ready(&self, map: &mut EntityCommands, _data: &SpriteFusionMap) {
+///         map.insert(LevelStarted);
+///     }
+/// }
+///
+/// app.add_spritefusion_hooks(LevelHooks);
+/// ```
+pub trait SpriteFusionHooks: Send + Sync + 'static {
+    /// Called once the assets of a map are loaded, before the map is prepared.
+    ///
+    /// Changes to `data` only apply to this map entity, not to the map asset.
+    fn on_map_loaded(&self, map: Entity, data: &mut SpriteFusionMap) {
+        let _ = (map, data);
+    }
+
+    /// Called for every spawned layer, after the crate inserted its own components.
+    fn on_layer_spawn(&self, layer: &mut EntityCommands, data: &SpriteFusionLayer) {
+        let _ = (layer, data);
+    }
+
+    /// Called for every spawned tile, after the crate inserted its own components.
+    fn on_tile_spawn(
+        &self,
+        tile: &mut EntityCommands,
+        data: &SpriteFusionTile,
+        layer: &SpriteFusionLayer,
+    ) {
+        let _ = (tile, data, layer);
+    }
+
+    /// Called once all layers and tiles of a map are spawned.
+    fn on_map_ready(&self, map: &mut EntityCommands, data: &SpriteFusionMap) {
+        let _ = (map, data);
+    }
+
+    /// Called before a map is despawned or respawned with
+    /// [`SpriteFusionCommandsExt`](crate::commands::SpriteFusionCommandsExt), while its
+    /// layers and tiles still exist.
+    fn on_map_despawn(&self, world: &mut World, map: Entity) {
+        let _ = (world, map);
+    }
+}
+
+/// A function called for every spawned tile, see
+/// [`TileSpawnHookAppExt::add_tile_spawn_hook`].
+pub type TileSpawnHook =
+    dyn Fn(&mut EntityCommands, &SpriteFusionTile, &SpriteFusionLayer) + Send + Sync;
+
+struct TileSpawnFn(Box<TileSpawnHook>);
+
+impl SpriteFusionHooks for TileSpawnFn {
+    fn on_tile_spawn(
+        &self,
+        tile: &mut EntityCommands,
+        data: &SpriteFusionTile,
+        layer: &SpriteFusionLayer,
+    ) {
+        (self.0)(tile, data, layer);
+    }
+}
+
+/// The registered [`SpriteFusionHooks`], run in registration order.
+#[derive(Resource, Default, Clone)]
+pub struct SpriteFusionHookRegistry(Vec<Arc<dyn SpriteFusionHooks>>);
+
+impl SpriteFusionHookRegistry {
+    /// Register hooks.
+    pub fn add(&mut self, hooks: impl SpriteFusionHooks) {
+        self.0.push(Arc::new(hooks));
+    }
+
+    /// Register a function called for every spawned tile.
+    pub fn add_tile_spawn_hook(
+        &mut self,
+        hook: impl Fn(&mut EntityCommands, &SpriteFusionTile, &SpriteFusionLayer)
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.add(TileSpawnFn(Box::new(hook)));
+    }
+
+    /// Check if no hooks are registered.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn map_loaded(&self, map: Entity, data: &mut SpriteFusionMap) {
+        for hooks in &self.0 {
+            hooks.on_map_loaded(map, data);
+        }
+    }
+
+    pub(crate) fn layer_spawned(&self, layer: &mut EntityCommands, data: &SpriteFusionLayer) {
+        for hooks in &self.0 {
+            hooks.on_layer_spawn(layer, data);
+        }
+    }
+
+    pub(crate) fn tile_spawned(
+        &self,
+        tile: &mut EntityCommands,
+        data: &SpriteFusionTile,
+        layer: &SpriteFusionLayer,
+    ) {
+        for hooks in &self.0 {
+            hooks.on_tile_spawn(tile, data, layer);
+        }
+    }
+
+    pub(crate) fn map_ready(&self, map: &mut EntityCommands, data: &SpriteFusionMap) {
+        for hooks in &self.0 {
+            hooks.on_map_ready(map, data);
+        }
+    }
+
+    pub(crate) fn map_despawned(&self, world: &mut World, map: Entity) {
+        for hooks in &self.0 {
+            hooks.on_map_despawn(world, map);
+        }
+    }
+}
+
+/// Extension methods on [`App`] registering [`SpriteFusionHooks`].
+pub trait SpriteFusionHooksAppExt {
+    /// Register hooks, run after the ones registered before.
+    fn add_spritefusion_hooks(&mut self, hooks: impl SpriteFusionHooks) -> &mut Self;
+}
+
+impl SpriteFusionHooksAppExt for App {
+    fn add_spritefusion_hooks(&mut self, hooks: impl SpriteFusionHooks) -> &mut Self {
+        self.init_resource::<SpriteFusionHookRegistry>();
+        self.world_mut()
+            .resource_mut::<SpriteFusionHookRegistry>()
+            .add(hooks);
+        self
+    }
+}
+
+/// Extension methods on [`App`] registering tile spawn hooks.
+pub trait TileSpawnHookAppExt {
+    /// Call `hook` for every spawned tile.
+    ///
+    /// A shorthand for [`SpriteFusionHooks::on_tile_spawn`].
+    ///
+    /// ```rust,ignore
+    /// #[derive(Component)]
+    /// struct Spikes;
+    ///
+    /// app.add_tile_spawn_hook(|tile, data, _layer| {
+    ///     if data.attributes.as_ref().is_some_and(|attrs| attrs.contains_key("spikes")) {
+    ///         tile.insert(Spikes);
+    ///     }
+    /// });
+    /// ```
+    fn add_tile_spawn_hook(
+        &mut self,
+        hook: impl Fn(&mut EntityCommands, &SpriteFusionTile, &SpriteFusionLayer)
+            + Send
+            + Sync
+            + 'static,
+    ) -> &mut Self;
+}
+
+impl TileSpawnHookAppExt for App {
+    fn add_tile_spawn_hook(
+        &mut self,
+        hook: impl Fn(&mut EntityCommands, &SpriteFusionTile, &SpriteFusionLayer)
+            + Send
+            + Sync
+            + 'static,
+    ) -> &mut Self {
+        self.init_resource::<SpriteFusionHookRegistry>();
+        self.world_mut()
+            .resource_mut::<SpriteFusionHookRegistry>()
+            .add_tile_spawn_hook(hook);
+        self
+    }
+}
@@ -0,0 +1,280 @@
+//! Interaction prompts for tiles tagged in the editor.
+//!
+//! Tiles with an `"interact"` attribute (e.g. `"interact": "read_sign"`) get an
+//! [`Interactable`] component. [`InteractionPlugin`] then tells when a player stands
+//! next to one, and when the player presses the interaction key.
+
+use std::{collections::HashMap, marker::PhantomData};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::{AttrKey, StoredTileAttributes},
+    geometry::{tile_to_world, world_rect_to_tiles},
+    plugin::SpriteFusionSettings,
+    types::{MapPaused, SpriteFusionLayerMarker, TileAttributes},
+};
+
+/// Attribute naming the action of an interactable tile.
+pub const INTERACT_ATTRIBUTE: &str = "interact";
+/// Attribute holding the prompt text of an interactable tile.
+pub const PROMPT_ATTRIBUTE: &str = "prompt";
+
+/// Plugin that reports interactions between players marked with `P` and
+/// [`Interactable`] tiles.
+///
+/// ```rust,ignore
+/// #[derive(Component)]
+/// struct Player;
+///
+/// app.add_plugins(InteractionPlugin::<Player>::default());
+///
+/// fn interact(mut events: MessageReader<InteractTriggered<Player>>) {
+///     for event in events.read() {
+///         if event.action == "read_sign" {
+///             // ...
+///         }
+///     }
+/// }
+/// ```
+pub struct InteractionPlugin<P> {
+    /// Key triggering the interaction.
+    pub key: KeyCode,
+    /// Maximum distance in tiles between the player and the tile.
+    pub range: u32,
+    _marker: PhantomData<fn() -> P>,
+}
+
+impl<P> Default for InteractionPlugin<P> {
+    fn default() -> Self {
+        Self {
+            key: KeyCode::KeyE,
+            range: 1,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<P: Component> Plugin for InteractionPlugin<P> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Interactions::<P> {
+            key: self.key,
+            range: self.range,
+            targets: HashMap::new(),
+            _marker: PhantomData,
+        })
+        .add_message::<InteractAvailable<P>>()
+        .add_message::<InteractUnavailable<P>>()
+        .add_message::<InteractTriggered<P>>()
+        .add_systems(
+            Update,
+            (
+                (tag_interactables, tag_stored_interactables),
+                detect_interactions::<P>,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Component attached to tiles with an `"interact"` attribute.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct Interactable {
+    /// The action to perform, from the `"interact"` attribute.
+    pub action: String,
+    /// Text to show to the player, from the optional `"prompt"` attribute.
+    pub prompt: Option<String>,
+}
+
+impl Interactable {
+    fn from_attributes(attrs: &TileAttributes, action_key: &str, prompt_key: &str) -> Option<Self> {
+        Some(Self {
+            action: attrs.get_str(action_key)?.to_string(),
+            prompt: attrs.get_str(prompt_key).map(str::to_string),
+        })
+    }
+}
+
+/// Sent when a player marked with `P` gets a new interactable tile in range.
+#[derive(Message)]
+pub struct InteractAvailable<P> {
+    /// The player entity.
+    pub player: Entity,
+    /// The nearest interactable tile.
+    pub tile: Entity,
+    /// The action of the tile.
+    pub action: String,
+    /// The prompt of the tile.
+    pub prompt: Option<String>,
+    _marker: PhantomData<fn() -> P>,
+}
+
+/// Sent when a player marked with `P` no longer has an interactable tile in range.
+#[derive(Message)]
+pub struct InteractUnavailable<P> {
+    /// The player entity.
+    pub player: Entity,
+    _marker: PhantomData<fn() -> P>,
+}
+
+/// Sent when a player marked with `P` presses the interaction key next to a tile.
+#[derive(Message)]
+pub struct InteractTriggered<P> {
+    /// The player entity.
+    pub player: Entity,
+    /// The tile interacted with.
+    pub tile: Entity,
+    /// The action of the tile.
+    pub action: String,
+    _marker: PhantomData<fn() -> P>,
+}
+
+/// Interaction settings and the current target of each player marked with `P`.
+#[derive(Resource)]
+pub struct Interactions<P> {
+    /// Key triggering the interaction.
+    pub key: KeyCode,
+    /// Maximum distance in tiles between the player and the tile.
+    pub range: u32,
+    targets: HashMap<Entity, Entity>,
+    _marker: PhantomData<fn() -> P>,
+}
+
+impl<P> Interactions<P> {
+    /// Get the interactable tile currently in range of a player.
+    pub fn target(&self, player: Entity) -> Option<Entity> {
+        self.targets.get(&player).copied()
+    }
+}
+
+/// System that keeps [`Interactable`] in sync with the attribute components of tiles.
+fn tag_interactables(
+    mut commands: Commands,
+    tiles: Query<(Entity, &TileAttributes), Changed<TileAttributes>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let action_key = settings.reserved_attribute(INTERACT_ATTRIBUTE);
+    let prompt_key = settings.reserved_attribute(PROMPT_ATTRIBUTE);
+    for (tile, attrs) in tiles.iter() {
+        match Interactable::from_attributes(attrs, &action_key, &prompt_key) {
+            Some(interactable) => commands.entity(tile).insert(interactable),
+            None => commands.entity(tile).remove::<Interactable>(),
+        };
+    }
+}
+
+/// System that inserts [`Interactable`] on tiles using an attribute store.
+fn tag_stored_interactables(
+    mut commands: Commands,
+    tiles: Query<Entity, Added<AttrKey>>,
+    attributes: StoredTileAttributes,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let action_key = settings.reserved_attribute(INTERACT_ATTRIBUTE);
+    let prompt_key = settings.reserved_attribute(PROMPT_ATTRIBUTE);
+    for tile in tiles.iter() {
+        let interactable = attributes
+            .get(tile)
+            .and_then(|attrs| Interactable::from_attributes(attrs, &action_key, &prompt_key));
+        if let Some(interactable) = interactable {
+            commands.entity(tile).insert(interactable);
+        }
+    }
+}
+
+/// System that finds the nearest interactable tile of each player.
+#[allow(clippy::too_many_arguments)]
+fn detect_interactions<P: Component>(
+    players: Query<(Entity, &GlobalTransform), With<P>>,
+    layers: Query<(
+        &SpriteFusionLayerMarker,
+        &TileStorage,
+        &TilemapSize,
+        &TilemapGridSize,
+        &GlobalTransform,
+    )>,
+    paused_maps: Query<(), With<MapPaused>>,
+    interactables: Query<(&Interactable, &TilePos)>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut interactions: ResMut<Interactions<P>>,
+    mut available: MessageWriter<InteractAvailable<P>>,
+    mut unavailable: MessageWriter<InteractUnavailable<P>>,
+    mut triggered: MessageWriter<InteractTriggered<P>>,
+) {
+    let mut previous = std::mem::take(&mut interactions.targets);
+    let range = interactions.range as f32;
+
+    for (player, player_transform) in players.iter() {
+        let center = player_transform.translation().truncate();
+        let mut nearest: Option<(f32, Entity)> = None;
+
+        for (layer, storage, map_size, grid_size, layer_transform) in layers.iter() {
+            if paused_maps.contains(layer.map) {
+                continue;
+            }
+            let half_size = Vec2::new(grid_size.x, grid_size.y) * range;
+            let Some((min, max)) =
+                world_rect_to_tiles(center, half_size, layer_transform, grid_size, map_size)
+            else {
+                continue;
+            };
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let Some(tile) = storage.get(&TilePos { x, y }) else {
+                        continue;
+                    };
+                    let Ok((_, tile_pos)) = interactables.get(tile) else {
+                        continue;
+                    };
+                    let distance = tile_to_world(tile_pos, layer_transform, grid_size)
+                        .distance_squared(center);
+                    if nearest.is_none_or(|(d, _)| distance < d) {
+                        nearest = Some((distance, tile));
+                    }
+                }
+            }
+        }
+
+        let before = previous.remove(&player);
+        let Some((_, tile)) = nearest else {
+            if before.is_some() {
+                unavailable.write(InteractUnavailable {
+                    player,
+                    _marker: PhantomData,
+                });
+            }
+            continue;
+        };
+        interactions.targets.insert(player, tile);
+
+        let Ok((interactable, _)) = interactables.get(tile) else {
+            continue;
+        };
+        if before != Some(tile) {
+            available.write(InteractAvailable {
+                player,
+                tile,
+                action: interactable.action.clone(),
+                prompt: interactable.prompt.clone(),
+                _marker: PhantomData,
+            });
+        }
+        if keyboard.just_pressed(interactions.key) {
+            triggered.write(InteractTriggered {
+                player,
+                tile,
+                action: interactable.action.clone(),
+                _marker: PhantomData,
+            });
+        }
+    }
+
+    // Players that were despawned or lost their marker
+    for player in previous.into_keys() {
+        unavailable.write(InteractUnavailable {
+            player,
+            _marker: PhantomData,
+        });
+    }
+}
@@ -0,0 +1,230 @@
+//! Text labels from tile attributes.
+//!
+//! Designers annotate tiles in the Sprite Fusion editor with a `"label"`
+//! attribute: sign text, tutorial hints, notes for the team. With
+//! [`TileLabelPlugin`], those annotations show up in-game as [`Text2d`] entities
+//! attached to their tile, see
+//! [`attach_to_tile`](crate::commands::SpriteFusionCommandsExt::attach_to_tile).
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    attachments::AttachedToTile,
+    attributes::{AttrKey, StoredTileAttributes},
+    commands::SpriteFusionCommandsExt,
+    types::TileAttributes,
+};
+
+/// Attribute holding the text of a tile label.
+pub const LABEL_ATTRIBUTE: &str = "label";
+
+/// Plugin that spawns a text label on tiles with a [`LABEL_ATTRIBUTE`] attribute.
+///
+/// Labels only show in debug builds by default; set
+/// [`TileLabelSettings::enabled`] to keep them in release builds, e.g. for signs,
+/// or to toggle them at runtime.
+///
+/// ```rust,ignore
+/// app.add_plugins(TileLabelPlugin)
+///     .insert_resource(TileLabelSettings {
+///         enabled: true,
+///         ..default()
+///     });
+/// ```
+pub struct TileLabelPlugin;
+
+impl Plugin for TileLabelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileLabelSettings>().add_systems(
+            Update,
+            (
+                rebuild_tile_labels,
+                update_tile_labels,
+                update_stored_tile_labels,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Settings of tile labels.
+#[derive(Resource, Debug, Clone)]
+pub struct TileLabelSettings {
+    /// Whether labels are shown. Defaults to `true` in debug builds only.
+    pub enabled: bool,
+    /// Attribute holding the label text. Defaults to [`LABEL_ATTRIBUTE`].
+    pub attribute: String,
+    /// Font size of labels.
+    pub font_size: f32,
+    /// Color of labels.
+    pub color: Color,
+}
+
+impl Default for TileLabelSettings {
+    fn default() -> Self {
+        Self {
+            enabled: cfg!(debug_assertions),
+            attribute: LABEL_ATTRIBUTE.to_string(),
+            font_size: 8.0,
+            color: Color::WHITE,
+        }
+    }
+}
+
+impl TileLabelSettings {
+    /// Get the label text of a tile, if it has one.
+    pub fn text(&self, attrs: &TileAttributes) -> Option<String> {
+        match attrs.0.get(&self.attribute)? {
+            serde_json::Value::String(text) => Some(text.clone()),
+            serde_json::Value::Null => None,
+            value => Some(value.to_string()),
+        }
+    }
+}
+
+/// Marker component of tile label entities.
+///
+/// Labels are attached to their tile with an [`AttachedToTile`], so they follow
+/// it and are despawned with it.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct TileLabel;
+
+/// Spawn a label attached to a tile.
+fn spawn_label(
+    commands: &mut Commands,
+    settings: &TileLabelSettings,
+    tile: Entity,
+    text: String,
+) -> Entity {
+    commands.attach_to_tile(
+        tile,
+        (
+            TileLabel,
+            Text2d::new(text),
+            TextFont::from_font_size(settings.font_size),
+            TextColor(settings.color),
+        ),
+    )
+}
+
+/// Spawn, update or despawn the label of a tile.
+fn set_label(
+    commands: &mut Commands,
+    settings: &TileLabelSettings,
+    existing: &mut HashMap<Entity, Entity>,
+    texts: &mut Query<&mut Text2d, With<TileLabel>>,
+    tile: Entity,
+    text: Option<String>,
+) {
+    match (existing.get(&tile), text) {
+        (Some(label), Some(text)) => {
+            if let Ok(mut label_text) = texts.get_mut(*label) {
+                if label_text.0 != text {
+                    label_text.0 = text;
+                }
+            }
+        }
+        (Some(label), None) => {
+            commands.entity(*label).despawn();
+            existing.remove(&tile);
+        }
+        (None, Some(text)) => {
+            existing.insert(tile, spawn_label(commands, settings, tile, text));
+        }
+        (None, None) => {}
+    }
+}
+
+/// Map tiles to their label entity.
+fn labels_by_tile(
+    labels: &Query<(Entity, &AttachedToTile), With<TileLabel>>,
+) -> HashMap<Entity, Entity> {
+    labels
+        .iter()
+        .map(|(label, attachment)| (attachment.tile, label))
+        .collect()
+}
+
+/// System that respawns every label when the settings change.
+fn rebuild_tile_labels(
+    mut commands: Commands,
+    settings: Res<TileLabelSettings>,
+    labels: Query<Entity, With<TileLabel>>,
+    tiles: Query<(Entity, &TileAttributes)>,
+    stored_tiles: Query<Entity, With<AttrKey>>,
+    attributes: StoredTileAttributes,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for label in labels.iter() {
+        commands.entity(label).despawn();
+    }
+    if !settings.enabled {
+        return;
+    }
+    let attrs = tiles.iter().chain(
+        stored_tiles
+            .iter()
+            .filter_map(|tile| Some((tile, attributes.get(tile)?))),
+    );
+    for (tile, attrs) in attrs {
+        if let Some(text) = settings.text(attrs) {
+            spawn_label(&mut commands, &settings, tile, text);
+        }
+    }
+}
+
+/// System that keeps labels in sync with the attribute components of tiles.
+fn update_tile_labels(
+    mut commands: Commands,
+    settings: Res<TileLabelSettings>,
+    tiles: Query<(Entity, &TileAttributes), Changed<TileAttributes>>,
+    labels: Query<(Entity, &AttachedToTile), With<TileLabel>>,
+    mut texts: Query<&mut Text2d, With<TileLabel>>,
+) {
+    // Settings changes respawn every label
+    if !settings.enabled || settings.is_changed() || tiles.is_empty() {
+        return;
+    }
+    let mut existing = labels_by_tile(&labels);
+    for (tile, attrs) in tiles.iter() {
+        let text = settings.text(attrs);
+        set_label(
+            &mut commands,
+            &settings,
+            &mut existing,
+            &mut texts,
+            tile,
+            text,
+        );
+    }
+}
+
+/// System that spawns labels on tiles using an attribute store.
+fn update_stored_tile_labels(
+    mut commands: Commands,
+    settings: Res<TileLabelSettings>,
+    tiles: Query<Entity, Added<AttrKey>>,
+    attributes: StoredTileAttributes,
+    labels: Query<(Entity, &AttachedToTile), With<TileLabel>>,
+    mut texts: Query<&mut Text2d, With<TileLabel>>,
+) {
+    if !settings.enabled || settings.is_changed() || tiles.is_empty() {
+        return;
+    }
+    let mut existing = labels_by_tile(&labels);
+    for tile in tiles.iter() {
+        let text = attributes.get(tile).and_then(|attrs| settings.text(attrs));
+        set_label(
+            &mut commands,
+            &settings,
+            &mut existing,
+            &mut texts,
+            tile,
+            text,
+        );
+    }
+}
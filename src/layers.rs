@@ -0,0 +1,90 @@
+//! Access to the spawned layers of maps by name.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::types::SpriteFusionLayerMarker;
+
+/// A [`SystemParam`] finding the layers of spawned maps by name, and showing or
+/// hiding them, e.g. to reveal a hidden room or toggle a debug layer at runtime.
+///
+/// Layers are matched by their name in Sprite Fusion. If a map has several layers
+/// with the same name, all of them are affected. The methods changing visibility
+/// return `false` if the map has no such layer.
+///
+/// ```rust,ignore
+/// fn reveal_secret(mut layers: SpriteFusionLayers, found: Query<&SecretFound, Added<SecretFound>>) {
+///     for secret in found.iter() {
+///         layers.show_layer(secret.map, "Secret room");
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct SpriteFusionLayers<'w, 's> {
+    layers: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static SpriteFusionLayerMarker,
+            &'static mut Visibility,
+        ),
+    >,
+}
+
+impl SpriteFusionLayers<'_, '_> {
+    /// Get the tilemap entity of the first layer named `name` in a map.
+    pub fn get(&self, map: Entity, name: &str) -> Option<Entity> {
+        self.layers
+            .iter()
+            .find(|(_, layer, _)| layer.map == map && layer.name == name)
+            .map(|(entity, _, _)| entity)
+    }
+
+    /// Check if a layer is visible, not counting the visibility of its map.
+    ///
+    /// Returns `None` if the map has no such layer.
+    pub fn is_layer_visible(&self, map: Entity, name: &str) -> Option<bool> {
+        self.layers
+            .iter()
+            .find(|(_, layer, _)| layer.map == map && layer.name == name)
+            .map(|(_, _, visibility)| *visibility != Visibility::Hidden)
+    }
+
+    /// Show a layer hidden with [`hide_layer`](Self::hide_layer).
+    ///
+    /// The layer stays hidden while its map is hidden.
+    pub fn show_layer(&mut self, map: Entity, name: &str) -> bool {
+        self.set_layer_visible(map, name, true)
+    }
+
+    /// Hide a layer with all of its tiles.
+    pub fn hide_layer(&mut self, map: Entity, name: &str) -> bool {
+        self.set_layer_visible(map, name, false)
+    }
+
+    /// Hide a visible layer, or show a hidden one.
+    pub fn toggle_layer(&mut self, map: Entity, name: &str) -> bool {
+        let Some(visible) = self.is_layer_visible(map, name) else {
+            return false;
+        };
+        self.set_layer_visible(map, name, !visible)
+    }
+
+    /// Show or hide a layer.
+    pub fn set_layer_visible(&mut self, map: Entity, name: &str, visible: bool) -> bool {
+        let mut found = false;
+        for (_, layer, mut visibility) in self.layers.iter_mut() {
+            if layer.map != map || layer.name != name {
+                continue;
+            }
+            found = true;
+            // Inherited keeps the layer hidden while its map is hidden
+            visibility.set_if_neq(if visible {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            });
+        }
+        found
+    }
+}
@@ -38,8 +38,19 @@
 //! - **Layers**: Each Sprite Fusion layer becomes a separate tilemap
 //! - **Colliders**: Layers marked as colliders get a `Collider` component on their tiles
 //! - **Tile Attributes**: Custom attributes from Sprite Fusion are preserved as `TileAttributes` components. They can be useful for things like areas data, danger zones, etc.
+//! - **Overlap Events**: [`TileOverlapPlugin`](overlap::TileOverlapPlugin) reports when marked actors enter or leave marked tiles
 //! - **bevy_ecs_tilemap Integration**: Full compatibility with the bevy_ecs_tilemap ecosystem
 //!
+//! ## Cargo features
+//!
+//! - `render` (default): spawning and rendering maps through `bevy_ecs_tilemap`. Without it,
+//!   the crate only provides the map data types and asset loading
+//!   (`SpriteFusionAssetPlugin`), so headless tools and dedicated servers don't pull in the
+//!   renderer.
+//! - `bevy` (default, enabled by `render`): the map asset, its loader and the components.
+//!   Without it, only the [`core`] data model is available, with no Bevy dependency at all.
+//! - `labels`: `Text2d` labels on tiles with a `"label"` attribute, see the `labels` module.
+//!
 //! ## Querying Tiles
 //!
 //! ```rust,ignore
@@ -64,20 +75,260 @@
 //! }
 //! ```
 
+#[cfg(feature = "render")]
+pub mod animation;
+#[cfg(feature = "render")]
+pub mod atlas;
+#[cfg(feature = "render")]
+pub mod attachments;
+#[cfg(feature = "render")]
+pub mod attributes;
+#[cfg(feature = "render")]
+pub mod audit;
+#[cfg(feature = "render")]
+pub mod camera_path;
+#[cfg(feature = "render")]
+pub mod cameras;
+#[cfg(feature = "render")]
+pub mod capture;
+#[cfg(feature = "render")]
+pub mod colliders;
+#[cfg(feature = "render")]
+pub mod collision_grid;
+#[cfg(feature = "render")]
+pub mod collision_mask;
+#[cfg(feature = "render")]
+pub mod colorkey;
+#[cfg(feature = "render")]
+pub mod commands;
+#[cfg(feature = "render")]
+pub mod convert;
+pub mod core;
+#[cfg(feature = "render")]
+pub mod culling;
+#[cfg(feature = "render")]
+pub mod decals;
+#[cfg(feature = "render")]
+pub mod diagnostics;
+#[cfg(feature = "render")]
+pub mod directions;
+#[cfg(feature = "render")]
+pub mod editing;
+#[cfg(feature = "render")]
+pub mod export;
+#[cfg(feature = "render")]
+pub mod fallback;
+#[cfg(feature = "render")]
+pub mod floors;
+#[cfg(feature = "render")]
+pub mod footprints;
+#[cfg(feature = "render")]
+pub mod geometry;
+#[cfg(feature = "render")]
+pub mod heatmap;
+pub mod heightmap;
+#[cfg(feature = "render")]
+pub mod hooks;
+#[cfg(feature = "render")]
+pub mod interaction;
+#[cfg(feature = "labels")]
+pub mod labels;
+#[cfg(feature = "render")]
+pub mod layers;
+#[cfg(feature = "bevy")]
 pub mod loader;
+#[cfg(feature = "render")]
+pub mod map_labels;
+#[cfg(feature = "render")]
+pub mod music;
+#[cfg(feature = "render")]
+pub mod occlusion;
+#[cfg(feature = "render")]
+pub mod overlap;
+#[cfg(feature = "render")]
+pub mod pathfinding;
+#[cfg(feature = "render")]
+pub mod patrol;
+#[cfg(feature = "avian2d")]
+pub mod physics;
+#[cfg(feature = "render")]
+pub mod placement;
+#[cfg(feature = "render")]
 pub mod plugin;
+#[cfg(feature = "render")]
+pub mod pool;
+#[cfg(feature = "render")]
+pub mod preload;
+#[cfg(feature = "render")]
+pub mod prepare;
+#[cfg(feature = "render")]
+pub mod prewarm;
+#[cfg(feature = "render")]
+pub mod reload;
+#[cfg(feature = "render")]
+pub mod replay;
+#[cfg(feature = "render")]
+pub mod resources;
+#[cfg(feature = "render")]
+pub mod rooms;
+#[cfg(feature = "render")]
+pub mod seed;
+#[cfg(feature = "render")]
+pub mod spawn_points;
+#[cfg(feature = "render")]
+pub mod spawners;
+#[cfg(feature = "render")]
+pub mod state;
+#[cfg(feature = "render")]
+pub mod streaming;
+#[cfg(feature = "render")]
+pub mod terrain;
+#[cfg(feature = "render")]
+pub mod tile_index;
+#[cfg(feature = "bevy")]
 pub mod types;
+#[cfg(feature = "render")]
+pub mod variants;
+#[cfg(feature = "render")]
+pub mod zones;
 
 /// Convenient re-exports for common usage.
 pub mod prelude {
-    pub use crate::loader::SpriteFusionMapLoader;
-    pub use crate::plugin::{
-        PendingSpriteFusionMap, SpriteFusionBundle, SpriteFusionMapHandle, SpriteFusionPlugin,
-        SpriteFusionTilesetHandle,
+    pub use crate::core::{
+        ResizeAnchor, SpriteFusionAttributes, SpriteFusionLayer, SpriteFusionMap, SpriteFusionTile,
+        TileRect, TilesetDefaults, ValidationError,
     };
-    pub use crate::types::{
-        Collider, SpriteFusionLayer, SpriteFusionLayerMarker, SpriteFusionMap,
-        SpriteFusionMapMarker, SpriteFusionTile, TileAttributes,
+    pub use crate::heightmap::{extract_heightmap, Heightmap};
+
+    #[cfg(feature = "bevy")]
+    pub use crate::{
+        loader::{
+            MapBytesTransform, MapValidation, SpriteFusionAssetPlugin, SpriteFusionMapLoader,
+            SpriteFusionMapLoaderSettings, TilesetDefaultsLoader, XorTransform,
+        },
+        types::{
+            Collider, MapPaused, SpriteFusionLayerMarker, SpriteFusionMapMarker, TileAttributes,
+            TileSpawnIndex,
+        },
+    };
+
+    #[cfg(feature = "render")]
+    pub use crate::{
+        animation::{
+            SpriteFusionAnimationPlugin, TileAnimation, ANIM_FPS_ATTRIBUTE, ANIM_FRAMES_ATTRIBUTE,
+        },
+        atlas::{pack_tilesets, PackedAtlas},
+        attachments::{AttachedToTile, TileTarget},
+        attributes::{
+            reserved_attribute, AttrKey, AttrMarker, AttrMarkerPlugin, AttributeComponentAppExt,
+            AttributeComponentFn, AttributeComponentRegistry, AttributeKey, AttributeLocalizer,
+            AttributeStorage, AttributeStore, PendingTileAttributes, StoredTileAttributes,
+            TileAttributeChanged,
+        },
+        audit::{TilemapAuditPlugin, TilemapAuditReport},
+        camera_path::{
+            CameraPath, CameraPathFinished, FollowCameraPath, MapCameraPaths,
+            SpriteFusionCameraPathPlugin,
+        },
+        capture::{capture_region, CaptureError},
+        colliders::{
+            merge_tile_rects, par_merge_tile_rects, par_tile_outlines, tile_corner_to_local,
+            tile_outlines, tile_rect_to_local, MergedColliders,
+        },
+        collision_grid::CollisionGrid,
+        collision_mask::{collision_layer_from_mask, inject_collision_mask, CollisionMaskError},
+        colorkey::apply_color_key_to_image,
+        commands::SpriteFusionCommandsExt,
+        convert::{build_tilemap_data, DuplicateTilePolicy, LayerZPolicy, TileOrigin},
+        culling::CullingMargin,
+        decals::{DecalFade, MapDecals, SpawnDecal, SpriteFusionDecalsPlugin},
+        diagnostics::{MapMemoryEstimate, SpriteFusionDiagnosticsPlugin},
+        directions::{DirectionGrid, Pushable, SpriteFusionDirectionsPlugin, TileDirection},
+        editing::SpriteFusionMapCommands,
+        export::export_map,
+        fallback::checkerboard_tileset,
+        floors::{
+            layer_floor, Floor, FloorChanged, MapNavGrids, NavGrid, OnFloor, Stairs,
+            SpriteFusionFloorsPlugin,
+        },
+        footprints::{BakeFootprint, SpriteFusionFootprintsPlugin},
+        geometry::SpriteFusionMapGeometry,
+        heatmap::{MapHeatmap, SpriteFusionHeatmapPlugin},
+        hooks::{
+            SpriteFusionHookRegistry, SpriteFusionHooks, SpriteFusionHooksAppExt,
+            TileSpawnHookAppExt,
+        },
+        interaction::{
+            InteractAvailable, InteractTriggered, InteractUnavailable, Interactable,
+            InteractionPlugin, Interactions,
+        },
+        layers::SpriteFusionLayers,
+        loader::{SpriteFusionScene, SpriteFusionSceneLoader, SpriteFusionSceneLoaderSettings},
+        map_labels::{MapLabel, MapLabels},
+        music::{
+            CurrentMapMusic, MapMusicRequested, MusicListener, SpriteFusionMusicPlugin, ZoneMusic,
+        },
+        occlusion::{
+            Occluder, OcclusionFade, OcclusionFadePlugin, OcclusionScope, Silhouette,
+            SilhouettePlugin, SilhouetteSprite,
+        },
+        overlap::{
+            OverlapExtents, TileOverlapEnded, TileOverlapPlugin, TileOverlapStarted, TileOverlaps,
+        },
+        pathfinding::{
+            CornerCutting, MoveCosts, PathMovement, PathfindOptions, SpriteFusionPathfindingPlugin,
+            MOVE_COST_ATTRIBUTE,
+        },
+        patrol::{
+            extract_patrol_paths, FollowPatrol, MapPatrolPaths, PatrolPath,
+            SpriteFusionPatrolPlugin,
+        },
+        placement::{map_plane_camera, MapPlacement},
+        plugin::{
+            MapSpawnError, PendingSpriteFusionMap, SpawnPriority, SpriteFusionBundle,
+            SpriteFusionGameplayPlugins, SpriteFusionMapHandle, SpriteFusionMapSpawnFailed,
+            SpriteFusionMapSpawned, SpriteFusionPlugin, SpriteFusionSceneHandle,
+            SpriteFusionSettings, SpriteFusionSpawnProgress, SpriteFusionSystems,
+            SpriteFusionTileDefaults, SpriteFusionTilesetFallback, SpriteFusionTilesetHandle,
+            TextureIndexOffsets,
+        },
+        pool::{TileEntityPool, TileEntityPoolStats},
+        preload::{
+            map_dependencies, preload_map_folder, preload_maps, MapPreloadSet,
+            SpriteFusionPreloadPlugin, PORTAL_ATTRIBUTE, WORLD_MAPS_PROPERTY,
+        },
+        prepare::{prepare_map, PrepareError, PrepareOptions, PreparedMap},
+        prewarm::PrewarmChunks,
+        reload::TilesetReloaded,
+        replay::{MapRecorder, MapReplay, MapReplayPlayer, ReplayEvent, TileChange},
+        resources::{
+            MapResources, ResourceDepleted, ResourceEntry, ResourceNode,
+            SpriteFusionResourcesPlugin,
+        },
+        rooms::{
+            MapRooms, Room, RoomCamera, RoomDetection, RoomEntered, RoomTransition,
+            SpriteFusionRoomsPlugin,
+        },
+        seed::MapSeed,
+        spawn_points::{SpawnPoint, SpawnPointSettings},
+        spawners::{SpawnedFromTile, SpriteFusionSpawnersPlugin, TileEntitySettings},
+        state::{apply_runtime_state, serialize_runtime_state, MapRuntimeState, TileState},
+        streaming::MapStreaming,
+        terrain::{
+            extract_biomes, terrain_at, terrain_at_local, Biome, MapBiomes,
+            SpriteFusionTerrainPlugin,
+        },
+        tile_index::SpriteFusionTileIndex,
+        variants::{TilesetDensity, TilesetVariant, TilesetVariants},
+        zones::{
+            extract_zones, MapZone, SpriteFusionZonesPlugin, ZoneEntered, ZoneExited,
+            ZoneModifierAppExt, ZoneTracker, Zones,
+        },
     };
+    #[cfg(feature = "labels")]
+    pub use crate::labels::{TileLabel, TileLabelPlugin, TileLabelSettings, LABEL_ATTRIBUTE};
+    #[cfg(feature = "avian2d")]
+    pub use crate::physics::{AvianColliderMode, AvianColliderSettings, LayerColliderBody};
+    #[cfg(feature = "render")]
     pub use bevy_ecs_tilemap::prelude::TilePos;
 }
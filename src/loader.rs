@@ -1,16 +1,216 @@
 //! Asset loader for Sprite Fusion map files.
 
+use std::{error::Error as StdError, sync::Arc};
+
 use bevy::{
-    asset::{io::Reader, AssetLoader, LoadContext},
+    asset::{io::Reader, AssetLoader, LoadContext, LoadDirectError},
     prelude::*,
 };
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::types::SpriteFusionMap;
+use crate::{
+    core::{TilesetDefaults, ValidationError},
+    types::SpriteFusionMap,
+};
+
+pub use crate::core::parse_map;
+
+/// File extensions recognized by default: `*.sf.json`.
+pub const DEFAULT_EXTENSIONS: &[&str] = &["sf.json"];
+
+/// Label of the tileset image loaded along with a map, see
+/// [`SpriteFusionMapLoaderSettings`].
+pub const TILESET_LABEL: &str = "tileset";
+
+/// File name of the spritesheet Sprite Fusion exports next to the map.
+pub const SIBLING_TILESET: &str = "spritesheet.png";
+
+/// Map property giving the path of the tileset of a [`SpriteFusionScene`],
+/// relative to the map file.
+pub const TILESET_PROPERTY: &str = "tileset";
+
+/// Label of the map of a [`SpriteFusionScene`].
+pub const SCENE_MAP_LABEL: &str = "map";
+
+/// File extensions of [`TilesetDefaults`] sidecar files: `*.tiles.json`.
+pub const TILESET_DEFAULTS_EXTENSIONS: &[&str] = &["tiles.json"];
+
+/// Plugin that registers the [`SpriteFusionMap`] and [`TilesetDefaults`] assets and
+/// their loaders.
+///
+/// Added by [`SpriteFusionPlugin`](crate::plugin::SpriteFusionPlugin) with its
+/// defaults, unless already added: add it first to configure it. Add it on its own
+/// to load maps without spawning or rendering them (e.g. on a dedicated server).
+#[derive(Clone)]
+pub struct SpriteFusionAssetPlugin {
+    /// File extensions handled by the loader, without the leading dot.
+    pub extensions: Vec<&'static str>,
+    /// Transform applied to the file bytes before parsing, see [`MapBytesTransform`].
+    pub transform: Option<Arc<dyn MapBytesTransform>>,
+}
+
+impl Default for SpriteFusionAssetPlugin {
+    fn default() -> Self {
+        Self {
+            extensions: DEFAULT_EXTENSIONS.to_vec(),
+            transform: None,
+        }
+    }
+}
+
+impl SpriteFusionAssetPlugin {
+    /// Handle files with these extensions instead of the default `sf.json`.
+    pub fn with_extensions(extensions: &[&'static str]) -> Self {
+        Self {
+            extensions: extensions.to_vec(),
+            ..default()
+        }
+    }
+
+    /// Transform the file bytes before parsing them, e.g. to decrypt shipped maps.
+    pub fn with_transform(mut self, transform: impl MapBytesTransform) -> Self {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
+}
+
+impl Plugin for SpriteFusionAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SpriteFusionMap>()
+            .init_asset::<TilesetDefaults>()
+            .register_asset_loader(SpriteFusionMapLoader {
+                extensions: self.extensions.clone(),
+                transform: self.transform.clone(),
+            })
+            .register_asset_loader(TilesetDefaultsLoader);
+        #[cfg(feature = "render")]
+        app.init_asset::<SpriteFusionScene>()
+            .register_asset_loader(SpriteFusionSceneLoader {
+                extensions: self.extensions.clone(),
+                transform: self.transform.clone(),
+            });
+    }
+}
+
+/// A transform applied to map file bytes before they are parsed.
+///
+/// Lets studios decrypt or de-obfuscate shipped map files without replacing the
+/// loader. Closures taking and returning the bytes implement it too.
+///
+/// ```rust,ignore
+/// // Maps compressed with zstd
+/// let plugin = SpriteFusionAssetPlugin::default().with_transform(|bytes: Vec<u8>| {
+///     Ok(zstd::decode_all(bytes.as_slice())?)
+/// });
+/// app.add_plugins((plugin, SpriteFusionPlugin));
+/// ```
+pub trait MapBytesTransform: Send + Sync + 'static {
+    /// Transform the raw file bytes into Sprite Fusion JSON.
+    fn transform(&self, bytes: Vec<u8>) -> Result<Vec<u8>, Box<dyn StdError + Send + Sync>>;
+}
+
+impl<F> MapBytesTransform for F
+where
+    F: Fn(Vec<u8>) -> Result<Vec<u8>, Box<dyn StdError + Send + Sync>> + Send + Sync + 'static,
+{
+    fn transform(&self, bytes: Vec<u8>) -> Result<Vec<u8>, Box<dyn StdError + Send + Sync>> {
+        self(bytes)
+    }
+}
+
+/// XOR every byte with a repeating key.
+///
+/// Only a light obfuscation against casual inspection of shipped files, not
+/// encryption. XOR is its own inverse, so the same key obfuscates the maps at
+/// build time.
+#[derive(Debug, Clone)]
+pub struct XorTransform {
+    key: Vec<u8>,
+}
+
+impl XorTransform {
+    /// Create a transform with this key. An empty key leaves bytes unchanged.
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self { key: key.into() }
+    }
+
+    /// Apply the XOR to bytes in place.
+    pub fn apply(&self, bytes: &mut [u8]) {
+        if self.key.is_empty() {
+            return;
+        }
+        for (byte, key) in bytes.iter_mut().zip(self.key.iter().cycle()) {
+            *byte ^= key;
+        }
+    }
+}
+
+impl MapBytesTransform for XorTransform {
+    fn transform(&self, mut bytes: Vec<u8>) -> Result<Vec<u8>, Box<dyn StdError + Send + Sync>> {
+        self.apply(&mut bytes);
+        Ok(bytes)
+    }
+}
 
 /// Asset loader for SpriteFusion JSON map files.
-#[derive(Default, Reflect)]
-pub struct SpriteFusionMapLoader;
+#[derive(TypePath)]
+pub struct SpriteFusionMapLoader {
+    extensions: Vec<&'static str>,
+    transform: Option<Arc<dyn MapBytesTransform>>,
+}
+
+impl Default for SpriteFusionMapLoader {
+    fn default() -> Self {
+        Self {
+            extensions: DEFAULT_EXTENSIONS.to_vec(),
+            transform: None,
+        }
+    }
+}
+
+/// Settings of [`SpriteFusionMapLoader`].
+///
+/// With a tileset, the loader also loads the image as the `"tileset"` labeled
+/// asset of the map (e.g. `level1.sf.json#tileset`), and a
+/// [`SpriteFusionBundle`](crate::plugin::SpriteFusionBundle) without a tileset
+/// handle uses it, so a map spawns from a single handle:
+///
+/// ```rust,ignore
+/// let map = asset_server.load_with_settings(
+///     "maps/level1.sf.json",
+///     |settings: &mut SpriteFusionMapLoaderSettings| settings.sibling_tileset = true,
+/// );
+/// commands.spawn(SpriteFusionBundle {
+///     map: SpriteFusionMapHandle(map),
+///     ..default()
+/// });
+/// ```
+///
+/// Tilesets are only loaded with the `render` feature.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpriteFusionMapLoaderSettings {
+    /// Path of the tileset image, relative to the map file.
+    pub tileset: Option<String>,
+    /// Without a `tileset` path, load the `spritesheet.png` next to the map file,
+    /// as exported by Sprite Fusion, if there is one.
+    pub sibling_tileset: bool,
+    /// What to do with problems found by [`SpriteFusionMap::validate`].
+    pub validation: MapValidation,
+}
+
+/// What the map loaders do with problems found by [`SpriteFusionMap::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MapValidation {
+    /// Don't validate maps.
+    Off,
+    /// Log a warning per problem, and load the map anyway.
+    #[default]
+    Warn,
+    /// Fail to load maps with problems, with
+    /// [`SpriteFusionMapLoaderError::Validation`].
+    Error,
+}
 
 /// Errors that can occur when loading a SpriteFusion map.
 #[derive(Debug, Error)]
@@ -19,13 +219,130 @@ pub enum SpriteFusionMapLoaderError {
     Io(#[from] std::io::Error),
     #[error("Failed to parse map JSON: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("Failed to transform map bytes: {0}")]
+    Transform(Box<dyn StdError + Send + Sync>),
+    #[error("Invalid tileset path: {0}")]
+    TilesetPath(String),
+    #[error("Failed to load tileset: {0}")]
+    Tileset(#[from] Box<LoadDirectError>),
+    #[error("Map failed validation: {}", describe_errors(.0))]
+    Validation(Vec<ValidationError>),
+}
+
+fn describe_errors(errors: &[ValidationError]) -> String {
+    match errors {
+        [] => "no problems".to_string(),
+        [error] => error.to_string(),
+        [error, rest @ ..] => format!("{error}, and {} more problems", rest.len()),
+    }
+}
+
+/// Validate a loaded map as set by `mode`.
+fn validate_map(
+    map: &SpriteFusionMap,
+    mode: MapValidation,
+    load_context: &LoadContext<'_>,
+) -> Result<(), SpriteFusionMapLoaderError> {
+    if mode == MapValidation::Off {
+        return Ok(());
+    }
+    let errors = map.validate();
+    if errors.is_empty() {
+        return Ok(());
+    }
+    if mode == MapValidation::Error {
+        return Err(SpriteFusionMapLoaderError::Validation(errors));
+    }
+    for error in &errors {
+        warn!("Map {}: {error}", load_context.path());
+    }
+    Ok(())
 }
 
 impl AssetLoader for SpriteFusionMapLoader {
     type Asset = SpriteFusionMap;
-    type Settings = ();
+    type Settings = SpriteFusionMapLoaderSettings;
     type Error = SpriteFusionMapLoaderError;
 
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let map = read_map(reader, self.transform.as_deref()).await?;
+        validate_map(&map, settings.validation, load_context)?;
+        #[cfg(feature = "render")]
+        load_tileset(settings, load_context).await?;
+        #[cfg(not(feature = "render"))]
+        let _ = (settings, load_context);
+        Ok(map)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+}
+
+/// Read, transform and parse a map file.
+async fn read_map(
+    reader: &mut dyn Reader,
+    transform: Option<&dyn MapBytesTransform>,
+) -> Result<SpriteFusionMap, SpriteFusionMapLoaderError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+    if let Some(transform) = transform {
+        bytes = transform
+            .transform(bytes)
+            .map_err(SpriteFusionMapLoaderError::Transform)?;
+    }
+    Ok(parse_map(&bytes)?)
+}
+
+/// Load the tileset of a map as its `"tileset"` labeled asset.
+#[cfg(feature = "render")]
+async fn load_tileset(
+    settings: &SpriteFusionMapLoaderSettings,
+    load_context: &mut LoadContext<'_>,
+) -> Result<(), SpriteFusionMapLoaderError> {
+    let (path, required) = match &settings.tileset {
+        Some(path) => (path.as_str(), true),
+        None if settings.sibling_tileset => (SIBLING_TILESET, false),
+        None => return Ok(()),
+    };
+    let path = load_context
+        .path()
+        .resolve_embed(path)
+        .map_err(|err| SpriteFusionMapLoaderError::TilesetPath(err.to_string()))?;
+    match load_context.loader().immediate().load::<Image>(path).await {
+        Ok(image) => {
+            load_context.add_loaded_labeled_asset(TILESET_LABEL, image);
+        }
+        // A missing sibling spritesheet only means the tileset is loaded separately
+        Err(err) if !required => debug!("No sibling tileset loaded: {err}"),
+        Err(err) => return Err(Box::new(err).into()),
+    }
+    Ok(())
+}
+
+/// Asset loader for [`TilesetDefaults`] sidecar files (`*.tiles.json`).
+#[derive(TypePath, Default)]
+pub struct TilesetDefaultsLoader;
+
+/// Errors that can occur when loading tileset defaults.
+#[derive(Debug, Error)]
+pub enum TilesetDefaultsLoaderError {
+    #[error("Failed to read tileset defaults file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse tileset defaults JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl AssetLoader for TilesetDefaultsLoader {
+    type Asset = TilesetDefaults;
+    type Settings = ();
+    type Error = TilesetDefaultsLoaderError;
+
     async fn load(
         &self,
         reader: &mut dyn Reader,
@@ -34,11 +351,86 @@ impl AssetLoader for SpriteFusionMapLoader {
     ) -> Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
-        let map: SpriteFusionMap = serde_json::from_slice(&bytes)?;
-        Ok(map)
+        Ok(TilesetDefaults::from_json_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        TILESET_DEFAULTS_EXTENSIONS
+    }
+}
+
+/// A map file loaded along with its tileset.
+///
+/// Loading a map file as a scene gives a single handle to spawn the map with,
+/// see [`SpriteFusionSceneHandle`](crate::plugin::SpriteFusionSceneHandle):
+///
+/// ```rust,ignore
+/// commands.spawn(SpriteFusionSceneHandle(asset_server.load("level1.sf.json")));
+/// ```
+#[cfg(feature = "render")]
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct SpriteFusionScene {
+    /// The map, the `"map"` labeled asset of the scene.
+    #[dependency]
+    pub map: Handle<SpriteFusionMap>,
+    /// The tileset image.
+    #[dependency]
+    pub tileset: Handle<Image>,
+}
+
+/// Settings of [`SpriteFusionSceneLoader`].
+#[cfg(feature = "render")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpriteFusionSceneLoaderSettings {
+    /// Path of the tileset image, relative to the map file.
+    ///
+    /// Defaults to the `"tileset"` property of the map, or else to the
+    /// `spritesheet.png` next to the map file, as exported by Sprite Fusion.
+    pub tileset: Option<String>,
+    /// What to do with problems found by [`SpriteFusionMap::validate`].
+    pub validation: MapValidation,
+}
+
+/// Asset loader for map files loaded as a [`SpriteFusionScene`].
+///
+/// Handles the same files as [`SpriteFusionMapLoader`]; which one loads a file
+/// depends on the asset type requested.
+#[cfg(feature = "render")]
+#[derive(TypePath)]
+pub struct SpriteFusionSceneLoader {
+    extensions: Vec<&'static str>,
+    transform: Option<Arc<dyn MapBytesTransform>>,
+}
+
+#[cfg(feature = "render")]
+impl AssetLoader for SpriteFusionSceneLoader {
+    type Asset = SpriteFusionScene;
+    type Settings = SpriteFusionSceneLoaderSettings;
+    type Error = SpriteFusionMapLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let map = read_map(reader, self.transform.as_deref()).await?;
+        validate_map(&map, settings.validation, load_context)?;
+        let tileset_path = settings
+            .tileset
+            .as_deref()
+            .or_else(|| map.properties.get(TILESET_PROPERTY)?.as_str())
+            .unwrap_or(SIBLING_TILESET);
+        let tileset_path = load_context
+            .path()
+            .resolve_embed(tileset_path)
+            .map_err(|err| SpriteFusionMapLoaderError::TilesetPath(err.to_string()))?;
+        let tileset = load_context.load(tileset_path);
+        let map = load_context.add_labeled_asset(SCENE_MAP_LABEL.to_string(), map);
+        Ok(SpriteFusionScene { map, tileset })
     }
 
     fn extensions(&self) -> &[&str] {
-        &["sf.json"]
+        &self.extensions
     }
 }
@@ -0,0 +1,111 @@
+//! Labels grouping map instances.
+//!
+//! Games often spawn several instances of the same kind of map: every floor of a
+//! dungeon, every background room. Insert a [`MapLabel`] on their map entities to
+//! find them all at once through the [`MapLabels`] index.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+pub(crate) struct SpriteFusionMapLabelsPlugin;
+
+impl Plugin for SpriteFusionMapLabelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapLabels>()
+            .add_systems(PreUpdate, index_map_labels);
+    }
+}
+
+/// Component labeling a map entity, to address it along with every map sharing
+/// the label, see [`MapLabels`].
+///
+/// ```rust,ignore
+/// commands.spawn((
+///     SpriteFusionBundle {
+///         map: asset_server.load("floor_1.sf.json"),
+///         ..default()
+///     },
+///     MapLabel::new("dungeon_floor"),
+/// ));
+/// ```
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash, Deref)]
+pub struct MapLabel(pub String);
+
+impl MapLabel {
+    /// A label with this name.
+    pub fn new(label: impl Into<String>) -> Self {
+        Self(label.into())
+    }
+}
+
+/// Index of map entities by [`MapLabel`].
+///
+/// Updated in [`PreUpdate`], so labels inserted during a frame are indexed from
+/// the next one. Maps are listed in entity order.
+///
+/// ```rust,ignore
+/// fn hide_floors(labels: Res<MapLabels>, mut commands: Commands) {
+///     for &map in labels.maps_with_label("dungeon_floor") {
+///         commands.entity(map).insert(Visibility::Hidden);
+///     }
+/// }
+/// ```
+#[derive(Resource, Debug, Default)]
+pub struct MapLabels {
+    maps: HashMap<String, Vec<Entity>>,
+    labels: HashMap<Entity, String>,
+}
+
+impl MapLabels {
+    /// Get the maps with a label.
+    pub fn maps_with_label(&self, label: &str) -> &[Entity] {
+        self.maps.get(label).map_or(&[], Vec::as_slice)
+    }
+
+    /// Get the label of a map, if it has one.
+    pub fn label(&self, map: Entity) -> Option<&str> {
+        self.labels.get(&map).map(String::as_str)
+    }
+
+    /// Iterate over the labels in use.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.maps.keys().map(String::as_str)
+    }
+
+    fn insert(&mut self, map: Entity, label: &str) {
+        self.remove(map);
+        let maps = self.maps.entry(label.to_string()).or_default();
+        if let Err(index) = maps.binary_search(&map) {
+            maps.insert(index, map);
+        }
+        self.labels.insert(map, label.to_string());
+    }
+
+    fn remove(&mut self, map: Entity) {
+        let Some(label) = self.labels.remove(&map) else {
+            return;
+        };
+        if let Some(maps) = self.maps.get_mut(&label) {
+            maps.retain(|entity| *entity != map);
+            if maps.is_empty() {
+                self.maps.remove(&label);
+            }
+        }
+    }
+}
+
+/// System that keeps the [`MapLabels`] index in sync with [`MapLabel`]
+/// components.
+fn index_map_labels(
+    mut index: ResMut<MapLabels>,
+    labels: Query<(Entity, &MapLabel), Changed<MapLabel>>,
+    mut removed: RemovedComponents<MapLabel>,
+) {
+    for map in removed.read() {
+        index.remove(map);
+    }
+    for (map, label) in labels.iter() {
+        index.insert(map, label);
+    }
+}
@@ -0,0 +1,184 @@
+//! Music metadata of maps and zones.
+//!
+//! A map-level `"music"` property (see
+//! [`SpriteFusionMap::properties`](crate::core::SpriteFusionMap::properties)) sets
+//! the track of a level, and tiles of a zone with a `"music"` attribute set the
+//! track of that zone. This module doesn't play anything: it keeps
+//! [`CurrentMapMusic`] up to date and sends [`MapMusicRequested`], for your audio
+//! code to switch tracks.
+
+use bevy::prelude::*;
+
+use crate::{
+    plugin::SpriteFusionSettings,
+    types::SpriteFusionMapMarker,
+    zones::{MapZone, SpriteFusionZonesPlugin, ZoneEntered, ZoneExited, ZoneTracker},
+};
+
+/// Map property and tile attribute naming a music track.
+pub const MUSIC_KEY: &str = "music";
+
+/// Plugin that tracks the music requested by maps and zones.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+/// Adds [`SpriteFusionZonesPlugin`] if it's missing, to follow zone music: when
+/// adding both by hand, add the zones plugin first.
+pub struct SpriteFusionMusicPlugin;
+
+impl Plugin for SpriteFusionMusicPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SpriteFusionZonesPlugin>() {
+            app.add_plugins(SpriteFusionZonesPlugin);
+        }
+        app.init_resource::<CurrentMapMusic>()
+            .add_message::<MapMusicRequested>()
+            .add_systems(
+                Update,
+                (request_map_music, tag_zone_music, request_zone_music).chain(),
+            );
+    }
+}
+
+/// The music track currently requested.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CurrentMapMusic {
+    /// The track, as written in the map.
+    pub track: Option<String>,
+    /// The map requesting it.
+    pub map: Option<Entity>,
+}
+
+/// Sent when the requested music track changes.
+///
+/// ```rust,ignore
+/// fn switch_music(mut events: MessageReader<MapMusicRequested>, asset_server: Res<AssetServer>) {
+///     for event in events.read() {
+///         let track: Handle<AudioSource> = asset_server.load(format!("music/{}.ogg", event.track));
+///         // ...
+///     }
+/// }
+/// ```
+#[derive(Message, Debug, Clone)]
+pub struct MapMusicRequested {
+    /// The track, as written in the map.
+    pub track: String,
+    /// The map requesting it.
+    pub map: Entity,
+}
+
+/// Marker component for the entity whose zone changes drive the music, usually
+/// the player. It also needs a [`ZoneTracker`].
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct MusicListener;
+
+/// Music track of a zone, attached to [`MapZone`] entities.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct ZoneMusic(pub String);
+
+impl CurrentMapMusic {
+    fn request(
+        &mut self,
+        track: &str,
+        map: Entity,
+        requested: &mut MessageWriter<MapMusicRequested>,
+    ) {
+        self.map = Some(map);
+        if self.track.as_deref() == Some(track) {
+            return;
+        }
+        self.track = Some(track.to_string());
+        requested.write(MapMusicRequested {
+            track: track.to_string(),
+            map,
+        });
+    }
+}
+
+fn map_music<'a>(marker: &'a SpriteFusionMapMarker, key: &str) -> Option<&'a str> {
+    marker.map.properties.get(key).and_then(|v| v.as_str())
+}
+
+/// System that requests the music of newly spawned maps.
+fn request_map_music(
+    maps: Query<(Entity, &SpriteFusionMapMarker), Added<SpriteFusionMapMarker>>,
+    mut current: ResMut<CurrentMapMusic>,
+    mut requested: MessageWriter<MapMusicRequested>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let key = settings.reserved_attribute(MUSIC_KEY);
+    for (map, marker) in maps.iter() {
+        if let Some(track) = map_music(marker, &key) {
+            current.request(track, map, &mut requested);
+        }
+    }
+}
+
+/// System that inserts [`ZoneMusic`] on new zones with a music attribute.
+fn tag_zone_music(
+    mut commands: Commands,
+    zones: Query<(Entity, &MapZone), Added<MapZone>>,
+    maps: Query<&SpriteFusionMapMarker>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let key = settings.reserved_attribute(MUSIC_KEY);
+    for (entity, zone) in zones.iter() {
+        let Ok(marker) = maps.get(zone.map) else {
+            continue;
+        };
+        let track = marker
+            .map
+            .layers
+            .iter()
+            .flat_map(|layer| layer.tiles.iter())
+            .filter(|tile| zone.tiles.contains(&(tile.x, tile.y)))
+            .find_map(|tile| tile.attributes.as_ref()?.get(&*key)?.as_str());
+        if let Some(track) = track {
+            commands.entity(entity).insert(ZoneMusic(track.to_string()));
+        }
+    }
+}
+
+/// System that follows the zones of the [`MusicListener`].
+#[allow(clippy::too_many_arguments)]
+fn request_zone_music(
+    listeners: Query<&ZoneTracker, With<MusicListener>>,
+    zones: Query<(&MapZone, Option<&ZoneMusic>)>,
+    maps: Query<&SpriteFusionMapMarker>,
+    mut entered: MessageReader<ZoneEntered>,
+    mut exited: MessageReader<ZoneExited>,
+    mut current: ResMut<CurrentMapMusic>,
+    mut requested: MessageWriter<MapMusicRequested>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let key = settings.reserved_attribute(MUSIC_KEY);
+    for event in exited.read() {
+        let Ok(tracker) = listeners.get(event.entity) else {
+            continue;
+        };
+        // Back to the music of another zone the listener is still in, or to the map's
+        let zone_track = tracker.zones().iter().find_map(|&zone| {
+            let (map_zone, music) = zones.get(zone).ok()?;
+            Some((music?.0.as_str(), map_zone.map))
+        });
+        if let Some((track, map)) = zone_track {
+            current.request(track, map, &mut requested);
+        } else if let Ok((zone, _)) = zones.get(event.zone) {
+            if let Some(track) = maps
+                .get(zone.map)
+                .ok()
+                .and_then(|marker| map_music(marker, &key))
+            {
+                current.request(track, zone.map, &mut requested);
+            }
+        }
+    }
+
+    for event in entered.read() {
+        if !listeners.contains(event.entity) {
+            continue;
+        }
+        if let Ok((zone, Some(music))) = zones.get(event.zone) {
+            current.request(&music.0, zone.map, &mut requested);
+        }
+    }
+}
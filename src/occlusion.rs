@@ -0,0 +1,353 @@
+//! Fading of roofs and canopies covering tracked entities.
+//!
+//! Tiles with an `"occluder"` attribute (e.g. `"occluder": true` on a roof layer)
+//! get an [`Occluder`] component. [`OcclusionFadePlugin`] fades the occluders
+//! covering entities marked with `T` out, and back in when they leave. Without
+//! fading, [`SilhouettePlugin`] draws a silhouette of covered entities instead.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    marker::PhantomData,
+};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::{AttrKey, StoredTileAttributes},
+    geometry::world_to_tile,
+    plugin::SpriteFusionSettings,
+    types::{MapPaused, SpriteFusionLayerMarker, TileAttributes},
+};
+
+/// Attribute marking a tile as an occluder.
+pub const OCCLUDER_ATTRIBUTE: &str = "occluder";
+
+/// Plugin that fades the [`Occluder`] tiles covering entities marked with `T`.
+///
+/// ```rust,ignore
+/// #[derive(Component)]
+/// struct Player;
+///
+/// app.add_plugins(OcclusionFadePlugin::<Player>::default());
+/// ```
+pub struct OcclusionFadePlugin<T> {
+    /// Alpha of faded tiles.
+    pub alpha: f32,
+    /// Alpha change per second while fading.
+    pub speed: f32,
+    /// What fades with the tile covering the entity.
+    pub scope: OcclusionScope,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for OcclusionFadePlugin<T> {
+    fn default() -> Self {
+        Self {
+            alpha: 0.25,
+            speed: 4.0,
+            scope: OcclusionScope::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Component> Plugin for OcclusionFadePlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(OcclusionFade::<T> {
+            alpha: self.alpha,
+            speed: self.speed,
+            scope: self.scope,
+            _marker: PhantomData,
+        })
+        .add_systems(Update, fade_occluders::<T>);
+        if !app.is_plugin_added::<OccluderTagsPlugin>() {
+            app.add_plugins(OccluderTagsPlugin);
+        }
+    }
+}
+
+/// Plugin drawing a [`Silhouette`] of entities covered by [`Occluder`] tiles.
+///
+/// The silhouette is a tinted copy of the entity's [`Sprite`], drawn as a child
+/// just above the covering layer, so the character stays visible under a roof
+/// without fading it.
+///
+/// ```rust,ignore
+/// app.add_plugins(SilhouettePlugin);
+/// commands.spawn((Sprite::from_image(player), Silhouette::default()));
+/// ```
+pub struct SilhouettePlugin;
+
+impl Plugin for SilhouettePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_silhouettes);
+        if !app.is_plugin_added::<OccluderTagsPlugin>() {
+            app.add_plugins(OccluderTagsPlugin);
+        }
+    }
+}
+
+/// Tags occluder tiles, shared by the occlusion plugins.
+struct OccluderTagsPlugin;
+
+impl Plugin for OccluderTagsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (tag_occluders, tag_stored_occluders));
+    }
+}
+
+/// The tiles faded when an entity is under an occluder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OcclusionScope {
+    /// The occluder tiles connected to the covering one, e.g. a single roof.
+    #[default]
+    Region,
+    /// Every occluder tile of the covering layer.
+    Layer,
+}
+
+/// Marker component for tiles with an `"occluder"` attribute.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Occluder;
+
+/// Component drawing a silhouette of a sprite entity while it's covered by an
+/// [`Occluder`] tile, see [`SilhouettePlugin`].
+#[derive(Component, Debug, Clone)]
+pub struct Silhouette {
+    /// Tint of the silhouette, multiplied with the sprite image.
+    pub color: Color,
+    sprite: Option<Entity>,
+}
+
+impl Silhouette {
+    /// Draw the silhouette with this tint.
+    pub fn new(color: Color) -> Self {
+        Self {
+            color,
+            sprite: None,
+        }
+    }
+
+    /// Check if the silhouette is currently drawn.
+    pub fn is_visible(&self) -> bool {
+        self.sprite.is_some()
+    }
+}
+
+impl Default for Silhouette {
+    fn default() -> Self {
+        Self::new(Color::srgba(1.0, 1.0, 1.0, 0.5))
+    }
+}
+
+/// Marker component for the child sprite drawing a [`Silhouette`].
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SilhouetteSprite;
+
+/// Occlusion fade settings of entities marked with `T`.
+#[derive(Resource)]
+pub struct OcclusionFade<T> {
+    /// Alpha of faded tiles.
+    pub alpha: f32,
+    /// Alpha change per second while fading.
+    pub speed: f32,
+    /// What fades with the tile covering the entity.
+    pub scope: OcclusionScope,
+    _marker: PhantomData<fn() -> T>,
+}
+
+fn is_occluder(attrs: &TileAttributes, key: &str) -> bool {
+    attrs.get_bool(key).unwrap_or(false)
+}
+
+/// System that keeps [`Occluder`] in sync with the attribute components of tiles.
+fn tag_occluders(
+    mut commands: Commands,
+    tiles: Query<(Entity, &TileAttributes), Changed<TileAttributes>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let key = settings.reserved_attribute(OCCLUDER_ATTRIBUTE);
+    for (tile, attrs) in tiles.iter() {
+        if is_occluder(attrs, &key) {
+            commands.entity(tile).insert(Occluder);
+        } else {
+            commands.entity(tile).remove::<Occluder>();
+        }
+    }
+}
+
+/// System that inserts [`Occluder`] on tiles using an attribute store.
+fn tag_stored_occluders(
+    mut commands: Commands,
+    tiles: Query<Entity, Added<AttrKey>>,
+    attributes: StoredTileAttributes,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let key = settings.reserved_attribute(OCCLUDER_ATTRIBUTE);
+    for tile in tiles.iter() {
+        if attributes
+            .get(tile)
+            .is_some_and(|attrs| is_occluder(attrs, &key))
+        {
+            commands.entity(tile).insert(Occluder);
+        }
+    }
+}
+
+/// Collect the occluder tiles connected to `start` on a layer.
+fn occluder_region(
+    start: TilePos,
+    storage: &TileStorage,
+    occluders: &Query<(Entity, &TilemapId, &mut TileColor), With<Occluder>>,
+    region: &mut HashSet<Entity>,
+) {
+    let mut queue = VecDeque::from([start]);
+    while let Some(pos) = queue.pop_front() {
+        let Some(tile) = storage.checked_get(&pos) else {
+            continue;
+        };
+        if !occluders.contains(tile) || !region.insert(tile) {
+            continue;
+        }
+        let neighbors = [
+            pos.x.checked_sub(1).map(|x| TilePos { x, y: pos.y }),
+            Some(TilePos {
+                x: pos.x + 1,
+                y: pos.y,
+            }),
+            pos.y.checked_sub(1).map(|y| TilePos { x: pos.x, y }),
+            Some(TilePos {
+                x: pos.x,
+                y: pos.y + 1,
+            }),
+        ];
+        queue.extend(neighbors.into_iter().flatten());
+    }
+}
+
+/// System that fades occluders covering entities marked with `T`, and restores the others.
+#[allow(clippy::type_complexity)]
+fn fade_occluders<T: Component>(
+    targets: Query<&GlobalTransform, With<T>>,
+    layers: Query<(
+        Entity,
+        &SpriteFusionLayerMarker,
+        &TileStorage,
+        &TilemapSize,
+        &TilemapGridSize,
+        &GlobalTransform,
+    )>,
+    paused_maps: Query<(), With<MapPaused>>,
+    mut occluders: Query<(Entity, &TilemapId, &mut TileColor), With<Occluder>>,
+    fade: Res<OcclusionFade<T>>,
+    time: Res<Time>,
+) {
+    let mut faded = HashSet::new();
+    let mut faded_layers = HashSet::new();
+    for target in targets.iter() {
+        let position = target.translation().truncate();
+        for (layer_entity, layer, storage, map_size, grid_size, layer_transform) in layers.iter() {
+            if paused_maps.contains(layer.map) {
+                continue;
+            }
+            let Some(pos) = world_to_tile(position, layer_transform, grid_size, map_size) else {
+                continue;
+            };
+            if !storage
+                .get(&pos)
+                .is_some_and(|tile| occluders.contains(tile))
+            {
+                continue;
+            }
+            match fade.scope {
+                OcclusionScope::Region => occluder_region(pos, storage, &occluders, &mut faded),
+                OcclusionScope::Layer => {
+                    faded_layers.insert(layer_entity);
+                }
+            }
+        }
+    }
+
+    let step = fade.speed * time.delta_secs();
+    for (tile, tilemap_id, mut color) in occluders.iter_mut() {
+        let target = if faded.contains(&tile) || faded_layers.contains(&tilemap_id.0) {
+            fade.alpha
+        } else {
+            1.0
+        };
+        let alpha = color.0.alpha();
+        // Only touch tiles still fading, so settled chunks aren't extracted again
+        if alpha != target {
+            let alpha = if alpha < target {
+                (alpha + step).min(target)
+            } else {
+                (alpha - step).max(target)
+            };
+            color.0.set_alpha(alpha);
+        }
+    }
+}
+
+/// System that shows, syncs and hides the silhouettes of covered entities.
+#[allow(clippy::type_complexity)]
+fn update_silhouettes(
+    mut commands: Commands,
+    mut entities: Query<(Entity, &mut Silhouette, &Sprite, &GlobalTransform)>,
+    mut sprites: Query<
+        (&mut Sprite, &mut Transform),
+        (With<SilhouetteSprite>, Without<Silhouette>),
+    >,
+    layers: Query<(
+        &SpriteFusionLayerMarker,
+        &TileStorage,
+        &TilemapSize,
+        &TilemapGridSize,
+        &GlobalTransform,
+    )>,
+    paused_maps: Query<(), With<MapPaused>>,
+    occluders: Query<(), With<Occluder>>,
+) {
+    for (entity, mut silhouette, sprite, transform) in entities.iter_mut() {
+        let translation = transform.translation();
+        // The highest covering occluder layer above the entity
+        let cover_z = layers
+            .iter()
+            .filter(|(layer, ..)| !paused_maps.contains(layer.map))
+            .filter_map(|(_, storage, map_size, grid_size, layer_transform)| {
+                let pos =
+                    world_to_tile(translation.truncate(), layer_transform, grid_size, map_size)?;
+                let tile = storage.get(&pos)?;
+                let z = layer_transform.translation().z;
+                (occluders.contains(tile) && z > translation.z).then_some(z)
+            })
+            .reduce(f32::max);
+
+        let Some(cover_z) = cover_z else {
+            if let Some(child) = silhouette.sprite.take() {
+                commands.entity(child).despawn();
+            }
+            continue;
+        };
+
+        let mut copy = sprite.clone();
+        copy.color = silhouette.color;
+        // Just above the cover, in the entity's local space
+        let local = Transform::from_xyz(0.0, 0.0, cover_z - translation.z + 0.001);
+        match silhouette
+            .sprite
+            .and_then(|child| sprites.get_mut(child).ok())
+        {
+            Some((mut child_sprite, mut child_transform)) => {
+                *child_sprite = copy;
+                *child_transform = local;
+            }
+            None => {
+                let child = commands
+                    .spawn((copy, local, SilhouetteSprite, ChildOf(entity)))
+                    .id();
+                silhouette.sprite = Some(child);
+            }
+        }
+    }
+}
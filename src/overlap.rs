@@ -0,0 +1,232 @@
+//! Generic overlap detection between marked tiles and actors.
+
+use std::{
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    floors::{Floor, OnFloor},
+    geometry::world_rect_to_tiles,
+    types::{MapPaused, SpriteFusionLayerMarker},
+};
+
+/// Plugin that reports overlaps between tiles marked with `T` and actors marked with `A`.
+///
+/// Add one instance per (tile marker, actor marker) pair. Every pair uses the same
+/// grid broadphase: an actor only inspects the tiles it covers on each layer.
+/// Actors with an [`OnFloor`] component only overlap tiles of layers on their floor.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use bevy::prelude::*;
+/// use bevy_spritefusion::prelude::*;
+///
+/// #[derive(Component)]
+/// struct Player;
+///
+/// fn main() {
+///     App::new()
+///         .add_plugins(DefaultPlugins)
+///         .add_plugins(SpriteFusionPlugin)
+///         .add_plugins(TileOverlapPlugin::<Collider, Player>::default())
+///         .add_systems(Update, on_touch)
+///         .run();
+/// }
+///
+/// fn on_touch(mut events: MessageReader<TileOverlapStarted<Collider, Player>>) {
+///     for event in events.read() {
+///         info!("Touching wall at ({}, {})", event.tile_pos.x, event.tile_pos.y);
+///     }
+/// }
+/// ```
+pub struct TileOverlapPlugin<T, A> {
+    _marker: PhantomData<fn() -> (T, A)>,
+}
+
+impl<T, A> Default for TileOverlapPlugin<T, A> {
+    fn default() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Component, A: Component> Plugin for TileOverlapPlugin<T, A> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileOverlaps<T, A>>()
+            .add_message::<TileOverlapStarted<T, A>>()
+            .add_message::<TileOverlapEnded<T, A>>()
+            .add_systems(Update, detect_tile_overlaps::<T, A>);
+    }
+}
+
+/// Half-size of an actor used for overlap tests.
+///
+/// Actors without this component are treated as a single point.
+#[derive(Component, Debug, Clone, Copy, Default, Deref, DerefMut)]
+pub struct OverlapExtents(pub Vec2);
+
+/// Sent when an actor marked with `A` starts overlapping a tile marked with `T`.
+#[derive(Message)]
+pub struct TileOverlapStarted<T, A> {
+    /// The actor entity.
+    pub actor: Entity,
+    /// The tile entity.
+    pub tile: Entity,
+    /// Position of the tile in its layer.
+    pub tile_pos: TilePos,
+    _marker: PhantomData<fn() -> (T, A)>,
+}
+
+/// Sent when an actor marked with `A` stops overlapping a tile marked with `T`.
+///
+/// Also sent when either entity is despawned or loses its marker.
+#[derive(Message)]
+pub struct TileOverlapEnded<T, A> {
+    /// The actor entity.
+    pub actor: Entity,
+    /// The tile entity.
+    pub tile: Entity,
+    _marker: PhantomData<fn() -> (T, A)>,
+}
+
+/// Current overlaps between actors marked with `A` and tiles marked with `T`.
+#[derive(Resource)]
+pub struct TileOverlaps<T, A> {
+    current: HashMap<Entity, HashSet<Entity>>,
+    _marker: PhantomData<fn() -> (T, A)>,
+}
+
+impl<T, A> Default for TileOverlaps<T, A> {
+    fn default() -> Self {
+        Self {
+            current: HashMap::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, A> TileOverlaps<T, A> {
+    /// Get the tiles an actor currently overlaps.
+    pub fn tiles(&self, actor: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.current.get(&actor).into_iter().flatten().copied()
+    }
+
+    /// Check if an actor currently overlaps a specific tile.
+    pub fn is_overlapping(&self, actor: Entity, tile: Entity) -> bool {
+        self.current
+            .get(&actor)
+            .map(|tiles| tiles.contains(&tile))
+            .unwrap_or(false)
+    }
+}
+
+/// System that updates overlaps for one (tile marker, actor marker) pair.
+#[allow(clippy::type_complexity)]
+fn detect_tile_overlaps<T: Component, A: Component>(
+    actors: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            Option<&OverlapExtents>,
+            Option<&OnFloor>,
+        ),
+        With<A>,
+    >,
+    layers: Query<(
+        &SpriteFusionLayerMarker,
+        Option<&Floor>,
+        &TileStorage,
+        &TilemapSize,
+        &TilemapGridSize,
+        &GlobalTransform,
+    )>,
+    paused_maps: Query<(), With<MapPaused>>,
+    tiles: Query<(&TilePos, &TilemapId), With<T>>,
+    mut overlaps: ResMut<TileOverlaps<T, A>>,
+    mut started: MessageWriter<TileOverlapStarted<T, A>>,
+    mut ended: MessageWriter<TileOverlapEnded<T, A>>,
+) {
+    let mut previous = std::mem::take(&mut overlaps.current);
+    let is_paused = |layer: &SpriteFusionLayerMarker| paused_maps.contains(layer.map);
+
+    for (actor, actor_transform, extents, on_floor) in actors.iter() {
+        let center = actor_transform.translation().truncate();
+        let half_size = extents.map(|e| e.0).unwrap_or(Vec2::ZERO);
+        let before = previous.remove(&actor).unwrap_or_default();
+
+        // Overlaps on paused maps are frozen as they are
+        let mut covered: HashSet<Entity> = before
+            .iter()
+            .copied()
+            .filter(|&tile| {
+                tiles
+                    .get(tile)
+                    .ok()
+                    .and_then(|(_, tilemap_id)| layers.get(tilemap_id.0).ok())
+                    .is_some_and(|(layer, ..)| is_paused(layer))
+            })
+            .collect();
+
+        for (layer, floor, storage, map_size, grid_size, layer_transform) in layers.iter() {
+            if is_paused(layer) {
+                continue;
+            }
+            if on_floor.is_some_and(|on| floor.map_or(0, |f| f.0) != on.floor) {
+                continue;
+            }
+            let Some((min, max)) =
+                world_rect_to_tiles(center, half_size, layer_transform, grid_size, map_size)
+            else {
+                continue;
+            };
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    if let Some(tile) = storage.get(&TilePos { x, y }) {
+                        if tiles.contains(tile) {
+                            covered.insert(tile);
+                        }
+                    }
+                }
+            }
+        }
+
+        for &tile in covered.difference(&before) {
+            if let Ok((tile_pos, _)) = tiles.get(tile) {
+                started.write(TileOverlapStarted {
+                    actor,
+                    tile,
+                    tile_pos: *tile_pos,
+                    _marker: PhantomData,
+                });
+            }
+        }
+        for &tile in before.difference(&covered) {
+            ended.write(TileOverlapEnded {
+                actor,
+                tile,
+                _marker: PhantomData,
+            });
+        }
+
+        if !covered.is_empty() {
+            overlaps.current.insert(actor, covered);
+        }
+    }
+
+    // Actors that were despawned or lost their marker
+    for (actor, tiles) in previous {
+        for tile in tiles {
+            ended.write(TileOverlapEnded {
+                actor,
+                tile,
+                _marker: PhantomData,
+            });
+        }
+    }
+}
@@ -0,0 +1,540 @@
+//! A* pathfinding over the collision grid of maps.
+//!
+//! [`CollisionGrid::pathfind`] finds the cheapest path of tiles between two
+//! cells, walking four or eight ways around blocked cells. Tiles with a
+//! `"moveCost": n` attribute make up the [`MoveCosts`] of their map, which
+//! [`CollisionGrid::pathfind_weighted`] takes into account to prefer roads over
+//! swamps.
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    f32::consts::SQRT_2,
+};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::reserved_attribute,
+    collision_grid::CollisionGrid,
+    convert::{layer_tiles, TileOrigin},
+    core::SpriteFusionTile,
+    geometry::SpriteFusionMapGeometry,
+    plugin::{SpriteFusionSettings, SpriteFusionSystems},
+    types::{SpriteFusionMap, SpriteFusionMapMarker},
+};
+
+/// Attribute giving the cost of moving onto a tile.
+pub const MOVE_COST_ATTRIBUTE: &str = "moveCost";
+
+/// Plugin that adds the [`MoveCosts`] of spawned maps.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionPathfindingPlugin;
+
+impl Plugin for SpriteFusionPathfindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_move_costs.after(SpriteFusionSystems::Spawn));
+    }
+}
+
+/// How paths move from a cell to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PathMovement {
+    /// Up, down, left and right only.
+    #[default]
+    FourWay,
+    /// Diagonally too, following [`CornerCutting`].
+    EightWay,
+}
+
+/// When diagonal moves may pass by blocked cells.
+///
+/// A diagonal move passes by the two cells sharing an edge with both its start
+/// and its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CornerCutting {
+    /// Diagonal moves need both cells beside them free, so paths never touch
+    /// the corner of a wall.
+    #[default]
+    Never,
+    /// Diagonal moves need one of the cells beside them free, so paths can cut
+    /// around the corner of a wall but not squeeze between two diagonal walls.
+    OneCorner,
+    /// Diagonal moves only need their destination free.
+    Always,
+}
+
+/// Options of [`CollisionGrid::pathfind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PathfindOptions {
+    /// How paths move from a cell to the next.
+    pub movement: PathMovement,
+    /// When diagonal moves may pass by blocked cells, with
+    /// [`PathMovement::EightWay`].
+    pub corner_cutting: CornerCutting,
+}
+
+impl PathfindOptions {
+    /// Move up, down, left and right only.
+    pub fn four_way() -> Self {
+        Self::default()
+    }
+
+    /// Move diagonally too, with the given corner cutting rule.
+    pub fn eight_way(corner_cutting: CornerCutting) -> Self {
+        Self {
+            movement: PathMovement::EightWay,
+            corner_cutting,
+        }
+    }
+}
+
+/// Component of spawned maps with tiles having a `"moveCost"` attribute, giving
+/// the cost of moving onto each cell.
+///
+/// Cells without a cost cost 1. Where several layers have a cost at the same
+/// position, the topmost one wins. Costs must be positive; other values are
+/// ignored.
+///
+/// Built when the map spawns, and updated cell by cell when
+/// [`SpriteFusionMapCommands`](crate::editing::SpriteFusionMapCommands) removes
+/// tiles. After changing tile attributes in the map data by hand, call
+/// [`update_cell`](Self::update_cell) for the cells changed.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct MoveCosts {
+    costs: HashMap<TilePos, f32>,
+    min_cost: f32,
+}
+
+impl MoveCosts {
+    /// Collect the move costs of a map's tiles, by spawned [`TilePos`],
+    /// reading attributes with the given
+    /// [`SpriteFusionSettings::attribute_prefix`].
+    pub fn from_map(map: &SpriteFusionMap, origin: TileOrigin, prefix: &str) -> Self {
+        let key = reserved_attribute(prefix, MOVE_COST_ATTRIBUTE);
+        let mut costs = HashMap::new();
+        // Walk layers bottom to top, so upper layers overwrite lower ones
+        for layer in map.layers.iter().rev() {
+            for tile in layer_tiles(map, layer, origin) {
+                if let Some(cost) = tile_move_cost(tile.tile, &key) {
+                    costs.insert(tile.position, cost);
+                }
+            }
+        }
+        let min_cost = costs.values().copied().fold(1.0, f32::min);
+        Self { costs, min_cost }
+    }
+
+    /// Recompute the cost of one cell from the map data, after its tiles
+    /// changed.
+    pub fn update_cell(
+        &mut self,
+        map: &SpriteFusionMap,
+        geometry: &SpriteFusionMapGeometry,
+        tile_pos: TilePos,
+        prefix: &str,
+    ) {
+        let key = reserved_attribute(prefix, MOVE_COST_ATTRIBUTE);
+        let pos = geometry.sprite_fusion_pos(tile_pos);
+        // Layers are listed top to bottom, the topmost cost wins
+        let cost = map
+            .layers
+            .iter()
+            .flat_map(|layer| &layer.tiles)
+            .filter(|tile| tile.x == pos.x && tile.y == pos.y)
+            .find_map(|tile| tile_move_cost(tile, &key));
+        match cost {
+            Some(cost) => self.set_cost(tile_pos, cost),
+            None => {
+                self.costs.remove(&tile_pos);
+            }
+        }
+    }
+
+    /// Check if no tile has a move cost.
+    pub fn is_empty(&self) -> bool {
+        self.costs.is_empty()
+    }
+
+    /// Get the cost of moving onto a cell.
+    pub fn cost(&self, tile_pos: TilePos) -> f32 {
+        self.costs.get(&tile_pos).copied().unwrap_or(1.0)
+    }
+
+    /// Set the cost of moving onto a cell. Non-positive costs reset it to 1.
+    ///
+    /// The cheapest cost ever set still bounds path estimates after it's
+    /// replaced, which keeps paths optimal but can make searches slower.
+    pub fn set_cost(&mut self, tile_pos: TilePos, cost: f32) {
+        if cost.is_finite() && cost > 0.0 {
+            self.costs.insert(tile_pos, cost);
+            self.min_cost = self.min_cost.min(cost);
+        } else {
+            self.costs.remove(&tile_pos);
+        }
+    }
+}
+
+impl CollisionGrid {
+    /// Find the shortest path from `start` to `goal` around blocked cells.
+    ///
+    /// The path lists every cell from `start` to `goal`, both included. Returns
+    /// `None` if `goal` is blocked or can't be reached. `start` itself may be
+    /// blocked, e.g. for an actor standing in a doorway.
+    ///
+    /// ```rust,ignore
+    /// fn chase(maps: Query<&CollisionGrid>, mut enemies: Query<&mut Path, With<Enemy>>, target: Res<Target>) {
+    ///     let Ok(grid) = maps.single() else {
+    ///         return;
+    ///     };
+    ///     let options = PathfindOptions::eight_way(CornerCutting::Never);
+    ///     for mut path in enemies.iter_mut() {
+    ///         path.tiles = grid.pathfind(path.current, target.0, &options).unwrap_or_default();
+    ///     }
+    /// }
+    /// ```
+    pub fn pathfind(
+        &self,
+        start: TilePos,
+        goal: TilePos,
+        options: &PathfindOptions,
+    ) -> Option<Vec<TilePos>> {
+        astar(self, None, start, goal, options)
+    }
+
+    /// Like [`pathfind`](Self::pathfind), but find the cheapest path, moves
+    /// costing the [`MoveCosts`] of the cell they end on. Diagonal moves cost
+    /// `√2` times as much.
+    pub fn pathfind_weighted(
+        &self,
+        costs: &MoveCosts,
+        start: TilePos,
+        goal: TilePos,
+        options: &PathfindOptions,
+    ) -> Option<Vec<TilePos>> {
+        astar(self, Some(costs), start, goal, options)
+    }
+}
+
+/// A cell of the open set, ordered by lowest estimated total cost first.
+#[derive(Debug, Clone, Copy)]
+struct OpenCell {
+    estimate: f32,
+    cost: f32,
+    pos: TilePos,
+}
+
+impl PartialEq for OpenCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenCell {}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed, BinaryHeap pops the greatest
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+const ORTHOGONAL: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+const DIAGONAL: [IVec2; 4] = [
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+fn astar(
+    grid: &CollisionGrid,
+    costs: Option<&MoveCosts>,
+    start: TilePos,
+    goal: TilePos,
+    options: &PathfindOptions,
+) -> Option<Vec<TilePos>> {
+    if !start.within_map_bounds(&grid.size()) || grid.is_blocked(goal) {
+        return None;
+    }
+    let cell_cost = |pos: TilePos| costs.map_or(1.0, |costs| costs.cost(pos));
+    // Scaled by the cheapest cell, so the estimate never overshoots
+    let min_cost = costs.map_or(1.0, |costs| costs.min_cost);
+    let heuristic = |pos: TilePos| {
+        let dx = pos.x.abs_diff(goal.x) as f32;
+        let dy = pos.y.abs_diff(goal.y) as f32;
+        let distance = match options.movement {
+            PathMovement::FourWay => dx + dy,
+            PathMovement::EightWay => dx.max(dy) + (SQRT_2 - 1.0) * dx.min(dy),
+        };
+        distance * min_cost
+    };
+    let free = |pos: IVec2| to_tile_pos(pos).is_some_and(|pos| !grid.is_blocked(pos));
+
+    // Best known cost of each reached cell, with the cell it was reached from
+    let mut reached: HashMap<TilePos, (f32, TilePos)> = HashMap::new();
+    let mut open = BinaryHeap::new();
+    reached.insert(start, (0.0, start));
+    open.push(OpenCell {
+        estimate: heuristic(start),
+        cost: 0.0,
+        pos: start,
+    });
+
+    while let Some(OpenCell { cost, pos, .. }) = open.pop() {
+        if pos == goal {
+            let mut path = vec![goal];
+            let mut current = goal;
+            while current != start {
+                current = reached[&current].1;
+                path.push(current);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if reached.get(&pos).is_some_and(|&(best, _)| cost > best) {
+            continue;
+        }
+
+        let cell = IVec2::new(pos.x as i32, pos.y as i32);
+        let diagonals = match options.movement {
+            PathMovement::FourWay => &[][..],
+            PathMovement::EightWay => &DIAGONAL[..],
+        };
+        for &step in ORTHOGONAL.iter().chain(diagonals) {
+            let next = cell + step;
+            if !free(next) {
+                continue;
+            }
+            let diagonal = step.x != 0 && step.y != 0;
+            if diagonal {
+                let beside = [
+                    free(cell + IVec2::new(step.x, 0)),
+                    free(cell + IVec2::new(0, step.y)),
+                ];
+                let allowed = match options.corner_cutting {
+                    CornerCutting::Never => beside[0] && beside[1],
+                    CornerCutting::OneCorner => beside[0] || beside[1],
+                    CornerCutting::Always => true,
+                };
+                if !allowed {
+                    continue;
+                }
+            }
+            let Some(next) = to_tile_pos(next) else {
+                continue;
+            };
+            let length = if diagonal { SQRT_2 } else { 1.0 };
+            let next_cost = cost + length * cell_cost(next);
+            if reached
+                .get(&next)
+                .is_some_and(|&(best, _)| next_cost >= best)
+            {
+                continue;
+            }
+            reached.insert(next, (next_cost, pos));
+            open.push(OpenCell {
+                estimate: next_cost + heuristic(next),
+                cost: next_cost,
+                pos: next,
+            });
+        }
+    }
+    None
+}
+
+/// Get the move cost attribute of a tile, if it's a valid cost.
+pub(crate) fn tile_move_cost(tile: &SpriteFusionTile, key: &str) -> Option<f32> {
+    tile.attributes
+        .as_ref()?
+        .get(key)?
+        .as_f64()
+        .map(|cost| cost as f32)
+        .filter(|cost| cost.is_finite() && *cost > 0.0)
+}
+
+fn to_tile_pos(cell: IVec2) -> Option<TilePos> {
+    (cell.x >= 0 && cell.y >= 0).then_some(TilePos {
+        x: cell.x as u32,
+        y: cell.y as u32,
+    })
+}
+
+/// System that collects the move costs of newly spawned maps.
+fn update_move_costs(
+    mut commands: Commands,
+    maps: Query<
+        (Entity, &SpriteFusionMapMarker, &SpriteFusionMapGeometry),
+        Added<SpriteFusionMapMarker>,
+    >,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (entity, marker, geometry) in maps.iter() {
+        let costs = MoveCosts::from_map(
+            &marker.map,
+            geometry.tile_origin,
+            &settings.attribute_prefix,
+        );
+        if costs.is_empty() {
+            commands.entity(entity).remove::<MoveCosts>();
+        } else {
+            commands.entity(entity).insert(costs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked_grid(size: (u32, u32), blocked: &[(u32, u32)]) -> CollisionGrid {
+        let mut grid = CollisionGrid::new(TilemapSize {
+            x: size.0,
+            y: size.1,
+        });
+        for &(x, y) in blocked {
+            grid.set_blocked(TilePos { x, y }, true);
+        }
+        grid
+    }
+
+    fn pos(x: u32, y: u32) -> TilePos {
+        TilePos { x, y }
+    }
+
+    /// Cost of walking a path, as the search counts it.
+    fn path_cost(path: &[TilePos], costs: &MoveCosts) -> f32 {
+        path.windows(2)
+            .map(|step| {
+                let diagonal = step[0].x != step[1].x && step[0].y != step[1].y;
+                costs.cost(step[1]) * if diagonal { SQRT_2 } else { 1.0 }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn four_way_path_goes_around_walls() {
+        // A wall across the grid, with a gap at the top
+        let grid = blocked_grid((5, 4), &[(2, 0), (2, 1), (2, 2)]);
+        let path = grid
+            .pathfind(pos(0, 0), pos(4, 0), &PathfindOptions::four_way())
+            .unwrap();
+        assert_eq!(path.first(), Some(&pos(0, 0)));
+        assert_eq!(path.last(), Some(&pos(4, 0)));
+        assert_eq!(path.len(), 11);
+        assert!(path.contains(&pos(2, 3)));
+        assert!(path.iter().all(|&cell| !grid.is_blocked(cell)));
+        for step in path.windows(2) {
+            assert_eq!(
+                step[0].x.abs_diff(step[1].x) + step[0].y.abs_diff(step[1].y),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn corner_cutting_around_one_wall() {
+        let grid = blocked_grid((3, 3), &[(1, 0)]);
+        let never = grid
+            .pathfind(
+                pos(0, 0),
+                pos(1, 1),
+                &PathfindOptions::eight_way(CornerCutting::Never),
+            )
+            .unwrap();
+        assert_eq!(never, vec![pos(0, 0), pos(0, 1), pos(1, 1)]);
+        for corner_cutting in [CornerCutting::OneCorner, CornerCutting::Always] {
+            let path = grid
+                .pathfind(
+                    pos(0, 0),
+                    pos(1, 1),
+                    &PathfindOptions::eight_way(corner_cutting),
+                )
+                .unwrap();
+            assert_eq!(path, vec![pos(0, 0), pos(1, 1)]);
+        }
+    }
+
+    #[test]
+    fn corner_cutting_between_diagonal_walls() {
+        let grid = blocked_grid((2, 2), &[(1, 0), (0, 1)]);
+        for corner_cutting in [CornerCutting::Never, CornerCutting::OneCorner] {
+            let options = PathfindOptions::eight_way(corner_cutting);
+            assert_eq!(grid.pathfind(pos(0, 0), pos(1, 1), &options), None);
+        }
+        let options = PathfindOptions::eight_way(CornerCutting::Always);
+        assert_eq!(
+            grid.pathfind(pos(0, 0), pos(1, 1), &options),
+            Some(vec![pos(0, 0), pos(1, 1)])
+        );
+    }
+
+    #[test]
+    fn weighted_path_takes_cheap_road() {
+        // A road two rows up, cheaper than one cell: the heuristic must not
+        // overestimate with it, or the straight path would win
+        let grid = blocked_grid((10, 3), &[]);
+        let mut costs = MoveCosts::default();
+        for x in 0..10 {
+            costs.set_cost(pos(x, 2), 0.1);
+        }
+        for options in [
+            PathfindOptions::four_way(),
+            PathfindOptions::eight_way(CornerCutting::Never),
+        ] {
+            let path = grid
+                .pathfind_weighted(&costs, pos(0, 0), pos(9, 0), &options)
+                .unwrap();
+            assert!(path.iter().any(|cell| cell.y == 2), "{path:?}");
+            let straight = grid.pathfind(pos(0, 0), pos(9, 0), &options).unwrap();
+            assert!(path_cost(&path, &costs) < path_cost(&straight, &costs));
+        }
+        let path = grid
+            .pathfind_weighted(&costs, pos(0, 0), pos(9, 0), &PathfindOptions::four_way())
+            .unwrap();
+        assert!((path_cost(&path, &costs) - 4.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn weighted_path_avoids_expensive_cells() {
+        let grid = blocked_grid((5, 3), &[]);
+        let mut costs = MoveCosts::default();
+        costs.set_cost(pos(2, 0), 10.0);
+        let path = grid
+            .pathfind_weighted(&costs, pos(0, 0), pos(4, 0), &PathfindOptions::four_way())
+            .unwrap();
+        assert!(!path.contains(&pos(2, 0)));
+        assert_eq!(path_cost(&path, &costs), 6.0);
+    }
+
+    #[test]
+    fn blocked_start_and_goal() {
+        let grid = blocked_grid((3, 3), &[(0, 0), (2, 2)]);
+        let options = PathfindOptions::four_way();
+        // Actors standing in a blocked cell can walk out of it
+        let path = grid.pathfind(pos(0, 0), pos(0, 2), &options).unwrap();
+        assert_eq!(path, vec![pos(0, 0), pos(0, 1), pos(0, 2)]);
+        assert_eq!(grid.pathfind(pos(0, 1), pos(2, 2), &options), None);
+        assert_eq!(grid.pathfind(pos(5, 0), pos(1, 1), &options), None);
+        assert_eq!(grid.pathfind(pos(1, 1), pos(5, 0), &options), None);
+        assert_eq!(
+            grid.pathfind(pos(1, 1), pos(1, 1), &options),
+            Some(vec![pos(1, 1)])
+        );
+    }
+
+    #[test]
+    fn unreachable_goal() {
+        let grid = blocked_grid((5, 5), &[(3, 4), (3, 3), (4, 3)]);
+        let options = PathfindOptions::eight_way(CornerCutting::Always);
+        assert!(grid.pathfind(pos(0, 0), pos(4, 4), &options).is_none());
+    }
+}
@@ -0,0 +1,198 @@
+//! Patrol routes drawn in the editor with attribute tiles.
+//!
+//! Tag tiles with `"patrol": "<name>"` and `"order": n` to draw the waypoints of a
+//! route. Spawned maps get a [`MapPatrolPaths`] component with every route, and
+//! entities with a [`FollowPatrol`] component walk along one of them.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    attributes::reserved_attribute,
+    convert::map_local_position,
+    plugin::SpriteFusionSettings,
+    types::{MapPaused, SpriteFusionMap, SpriteFusionMapMarker},
+};
+
+/// Attribute naming the patrol route a tile belongs to.
+pub const PATROL_ATTRIBUTE: &str = "patrol";
+/// Attribute giving the position of a tile in its patrol route.
+pub const ORDER_ATTRIBUTE: &str = "order";
+
+/// Plugin that extracts patrol routes from maps and moves [`FollowPatrol`] entities.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionPatrolPlugin;
+
+impl Plugin for SpriteFusionPatrolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (insert_patrol_paths, follow_patrols).chain());
+    }
+}
+
+/// A patrol route: waypoints in the local space of their map entity.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PatrolPath {
+    /// Waypoints, in patrol order.
+    pub waypoints: Vec<Vec2>,
+}
+
+/// Patrol routes of a spawned map, by name.
+#[derive(Component, Debug, Clone, Default)]
+pub struct MapPatrolPaths(pub HashMap<String, PatrolPath>);
+
+impl MapPatrolPaths {
+    /// Get a route by name.
+    pub fn get(&self, name: &str) -> Option<&PatrolPath> {
+        self.0.get(name)
+    }
+}
+
+/// Collect the patrol routes of a map, reading attributes with the given
+/// [`SpriteFusionSettings::attribute_prefix`](crate::plugin::SpriteFusionSettings::attribute_prefix).
+///
+/// Waypoints are sorted by their `"order"` attribute; tiles without it come first.
+pub fn extract_patrol_paths(map: &SpriteFusionMap, prefix: &str) -> HashMap<String, PatrolPath> {
+    ordered_waypoints(map, PATROL_ATTRIBUTE, prefix)
+        .into_iter()
+        .map(|(name, waypoints)| (name, PatrolPath { waypoints }))
+        .collect()
+}
+
+/// Collect the tiles tagged with the reserved `attribute` into named routes, in
+/// map-local space, sorted by their `"order"` attribute.
+pub(crate) fn ordered_waypoints(
+    map: &SpriteFusionMap,
+    attribute: &str,
+    prefix: &str,
+) -> HashMap<String, Vec<Vec2>> {
+    let attribute = reserved_attribute(prefix, attribute);
+    let order_key = reserved_attribute(prefix, ORDER_ATTRIBUTE);
+    let mut routes: HashMap<String, Vec<(i64, Vec2)>> = HashMap::new();
+    for tile in map.layers.iter().flat_map(|layer| layer.tiles.iter()) {
+        let Some(attrs) = &tile.attributes else {
+            continue;
+        };
+        let Some(name) = attrs.get(&*attribute).and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let order = attrs.get(&*order_key).and_then(|v| v.as_i64()).unwrap_or(0);
+        routes
+            .entry(name.to_string())
+            .or_default()
+            .push((order, map_local_position(map, tile.x, tile.y)));
+    }
+
+    routes
+        .into_iter()
+        .map(|(name, mut waypoints)| {
+            waypoints.sort_by_key(|(order, _)| *order);
+            (name, waypoints.into_iter().map(|(_, pos)| pos).collect())
+        })
+        .collect()
+}
+
+/// Component moving an entity along a patrol route of a map.
+///
+/// The entity's [`Transform`] is treated as world space, so it shouldn't have a
+/// parent. Its Z is left untouched. The component is removed at the end of a
+/// route that doesn't loop.
+///
+/// ```rust,ignore
+/// commands.spawn((Sprite::from_image(guard), FollowPatrol::new(map, "guard_1", 40.0)));
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct FollowPatrol {
+    /// The map entity holding the route.
+    pub map: Entity,
+    /// Name of the route.
+    pub path: String,
+    /// Speed in world units per second.
+    pub speed: f32,
+    /// Whether to go back to the first waypoint after the last one.
+    pub looping: bool,
+    next: usize,
+}
+
+impl FollowPatrol {
+    /// Follow a looping route.
+    pub fn new(map: Entity, path: impl Into<String>, speed: f32) -> Self {
+        Self {
+            map,
+            path: path.into(),
+            speed,
+            looping: true,
+            next: 0,
+        }
+    }
+
+    /// Index of the waypoint the entity is heading to.
+    pub fn next_waypoint(&self) -> usize {
+        self.next
+    }
+}
+
+/// System that adds the patrol routes of newly spawned maps.
+fn insert_patrol_paths(
+    mut commands: Commands,
+    maps: Query<(Entity, &SpriteFusionMapMarker), Added<SpriteFusionMapMarker>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (entity, marker) in maps.iter() {
+        let paths = extract_patrol_paths(&marker.map, &settings.attribute_prefix);
+        if !paths.is_empty() {
+            commands.entity(entity).insert(MapPatrolPaths(paths));
+        }
+    }
+}
+
+/// System that moves entities along their patrol route.
+fn follow_patrols(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut followers: Query<(Entity, &mut FollowPatrol, &mut Transform)>,
+    maps: Query<(&MapPatrolPaths, &GlobalTransform, Has<MapPaused>)>,
+) {
+    for (entity, mut patrol, mut transform) in followers.iter_mut() {
+        let Ok((paths, map_transform, paused)) = maps.get(patrol.map) else {
+            continue;
+        };
+        let Some(path) = paths.get(&patrol.path) else {
+            continue;
+        };
+        if paused || path.waypoints.is_empty() {
+            continue;
+        }
+
+        let mut step = patrol.speed * time.delta_secs();
+        // Bounded, so routes with overlapping waypoints can't loop forever
+        for _ in 0..=path.waypoints.len() {
+            if step <= 0.0 {
+                break;
+            }
+            let Some(waypoint) = path.waypoints.get(patrol.next) else {
+                commands.entity(entity).remove::<FollowPatrol>();
+                break;
+            };
+            let target = map_transform
+                .transform_point(waypoint.extend(0.0))
+                .truncate();
+            let position = transform.translation.truncate();
+            let distance = position.distance(target);
+            if distance > step {
+                let moved = position + (target - position) / distance * step;
+                transform.translation = moved.extend(transform.translation.z);
+                break;
+            }
+
+            // Reached the waypoint, carry the rest of the step to the next one
+            transform.translation = target.extend(transform.translation.z);
+            step -= distance;
+            patrol.next += 1;
+            if patrol.next >= path.waypoints.len() && patrol.looping {
+                patrol.next = 0;
+            }
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! [avian2d](https://github.com/Jondolf/avian) colliders for collider layers.
+//!
+//! Enabled by the `avian2d` feature. Tiles of collider layers get a static
+//! rectangle collider each, or every collider layer gets one merged collider,
+//! depending on [`AvianColliderSettings`]. They follow the [`Collider`] marker
+//! of tiles: tiles removed, hidden or streamed out stop colliding, and merged
+//! colliders are rebuilt from [`MergedColliders`] whenever it changes.
+
+use avian2d::prelude as avian;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    colliders::{tile_rect_to_local, MergedColliders},
+    plugin::SpriteFusionSystems,
+    types::Collider,
+};
+
+/// Plugin that adds avian2d colliders to collider layers.
+///
+/// Added by [`SpriteFusionPlugin`](crate::plugin::SpriteFusionPlugin) with the
+/// `avian2d` feature. Add avian's own `PhysicsPlugins` as usual.
+pub struct SpriteFusionAvianPlugin;
+
+impl Plugin for SpriteFusionAvianPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AvianColliderSettings>()
+            .add_systems(
+                Update,
+                (remove_tile_colliders, insert_tile_colliders).chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                update_layer_colliders.after(SpriteFusionSystems::Sync),
+            );
+    }
+}
+
+/// How collider layers get their avian2d colliders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AvianColliderMode {
+    /// One static rectangle collider on each [`Collider`] tile.
+    #[default]
+    PerTile,
+    /// One static compound collider per collider layer, on a [`LayerColliderBody`]
+    /// child of the layer, made of adjacent tiles merged into rectangles. Much
+    /// cheaper for the physics engine on large maps.
+    Merged,
+}
+
+/// Settings of avian2d collider generation.
+///
+/// Changes only apply to maps spawned afterwards.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct AvianColliderSettings {
+    /// How collider layers get their colliders.
+    pub mode: AvianColliderMode,
+}
+
+/// Marker component for the entity holding the merged collider of a layer.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct LayerColliderBody;
+
+fn tile_local_position(pos: &TilePos, grid_size: &TilemapGridSize) -> Vec2 {
+    Vec2::new(pos.x as f32 * grid_size.x, pos.y as f32 * grid_size.y)
+}
+
+/// System that inserts a rectangle collider on new collider tiles.
+fn insert_tile_colliders(
+    mut commands: Commands,
+    settings: Res<AvianColliderSettings>,
+    tiles: Query<(Entity, &TilePos, &TilemapId), Added<Collider>>,
+    layers: Query<&TilemapGridSize>,
+) {
+    if settings.mode != AvianColliderMode::PerTile {
+        return;
+    }
+    for (tile, pos, tilemap_id) in tiles.iter() {
+        let Ok(grid_size) = layers.get(tilemap_id.0) else {
+            continue;
+        };
+        // Tiles are children of their layer, so this is relative to it
+        commands.entity(tile).insert((
+            avian::RigidBody::Static,
+            avian::Collider::rectangle(grid_size.x, grid_size.y),
+            Transform::from_translation(tile_local_position(pos, grid_size).extend(0.0)),
+        ));
+    }
+}
+
+/// System that removes the rectangle collider of tiles that lost their
+/// [`Collider`].
+fn remove_tile_colliders(mut commands: Commands, mut removed: RemovedComponents<Collider>) {
+    for tile in removed.read() {
+        // Despawned tiles are skipped
+        commands
+            .entity(tile)
+            .try_remove::<(avian::RigidBody, avian::Collider)>();
+    }
+}
+
+/// System that builds the merged collider of collider layers, and rebuilds it
+/// when their tiles change.
+fn update_layer_colliders(
+    mut commands: Commands,
+    settings: Res<AvianColliderSettings>,
+    layers: Query<
+        (
+            Entity,
+            &MergedColliders,
+            &TilemapGridSize,
+            Option<&Children>,
+        ),
+        Changed<MergedColliders>,
+    >,
+    bodies: Query<(), With<LayerColliderBody>>,
+) {
+    if settings.mode != AvianColliderMode::Merged {
+        return;
+    }
+    for (entity, merged, grid_size, children) in layers.iter() {
+        let body = children
+            .into_iter()
+            .flatten()
+            .copied()
+            .find(|&child| bodies.contains(child));
+        let shapes: Vec<_> = merged
+            .tile_rects()
+            .iter()
+            .map(|&rect| {
+                let rect = tile_rect_to_local(rect, grid_size);
+                (
+                    rect.center(),
+                    avian::Rotation::default(),
+                    avian::Collider::rectangle(rect.width(), rect.height()),
+                )
+            })
+            .collect();
+        match (body, shapes.is_empty()) {
+            (Some(body), true) => {
+                commands.entity(body).despawn();
+            }
+            (Some(body), false) => {
+                commands
+                    .entity(body)
+                    .insert(avian::Collider::compound(shapes));
+            }
+            (None, true) => {}
+            (None, false) => {
+                commands.spawn((
+                    LayerColliderBody,
+                    avian::RigidBody::Static,
+                    avian::Collider::compound(shapes),
+                    Transform::default(),
+                    ChildOf(entity),
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Placing maps on arbitrary planes in 3D space.
+//!
+//! Tilemaps are drawn by the 2D render pipeline, so they must be viewed through a
+//! [`Camera2d`]. To show a map inside a 3D scene, give a [`Camera2d`] a perspective
+//! projection with [`map_plane_camera`], and either use it alone or layer it on top
+//! of a [`Camera3d`] sharing the same transform and projection.
+
+use bevy::prelude::*;
+
+use crate::plugin::SpriteFusionSystems;
+
+/// Plugin that applies [`MapPlacement`] to map entities.
+///
+/// Added by [`SpriteFusionPlugin`](crate::plugin::SpriteFusionPlugin).
+pub struct SpriteFusionPlacementPlugin;
+
+impl Plugin for SpriteFusionPlacementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            apply_map_placement.in_set(SpriteFusionSystems::Place),
+        );
+    }
+}
+
+/// Orientation of a map in world space.
+///
+/// Insert it on a map entity. The map's [`Transform`] rotation is driven by the
+/// placement, while its translation is set to the placement origin.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub enum MapPlacement {
+    /// The regular 2D placement on the XY plane. The transform is left untouched.
+    #[default]
+    Screen,
+    /// Lying flat on the ground (XZ plane), map up pointing towards -Z.
+    Ground {
+        /// World position of the map origin.
+        origin: Vec3,
+    },
+    /// On an arbitrary plane.
+    Plane {
+        /// World position of the map origin.
+        origin: Vec3,
+        /// Rotation from the XY plane to the target plane.
+        rotation: Quat,
+    },
+    /// Upright, always rotated around Y to face a camera (2.5D).
+    Billboard {
+        /// World position of the map origin.
+        origin: Vec3,
+        /// The camera to face.
+        camera: Entity,
+    },
+}
+
+impl MapPlacement {
+    /// Placement on the plane going through `origin` with the given `normal`.
+    pub fn on_plane(origin: Vec3, normal: Dir3) -> Self {
+        Self::Plane {
+            origin,
+            rotation: Quat::from_rotation_arc(Vec3::Z, *normal),
+        }
+    }
+}
+
+/// Build a perspective [`Camera2d`] looking at `focus` from `eye`, able to render
+/// maps placed on 3D planes.
+///
+/// ```rust,ignore
+/// commands.spawn((
+///     SpriteFusionBundle { /* ... */ ..default() },
+///     MapPlacement::Ground { origin: Vec3::ZERO },
+/// ));
+/// commands.spawn(map_plane_camera(Vec3::new(300.0, 400.0, 400.0), Vec3::new(300.0, 0.0, -100.0)));
+/// ```
+pub fn map_plane_camera(eye: Vec3, focus: Vec3) -> impl Bundle {
+    (
+        Camera2d,
+        Projection::Perspective(PerspectiveProjection {
+            far: 10_000.0,
+            ..default()
+        }),
+        Transform::from_translation(eye).looking_at(focus, Vec3::Y),
+    )
+}
+
+/// System that orients map entities according to their placement.
+fn apply_map_placement(
+    mut maps: Query<(&MapPlacement, &mut Transform), Without<Camera>>,
+    cameras: Query<&GlobalTransform, With<Camera>>,
+) {
+    for (placement, mut transform) in maps.iter_mut() {
+        match *placement {
+            MapPlacement::Screen => {}
+            MapPlacement::Ground { origin } => {
+                transform.translation = origin;
+                transform.rotation = Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2);
+            }
+            MapPlacement::Plane { origin, rotation } => {
+                transform.translation = origin;
+                transform.rotation = rotation;
+            }
+            MapPlacement::Billboard { origin, camera } => {
+                transform.translation = origin;
+                let Ok(camera_transform) = cameras.get(camera) else {
+                    continue;
+                };
+                let to_camera = camera_transform.translation() - origin;
+                let yaw = to_camera.x.atan2(to_camera.z);
+                transform.rotation = Quat::from_rotation_y(yaw);
+            }
+        }
+    }
+}
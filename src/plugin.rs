@@ -1,15 +1,85 @@
 //! Sprite Fusion plugin for Bevy.
 
-use bevy::prelude::*;
+use bevy::{
+    app::PluginGroupBuilder,
+    asset::{AssetLoadError, LoadState},
+    prelude::*,
+};
 use bevy_ecs_tilemap::prelude::*;
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+use thiserror::Error;
 
 use crate::{
-    loader::SpriteFusionMapLoader,
-    types::{Collider, SpriteFusionLayerMarker, SpriteFusionMap, SpriteFusionMapMarker, TileAttributes},
+    animation::SpriteFusionAnimationPlugin,
+    attachments::SpriteFusionAttachmentsPlugin,
+    attributes::{
+        insert_pending_attributes, reserved_attribute, AttrMarkerRegistry,
+        AttributeComponentRegistry, AttributeLocalizer, AttributeStorage, AttributeStore,
+        PendingTileAttributes, TileAttributeChanged,
+    },
+    camera_path::{MapCameraPaths, SpriteFusionCameraPathPlugin},
+    cameras::SpriteFusionCamerasPlugin,
+    colliders::SpriteFusionCollidersPlugin,
+    collision_grid::{CollisionGrid, SpriteFusionCollisionGridPlugin},
+    colorkey::SpriteFusionColorKeyPlugin,
+    convert::{DuplicateTilePolicy, LayerZPolicy, TileOrigin},
+    core::TilesetDefaults,
+    culling::SpriteFusionCullingPlugin,
+    decals::{MapDecals, SpriteFusionDecalsPlugin},
+    diagnostics::MapMemoryEstimate,
+    directions::{DirectionGrid, SpriteFusionDirectionsPlugin},
+    fallback::map_fallback_tileset,
+    floors::{MapNavGrids, SpriteFusionFloorsPlugin},
+    footprints::SpriteFusionFootprintsPlugin,
+    geometry::{SpriteFusionGeometryPlugin, SpriteFusionMapGeometry},
+    heatmap::SpriteFusionHeatmapPlugin,
+    hooks::SpriteFusionHookRegistry,
+    loader::{SpriteFusionAssetPlugin, SpriteFusionScene, TILESET_LABEL},
+    map_labels::SpriteFusionMapLabelsPlugin,
+    music::SpriteFusionMusicPlugin,
+    pathfinding::{MoveCosts, SpriteFusionPathfindingPlugin},
+    patrol::{MapPatrolPaths, SpriteFusionPatrolPlugin},
+    placement::SpriteFusionPlacementPlugin,
+    pool::TileEntityPool,
+    preload::SpriteFusionPreloadPlugin,
+    prepare::{
+        spawn_prepared, MapPreparation, MapSpawn, MapSpawnJob, NextTileSpawnIndex, PrepareError,
+        PrepareOptions,
+    },
+    prewarm::{PrewarmChunks, SpriteFusionPrewarmPlugin},
+    reload::SpriteFusionReloadPlugin,
+    replay::SpriteFusionReplayPlugin,
+    resources::{MapResources, SpriteFusionResourcesPlugin},
+    rooms::{MapRooms, SpriteFusionRoomsPlugin},
+    seed::{MapSeed, SpriteFusionSeedPlugin},
+    spawn_points::{SpawnPoint, SpawnPointSettings, SpriteFusionSpawnPointsPlugin},
+    spawners::{SpawnedFromTile, SpriteFusionSpawnersPlugin},
+    streaming::{MapStreaming, SpriteFusionStreamingPlugin, StreamedChunks},
+    terrain::{MapBiomes, SpriteFusionTerrainPlugin},
+    tile_index::{SpriteFusionTileIndex, SpriteFusionTileIndexPlugin},
+    types::{
+        Collider, MapPaused, SpriteFusionLayerMarker, SpriteFusionMap, SpriteFusionMapMarker,
+        TileAttributes, TileSpawnIndex,
+    },
+    variants::SpriteFusionVariantsPlugin,
+    zones::{MapZone, SpriteFusionZonesPlugin},
 };
 
 /// Plugin that enables loading and rendering Sprite Fusion maps. Sprite Fusion is a free, web-based tilemap editor: https://www.spritefusion.com/
 ///
+/// # Spawn order
+///
+/// Maps are spawned in a deterministic order: layers by index, then tiles row by row
+/// (top to bottom, left to right in Sprite Fusion coordinates). Each tile gets a
+/// [`TileSpawnIndex`](crate::types::TileSpawnIndex) reflecting that order, so
+/// lockstep and replay systems see the same entity layout on every run.
+///
+/// The map data is prepared on the
+/// [`AsyncComputeTaskPool`](bevy::tasks::AsyncComputeTaskPool) first, so maps
+/// spawn a frame or more after their assets are loaded. Read
+/// [`SpriteFusionMapSpawned`] and [`SpriteFusionMapSpawnFailed`] messages to know
+/// when a map is ready, or why it won't be.
+///
 /// # Example
 ///
 /// ```rust,ignore
@@ -32,31 +102,367 @@ use crate::{
 ///     });
 /// }
 /// ```
+///
+/// # Custom extensions and encrypted maps
+///
+/// Maps are loaded by [`SpriteFusionAssetPlugin`], added with its defaults
+/// unless already added. Add it before this plugin to match your own file
+/// extensions, or to decrypt map files before they are parsed:
+///
+/// ```rust,ignore
+/// app.add_plugins((
+///     SpriteFusionAssetPlugin::with_extensions(&["map.json", "level"])
+///         .with_transform(XorTransform::new(*b"secret")),
+///     SpriteFusionPlugin,
+/// ));
+/// ```
+///
+/// # Gameplay subsystems
+///
+/// Zones, rooms, pathfinding costs, animated tiles and the other features
+/// reading tile attributes for gameplay are opt-in: add
+/// [`SpriteFusionGameplayPlugins`], or only the plugins you use.
 pub struct SpriteFusionPlugin;
 
 impl Plugin for SpriteFusionPlugin {
     fn build(&self, app: &mut App) {
-        app.init_asset::<SpriteFusionMap>()
-            .init_asset_loader::<SpriteFusionMapLoader>()
-            .add_plugins(TilemapPlugin)
-            .add_systems(Update, spawn_spritefusion_maps);
+        if !app.is_plugin_added::<SpriteFusionAssetPlugin>() {
+            app.add_plugins(SpriteFusionAssetPlugin::default());
+        }
+        app.init_resource::<TileEntityPool>()
+            .init_resource::<SpriteFusionSettings>()
+            .init_resource::<PendingTileAttributes>()
+            .init_resource::<AttrMarkerRegistry>()
+            .init_resource::<AttributeComponentRegistry>()
+            .init_resource::<SpriteFusionHookRegistry>()
+            .add_message::<TileAttributeChanged>()
+            .add_message::<SpriteFusionMapSpawned>()
+            .add_message::<SpriteFusionMapSpawnFailed>()
+            .add_message::<SpriteFusionTilesetFallback>()
+            .register_type::<SpriteFusionMap>()
+            .register_type::<SpriteFusionMapMarker>()
+            .register_type::<SpriteFusionLayerMarker>()
+            .register_type::<TileAttributes>()
+            .register_type::<TileSpawnIndex>()
+            .register_type::<Collider>()
+            .register_type::<MapPaused>()
+            .register_type::<PendingSpriteFusionMap>()
+            .add_plugins((
+                TilemapPlugin,
+                SpriteFusionReplayPlugin,
+                SpriteFusionCamerasPlugin,
+                SpriteFusionPlacementPlugin,
+                SpriteFusionCollidersPlugin,
+                SpriteFusionPrewarmPlugin,
+                SpriteFusionReloadPlugin,
+                SpriteFusionVariantsPlugin,
+                SpriteFusionColorKeyPlugin,
+                SpriteFusionGeometryPlugin,
+                SpriteFusionCullingPlugin,
+                SpriteFusionSeedPlugin,
+            ))
+            .add_plugins((
+                SpriteFusionAttachmentsPlugin,
+                SpriteFusionSpawnPointsPlugin,
+                SpriteFusionStreamingPlugin,
+                SpriteFusionMapLabelsPlugin,
+                SpriteFusionTileIndexPlugin,
+                SpriteFusionCollisionGridPlugin,
+            ))
+            .configure_sets(
+                PostUpdate,
+                (
+                    SpriteFusionSystems::Place.before(TransformSystems::Propagate),
+                    SpriteFusionSystems::Sync.after(TransformSystems::Propagate),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    resolve_spritefusion_scenes,
+                    prepare_spritefusion_maps,
+                    spawn_spritefusion_maps,
+                    continue_spritefusion_spawns,
+                    insert_pending_attributes,
+                )
+                    .chain()
+                    .in_set(SpriteFusionSystems::Spawn),
+            );
+        #[cfg(feature = "avian2d")]
+        app.add_plugins(crate::physics::SpriteFusionAvianPlugin);
+    }
+}
+
+/// Opt-in plugins of the gameplay features read from tile attributes and map
+/// properties.
+///
+/// None of them is added by [`SpriteFusionPlugin`]. Add the group after it, and
+/// disable the plugins you don't need:
+///
+/// ```rust,ignore
+/// app.add_plugins((
+///     SpriteFusionPlugin,
+///     SpriteFusionGameplayPlugins
+///         .build()
+///         .disable::<SpriteFusionMusicPlugin>()
+///         .disable::<SpriteFusionHeatmapPlugin>(),
+/// ));
+/// ```
+pub struct SpriteFusionGameplayPlugins;
+
+impl PluginGroup for SpriteFusionGameplayPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(SpriteFusionZonesPlugin)
+            .add(SpriteFusionMusicPlugin)
+            .add(SpriteFusionRoomsPlugin)
+            .add(SpriteFusionPatrolPlugin)
+            .add(SpriteFusionCameraPathPlugin)
+            .add(SpriteFusionFloorsPlugin)
+            .add(SpriteFusionFootprintsPlugin)
+            .add(SpriteFusionTerrainPlugin)
+            .add(SpriteFusionResourcesPlugin)
+            .add(SpriteFusionDirectionsPlugin)
+            .add(SpriteFusionSpawnersPlugin)
+            .add(SpriteFusionAnimationPlugin)
+            .add(SpriteFusionDecalsPlugin)
+            .add(SpriteFusionHeatmapPlugin)
+            .add(SpriteFusionPathfindingPlugin)
+            .add(SpriteFusionPreloadPlugin)
+    }
+}
+
+/// System sets of the crate's systems, to order your own systems around them.
+///
+/// ```rust,ignore
+/// // Read the tiles of maps spawned this frame
+/// app.add_systems(Update, tag_doors.after(SpriteFusionSystems::Spawn));
+/// ```
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpriteFusionSystems {
+    /// Spawns loaded maps and streamed chunks, in [`Update`].
+    Spawn,
+    /// Moves maps, room cameras and entities attached to tiles, in
+    /// [`PostUpdate`] before [`TransformSystems::Propagate`].
+    Place,
+    /// Updates map geometry and merged colliders from the propagated
+    /// transforms, in [`PostUpdate`] after [`TransformSystems::Propagate`].
+    Sync,
+}
+
+/// Global settings for spawning Sprite Fusion maps.
+///
+/// Changes only apply to maps spawned afterwards.
+///
+/// ```rust,ignore
+/// app.insert_resource(SpriteFusionSettings {
+///     tile_origin: TileOrigin::TopLeft,
+///     ..default()
+/// });
+/// ```
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SpriteFusionSettings {
+    /// Coordinate convention of spawned [`TilePos`] components.
+    pub tile_origin: TileOrigin,
+    /// How to handle several tiles at the same position in a layer.
+    pub duplicate_tiles: DuplicateTilePolicy,
+    /// Maximum number of [`TileAttributes`](crate::types::TileAttributes) components
+    /// inserted per frame.
+    ///
+    /// `None` (the default) inserts them all while spawning. On maps with many
+    /// attribute-bearing tiles, a budget spreads that cost over several frames; see
+    /// [`PendingTileAttributes`] to know when all of them are in.
+    pub attribute_budget: Option<usize>,
+    /// How spawned tiles hold their attributes.
+    ///
+    /// [`AttributeStorage::Store`] doesn't insert
+    /// [`TileAttributes`](crate::types::TileAttributes) components, so
+    /// `attribute_budget` doesn't apply to it.
+    pub attribute_storage: AttributeStorage,
+    /// Maximum number of maps spawned per frame.
+    ///
+    /// `None` (the default) spawns every loaded map right away. With a budget,
+    /// loaded maps wait in a queue: highest [`SpawnPriority`] first, then nearest
+    /// to a camera.
+    pub max_maps_per_frame: Option<usize>,
+    /// Maximum number of tiles spawned per frame, shared by every spawning map.
+    ///
+    /// `None` (the default) spawns each map in the frame it's ready. With a
+    /// budget, huge maps spawn over several frames without a frame spike: their
+    /// map entity has a [`SpriteFusionSpawnProgress`] meanwhile, and gets its
+    /// [`SpriteFusionMapMarker`] once every tile is in. A budget of 0 is raised
+    /// to 1, so spawns always make progress.
+    pub tiles_per_frame: Option<usize>,
+    /// Color made transparent in map spritesheets, for legacy tilesets without an
+    /// alpha channel, e.g. `Some(Color::srgb(1.0, 0.0, 1.0))` for magenta.
+    ///
+    /// `None` (the default) leaves spritesheets untouched.
+    pub color_key: Option<Color>,
+    /// Names of layers not to spawn, e.g. a `"Markers"` layer only read as data.
+    ///
+    /// A `*` in a name matches any characters, so `"Spawn*"` skips both
+    /// `"Spawn Points"` and `"Spawners"`. Skipped layers stay in the map data of
+    /// [`SpriteFusionMapMarker`], but get no tilemap entity.
+    pub skip_layers: Vec<String>,
+    /// How layers get their Z coordinate. Defaults to 0.1 apart, top layer at 0.
+    pub layer_z: LayerZPolicy,
+    /// Tiny Z offset moving each layer below its [`layer_z`](Self::layer_z), so
+    /// sprites placed exactly at a layer's Z (see
+    /// [`SpriteFusionMapGeometry::layer_z`]) draw above it instead of z-fighting.
+    ///
+    /// Layer `i` moves down by `layer_z_jitter * (i + 1)`, so layers sharing a Z
+    /// also keep their Sprite Fusion order. `0.0` (the default) disables it;
+    /// `0.001` suits the default spacing.
+    pub layer_z_jitter: f32,
+    /// Seed mixed into the [`MapSeed`] of every map, e.g. a save-game seed.
+    ///
+    /// `None` (the default) seeds maps from their content only.
+    pub seed: Option<u64>,
+    /// Spawn maps whose tileset fails to load with a generated checkerboard
+    /// tileset instead, see [`checkerboard_tileset`](crate::fallback::checkerboard_tileset).
+    ///
+    /// `false` (the default) doesn't spawn them. Either way, a message tells
+    /// about the failure: [`SpriteFusionTilesetFallback`] with the fallback,
+    /// [`SpriteFusionMapSpawnFailed`] without.
+    pub fallback_tileset: bool,
+    /// Prefix of the tile attributes and map properties read by built-in
+    /// subsystems, e.g. `"sf:"` to read `"sf:zone"` instead of `"zone"`.
+    ///
+    /// Built-in subsystems read attributes like `"animFrames"`, `"zone"` or
+    /// `"dir"`. If those names already mean something else in your attribute
+    /// schema, a prefix leaves every other attribute to you. Empty by default.
+    /// Attributes named in settings, like
+    /// [`TileEntitySettings::attribute`](crate::spawners::TileEntitySettings::attribute),
+    /// are read as configured.
+    pub attribute_prefix: String,
+}
+
+impl SpriteFusionSettings {
+    /// Get the key built-in subsystems read for one of their attributes, with
+    /// the [`attribute_prefix`](Self::attribute_prefix), see [`reserved_attribute`].
+    pub fn reserved_attribute<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        reserved_attribute(&self.attribute_prefix, name)
+    }
+
+    /// Check if a layer is skipped by [`skip_layers`](Self::skip_layers).
+    pub fn skips_layer(&self, name: &str) -> bool {
+        self.skip_layers
+            .iter()
+            .any(|pattern| matches_wildcard(pattern, name))
+    }
+}
+
+/// Match a name against a pattern where `*` matches any characters.
+pub(crate) fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    // Without a `*`, the whole name must match
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
     }
+    rest.ends_with(last)
 }
 
 /// Handle wrapper for SpriteFusion map assets.
 #[derive(Component, Default, Clone, Debug, Deref, DerefMut)]
 pub struct SpriteFusionMapHandle(pub Handle<SpriteFusionMap>);
 
+/// Handle of a [`SpriteFusionScene`], to spawn a map from a single handle.
+///
+/// Spawning it alone is enough: the map and tileset handles are filled in from
+/// the scene once it's loaded.
+///
+/// ```rust,ignore
+/// commands.spawn((
+///     SpriteFusionSceneHandle(asset_server.load("level1.sf.json")),
+///     Transform::from_xyz(0.0, 0.0, 0.0),
+/// ));
+/// ```
+#[derive(Component, Default, Clone, Debug, Deref, DerefMut)]
+#[require(
+    SpriteFusionMapHandle,
+    SpriteFusionTilesetHandle,
+    Transform,
+    Visibility,
+    PendingSpriteFusionMap
+)]
+pub struct SpriteFusionSceneHandle(pub Handle<SpriteFusionScene>);
+
 /// Handle wrapper for tileset/spritesheet images.
 #[derive(Component, Default, Clone, Debug, Deref, DerefMut)]
 pub struct SpriteFusionTilesetHandle(pub Handle<Image>);
 
+/// Handle to the [`TilesetDefaults`] of a map, merged under its tile attributes.
+///
+/// Insert it next to a [`SpriteFusionBundle`]; the map waits for it to load
+/// before spawning.
+///
+/// ```rust,ignore
+/// commands.spawn((
+///     SpriteFusionBundle {
+///         map: asset_server.load("map.json"),
+///         tileset: asset_server.load("spritesheet.png"),
+///         ..default()
+///     },
+///     SpriteFusionTileDefaults(asset_server.load("spritesheet.tiles.json")),
+/// ));
+/// ```
+#[derive(Component, Default, Clone, Debug, Deref, DerefMut)]
+pub struct SpriteFusionTileDefaults(pub Handle<TilesetDefaults>);
+
+/// Texture index offsets added to the tiles of a map, per layer.
+///
+/// Insert it next to a [`SpriteFusionBundle`] when several Sprite Fusion projects
+/// share one packed spritesheet, and the layers of this map were authored against
+/// a sub-region of it.
+///
+/// ```rust,ignore
+/// commands.spawn((
+///     SpriteFusionBundle { /* ... */ ..default() },
+///     // Decoration tiles start at index 128 in the shared spritesheet
+///     TextureIndexOffsets::default().with_layer("Decoration", 128),
+/// ));
+/// ```
+#[derive(Component, Default, Clone, Debug)]
+pub struct TextureIndexOffsets {
+    /// Offset for layers without their own entry.
+    pub default: u32,
+    /// Offsets by layer name.
+    pub layers: HashMap<String, u32>,
+}
+
+impl TextureIndexOffsets {
+    /// Set the offset of a layer.
+    pub fn with_layer(mut self, name: impl Into<String>, offset: u32) -> Self {
+        self.layers.insert(name.into(), offset);
+        self
+    }
+
+    /// Get the offset of a layer.
+    pub fn get(&self, name: &str) -> u32 {
+        self.layers.get(name).copied().unwrap_or(self.default)
+    }
+}
+
 /// Bundle for spawning a SpriteFusion map.
 #[derive(Bundle, Default)]
 pub struct SpriteFusionBundle {
     /// Handle to the SpriteFusion map JSON file.
     pub map: SpriteFusionMapHandle,
     /// Handle to the tileset/spritesheet image.
+    ///
+    /// Leave it to its default to use the tileset loaded along with the map, see
+    /// [`SpriteFusionMapLoaderSettings`](crate::loader::SpriteFusionMapLoaderSettings).
     pub tileset: SpriteFusionTilesetHandle,
     /// Transform for the map.
     pub transform: Transform,
@@ -73,129 +479,444 @@ pub struct SpriteFusionBundle {
 }
 
 /// Marker component for maps that haven't been spawned yet.
-#[derive(Component, Default)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component, Default)]
 pub struct PendingSpriteFusionMap;
 
+/// Spawn priority of a pending map, higher spawns first. Maps without it have priority 0.
+///
+/// Only matters with
+/// [`SpriteFusionSettings::max_maps_per_frame`], when more maps are loaded than
+/// spawned in a frame.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SpawnPriority(pub i32);
 
-/// System that spawns tilemaps for pending SpriteFusion maps.
-fn spawn_spritefusion_maps(
+/// Message written once the layers and tiles of a map have been spawned.
+///
+/// The entities are queued through [`Commands`], so systems reading this message
+/// after [`SpriteFusionPlugin`]'s systems can query them right away.
+#[derive(Message, Debug, Clone)]
+pub struct SpriteFusionMapSpawned {
+    /// The map entity.
+    pub map: Entity,
+    /// The layer entities, in layer order. Layers skipped by
+    /// [`SpriteFusionSettings::skip_layers`] are left out.
+    pub layers: Vec<Entity>,
+    /// Number of tiles spawned on each layer, in layer order.
+    pub tile_counts: Vec<usize>,
+}
+
+/// Progress of a map spawning over several frames, see
+/// [`SpriteFusionSettings::tiles_per_frame`].
+///
+/// The map entity has it from the frame the map starts spawning until
+/// [`SpriteFusionMapSpawned`] is written, e.g. to show a loading bar:
+///
+/// ```rust,ignore
+/// fn update_loading_bar(
+///     maps: Query<&SpriteFusionSpawnProgress>,
+///     mut bar: Single<&mut Node, With<LoadingBar>>,
+/// ) {
+///     if let Some(progress) = maps.iter().next() {
+///         bar.width = Val::Percent(progress.fraction() * 100.0);
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpriteFusionSpawnProgress {
+    /// Tiles spawned so far.
+    pub spawned: usize,
+    /// Tiles to spawn, those of skipped layers left out.
+    pub total: usize,
+}
+
+impl SpriteFusionSpawnProgress {
+    /// Get the fraction of tiles spawned, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.spawned as f32 / self.total as f32
+        }
+    }
+}
+
+/// Message written when a pending map can't be spawned.
+///
+/// The map entity loses its [`PendingSpriteFusionMap`] marker and stays empty.
+#[derive(Message, Debug, Clone)]
+pub struct SpriteFusionMapSpawnFailed {
+    /// The map entity.
+    pub map: Entity,
+    /// Why the map wasn't spawned.
+    pub error: MapSpawnError,
+}
+
+/// Message written when a map spawns with a fallback tileset because its own
+/// failed to load, see [`SpriteFusionSettings::fallback_tileset`].
+///
+/// The map entity's [`SpriteFusionTilesetHandle`] is replaced by the fallback
+/// tileset, so fixing the file needs a respawn.
+#[derive(Message, Debug, Clone)]
+pub struct SpriteFusionTilesetFallback {
+    /// The map entity.
+    pub map: Entity,
+    /// Why the tileset failed to load.
+    pub error: MapSpawnError,
+}
+
+/// Why a map wasn't spawned, see [`SpriteFusionMapSpawnFailed`].
+#[derive(Debug, Clone, Error)]
+pub enum MapSpawnError {
+    /// The map asset failed to load.
+    #[error("failed to load the map: {0}")]
+    MapLoad(Arc<AssetLoadError>),
+    /// The tileset image failed to load.
+    #[error("failed to load the tileset: {0}")]
+    TilesetLoad(Arc<AssetLoadError>),
+    /// The map has no tileset handle, and wasn't loaded from a file it could
+    /// load one with.
+    #[error("no tileset for a map without an asset path")]
+    MissingTileset,
+    /// The tileset defaults failed to load.
+    #[error("failed to load the tileset defaults: {0}")]
+    DefaultsLoad(Arc<AssetLoadError>),
+    /// The map data couldn't be prepared.
+    #[error(transparent)]
+    Prepare(#[from] PrepareError),
+}
+
+
+/// System that fills in the map and tileset handles of pending maps from their
+/// scene, once it's loaded.
+fn resolve_spritefusion_scenes(
     mut commands: Commands,
-    pending_maps: Query<(Entity, &SpriteFusionMapHandle, &SpriteFusionTilesetHandle, &Transform), With<PendingSpriteFusionMap>>,
+    pending_maps: Query<(Entity, &SpriteFusionSceneHandle, &SpriteFusionMapHandle), With<PendingSpriteFusionMap>>,
+    scene_assets: Res<Assets<SpriteFusionScene>>,
+    asset_server: Res<AssetServer>,
+    mut failed: MessageWriter<SpriteFusionMapSpawnFailed>,
+) {
+    for (entity, scene_handle, map_handle) in pending_maps.iter() {
+        if let Some(LoadState::Failed(err)) = asset_server.get_load_state(scene_handle.id()) {
+            let error = MapSpawnError::MapLoad(err);
+            error!("Not spawning SpriteFusion map: {error}");
+            commands.entity(entity).remove::<PendingSpriteFusionMap>();
+            failed.write(SpriteFusionMapSpawnFailed { map: entity, error });
+            continue;
+        }
+        let Some(scene) = scene_assets.get(&**scene_handle) else {
+            continue;
+        };
+        if map_handle.0 != scene.map {
+            commands.entity(entity).insert((
+                SpriteFusionMapHandle(scene.map.clone()),
+                SpriteFusionTilesetHandle(scene.tileset.clone()),
+            ));
+        }
+    }
+}
+
+/// System that starts preparing pending maps once their assets are loaded.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn prepare_spritefusion_maps(
+    mut commands: Commands,
+    pending_maps: Query<(Entity, &SpriteFusionMapHandle, &SpriteFusionTilesetHandle, Option<&SpriteFusionTileDefaults>, Option<&TextureIndexOffsets>, Option<&LayerZPolicy>), (With<PendingSpriteFusionMap>, Without<MapPreparation>)>,
     map_assets: Res<Assets<SpriteFusionMap>>,
-    image_assets: Res<Assets<Image>>,
+    mut image_assets: ResMut<Assets<Image>>,
+    defaults_assets: Res<Assets<TilesetDefaults>>,
+    settings: Res<SpriteFusionSettings>,
+    localizer: Option<Res<AttributeLocalizer>>,
+    asset_server: Res<AssetServer>,
+    hooks: Res<SpriteFusionHookRegistry>,
+    spawn_points: Res<SpawnPointSettings>,
+    mut failed: MessageWriter<SpriteFusionMapSpawnFailed>,
+    mut fallbacks: MessageWriter<SpriteFusionTilesetFallback>,
 ) {
-    for (entity, map_handle, tileset_handle, transform) in pending_maps.iter() {
-        // Wait for both assets to be loaded
+    for (entity, map_handle, tileset_handle, tile_defaults, texture_offsets, layer_z) in pending_maps.iter() {
+        // Maps spawned from a scene wait for it to fill in their handles
+        if map_handle.0 == Handle::default() {
+            continue;
+        }
+        // Without a tileset handle, use the tileset loaded along with the map
+        if tileset_handle.0 == Handle::default() {
+            let error = match asset_server.get_path(map_handle.id()) {
+                Some(path) => {
+                    let tileset = asset_server.load(path.into_owned().with_label(TILESET_LABEL));
+                    commands.entity(entity).insert(SpriteFusionTilesetHandle(tileset));
+                    continue;
+                }
+                None => MapSpawnError::MissingTileset,
+            };
+            error!("Not spawning SpriteFusion map: {error}");
+            commands.entity(entity).remove::<PendingSpriteFusionMap>();
+            failed.write(SpriteFusionMapSpawnFailed { map: entity, error });
+            continue;
+        }
+        let load_error = match (
+            asset_server.get_load_state(map_handle.id()),
+            asset_server.get_load_state(tileset_handle.id()),
+            tile_defaults.and_then(|defaults| asset_server.get_load_state(defaults.id())),
+        ) {
+            (Some(LoadState::Failed(err)), ..) => Some(MapSpawnError::MapLoad(err)),
+            (_, Some(LoadState::Failed(err)), _) if !settings.fallback_tileset => {
+                Some(MapSpawnError::TilesetLoad(err))
+            }
+            (.., Some(LoadState::Failed(err))) => Some(MapSpawnError::DefaultsLoad(err)),
+            _ => None,
+        };
+        if let Some(error) = load_error {
+            error!("Not spawning SpriteFusion map: {error}");
+            commands.entity(entity).remove::<PendingSpriteFusionMap>();
+            failed.write(SpriteFusionMapSpawnFailed { map: entity, error });
+            continue;
+        }
+
+        // Wait for all assets to be loaded
         let Some(map) = map_assets.get(&**map_handle) else {
             continue;
         };
-        let Some(_tileset_image) = image_assets.get(&**tileset_handle) else {
+        if let Some(LoadState::Failed(err)) = asset_server.get_load_state(tileset_handle.id()) {
+            let error = MapSpawnError::TilesetLoad(err);
+            warn!("Spawning SpriteFusion map with a fallback tileset: {error}");
+            let offset = texture_offsets.map_or(0, |offsets| {
+                offsets.layers.values().copied().fold(offsets.default, u32::max)
+            });
+            let tileset = image_assets.add(map_fallback_tileset(map, offset));
+            commands.entity(entity).insert(SpriteFusionTilesetHandle(tileset));
+            fallbacks.write(SpriteFusionTilesetFallback { map: entity, error });
+            continue;
+        }
+        let Some(tileset) = image_assets.get(&**tileset_handle) else {
             continue;
         };
+        let defaults = match tile_defaults {
+            Some(handle) => match defaults_assets.get(&**handle) {
+                Some(defaults) => Some(defaults),
+                None => continue,
+            },
+            None => None,
+        };
+        // Tiles past the end of the spritesheet render as garbage or nothing
+        let columns = tileset.width() / map.tile_size.max(1);
+        let rows = tileset.height() / map.tile_size.max(1);
+        for error in map.validate_tile_capacity(columns * rows) {
+            warn!("SpriteFusion map: {error}");
+        }
+        let mut map = map.clone();
+        if let Some(defaults) = defaults {
+            map.apply_tile_defaults(defaults);
+        }
+        hooks.map_loaded(entity, &mut map);
+        let mut settings = settings.clone();
+        if let Some(layer_z) = layer_z {
+            settings.layer_z = layer_z.clone();
+        }
+        // Spawn point layers are only read as data
+        settings.skip_layers.extend(spawn_points.layers.iter().cloned());
+        let options = PrepareOptions {
+            settings,
+            texture_offsets: texture_offsets.cloned(),
+            localizer: localizer.as_deref().cloned(),
+        };
+        commands
+            .entity(entity)
+            .insert(MapPreparation::start(map, options));
+    }
+}
 
-        // Remove pending marker and add map marker
-        commands.entity(entity).remove::<PendingSpriteFusionMap>();
-        commands.entity(entity).insert(SpriteFusionMapMarker {
-            map: map.clone(),
-        });
-
-        let tile_size = map.tile_size;
-
-        // Spawn each layer as a separate tilemap
-        for (layer_index, layer) in map.layers.iter().enumerate() {
-            let map_size = TilemapSize {
-                x: map.map_width,
-                y: map.map_height,
-            };
-
-            let tilemap_entity = commands.spawn_empty().id();
-            let mut tile_storage = TileStorage::empty(map_size);
-
-            // Spawn tiles for this layer
-            for tile in &layer.tiles {
-                let tile_id = tile.tile_id();
-                let tile_pos = TilePos {
-                    x: tile.x as u32,
-                    y: (map.map_height - 1) - tile.y as u32, // Sprite Fusion uses top-left origin
-                };
-
-                // Calculate texture index from tile ID
-                let texture_index = TileTextureIndex(tile_id);
-
-                let mut tile_entity_commands = commands.spawn(TileBundle {
-                    position: tile_pos,
-                    tilemap_id: TilemapId(tilemap_entity),
-                    texture_index,
-                    ..default()
-                });
+/// System that spawns tilemaps for prepared SpriteFusion maps.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn spawn_spritefusion_maps(
+    mut commands: Commands,
+    mut pending_maps: Query<(Entity, &SpriteFusionTilesetHandle, &mut MapPreparation, Option<&SpawnPriority>, &GlobalTransform, Option<&MapStreaming>), With<PendingSpriteFusionMap>>,
+    cameras: Query<&GlobalTransform, With<Camera>>,
+    mut pool: ResMut<TileEntityPool>,
+    mut pending_attributes: ResMut<PendingTileAttributes>,
+    settings: Res<SpriteFusionSettings>,
+    attr_markers: Res<AttrMarkerRegistry>,
+    attr_components: Res<AttributeComponentRegistry>,
+    hooks: Res<SpriteFusionHookRegistry>,
+    mut spawned: MessageWriter<SpriteFusionMapSpawned>,
+    mut failed: MessageWriter<SpriteFusionMapSpawnFailed>,
+) {
+    let mut ready: Vec<_> = pending_maps
+        .iter_mut()
+        .filter_map(|(entity, tileset_handle, mut preparation, priority, transform, streaming)| {
+            preparation.poll().then(|| {
+                let priority = priority.copied().unwrap_or_default();
+                let chunk_size = streaming.map(|streaming| streaming.chunk_size);
+                (entity, tileset_handle.0.clone(), priority, *transform, chunk_size)
+            })
+        })
+        .collect();
 
-                // Add collider marker if layer has collision
-                if layer.collider {
-                    tile_entity_commands.insert(Collider);
-                }
+    // Highest priority first, then nearest to a camera
+    let camera_distance = |transform: &GlobalTransform| {
+        let position = transform.translation().truncate();
+        cameras
+            .iter()
+            .map(|camera| camera.translation().truncate().distance_squared(position))
+            .reduce(f32::min)
+            .unwrap_or(0.0)
+    };
+    ready.sort_by(|(_, _, priority_a, transform_a, _), (_, _, priority_b, transform_b, _)| {
+        priority_b
+            .cmp(priority_a)
+            .then_with(|| camera_distance(transform_a).total_cmp(&camera_distance(transform_b)))
+    });
+    ready.truncate(settings.max_maps_per_frame.unwrap_or(usize::MAX));
 
-                // Add tile attributes if present
-                if let Some(attrs) = &tile.attributes {
-                    if !attrs.is_empty() {
-                        tile_entity_commands.insert(TileAttributes(attrs.clone()));
-                    }
+    for (entity, tileset, _, transform, chunk_size) in ready {
+        let Some(result) = pending_maps
+            .get_mut(entity)
+            .ok()
+            .and_then(|(_, _, mut preparation, ..)| preparation.take())
+        else {
+            continue;
+        };
+        commands
+            .entity(entity)
+            .remove::<(PendingSpriteFusionMap, MapPreparation)>();
+        match result {
+            Ok(prepared) => {
+                let spawn = MapSpawn::new(&mut commands, entity, transform, tileset, prepared, chunk_size);
+                if settings.tiles_per_frame.is_some() {
+                    commands.entity(entity).insert((
+                        SpriteFusionSpawnProgress {
+                            spawned: 0,
+                            total: spawn.remaining(),
+                        },
+                        MapSpawnJob(Some(spawn)),
+                    ));
+                } else {
+                    spawned.write(spawn_prepared(
+                        &mut commands,
+                        &mut pool,
+                        &mut pending_attributes,
+                        &attr_markers,
+                        &attr_components,
+                        &hooks,
+                        spawn,
+                    ));
                 }
-
-                let tile_entity = tile_entity_commands.id();
-                tile_storage.set(&tile_pos, tile_entity);
             }
+            Err(err) => {
+                error!("Not spawning SpriteFusion map: {err}");
+                failed.write(SpriteFusionMapSpawnFailed {
+                    map: entity,
+                    error: err.into(),
+                });
+            }
+        }
+    }
+}
 
-            let tile_size_vec = TilemapTileSize {
-                x: tile_size as f32,
-                y: tile_size as f32,
-            };
-            let grid_size = tile_size_vec.into();
-            let map_type = TilemapType::Square;
-
-            // Get the tileset handle from the wrapper
-            let texture = TilemapTexture::Single(tileset_handle.0.clone());
-
-            // Layer Z offset. In Sprite Fusion, layer 0 is on top, last layer is background
-            // So need to invert: higher index = lower Z
-            let layer_z = -((layer_index as f32) * 0.1);
-            let layer_transform = Transform::from_translation(Vec3::new(
-                transform.translation.x,
-                transform.translation.y,
-                transform.translation.z + layer_z,
-            ));
+/// System that spawns the tiles of maps spawning over several frames, up to
+/// [`SpriteFusionSettings::tiles_per_frame`] each frame.
+#[allow(clippy::too_many_arguments)]
+fn continue_spritefusion_spawns(
+    mut commands: Commands,
+    mut jobs: Query<(Entity, &mut MapSpawnJob, &mut SpriteFusionSpawnProgress)>,
+    mut pool: ResMut<TileEntityPool>,
+    mut pending_attributes: ResMut<PendingTileAttributes>,
+    settings: Res<SpriteFusionSettings>,
+    attr_markers: Res<AttrMarkerRegistry>,
+    attr_components: Res<AttributeComponentRegistry>,
+    hooks: Res<SpriteFusionHookRegistry>,
+    mut spawned: MessageWriter<SpriteFusionMapSpawned>,
+) {
+    let mut budget = settings.tiles_per_frame.map_or(usize::MAX, |budget| budget.max(1));
+    for (entity, mut job, mut progress) in jobs.iter_mut() {
+        let Some(spawn) = job.0.as_mut() else {
+            continue;
+        };
+        let count = spawn.step(&mut commands, &mut pool, &attr_markers, &attr_components, &hooks, budget);
+        budget -= count;
+        progress.spawned += count;
+        if spawn.is_done() {
+            let spawn = job.0.take().expect("spawn job is running");
+            commands
+                .entity(entity)
+                .remove::<(MapSpawnJob, SpriteFusionSpawnProgress)>();
+            spawned.write(spawn.finish(&mut commands, &mut pending_attributes, &hooks));
+        }
+        if budget == 0 {
+            break;
+        }
+    }
+}
 
-            commands.entity(tilemap_entity).insert((
-                TilemapBundle {
-                    grid_size,
-                    map_type,
-                    size: map_size,
-                    storage: tile_storage,
-                    texture,
-                    tile_size: tile_size_vec,
-                    transform: layer_transform,
-                    ..default()
-                },
-                SpriteFusionLayerMarker {
-                    name: layer.name.clone(),
-                    index: layer_index,
-                    collider: layer.collider,
-                },
-            ));
+/// Despawn the layers, tiles, zones and tile entities of a map, and remove the components added
+/// while spawning it. The map entity keeps its handles and transform.
+///
+/// With `respawn`, the map gets a [`PendingSpriteFusionMap`] marker again and is
+/// spawned anew from its handles.
+pub(crate) fn despawn_map(world: &mut World, map: Entity, respawn: bool) {
+    let spawned = world
+        .get_entity(map)
+        .is_ok_and(|entity| entity.contains::<SpriteFusionMapMarker>());
+    if let Some(hooks) = world.get_resource::<SpriteFusionHookRegistry>().cloned() {
+        if spawned {
+            hooks.map_despawned(world, map);
+        }
+    }
+    let children: Vec<Entity> = world
+        .get::<Children>(map)
+        .map(|children| children.iter().collect())
+        .unwrap_or_default();
+    // Tile entities reserved by an unfinished spawn aren't children of their layer yet
+    let reserved: Vec<Entity> = world
+        .get::<MapSpawnJob>(map)
+        .and_then(|job| job.0.as_ref())
+        .map(|spawn| spawn.reserved_tiles().collect())
+        .unwrap_or_default();
+    for tile in reserved {
+        world.despawn(tile);
+    }
+    for child in children {
+        let entity = world.entity(child);
+        if entity.contains::<SpriteFusionLayerMarker>()
+            || entity.contains::<MapZone>()
+            || entity.contains::<SpawnedFromTile>()
+            || entity.contains::<SpawnPoint>()
+        {
+            world.despawn(child);
+        }
+    }
+    world.resource_mut::<PendingTileAttributes>().remove_map(map);
 
-            // Make the tilemap a child of the map entity
-            commands.entity(entity).add_child(tilemap_entity);
-        }
-
-        let tiles_with_attrs = map.layers.iter()
-            .flat_map(|l| l.tiles.iter())
-            .filter(|t| t.attributes.as_ref().map(|a| !a.is_empty()).unwrap_or(false))
-            .count();
-        
-        info!(
-            "Spawned SpriteFusion map with {} layers ({} tiles total, {} with attributes)",
-            map.layers.len(),
-            map.layers.iter().map(|l| l.tiles.len()).sum::<usize>(),
-            tiles_with_attrs
-        );
+    let Ok(mut map_entity) = world.get_entity_mut(map) else {
+        return;
+    };
+    map_entity.remove::<(
+        SpriteFusionMapMarker,
+        SpriteFusionMapGeometry,
+        MapPreparation,
+        (
+            MapSpawnJob,
+            SpriteFusionSpawnProgress,
+            StreamedChunks,
+            NextTileSpawnIndex,
+            SpriteFusionTileIndex,
+            CollisionGrid,
+            MoveCosts,
+        ),
+        AttributeStore,
+        MapNavGrids,
+        MapPatrolPaths,
+        MapCameraPaths,
+        MapBiomes,
+        DirectionGrid,
+        MapMemoryEstimate,
+        MapResources,
+        MapRooms,
+        MapSeed,
+        PrewarmChunks,
+    )>();
+    if let Some(mut decals) = map_entity.get_mut::<MapDecals>() {
+        decals.clear();
+    }
+    if respawn {
+        map_entity.insert(PendingSpriteFusionMap);
     }
 }
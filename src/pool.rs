@@ -0,0 +1,156 @@
+//! Entity pooling for tile entities.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::PendingTileAttributes,
+    hooks::SpriteFusionHookRegistry,
+    types::{SpriteFusionLayerMarker, SpriteFusionMapMarker},
+};
+
+/// Pool of empty tile entities reused across map despawn/spawn cycles.
+///
+/// Pooling is disabled by default (capacity 0). Insert the resource with a
+/// capacity to enable it, then recycle maps with
+/// [`recycle_map`](crate::commands::SpriteFusionCommandsExt::recycle_map)
+/// instead of despawning them.
+///
+/// ```rust,ignore
+/// app.insert_resource(TileEntityPool::with_capacity(50_000));
+/// ```
+#[derive(Resource, Debug, Default)]
+pub struct TileEntityPool {
+    capacity: usize,
+    free: Vec<Entity>,
+    stats: TileEntityPoolStats,
+}
+
+/// Usage statistics of a [`TileEntityPool`], useful to tune its capacity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TileEntityPoolStats {
+    /// Tile entities currently waiting in the pool.
+    pub pooled: usize,
+    /// Tile spawns served from the pool.
+    pub reused: usize,
+    /// Tile spawns that needed a new entity.
+    pub allocated: usize,
+    /// Tile entities returned to the pool.
+    pub released: usize,
+    /// Tile entities despawned because the pool was full.
+    pub discarded: usize,
+}
+
+impl TileEntityPool {
+    /// Create a pool holding at most `capacity` free entities.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            free: Vec::with_capacity(capacity),
+            stats: TileEntityPoolStats::default(),
+        }
+    }
+
+    /// Maximum number of free entities kept in the pool.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the capacity. Free entities above the new capacity stay pooled
+    /// until they are reused.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    /// Get the pool usage statistics.
+    pub fn stats(&self) -> TileEntityPoolStats {
+        TileEntityPoolStats {
+            pooled: self.free.len(),
+            ..self.stats
+        }
+    }
+
+    /// Take a free entity from the pool, if any.
+    pub(crate) fn take(&mut self) -> Option<Entity> {
+        let entity = self.free.pop();
+        if entity.is_some() {
+            self.stats.reused += 1;
+        } else {
+            self.stats.allocated += 1;
+        }
+        entity
+    }
+
+    /// Return an emptied entity to the pool. Returns `false` if the pool is full.
+    fn release(&mut self, entity: Entity) -> bool {
+        if self.free.len() >= self.capacity {
+            self.stats.discarded += 1;
+            return false;
+        }
+        self.free.push(entity);
+        self.stats.released += 1;
+        true
+    }
+}
+
+/// Get an empty tile entity, reusing a pooled one when available.
+pub(crate) fn reserve_tile(commands: &mut Commands, pool: &mut TileEntityPool) -> Entity {
+    pool.take().unwrap_or_else(|| commands.spawn_empty().id())
+}
+
+/// Despawn a map, returning its tile entities to the [`TileEntityPool`].
+///
+/// Runs the same teardown as despawning it: the
+/// [`on_map_despawn`](crate::hooks::SpriteFusionHooks::on_map_despawn) hooks
+/// first, while its tiles still exist, and its pending attributes are dropped.
+pub(crate) fn recycle_map(world: &mut World, map: Entity) {
+    let spawned = world
+        .get_entity(map)
+        .is_ok_and(|entity| entity.contains::<SpriteFusionMapMarker>());
+    if let Some(hooks) = world.get_resource::<SpriteFusionHookRegistry>().cloned() {
+        if spawned {
+            hooks.map_despawned(world, map);
+        }
+    }
+    if let Some(mut pending) = world.get_resource_mut::<PendingTileAttributes>() {
+        pending.remove_map(map);
+    }
+
+    let layers: Vec<Entity> = world
+        .get::<Children>(map)
+        .map(|children| children.iter().collect())
+        .unwrap_or_default();
+
+    let mut tiles = Vec::new();
+    for layer in layers {
+        if !world.entity(layer).contains::<SpriteFusionLayerMarker>() {
+            continue;
+        }
+        if let Some(storage) = world.get::<TileStorage>(layer) {
+            tiles.extend(storage.iter().flatten().copied());
+        }
+    }
+
+    release_tiles(world, tiles);
+
+    if let Ok(map_entity) = world.get_entity_mut(map) {
+        map_entity.despawn();
+    }
+}
+
+/// Return tile entities to the [`TileEntityPool`], despawning the ones that don't
+/// fit.
+pub(crate) fn release_tiles(world: &mut World, tiles: Vec<Entity>) {
+    world.resource_scope(|world, mut pool: Mut<TileEntityPool>| {
+        for tile in tiles {
+            let Ok(mut tile_entity) = world.get_entity_mut(tile) else {
+                continue;
+            };
+            if pool.release(tile) {
+                tile_entity.clear();
+            } else {
+                tile_entity.despawn();
+            }
+        }
+    });
+}
@@ -0,0 +1,299 @@
+//! Preloading of connected maps.
+//!
+//! Loading a level on the first door transition makes the game hitch.
+//! [`preload_maps`] starts loading a list of maps up front and returns a
+//! [`MapPreloadSet`] tracking them. Inserted as a resource or on an entity, the
+//! set also loads the maps they reference, found by [`map_dependencies`], so a
+//! whole connected set of levels is warm before gameplay starts.
+//! [`preload_map_folder`] loads every map of a folder instead.
+
+use std::collections::{BTreeMap, HashSet};
+
+use bevy::{
+    asset::{AssetPath, LoadState, LoadedFolder},
+    prelude::*,
+};
+
+use crate::{attributes::reserved_attribute, plugin::SpriteFusionSettings, types::SpriteFusionMap};
+
+/// Tile attribute naming the map a portal or door leads to, e.g.
+/// `"portalTo": "levels/cave.sf.json"`.
+pub const PORTAL_ATTRIBUTE: &str = "portalTo";
+/// Map property listing the maps of a world, e.g.
+/// `"worldMaps": ["levels/hub.sf.json", "levels/cave.sf.json"]`.
+pub const WORLD_MAPS_PROPERTY: &str = "worldMaps";
+
+/// Plugin that loads the maps referenced by [`MapPreloadSet`]s, and the maps
+/// of their folders.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionPreloadPlugin;
+
+impl Plugin for SpriteFusionPreloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, follow_map_dependencies);
+    }
+}
+
+/// Get the asset paths of the maps a map references, sorted and without
+/// duplicates.
+///
+/// Those are the values of [`PORTAL_ATTRIBUTE`] on its tiles and of its
+/// [`WORLD_MAPS_PROPERTY`] property, either a path or an array of paths. Paths
+/// are relative to the assets folder, like the paths given to the
+/// [`AssetServer`]. Keys are read with the given
+/// [`SpriteFusionSettings::attribute_prefix`].
+pub fn map_dependencies(map: &SpriteFusionMap, prefix: &str) -> Vec<String> {
+    let portal = reserved_attribute(prefix, PORTAL_ATTRIBUTE);
+    let world = reserved_attribute(prefix, WORLD_MAPS_PROPERTY);
+    let portals = map
+        .layers
+        .iter()
+        .flat_map(|layer| &layer.tiles)
+        .filter_map(|tile| tile.attributes.as_ref()?.get(&*portal));
+    let mut paths: Vec<String> = portals
+        .chain(map.properties.get(&*world))
+        .flat_map(|value| match value {
+            serde_json::Value::String(path) => vec![path.as_str()],
+            serde_json::Value::Array(paths) => paths.iter().filter_map(|v| v.as_str()).collect(),
+            _ => Vec::new(),
+        })
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Start loading maps, returning a [`MapPreloadSet`] tracking them.
+///
+/// Insert the set as a resource, or on an entity, for the maps they reference
+/// to be loaded too; see [`MapPreloadSet::without_dependencies`] to only load
+/// the listed maps.
+///
+/// ```rust,ignore
+/// fn start_loading(mut commands: Commands, asset_server: Res<AssetServer>) {
+///     commands.insert_resource(preload_maps(&asset_server, ["levels/hub.sf.json"]));
+/// }
+///
+/// fn enter_game(preload: Res<MapPreloadSet>, asset_server: Res<AssetServer>, mut next: ResMut<NextState<GameState>>) {
+///     if preload.is_loaded(&asset_server) {
+///         next.set(GameState::Playing);
+///     }
+/// }
+/// ```
+pub fn preload_maps<'a>(
+    asset_server: &AssetServer,
+    paths: impl IntoIterator<Item = impl Into<AssetPath<'a>>>,
+) -> MapPreloadSet {
+    let mut set = MapPreloadSet::default();
+    for path in paths {
+        set.load(asset_server, path.into());
+    }
+    set
+}
+
+/// Start loading every map of a folder of the assets, recursively, returning a
+/// [`MapPreloadSet`] tracking them.
+///
+/// See [`MapPreloadSet::load_folder`].
+///
+/// ```rust,ignore
+/// commands.insert_resource(preload_map_folder(&asset_server, "levels"));
+/// ```
+pub fn preload_map_folder<'a>(
+    asset_server: &AssetServer,
+    path: impl Into<AssetPath<'a>>,
+) -> MapPreloadSet {
+    let mut set = MapPreloadSet::default();
+    set.load_folder(asset_server, path);
+    set
+}
+
+/// Handles of maps being preloaded, by asset path.
+///
+/// The maps stay loaded while the set is alive: drop it, or remove it, once the
+/// maps are spawned or no longer needed.
+#[derive(Resource, Component, Debug, Clone)]
+pub struct MapPreloadSet {
+    handles: BTreeMap<String, Handle<SpriteFusionMap>>,
+    /// Folders whose maps aren't in `handles` yet.
+    folders: BTreeMap<String, Handle<LoadedFolder>>,
+    /// Maps whose dependencies were loaded already.
+    scanned: HashSet<String>,
+    follow_dependencies: bool,
+}
+
+impl Default for MapPreloadSet {
+    fn default() -> Self {
+        Self {
+            handles: BTreeMap::new(),
+            folders: BTreeMap::new(),
+            scanned: HashSet::new(),
+            follow_dependencies: true,
+        }
+    }
+}
+
+impl MapPreloadSet {
+    /// Only load the listed maps, not the maps they reference.
+    pub fn without_dependencies(mut self) -> Self {
+        self.follow_dependencies = false;
+        self
+    }
+
+    /// Start loading one more map, unless it's in the set already.
+    pub fn load<'a>(&mut self, asset_server: &AssetServer, path: impl Into<AssetPath<'a>>) {
+        let path = path.into();
+        self.handles
+            .entry(path.to_string())
+            .or_insert_with(|| asset_server.load(path.into_owned()));
+    }
+
+    /// Start loading every map of a folder, recursively.
+    ///
+    /// Bevy loads all the files of the folder, with the loader of their
+    /// extension; the files loaded as maps, by the Sprite Fusion loader, join
+    /// the set once the whole folder is loaded. That only happens while the set
+    /// is a resource or on an entity. Asset sources that can't list folders,
+    /// like the web, fail the folder, see [`failed`](Self::failed).
+    pub fn load_folder<'a>(&mut self, asset_server: &AssetServer, path: impl Into<AssetPath<'a>>) {
+        let path = path.into();
+        self.folders
+            .entry(path.to_string())
+            .or_insert_with(|| asset_server.load_folder(path.into_owned()));
+    }
+
+    /// Get the handle of a map of the set.
+    pub fn get(&self, path: &str) -> Option<&Handle<SpriteFusionMap>> {
+        self.handles.get(path)
+    }
+
+    /// Iterate over the maps of the set, with their asset path, sorted by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Handle<SpriteFusionMap>)> {
+        self.handles
+            .iter()
+            .map(|(path, handle)| (path.as_str(), handle))
+    }
+
+    /// Number of maps in the set, dependencies and maps of loaded folders found
+    /// so far included.
+    pub fn len(&self) -> usize {
+        self.handles.len()
+    }
+
+    /// Check if the set has no maps, and no folders left to load.
+    pub fn is_empty(&self) -> bool {
+        self.handles.is_empty() && self.folders.is_empty()
+    }
+
+    /// Get the number of maps loaded, out of the maps in the set.
+    ///
+    /// Folders count as one map until their maps are found. The total grows as
+    /// dependencies and maps of folders are found, so this is only final once
+    /// [`is_loaded`](Self::is_loaded) is true.
+    pub fn progress(&self, asset_server: &AssetServer) -> (usize, usize) {
+        let loaded = self
+            .handles
+            .values()
+            .filter(|handle| asset_server.is_loaded_with_dependencies(handle.id()))
+            .count();
+        (loaded, self.handles.len() + self.folders.len())
+    }
+
+    /// Check if every map of the set is loaded, and, when following
+    /// dependencies, every map they reference too.
+    ///
+    /// Dependencies are only followed while the set is a resource or on an
+    /// entity. Maps that failed to load keep this false, see
+    /// [`failed`](Self::failed).
+    pub fn is_loaded(&self, asset_server: &AssetServer) -> bool {
+        let (loaded, total) = self.progress(asset_server);
+        loaded == total && (!self.follow_dependencies || self.scanned.len() == total)
+    }
+
+    /// Iterate over the paths of the maps and folders that failed to load.
+    pub fn failed<'a>(&'a self, asset_server: &'a AssetServer) -> impl Iterator<Item = &'a str> {
+        let failed = |id| matches!(asset_server.get_load_state(id), Some(LoadState::Failed(_)));
+        let maps = self
+            .handles
+            .iter()
+            .filter(move |(_, handle)| failed(handle.id().untyped()))
+            .map(|(path, _)| path.as_str());
+        let folders = self
+            .folders
+            .iter()
+            .filter(move |(_, handle)| failed(handle.id().untyped()))
+            .map(|(path, _)| path.as_str());
+        maps.chain(folders)
+    }
+
+    /// Add the maps of the folders loaded since the last call.
+    fn add_folder_maps(&mut self, folders: &Assets<LoadedFolder>) {
+        let loaded: Vec<String> = self
+            .folders
+            .iter()
+            .filter(|(_, handle)| folders.contains(handle.id()))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in loaded {
+            let Some(folder) = self.folders.remove(&path).and_then(|h| folders.get(&h)) else {
+                continue;
+            };
+            for handle in &folder.handles {
+                let (Some(path), Ok(map)) = (
+                    handle.path().map(ToString::to_string),
+                    handle.clone().try_typed::<SpriteFusionMap>(),
+                ) else {
+                    continue;
+                };
+                self.handles.entry(path).or_insert(map);
+            }
+        }
+    }
+
+    /// Load the dependencies of the maps loaded since the last call.
+    fn load_dependencies(
+        &mut self,
+        asset_server: &AssetServer,
+        maps: &Assets<SpriteFusionMap>,
+        prefix: &str,
+    ) {
+        if !self.follow_dependencies {
+            return;
+        }
+        let mut found = Vec::new();
+        for (path, handle) in &self.handles {
+            if self.scanned.contains(path) {
+                continue;
+            }
+            let Some(map) = maps.get(handle) else {
+                continue;
+            };
+            self.scanned.insert(path.clone());
+            found.extend(map_dependencies(map, prefix));
+        }
+        for path in found {
+            self.load(asset_server, path);
+        }
+    }
+}
+
+/// System that adds the maps of loaded folders to preload sets, and loads the
+/// maps referenced by their maps.
+fn follow_map_dependencies(
+    asset_server: Res<AssetServer>,
+    maps: Res<Assets<SpriteFusionMap>>,
+    folders: Res<Assets<LoadedFolder>>,
+    resource: Option<ResMut<MapPreloadSet>>,
+    mut sets: Query<&mut MapPreloadSet>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for mut set in resource.map(Mut::from).into_iter().chain(sets.iter_mut()) {
+        if !set.folders.is_empty() {
+            set.add_folder_maps(&folders);
+        }
+        set.load_dependencies(&asset_server, &maps, &settings.attribute_prefix);
+    }
+}
@@ -0,0 +1,839 @@
+//! Preparation of map data for spawning.
+//!
+//! Turning a [`SpriteFusionMap`] into tile data (positions, texture indices,
+//! duplicate resolution, attribute copies) gives a [`PreparedMap`]. The plugin
+//! prepares pending maps on the [`AsyncComputeTaskPool`], so preloading many maps
+//! doesn't compete with the frame, and the main thread only spawns the entities.
+//!
+//! Tools can also prepare maps offline with [`prepare_map`], cache the result
+//! with any serde format, and spawn it with
+//! [`spawn_prepared_map`](crate::commands::SpriteFusionCommandsExt::spawn_prepared_map).
+
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, ComputeTaskPool, Task},
+};
+use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    attributes::{
+        clone_attributes, AttrKey, AttrMarkerRegistry, AttributeComponentRegistry,
+        AttributeLocalizer, AttributeStorage, AttributeStore, PendingTileAttributes,
+    },
+    convert::{layer_tiles, resolve_duplicates, tile_offset, tilemap_size, TileOrigin},
+    core::SpriteFusionLayer,
+    geometry::SpriteFusionMapGeometry,
+    hooks::SpriteFusionHookRegistry,
+    plugin::{SpriteFusionMapSpawned, SpriteFusionSettings, TextureIndexOffsets},
+    pool::{reserve_tile, TileEntityPool},
+    streaming::{MapStreaming, StreamedChunks, StreamedLayer},
+    types::{
+        Collider, SpriteFusionLayerMarker, SpriteFusionMap, SpriteFusionMapMarker, TileAttributes,
+        TileSpawnIndex,
+    },
+};
+
+/// A map turned into the data of its entities, ready to spawn.
+///
+/// Settings and [`PrepareOptions`] are baked in: the tile origin, duplicate
+/// resolution, texture offsets and attribute storage of the options it was
+/// prepared with apply when it spawns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreparedMap {
+    /// The source map data.
+    pub map: SpriteFusionMap,
+    /// Coordinate convention of the tile positions.
+    pub tile_origin: TileOrigin,
+    /// Prepared layers, in the order of `map.layers`.
+    pub layers: Vec<PreparedLayer>,
+    /// Distinct attribute sets of the map's
+    /// [`AttributeStore`], when prepared with [`AttributeStorage::Store`].
+    pub attribute_sets: Option<Vec<HashMap<String, serde_json::Value>>>,
+}
+
+impl PreparedMap {
+    /// Get the offset of tiles at negative coordinates, see
+    /// [`tile_offset`](crate::convert::tile_offset).
+    pub fn tile_offset(&self) -> UVec2 {
+        tile_offset(&self.map)
+    }
+
+    /// Get the tilemap size of every layer.
+    pub fn size(&self) -> TilemapSize {
+        tilemap_size(&self.map)
+    }
+
+    /// Get the bounds of the map in the local space of its map entity.
+    pub fn bounds(&self) -> Rect {
+        let tile_size = self.map.tile_size as f32;
+        let offset = self.tile_offset().as_vec2();
+        let size = Vec2::new(self.map.map_width as f32, self.map.map_height as f32);
+        // Tiles are centered on multiples of the tile size
+        let half = tile_size / 2.0;
+        Rect::new(
+            -offset.x * tile_size - half,
+            -half,
+            (size.x - 1.0) * tile_size + half,
+            (size.y + offset.y - 1.0) * tile_size + half,
+        )
+    }
+}
+
+/// A layer of a [`PreparedMap`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PreparedLayer {
+    /// Tiles in spawn order.
+    pub tiles: Vec<PreparedTile>,
+    /// Z of the layer relative to the map entity, see
+    /// [`SpriteFusionSettings::layer_z`].
+    pub z: f32,
+    /// Offset added to `z` when the layer spawns, see
+    /// [`SpriteFusionSettings::layer_z_jitter`].
+    #[serde(default)]
+    pub z_jitter: f32,
+    /// Whether the layer is skipped, see [`SpriteFusionSettings::skip_layers`].
+    /// Skipped layers have no tiles and no tilemap entity.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+}
+
+/// A tile of a [`PreparedLayer`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreparedTile {
+    /// Index of the tile in its layer's `tiles`.
+    pub index: usize,
+    /// X position of the spawned [`TilePos`].
+    pub x: u32,
+    /// Y position of the spawned [`TilePos`].
+    pub y: u32,
+    /// Texture index, texture offsets included.
+    pub texture_index: u32,
+    /// Whether the tile is flipped vertically, see [`TileOrigin::TopLeft`].
+    pub flip_y: bool,
+    /// How the tile gets its attributes.
+    pub attributes: PreparedAttributes,
+}
+
+impl PreparedTile {
+    /// Get the spawned position of the tile.
+    pub fn position(&self) -> TilePos {
+        TilePos {
+            x: self.x,
+            y: self.y,
+        }
+    }
+}
+
+/// How a prepared tile gets its attributes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum PreparedAttributes {
+    /// The tile has no attributes.
+    #[default]
+    None,
+    /// A [`TileAttributes`] component with these attributes.
+    Components(HashMap<String, serde_json::Value>),
+    /// An [`AttrKey`] into the attribute sets of the map.
+    Stored(u32),
+    /// Inserted over the next frames, see [`PendingTileAttributes`].
+    Pending,
+}
+
+/// Why a map couldn't be prepared.
+#[derive(Debug, Clone, Error)]
+pub enum PrepareError {
+    /// A layer has several tiles at the same position, with
+    /// [`DuplicateTilePolicy::Error`](crate::convert::DuplicateTilePolicy::Error).
+    #[error("layer '{layer}' has several tiles at ({x}, {y})")]
+    DuplicateTile {
+        /// Name of the layer.
+        layer: String,
+        /// X position of the tile, in Sprite Fusion coordinates.
+        x: i32,
+        /// Y position of the tile, in Sprite Fusion coordinates.
+        y: i32,
+    },
+}
+
+/// What [`prepare_map`] needs besides the map.
+#[derive(Clone, Default)]
+pub struct PrepareOptions {
+    /// Spawn settings.
+    pub settings: SpriteFusionSettings,
+    /// Texture index offsets of the map.
+    pub texture_offsets: Option<TextureIndexOffsets>,
+    /// Localizer of string attributes.
+    pub localizer: Option<AttributeLocalizer>,
+}
+
+/// Preparation task of a pending map.
+#[derive(Component)]
+pub(crate) struct MapPreparation {
+    task: Option<Task<Result<PreparedMap, PrepareError>>>,
+    result: Option<Result<PreparedMap, PrepareError>>,
+}
+
+impl MapPreparation {
+    /// Start preparing a map on the [`AsyncComputeTaskPool`].
+    pub(crate) fn start(map: SpriteFusionMap, options: PrepareOptions) -> Self {
+        let task = AsyncComputeTaskPool::get().spawn(async move { prepare_map(&map, &options) });
+        Self {
+            task: Some(task),
+            result: None,
+        }
+    }
+
+    /// Check if the preparation is done, keeping its result.
+    pub(crate) fn poll(&mut self) -> bool {
+        if let Some(task) = self.task.take_if(|task| task.is_finished()) {
+            self.result = Some(bevy::tasks::block_on(task));
+        }
+        self.result.is_some()
+    }
+
+    /// Take the result of a finished preparation.
+    pub(crate) fn take(&mut self) -> Option<Result<PreparedMap, PrepareError>> {
+        self.result.take()
+    }
+}
+
+/// Compute the entities of a map without spawning anything.
+///
+/// ```rust,ignore
+/// // At build time
+/// let prepared = prepare_map(&map, &PrepareOptions::default())?;
+/// std::fs::write("level1.prepared.json", serde_json::to_vec(&prepared)?)?;
+///
+/// // In game
+/// let prepared: PreparedMap = serde_json::from_slice(&bytes)?;
+/// let map = commands.spawn_prepared_map(prepared, tileset);
+/// ```
+pub fn prepare_map(
+    map: &SpriteFusionMap,
+    options: &PrepareOptions,
+) -> Result<PreparedMap, PrepareError> {
+    let settings = &options.settings;
+    let localizer = options.localizer.as_ref();
+    let mut attribute_store =
+        (settings.attribute_storage == AttributeStorage::Store).then(AttributeStore::default);
+
+    let mut layers = Vec::with_capacity(map.layers.len());
+    for layer in &map.layers {
+        if settings.skips_layer(&layer.name) {
+            layers.push(PreparedLayer {
+                tiles: Vec::new(),
+                z: 0.0,
+                z_jitter: 0.0,
+                skipped: true,
+            });
+            continue;
+        }
+        // Resolve duplicate tiles up front, so a rejected map spawns nothing
+        let mut tiles = layer_tiles(map, layer, settings.tile_origin);
+        resolve_duplicates(&mut tiles, settings.duplicate_tiles).map_err(|tile| {
+            PrepareError::DuplicateTile {
+                layer: layer.name.clone(),
+                x: tile.x,
+                y: tile.y,
+            }
+        })?;
+        let texture_offset = options
+            .texture_offsets
+            .as_ref()
+            .map_or(0, |offsets| offsets.get(&layer.name));
+
+        let tiles = tiles
+            .into_iter()
+            .map(|tile| {
+                let attributes = match (&tile.tile.attributes, &mut attribute_store) {
+                    (Some(attrs), _) if attrs.is_empty() => PreparedAttributes::None,
+                    (Some(attrs), Some(store)) => {
+                        PreparedAttributes::Stored(store.insert(attrs, localizer).0)
+                    }
+                    (Some(_), None) if settings.attribute_budget.is_some() => {
+                        PreparedAttributes::Pending
+                    }
+                    (Some(attrs), None) => {
+                        PreparedAttributes::Components(clone_attributes(attrs, localizer))
+                    }
+                    (None, _) => PreparedAttributes::None,
+                };
+                PreparedTile {
+                    index: tile.index,
+                    x: tile.position.x,
+                    y: tile.position.y,
+                    texture_index: tile.texture_index.0 + texture_offset,
+                    flip_y: tile.flip.y,
+                    attributes,
+                }
+            })
+            .collect();
+        layers.push(PreparedLayer {
+            tiles,
+            z: settings.layer_z.z(layers.len(), layer),
+            z_jitter: -settings.layer_z_jitter * (layers.len() + 1) as f32,
+            skipped: false,
+        });
+    }
+
+    Ok(PreparedMap {
+        map: map.clone(),
+        tile_origin: settings.tile_origin,
+        layers,
+        attribute_sets: attribute_store.map(AttributeStore::into_sets),
+    })
+}
+
+/// Get the transform of a spawned layer, relative to its map entity.
+fn layer_transform(prepared: &PreparedMap, layer_index: usize) -> Transform {
+    let tile_size = prepared.map.tile_size as f32;
+    // The layer is a child of the map entity, so its transform is relative to the map.
+    let layer = &prepared.layers[layer_index];
+    let layer_z = layer.z + layer.z_jitter;
+    // Move back the columns added for negative coordinates. Rows added for
+    // negative coordinates are above the map, so Y already lines up.
+    let layer_x = -(prepared.tile_offset().x as f32) * tile_size;
+    match prepared.tile_origin {
+        TileOrigin::BottomLeft => Transform::from_xyz(layer_x, 0.0, layer_z),
+        // Mirror Y around the map so row 0 is drawn at the top, where the
+        // bottom-left convention would draw it
+        TileOrigin::TopLeft => {
+            Transform::from_xyz(layer_x, (prepared.size().y - 1) as f32 * tile_size, layer_z)
+                .with_scale(Vec3::new(1.0, -1.0, 1.0))
+        }
+    }
+}
+
+/// A prepared map being spawned under its map entity, possibly over several
+/// frames, see [`SpriteFusionSettings::tiles_per_frame`].
+///
+/// A layer gets its tilemap when its first tile spawns, with every tile entity
+/// already reserved in its [`TileStorage`], so systems reading the layer see its
+/// full storage. The map gets its [`SpriteFusionMapMarker`] once every tile has
+/// spawned.
+///
+/// On maps with [`MapStreaming`](crate::streaming::MapStreaming), tiles are
+/// grouped in chunks instead, spawned later around cameras.
+pub(crate) struct MapSpawn {
+    entity: Entity,
+    /// Global transform of the map entity when the spawn started.
+    map_transform: GlobalTransform,
+    tileset: Handle<Image>,
+    map: SpriteFusionMap,
+    /// Layers left to start, skipped layers left out.
+    layers: std::vec::IntoIter<(usize, PreparedLayer, Transform)>,
+    current: Option<LayerSpawn>,
+    size: TilemapSize,
+    tile_size: TilemapTileSize,
+    tile_offset: UVec2,
+    tile_origin: TileOrigin,
+    layer_z: Vec<(String, f32)>,
+    spawn_index: u32,
+    /// Chunk size of streamed maps.
+    chunk_size: Option<UVec2>,
+    streamed_layers: Vec<StreamedLayer>,
+    /// Pending attributes, queued once the map is done so they find its marker.
+    pending_attributes: Vec<(Entity, usize, usize)>,
+    spawned: SpriteFusionMapSpawned,
+}
+
+/// A map spawning over several frames, until its [`MapSpawn`] is done.
+#[derive(Component)]
+pub(crate) struct MapSpawnJob(pub(crate) Option<MapSpawn>);
+
+/// The layer of a [`MapSpawn`] whose tiles are spawning.
+struct LayerSpawn {
+    index: usize,
+    tilemap: Entity,
+    tiles: Vec<ReservedTile>,
+    /// Index of the next tile to spawn in `tiles`.
+    next: usize,
+}
+
+impl LayerSpawn {
+    /// Get the tiles not spawned yet.
+    fn remaining(&self) -> &[ReservedTile] {
+        &self.tiles[self.next..]
+    }
+}
+
+/// Spawn index of the next tile placed on a spawned map by editing, following
+/// the tiles spawned with it.
+#[derive(Component, Debug)]
+pub(crate) struct NextTileSpawnIndex(pub(crate) u32);
+
+impl NextTileSpawnIndex {
+    /// Take the next spawn index.
+    pub(crate) fn take(&mut self) -> u32 {
+        let spawn_index = self.0;
+        self.0 += 1;
+        spawn_index
+    }
+}
+
+/// A tile entity reserved in the storage of its layer, and the tile it spawns.
+pub(crate) struct ReservedTile {
+    pub(crate) tile: PreparedTile,
+    pub(crate) entity: Entity,
+    pub(crate) spawn_index: u32,
+}
+
+impl MapSpawn {
+    /// Start spawning a prepared map under `entity`, streamed in chunks of
+    /// `chunk_size` tiles if given.
+    ///
+    /// Layers get their global transform from `map_transform` right away, so
+    /// they don't show at the origin when they spawn after transforms were
+    /// propagated.
+    pub(crate) fn new(
+        commands: &mut Commands,
+        entity: Entity,
+        map_transform: GlobalTransform,
+        tileset: Handle<Image>,
+        prepared: PreparedMap,
+        chunk_size: Option<UVec2>,
+    ) -> Self {
+        let size = prepared.size();
+        let tile_offset = prepared.tile_offset();
+        let tile_origin = prepared.tile_origin;
+        let transforms: Vec<_> = (0..prepared.layers.len())
+            .map(|index| layer_transform(&prepared, index))
+            .collect();
+        let layer_z = prepared
+            .map
+            .layers
+            .iter()
+            .zip(&prepared.layers)
+            .filter(|(_, layer)| !layer.skipped)
+            .map(|(data, layer)| (data.name.clone(), layer.z))
+            .collect();
+        let PreparedMap {
+            map,
+            layers,
+            attribute_sets,
+            ..
+        } = prepared;
+
+        // Tiles look up stored attributes through their map as soon as they spawn
+        if let Some(attribute_sets) = attribute_sets {
+            commands
+                .entity(entity)
+                .insert(AttributeStore::from_sets(attribute_sets));
+        }
+
+        let layers: Vec<_> = layers
+            .into_iter()
+            .zip(transforms)
+            .enumerate()
+            .filter(|(_, (layer, _))| !layer.skipped)
+            .map(|(index, (layer, transform))| (index, layer, transform))
+            .collect();
+        let tile_size = TilemapTileSize {
+            x: map.tile_size as f32,
+            y: map.tile_size as f32,
+        };
+        Self {
+            entity,
+            map_transform,
+            tileset,
+            map,
+            spawned: SpriteFusionMapSpawned {
+                map: entity,
+                layers: Vec::with_capacity(layers.len()),
+                tile_counts: Vec::with_capacity(layers.len()),
+            },
+            layers: layers.into_iter(),
+            current: None,
+            size,
+            tile_size,
+            tile_offset,
+            tile_origin,
+            layer_z,
+            spawn_index: 0,
+            chunk_size: chunk_size.map(|chunk_size| chunk_size.max(UVec2::ONE)),
+            streamed_layers: Vec::new(),
+            pending_attributes: Vec::new(),
+        }
+    }
+
+    /// Get the number of tiles left to spawn.
+    pub(crate) fn remaining(&self) -> usize {
+        // Streamed tiles spawn with their chunk
+        if self.chunk_size.is_some() {
+            return 0;
+        }
+        let current = self
+            .current
+            .as_ref()
+            .map_or(0, |layer| layer.remaining().len());
+        let layers: usize = self
+            .layers
+            .as_slice()
+            .iter()
+            .map(|(_, layer, _)| layer.tiles.len())
+            .sum();
+        current + layers
+    }
+
+    /// Check if every layer and tile has spawned.
+    pub(crate) fn is_done(&self) -> bool {
+        self.current.is_none() && self.layers.as_slice().is_empty()
+    }
+
+    /// Get the tile entities reserved in the storage of the current layer, but
+    /// not spawned yet. They aren't children of the layer, so they must be
+    /// despawned on their own.
+    pub(crate) fn reserved_tiles(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.current
+            .iter()
+            .flat_map(|layer| layer.remaining().iter().map(|tile| tile.entity))
+    }
+
+    /// Spawn up to `budget` tiles, returning how many spawned.
+    pub(crate) fn step(
+        &mut self,
+        commands: &mut Commands,
+        pool: &mut TileEntityPool,
+        attr_markers: &AttrMarkerRegistry,
+        attr_components: &AttributeComponentRegistry,
+        hooks: &SpriteFusionHookRegistry,
+        budget: usize,
+    ) -> usize {
+        let mut spawned = 0;
+        while spawned < budget {
+            let Some(mut layer) = self
+                .current
+                .take()
+                .or_else(|| self.start_layer(commands, pool, hooks))
+            else {
+                break;
+            };
+            let count = layer.remaining().len().min(budget - spawned);
+            self.spawn_tiles(
+                commands,
+                attr_markers,
+                attr_components,
+                hooks,
+                &mut layer,
+                count,
+            );
+            spawned += count;
+            if !layer.remaining().is_empty() {
+                self.current = Some(layer);
+            }
+        }
+        spawned
+    }
+
+    /// Spawn the tilemap of the next layer and reserve its tile entities.
+    fn start_layer(
+        &mut self,
+        commands: &mut Commands,
+        pool: &mut TileEntityPool,
+        hooks: &SpriteFusionHookRegistry,
+    ) -> Option<LayerSpawn> {
+        let (index, prepared_layer, transform) = self.layers.next()?;
+        let layer = &self.map.layers[index];
+        let tilemap_entity = commands.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(self.size);
+        let mut tiles = Vec::new();
+        if let Some(chunk_size) = self.chunk_size {
+            let mut chunks: HashMap<UVec2, Vec<_>> = HashMap::new();
+            for tile in prepared_layer.tiles {
+                let chunk = UVec2::new(tile.x, tile.y) / chunk_size;
+                chunks
+                    .entry(chunk)
+                    .or_default()
+                    .push((self.spawn_index, tile));
+                self.spawn_index += 1;
+            }
+            self.streamed_layers.push(StreamedLayer {
+                index,
+                tilemap: tilemap_entity,
+                chunks,
+            });
+        } else {
+            for tile in prepared_layer.tiles {
+                let entity = reserve_tile(commands, pool);
+                tile_storage.set(&tile.position(), entity);
+                tiles.push(ReservedTile {
+                    tile,
+                    entity,
+                    spawn_index: self.spawn_index,
+                });
+                self.spawn_index += 1;
+            }
+        }
+        self.spawned.layers.push(tilemap_entity);
+        self.spawned.tile_counts.push(tiles.len());
+
+        let mut layer_commands = commands.entity(tilemap_entity);
+        layer_commands.insert((
+            TilemapBundle {
+                grid_size: self.tile_size.into(),
+                map_type: TilemapType::Square,
+                size: self.size,
+                storage: tile_storage,
+                texture: TilemapTexture::Single(self.tileset.clone()),
+                tile_size: self.tile_size,
+                transform,
+                global_transform: self.map_transform.mul_transform(transform),
+                ..default()
+            },
+            SpriteFusionLayerMarker {
+                map: self.entity,
+                name: layer.name.clone(),
+                index,
+                collider: layer.collider,
+            },
+        ));
+        hooks.layer_spawned(&mut layer_commands, layer);
+
+        // Make the tilemap a child of the map entity
+        commands.entity(self.entity).add_child(tilemap_entity);
+        Some(LayerSpawn {
+            index,
+            tilemap: tilemap_entity,
+            tiles,
+            next: 0,
+        })
+    }
+
+    /// Insert the components of the next `count` reserved tile entities of a layer.
+    fn spawn_tiles(
+        &mut self,
+        commands: &mut Commands,
+        attr_markers: &AttrMarkerRegistry,
+        attr_components: &AttributeComponentRegistry,
+        hooks: &SpriteFusionHookRegistry,
+        layer_spawn: &mut LayerSpawn,
+        count: usize,
+    ) {
+        let range = layer_spawn.next..layer_spawn.next + count;
+        layer_spawn.next = range.end;
+        spawn_reserved_tiles(
+            commands,
+            attr_markers,
+            attr_components,
+            hooks,
+            &self.map.layers[layer_spawn.index],
+            layer_spawn.index,
+            layer_spawn.tilemap,
+            &mut layer_spawn.tiles[range],
+            &mut self.pending_attributes,
+        );
+    }
+
+    /// Insert the map components once every tile has spawned.
+    pub(crate) fn finish(
+        self,
+        commands: &mut Commands,
+        pending_attributes: &mut PendingTileAttributes,
+        hooks: &SpriteFusionHookRegistry,
+    ) -> SpriteFusionMapSpawned {
+        for (tile, layer, index) in self.pending_attributes {
+            pending_attributes.push(tile, self.entity, layer, index);
+        }
+
+        let map = self.map;
+        let tiles_with_attrs = map
+            .layers
+            .iter()
+            .flat_map(|l| l.tiles.iter())
+            .filter(|t| {
+                t.attributes
+                    .as_ref()
+                    .map(|a| !a.is_empty())
+                    .unwrap_or(false)
+            })
+            .count();
+        info!(
+            "Spawned SpriteFusion map with {} layers ({} tiles total, {} with attributes)",
+            map.layers.len(),
+            map.layers.iter().map(|l| l.tiles.len()).sum::<usize>(),
+            tiles_with_attrs
+        );
+
+        let mut map_commands = commands.entity(self.entity);
+        if let Some(chunk_size) = self.chunk_size {
+            map_commands.insert(StreamedChunks::new(chunk_size, self.streamed_layers));
+        }
+        map_commands.insert(NextTileSpawnIndex(self.spawn_index));
+        map_commands.insert(
+            SpriteFusionMapGeometry::new(
+                self.size,
+                self.tile_size.x,
+                self.tile_offset,
+                self.tile_origin,
+                self.layer_z,
+            )
+            .with_transform(self.map_transform),
+        );
+        hooks.map_ready(&mut map_commands, &map);
+        map_commands.insert(SpriteFusionMapMarker {
+            map,
+            tile_offset: self.tile_offset,
+        });
+        self.spawned
+    }
+}
+
+/// Number of tiles whose bundles are built by each task of [`tile_bundles`].
+const TILE_BUNDLE_CHUNK: usize = 4096;
+
+/// Components every spawned tile gets.
+type TileEntityBundle = (TileBundle, ChildOf, TileSpawnIndex);
+
+/// Build the bundles of reserved tile entities, in chunks on the
+/// [`ComputeTaskPool`] for large layers, keeping the tile order.
+fn tile_bundles(tiles: &[ReservedTile], tilemap: Entity) -> Vec<(Entity, TileEntityBundle)> {
+    let build = |chunk: &[ReservedTile]| {
+        chunk
+            .iter()
+            .map(|reserved| {
+                let tile = &reserved.tile;
+                let bundle = (
+                    TileBundle {
+                        position: tile.position(),
+                        tilemap_id: TilemapId(tilemap),
+                        texture_index: TileTextureIndex(tile.texture_index),
+                        flip: TileFlip {
+                            y: tile.flip_y,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    // Tiles are children of their layer so despawning and visibility follow the hierarchy
+                    ChildOf(tilemap),
+                    TileSpawnIndex(reserved.spawn_index),
+                );
+                (reserved.entity, bundle)
+            })
+            .collect::<Vec<_>>()
+    };
+    let Some(pool) = ComputeTaskPool::try_get().filter(|_| tiles.len() > TILE_BUNDLE_CHUNK) else {
+        return build(tiles);
+    };
+    let build = &build;
+    pool.scope(|scope| {
+        for chunk in tiles.chunks(TILE_BUNDLE_CHUNK) {
+            scope.spawn(async move { build(chunk) });
+        }
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Insert the components of reserved tile entities of a layer.
+///
+/// Tiles come row by row, so the entity layout doesn't depend on the order
+/// tiles appear in the export. Tiles whose attributes are inserted later are
+/// added to `pending` as `(tile, layer index, tile index)`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn spawn_reserved_tiles(
+    commands: &mut Commands,
+    attr_markers: &AttrMarkerRegistry,
+    attr_components: &AttributeComponentRegistry,
+    hooks: &SpriteFusionHookRegistry,
+    layer: &SpriteFusionLayer,
+    layer_index: usize,
+    tilemap: Entity,
+    tiles: &mut [ReservedTile],
+    pending: &mut Vec<(Entity, usize, usize)>,
+) {
+    // Components shared by every tile go in with one batch
+    commands.insert_batch(tile_bundles(tiles, tilemap));
+    if layer.collider {
+        let colliders: Vec<_> = tiles.iter().map(|tile| (tile.entity, Collider)).collect();
+        commands.insert_batch(colliders);
+    }
+
+    for reserved in tiles.iter_mut() {
+        let tile = &mut reserved.tile;
+        let data = layer.tiles.get(tile.index);
+        let attributes = std::mem::take(&mut tile.attributes);
+        let attrs = data.and_then(|tile| tile.attributes.as_ref());
+        if attributes == PreparedAttributes::None && attrs.is_none() && hooks.is_empty() {
+            continue;
+        }
+        let mut tile_entity_commands = commands.entity(reserved.entity);
+        if let Some(attrs) = attrs {
+            attr_markers.insert_markers(&mut tile_entity_commands, attrs);
+            attr_components.insert_components(&mut tile_entity_commands, attrs);
+        }
+        match attributes {
+            PreparedAttributes::None => {}
+            PreparedAttributes::Components(attrs) => {
+                tile_entity_commands.insert(TileAttributes(attrs));
+            }
+            PreparedAttributes::Stored(key) => {
+                tile_entity_commands.insert(AttrKey(key));
+            }
+            PreparedAttributes::Pending => {
+                pending.push((reserved.entity, layer_index, tile.index));
+            }
+        }
+        if let Some(data) = data {
+            hooks.tile_spawned(&mut tile_entity_commands, data, layer);
+        }
+    }
+}
+
+/// Spawn every layer and tile of a map, all at once.
+pub(crate) fn spawn_prepared(
+    commands: &mut Commands,
+    pool: &mut TileEntityPool,
+    pending_attributes: &mut PendingTileAttributes,
+    attr_markers: &AttrMarkerRegistry,
+    attr_components: &AttributeComponentRegistry,
+    hooks: &SpriteFusionHookRegistry,
+    mut spawn: MapSpawn,
+) -> SpriteFusionMapSpawned {
+    spawn.step(
+        commands,
+        pool,
+        attr_markers,
+        attr_components,
+        hooks,
+        usize::MAX,
+    );
+    spawn.finish(commands, pending_attributes, hooks)
+}
+
+/// System spawning a prepared map, run by
+/// [`spawn_prepared_map`](crate::commands::SpriteFusionCommandsExt::spawn_prepared_map).
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn spawn_prepared_map(
+    In((entity, tileset, prepared)): In<(Entity, Handle<Image>, PreparedMap)>,
+    mut commands: Commands,
+    mut pool: ResMut<TileEntityPool>,
+    mut pending_attributes: ResMut<PendingTileAttributes>,
+    attr_markers: Res<AttrMarkerRegistry>,
+    attr_components: Res<AttributeComponentRegistry>,
+    hooks: Res<SpriteFusionHookRegistry>,
+    maps: Query<(Option<&GlobalTransform>, Option<&MapStreaming>)>,
+    mut spawned: MessageWriter<SpriteFusionMapSpawned>,
+) {
+    let (transform, streaming) = maps.get(entity).unwrap_or_default();
+    let spawn = MapSpawn::new(
+        &mut commands,
+        entity,
+        transform.copied().unwrap_or_default(),
+        tileset,
+        prepared,
+        streaming.map(|streaming| streaming.chunk_size),
+    );
+    spawned.write(spawn_prepared(
+        &mut commands,
+        &mut pool,
+        &mut pending_attributes,
+        &attr_markers,
+        &attr_components,
+        &hooks,
+        spawn,
+    ));
+}
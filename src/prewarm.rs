@@ -0,0 +1,108 @@
+//! Pre-warming of tilemap chunk meshes.
+//!
+//! `bevy_ecs_tilemap` only builds the mesh of a chunk once it's in view, so the
+//! first camera pan across a big map can stutter while chunks build. Insert
+//! [`PrewarmChunks`] on a map entity to build every chunk shortly after the map
+//! spawns, a few layers per frame, by turning off frustum culling of each layer
+//! for a couple of frames.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::types::{SpriteFusionLayerMarker, SpriteFusionMapMarker};
+
+pub(crate) struct SpriteFusionPrewarmPlugin;
+
+impl Plugin for SpriteFusionPrewarmPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, prewarm_chunks);
+    }
+}
+
+/// Component building the chunk meshes of a map ahead of time.
+///
+/// Removed once every layer has been pre-warmed.
+///
+/// ```rust,ignore
+/// commands.spawn((
+///     SpriteFusionBundle {
+///         map: asset_server.load("big_map.json"),
+///         tileset: asset_server.load("spritesheet.png"),
+///         ..default()
+///     },
+///     PrewarmChunks::default(),
+/// ));
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct PrewarmChunks {
+    /// Layers starting to pre-warm each frame.
+    pub layers_per_frame: usize,
+    /// Frames a layer stays unculled, enough for its chunks to be built.
+    pub frames: u32,
+    next: usize,
+    warming: Vec<(Entity, u32, FrustumCulling)>,
+}
+
+impl PrewarmChunks {
+    /// Pre-warm this many layers per frame.
+    pub fn new(layers_per_frame: usize) -> Self {
+        Self {
+            layers_per_frame: layers_per_frame.max(1),
+            ..default()
+        }
+    }
+}
+
+impl Default for PrewarmChunks {
+    fn default() -> Self {
+        Self {
+            layers_per_frame: 1,
+            frames: 2,
+            next: 0,
+            warming: Vec::new(),
+        }
+    }
+}
+
+/// System that turns off culling of a few layers of pre-warming maps each frame,
+/// and restores it once their chunks had time to build.
+fn prewarm_chunks(
+    mut commands: Commands,
+    mut maps: Query<(Entity, &mut PrewarmChunks, &Children), With<SpriteFusionMapMarker>>,
+    mut layers: Query<&mut FrustumCulling, With<SpriteFusionLayerMarker>>,
+) {
+    for (map, mut prewarm, children) in maps.iter_mut() {
+        let prewarm = &mut *prewarm;
+        prewarm.warming.retain_mut(|(layer, frames, culling)| {
+            *frames = frames.saturating_sub(1);
+            if *frames > 0 {
+                return true;
+            }
+            if let Ok(mut layer_culling) = layers.get_mut(*layer) {
+                *layer_culling = *culling;
+            }
+            false
+        });
+
+        let pending: Vec<Entity> = children
+            .iter()
+            .filter(|&child| layers.contains(child))
+            .skip(prewarm.next)
+            .take(prewarm.layers_per_frame)
+            .collect();
+        for layer in pending {
+            let Ok(mut culling) = layers.get_mut(layer) else {
+                continue;
+            };
+            prewarm
+                .warming
+                .push((layer, prewarm.frames.max(1), *culling));
+            *culling = FrustumCulling(false);
+            prewarm.next += 1;
+        }
+
+        if prewarm.warming.is_empty() {
+            commands.entity(map).remove::<PrewarmChunks>();
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Hot reloading of the assets of spawned maps.
+//!
+//! When the spritesheet of a spawned map is modified on disk, or its
+//! [`SpriteFusionTilesetHandle`] is swapped for another image, the layers pick
+//! up the new texture in place: tiles aren't respawned, so their components and
+//! runtime state survive. A [`TilesetReloaded`] message is written either way.
+//!
+//! When the map asset itself is modified, e.g. re-exported from Sprite Fusion,
+//! its layers and tiles are despawned and the map is spawned again from the new
+//! data, writing a new
+//! [`SpriteFusionMapSpawned`](crate::plugin::SpriteFusionMapSpawned) message.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    commands::SpriteFusionCommandsExt,
+    plugin::{SpriteFusionMapHandle, SpriteFusionTilesetHandle},
+    prepare::MapPreparation,
+    types::{SpriteFusionLayerMarker, SpriteFusionMap, SpriteFusionMapMarker},
+};
+
+pub(crate) struct SpriteFusionReloadPlugin;
+
+impl Plugin for SpriteFusionReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<TilesetReloaded>()
+            .add_systems(Update, (reload_maps, reload_tilesets));
+    }
+}
+
+/// Message written when the tileset of a spawned map was modified or replaced.
+#[derive(Message, Debug, Clone)]
+pub struct TilesetReloaded {
+    /// The map entity.
+    pub map: Entity,
+    /// The tileset now used by the map.
+    pub tileset: Handle<Image>,
+}
+
+/// System that points layers at their map's current tileset, and refreshes them
+/// when the tileset image changes.
+pub(crate) fn reload_tilesets(
+    mut image_events: MessageReader<AssetEvent<Image>>,
+    maps: Query<(Entity, Ref<SpriteFusionTilesetHandle>, &Children), With<SpriteFusionMapMarker>>,
+    mut layers: Query<&mut TilemapTexture, With<SpriteFusionLayerMarker>>,
+    mut reloaded: MessageWriter<TilesetReloaded>,
+) {
+    let modified: HashSet<AssetId<Image>> = image_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (map, tileset, children) in maps.iter() {
+        // Maps spawned this frame already use their tileset
+        let replaced = tileset.is_changed() && !tileset.is_added();
+        if !replaced && !modified.contains(&tileset.id()) {
+            continue;
+        }
+        let mut layer_textures = layers.iter_many_mut(children);
+        while let Some(mut texture) = layer_textures.fetch_next() {
+            if replaced {
+                *texture = TilemapTexture::Single(tileset.0.clone());
+            } else {
+                // Extract the layer again, so the renderer uploads the new image
+                texture.set_changed();
+            }
+        }
+        reloaded.write(TilesetReloaded {
+            map,
+            tileset: tileset.0.clone(),
+        });
+    }
+}
+
+/// System that respawns maps whose map asset was modified.
+#[allow(clippy::type_complexity)]
+fn reload_maps(
+    mut commands: Commands,
+    mut map_events: MessageReader<AssetEvent<SpriteFusionMap>>,
+    maps: Query<
+        (Entity, &SpriteFusionMapHandle),
+        Or<(With<SpriteFusionMapMarker>, With<MapPreparation>)>,
+    >,
+) {
+    let modified: HashSet<AssetId<SpriteFusionMap>> = map_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if modified.is_empty() {
+        return;
+    }
+
+    for (map, handle) in maps.iter() {
+        if modified.contains(&handle.id()) {
+            info!("Map asset modified, respawning SpriteFusion map");
+            // Maps still being prepared start over with the new data too
+            commands.respawn_spritefusion_map(map);
+        }
+    }
+}
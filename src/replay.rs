@@ -0,0 +1,319 @@
+//! Recording and playback of tile changes on spawned maps.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    convert::TileOrigin,
+    geometry::SpriteFusionMapGeometry,
+    tile_index::SpriteFusionTileIndex,
+    types::{Collider, MapPaused, SpriteFusionLayerMarker},
+};
+
+/// Plugin that records and plays back tile changes.
+///
+/// Added by [`SpriteFusionPlugin`](crate::plugin::SpriteFusionPlugin).
+pub struct SpriteFusionReplayPlugin;
+
+impl Plugin for SpriteFusionReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (record_tile_changes, play_map_replays).chain());
+    }
+}
+
+/// A serializable log of tile changes on a map.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MapReplay {
+    /// Recorded changes, ordered by time.
+    pub events: Vec<ReplayEvent>,
+}
+
+impl MapReplay {
+    /// Duration of the replay in seconds.
+    pub fn duration(&self) -> f32 {
+        self.events.last().map(|e| e.time).unwrap_or(0.0)
+    }
+}
+
+/// A single recorded tile change.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    /// Time of the change in seconds since recording started.
+    pub time: f32,
+    /// Index of the layer the tile belongs to.
+    pub layer: usize,
+    /// X position of the tile.
+    pub x: u32,
+    /// Y position of the tile.
+    pub y: u32,
+    /// What changed.
+    pub change: TileChange,
+}
+
+/// Kind of change applied to a tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TileChange {
+    /// A tile was placed, with this texture index.
+    Added(u32),
+    /// The texture index changed.
+    Texture(u32),
+    /// The tile was shown or hidden.
+    Visible(bool),
+    /// The tile was removed.
+    Removed,
+}
+
+/// Component that records all tile changes of the map entity it's attached to.
+///
+/// ```rust,ignore
+/// // Start recording
+/// commands.entity(map).insert(MapRecorder::default());
+///
+/// // Later, grab the recording
+/// fn save(recorders: Query<&MapRecorder>) {
+///     let replay = recorders.single().unwrap().replay();
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Default)]
+pub struct MapRecorder {
+    elapsed: f32,
+    replay: MapReplay,
+    known_tiles: HashMap<Entity, (usize, TilePos)>,
+}
+
+impl MapRecorder {
+    /// Get the changes recorded so far.
+    pub fn replay(&self) -> &MapReplay {
+        &self.replay
+    }
+
+    /// Take the changes recorded so far, clearing the recorder.
+    pub fn take_replay(&mut self) -> MapReplay {
+        self.elapsed = 0.0;
+        std::mem::take(&mut self.replay)
+    }
+
+    fn push(&mut self, layer: usize, pos: TilePos, change: TileChange) {
+        self.replay.events.push(ReplayEvent {
+            time: self.elapsed,
+            layer,
+            x: pos.x,
+            y: pos.y,
+            change,
+        });
+    }
+}
+
+/// Component that re-applies a [`MapReplay`] to the map entity it's attached to.
+///
+/// The component is removed once the replay is finished.
+#[derive(Component, Debug, Clone)]
+pub struct MapReplayPlayer {
+    /// The replay being played.
+    pub replay: MapReplay,
+    /// Playback speed multiplier.
+    pub speed: f32,
+    elapsed: f32,
+    next: usize,
+}
+
+impl MapReplayPlayer {
+    /// Create a player for a replay at normal speed.
+    pub fn new(replay: MapReplay) -> Self {
+        Self {
+            replay,
+            speed: 1.0,
+            elapsed: 0.0,
+            next: 0,
+        }
+    }
+
+    /// Current playback position in seconds.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+}
+
+/// System that appends tile changes to the recorder of their map.
+#[allow(clippy::type_complexity)]
+fn record_tile_changes(
+    time: Res<Time>,
+    mut recorders: Query<(&mut MapRecorder, Option<&Children>), Without<MapPaused>>,
+    layers: Query<(&SpriteFusionLayerMarker, &TileStorage)>,
+    tile_positions: Query<&TilePos>,
+    changed_tiles: Query<
+        (
+            Entity,
+            &TilePos,
+            &TilemapId,
+            Ref<TileTextureIndex>,
+            Ref<TileVisible>,
+        ),
+        Or<(Changed<TileTextureIndex>, Changed<TileVisible>)>,
+    >,
+    mut removed_tiles: RemovedComponents<TilePos>,
+) {
+    // Removals first, so a tile replaced within a frame replays as removed then added
+    for entity in removed_tiles.read() {
+        for (mut recorder, _) in recorders.iter_mut() {
+            if let Some((layer, pos)) = recorder.known_tiles.remove(&entity) {
+                recorder.push(layer, pos, TileChange::Removed);
+            }
+        }
+    }
+
+    for (mut recorder, children) in recorders.iter_mut() {
+        recorder.elapsed += time.delta_secs();
+
+        // Index tiles that already existed when recording started
+        if recorder.is_added() {
+            for (layer, storage) in children
+                .into_iter()
+                .flatten()
+                .filter_map(|c| layers.get(*c).ok())
+            {
+                for tile in storage.iter().flatten() {
+                    if let Ok(pos) = tile_positions.get(*tile) {
+                        recorder.known_tiles.insert(*tile, (layer.index, *pos));
+                    }
+                }
+            }
+        }
+    }
+
+    for (entity, pos, tilemap_id, texture, visible) in changed_tiles.iter() {
+        let Ok((layer, _)) = layers.get(tilemap_id.0) else {
+            continue;
+        };
+        let Ok((mut recorder, _)) = recorders.get_mut(layer.map) else {
+            continue;
+        };
+        let known = recorder
+            .known_tiles
+            .insert(entity, (layer.index, *pos))
+            .is_some();
+        if texture.is_added() {
+            // Tiles indexed when recording started aren't new
+            if !known {
+                recorder.push(layer.index, *pos, TileChange::Added(texture.0));
+                if !visible.0 {
+                    recorder.push(layer.index, *pos, TileChange::Visible(false));
+                }
+            }
+            continue;
+        }
+        if texture.is_changed() {
+            recorder.push(layer.index, *pos, TileChange::Texture(texture.0));
+        }
+        if visible.is_changed() {
+            recorder.push(layer.index, *pos, TileChange::Visible(visible.0));
+        }
+    }
+}
+
+/// System that applies due replay events to their map.
+#[allow(clippy::type_complexity)]
+fn play_map_replays(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut players: Query<
+        (
+            Entity,
+            &mut MapReplayPlayer,
+            &Children,
+            Option<&SpriteFusionMapGeometry>,
+            Option<&mut SpriteFusionTileIndex>,
+        ),
+        Without<MapPaused>,
+    >,
+    mut layers: Query<(&SpriteFusionLayerMarker, &mut TileStorage)>,
+    mut tiles: Query<(&mut TileTextureIndex, &mut TileVisible)>,
+) {
+    for (map, mut player, children, geometry, mut tile_index) in players.iter_mut() {
+        player.elapsed += time.delta_secs() * player.speed;
+
+        while let Some(event) = player.replay.events.get(player.next).copied() {
+            if event.time > player.elapsed {
+                break;
+            }
+            player.next += 1;
+
+            let Some(layer_entity) = children.iter().find(|&child| {
+                layers
+                    .get(child)
+                    .is_ok_and(|(layer, _)| layer.index == event.layer)
+            }) else {
+                continue;
+            };
+            let Ok((layer, mut storage)) = layers.get_mut(layer_entity) else {
+                continue;
+            };
+            let pos = TilePos {
+                x: event.x,
+                y: event.y,
+            };
+            let tile = storage.checked_get(&pos);
+            if let (TileChange::Added(index), None) = (event.change, tile) {
+                if !pos.within_map_bounds(&storage.size) {
+                    continue;
+                }
+                let mut tile_commands = commands.spawn((
+                    TileBundle {
+                        position: pos,
+                        tilemap_id: TilemapId(layer_entity),
+                        texture_index: TileTextureIndex(index),
+                        flip: TileFlip {
+                            y: geometry.is_some_and(|g| g.tile_origin == TileOrigin::TopLeft),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    ChildOf(layer_entity),
+                ));
+                if layer.collider {
+                    tile_commands.insert(Collider);
+                }
+                storage.set(&pos, tile_commands.id());
+                if let Some(tile_index) = tile_index.as_mut() {
+                    tile_index.set_tile(layer_entity, pos, Some(tile_commands.id()));
+                }
+                continue;
+            }
+            let Some(tile) = tile else {
+                warn!(
+                    "Replay references missing tile ({}, {}) on layer {}",
+                    event.x, event.y, event.layer
+                );
+                continue;
+            };
+
+            match event.change {
+                // A tile is there already, as if the recording started later
+                TileChange::Added(index) | TileChange::Texture(index) => {
+                    if let Ok((mut texture, _)) = tiles.get_mut(tile) {
+                        texture.0 = index;
+                    }
+                }
+                TileChange::Visible(visible) => {
+                    if let Ok((_, mut tile_visible)) = tiles.get_mut(tile) {
+                        tile_visible.0 = visible;
+                    }
+                }
+                TileChange::Removed => {
+                    storage.remove(&pos);
+                    if let Some(tile_index) = tile_index.as_mut() {
+                        tile_index.set_tile(layer_entity, pos, None);
+                    }
+                    commands.entity(tile).despawn();
+                }
+            }
+        }
+
+        if player.next >= player.replay.events.len() {
+            commands.entity(map).remove::<MapReplayPlayer>();
+        }
+    }
+}
@@ -0,0 +1,312 @@
+//! Resource nodes for mining and harvesting gameplay.
+//!
+//! Tiles with `"resource": "<type>"` and `"amount": n` attributes (e.g. an ore
+//! vein with `"resource": "iron", "amount": 5`) get a [`ResourceNode`]
+//! component, and spawned maps index their nodes by type in [`MapResources`].
+//! Depleting a node to zero swaps its tile for the `"depletedTile"` texture, or
+//! hides it without one; regenerating it brings the original tile back.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::{AttrKey, StoredTileAttributes},
+    plugin::SpriteFusionSettings,
+    types::{MapPaused, SpriteFusionLayerMarker, TileAttributes},
+};
+
+/// Attribute giving the resource type of a node tile.
+pub const RESOURCE_ATTRIBUTE: &str = "resource";
+/// Attribute giving the amount of resource in a node tile.
+pub const AMOUNT_ATTRIBUTE: &str = "amount";
+/// Attribute giving the texture index of a node tile once depleted.
+pub const DEPLETED_TILE_ATTRIBUTE: &str = "depletedTile";
+
+/// Plugin that tags resource node tiles and swaps depleted nodes to their
+/// depleted tile.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionResourcesPlugin;
+
+impl Plugin for SpriteFusionResourcesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ResourceDepleted>().add_systems(
+            Update,
+            (
+                (tag_resource_nodes, tag_stored_resource_nodes),
+                index_resource_nodes,
+                update_resource_tiles,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// A tile holding an amount of some resource.
+///
+/// ```rust,ignore
+/// fn mine(mut nodes: Query<&mut ResourceNode>, target: Res<MiningTarget>, mut ore: ResMut<Ore>) {
+///     if let Ok(mut node) = nodes.get_mut(target.0) {
+///         ore.0 += node.deplete(1);
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct ResourceNode {
+    /// Resource type, from the `"resource"` attribute.
+    pub kind: String,
+    /// Amount left.
+    pub amount: u32,
+    /// Amount the node was spawned with, from the `"amount"` attribute.
+    pub max_amount: u32,
+    /// Texture index of the tile once depleted, from the `"depletedTile"` attribute.
+    /// Depleted tiles without one are hidden.
+    pub depleted_texture: Option<u32>,
+    texture: Option<u32>,
+}
+
+impl ResourceNode {
+    fn from_attributes(attrs: &TileAttributes, keys: &ResourceKeys) -> Option<Self> {
+        let kind = attrs.get_str(&keys.resource)?.to_string();
+        let amount = attrs
+            .get_i64(&keys.amount)
+            .map_or(1, |amount| amount.max(0) as u32);
+        Some(Self {
+            kind,
+            amount,
+            max_amount: amount,
+            depleted_texture: attrs
+                .get_i64(&keys.depleted_tile)
+                .and_then(|id| u32::try_from(id).ok()),
+            texture: None,
+        })
+    }
+
+    /// Check if the node has nothing left.
+    pub fn is_depleted(&self) -> bool {
+        self.amount == 0
+    }
+
+    /// Take up to `amount` from the node, returning what was taken.
+    pub fn deplete(&mut self, amount: u32) -> u32 {
+        let taken = amount.min(self.amount);
+        self.amount -= taken;
+        taken
+    }
+
+    /// Add `amount` back to the node, up to its spawned amount.
+    pub fn regenerate(&mut self, amount: u32) {
+        self.amount = (self.amount + amount).min(self.max_amount);
+    }
+}
+
+/// A node in a [`MapResources`] index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceEntry {
+    /// The node tile.
+    pub tile: Entity,
+    /// Position of the tile in its layer.
+    pub position: TilePos,
+}
+
+/// Resource nodes of a spawned map, by resource type.
+#[derive(Component, Debug, Clone, Default)]
+pub struct MapResources(BTreeMap<String, Vec<ResourceEntry>>);
+
+impl MapResources {
+    /// Get the nodes of a resource type.
+    pub fn nodes(&self, kind: &str) -> &[ResourceEntry] {
+        self.0.get(kind).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterate over the resource types of the map, sorted.
+    pub fn kinds(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// Iterate over all nodes, with their resource type.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ResourceEntry)> {
+        self.0
+            .iter()
+            .flat_map(|(kind, nodes)| nodes.iter().map(move |node| (kind.as_str(), node)))
+    }
+
+    fn remove(&mut self, tile: Entity) {
+        for nodes in self.0.values_mut() {
+            nodes.retain(|node| node.tile != tile);
+        }
+        self.0.retain(|_, nodes| !nodes.is_empty());
+    }
+}
+
+/// Message written when a [`ResourceNode`] runs out.
+#[derive(Message, Debug, Clone)]
+pub struct ResourceDepleted {
+    /// The node tile.
+    pub tile: Entity,
+    /// Resource type of the node.
+    pub kind: String,
+}
+
+/// Attribute keys of resource nodes, with the attribute prefix.
+struct ResourceKeys {
+    resource: Cow<'static, str>,
+    amount: Cow<'static, str>,
+    depleted_tile: Cow<'static, str>,
+}
+
+impl ResourceKeys {
+    fn new(settings: &SpriteFusionSettings) -> Self {
+        Self {
+            resource: settings.reserved_attribute(RESOURCE_ATTRIBUTE),
+            amount: settings.reserved_attribute(AMOUNT_ATTRIBUTE),
+            depleted_tile: settings.reserved_attribute(DEPLETED_TILE_ATTRIBUTE),
+        }
+    }
+}
+
+/// System that keeps [`ResourceNode`] in sync with the attribute components of tiles.
+fn tag_resource_nodes(
+    mut commands: Commands,
+    tiles: Query<(Entity, &TileAttributes, Option<&ResourceNode>), Changed<TileAttributes>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let keys = ResourceKeys::new(&settings);
+    for (tile, attrs, current) in tiles.iter() {
+        match ResourceNode::from_attributes(attrs, &keys) {
+            // Keep the remaining amount of nodes whose type didn't change
+            Some(node) if current.is_some_and(|current| current.kind == node.kind) => {}
+            Some(node) => {
+                commands.entity(tile).insert(node);
+            }
+            None => {
+                commands.entity(tile).remove::<ResourceNode>();
+            }
+        }
+    }
+}
+
+/// System that inserts [`ResourceNode`] on tiles using an attribute store.
+fn tag_stored_resource_nodes(
+    mut commands: Commands,
+    tiles: Query<Entity, Added<AttrKey>>,
+    attributes: StoredTileAttributes,
+    settings: Res<SpriteFusionSettings>,
+) {
+    let keys = ResourceKeys::new(&settings);
+    for tile in tiles.iter() {
+        let node = attributes
+            .get(tile)
+            .and_then(|attrs| ResourceNode::from_attributes(attrs, &keys));
+        if let Some(node) = node {
+            commands.entity(tile).insert(node);
+        }
+    }
+}
+
+/// System that keeps the [`MapResources`] of maps in sync with their nodes.
+fn index_resource_nodes(
+    mut commands: Commands,
+    new_nodes: Query<(Entity, &ResourceNode, &TilePos, &TilemapId), Added<ResourceNode>>,
+    layers: Query<&SpriteFusionLayerMarker>,
+    mut maps: Query<&mut MapResources>,
+    mut removed: RemovedComponents<ResourceNode>,
+) {
+    for tile in removed.read() {
+        for mut resources in maps.iter_mut() {
+            resources.remove(tile);
+        }
+    }
+
+    let mut added: HashMap<Entity, Vec<(String, ResourceEntry)>> = HashMap::new();
+    for (tile, node, position, tilemap_id) in new_nodes.iter() {
+        let Ok(layer) = layers.get(tilemap_id.0) else {
+            continue;
+        };
+        added.entry(layer.map).or_default().push((
+            node.kind.clone(),
+            ResourceEntry {
+                tile,
+                position: *position,
+            },
+        ));
+    }
+    for (map, entries) in added {
+        let mut new_resources = MapResources::default();
+        let resources = match maps.get_mut(map) {
+            Ok(resources) => resources.into_inner(),
+            Err(_) => &mut new_resources,
+        };
+        for (kind, entry) in entries {
+            // A node inserted again after a type change moves to its new type
+            resources.remove(entry.tile);
+            resources.0.entry(kind).or_default().push(entry);
+        }
+        if !new_resources.0.is_empty() {
+            commands.entity(map).insert(new_resources);
+        }
+    }
+}
+
+/// System that swaps the tiles of nodes as they are depleted and regenerated.
+///
+/// Nodes of paused maps keep their tile until the map resumes.
+fn update_resource_tiles(
+    changed: Query<Entity, Changed<ResourceNode>>,
+    mut nodes: Query<(
+        &mut ResourceNode,
+        &mut TileTextureIndex,
+        &mut TileVisible,
+        &TilemapId,
+    )>,
+    layers: Query<&SpriteFusionLayerMarker>,
+    paused_maps: Query<(), With<MapPaused>>,
+    mut deferred: Local<HashSet<Entity>>,
+    mut depleted: MessageWriter<ResourceDepleted>,
+) {
+    let mut pending: Vec<Entity> = deferred.drain().chain(changed.iter()).collect();
+    pending.sort();
+    pending.dedup();
+    for tile in pending {
+        let Ok((mut node, mut texture, mut visible, tilemap_id)) = nodes.get_mut(tile) else {
+            continue;
+        };
+        let paused = layers
+            .get(tilemap_id.0)
+            .is_ok_and(|layer| paused_maps.contains(layer.map));
+        if paused {
+            deferred.insert(tile);
+            continue;
+        }
+        // Remember the spawned texture, without flagging the node as changed again
+        let original = *node
+            .bypass_change_detection()
+            .texture
+            .get_or_insert(texture.0);
+        if node.is_depleted() {
+            match node.depleted_texture {
+                Some(depleted_texture) if texture.0 != depleted_texture => {
+                    texture.0 = depleted_texture;
+                }
+                None if visible.0 => visible.0 = false,
+                _ => continue,
+            }
+            depleted.write(ResourceDepleted {
+                tile,
+                kind: node.kind.clone(),
+            });
+        } else {
+            if texture.0 != original {
+                texture.0 = original;
+            }
+            if !visible.0 {
+                visible.0 = true;
+            }
+        }
+    }
+}
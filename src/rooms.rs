@@ -0,0 +1,340 @@
+//! Room-based cameras, as in top-down adventure games.
+//!
+//! Spawned maps get a [`MapRooms`] component listing their rectangular rooms,
+//! detected from zones or from connected regions of a floor layer depending on
+//! [`RoomDetection`]. Cameras with a [`RoomCamera`] stay within the room their
+//! target is in, and snap or slide to the next room as the target crosses into
+//! it.
+
+use std::collections::HashSet;
+
+use bevy::{math::curve::EaseFunction, prelude::*};
+
+use crate::{
+    convert::map_local_position,
+    plugin::{SpriteFusionSettings, SpriteFusionSystems},
+    types::{MapPaused, SpriteFusionMap, SpriteFusionMapMarker},
+    zones::extract_zones,
+};
+
+/// Plugin that detects the [`MapRooms`] of spawned maps and moves
+/// [`RoomCamera`]s between them.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionRoomsPlugin;
+
+impl Plugin for SpriteFusionRoomsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RoomDetection>()
+            .add_message::<RoomEntered>()
+            .add_systems(Update, insert_map_rooms)
+            .add_systems(PostUpdate, follow_rooms.in_set(SpriteFusionSystems::Place));
+    }
+}
+
+/// How the rooms of spawned maps are detected.
+#[derive(Resource, Debug, Clone, Default)]
+pub enum RoomDetection {
+    /// Each zone painted with the `"zone"` attribute is a room, bounded by the
+    /// rectangle around its tiles.
+    #[default]
+    Zones,
+    /// Each connected region of tiles of the named layer is a room, bounded by
+    /// the rectangle around its tiles.
+    FloorLayer(String),
+}
+
+/// A rectangular room of a map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Room {
+    /// Name of the zone the room comes from, if any.
+    pub name: Option<String>,
+    /// Bounds of the room in the local space of the map entity.
+    pub bounds: Rect,
+}
+
+/// Rooms of a spawned map.
+#[derive(Component, Debug, Clone, Default)]
+pub struct MapRooms(pub Vec<Room>);
+
+impl MapRooms {
+    /// Detect the rooms of a map, reading zones with the given
+    /// [`SpriteFusionSettings::attribute_prefix`].
+    pub fn from_map(map: &SpriteFusionMap, detection: &RoomDetection, prefix: &str) -> Self {
+        let regions = match detection {
+            RoomDetection::Zones => extract_zones(map, prefix)
+                .into_iter()
+                .map(|(name, tiles)| (Some(name), tiles))
+                .collect(),
+            RoomDetection::FloorLayer(layer) => floor_regions(map, layer)
+                .into_iter()
+                .map(|tiles| (None, tiles))
+                .collect::<Vec<_>>(),
+        };
+        let tile_size = Vec2::splat(map.tile_size as f32);
+        Self(
+            regions
+                .into_iter()
+                .map(|(name, tiles)| Room {
+                    name,
+                    bounds: tiles
+                        .iter()
+                        .map(|&(x, y)| {
+                            Rect::from_center_size(map_local_position(map, x, y), tile_size)
+                        })
+                        .reduce(|a, b| a.union(b))
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Get the index of the room at a position in the local space of the map
+    /// entity.
+    ///
+    /// Where rooms overlap, the first one wins.
+    pub fn room_at_local(&self, local: Vec2) -> Option<usize> {
+        self.0.iter().position(|room| room.bounds.contains(local))
+    }
+
+    /// Get a room by index.
+    pub fn get(&self, index: usize) -> Option<&Room> {
+        self.0.get(index)
+    }
+}
+
+/// Group the tiles of a layer into regions touching through their edges.
+fn floor_regions(map: &SpriteFusionMap, layer_name: &str) -> Vec<HashSet<(i32, i32)>> {
+    let Some(layer) = map.layers.iter().find(|layer| layer.name == layer_name) else {
+        return Vec::new();
+    };
+    let floor: HashSet<(i32, i32)> = layer.tiles.iter().map(|tile| (tile.x, tile.y)).collect();
+
+    // Sorted seeds keep room order stable between runs
+    let mut seeds: Vec<_> = floor.iter().copied().collect();
+    seeds.sort_by_key(|&(x, y)| (y, x));
+
+    let mut visited = HashSet::new();
+    let mut regions = Vec::new();
+    for seed in seeds {
+        if !visited.insert(seed) {
+            continue;
+        }
+        let mut tiles = HashSet::from([seed]);
+        let mut stack = vec![seed];
+        while let Some((x, y)) = stack.pop() {
+            for neighbor in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if floor.contains(&neighbor) && visited.insert(neighbor) {
+                    tiles.insert(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+        }
+        regions.push(tiles);
+    }
+    regions
+}
+
+/// How a [`RoomCamera`] moves to a new room.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoomTransition {
+    /// Jump to the new room at once.
+    Snap,
+    /// Slide to the new room in `duration` seconds.
+    Slide {
+        /// Duration of the slide, in seconds.
+        duration: f32,
+        /// Easing of the slide.
+        ease: EaseFunction,
+    },
+}
+
+impl Default for RoomTransition {
+    fn default() -> Self {
+        Self::Slide {
+            duration: 0.5,
+            ease: EaseFunction::SmoothStep,
+        }
+    }
+}
+
+/// Component keeping a camera within the room its target is in.
+///
+/// The camera follows the target inside rooms bigger than its view, and is
+/// centered on smaller ones. Outside of any room, it follows the target freely.
+/// The camera's [`Transform`] is treated as world space and its Z is left
+/// untouched.
+///
+/// ```rust,ignore
+/// commands.spawn((Camera2d, RoomCamera::new(player)));
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct RoomCamera {
+    /// The entity the camera keeps in view, usually the player.
+    pub target: Entity,
+    /// How the camera moves between rooms.
+    pub transition: RoomTransition,
+    room: Option<(Entity, usize)>,
+    slide: Option<(Vec2, f32)>,
+}
+
+impl RoomCamera {
+    /// Follow `target` from room to room, sliding between them.
+    pub fn new(target: Entity) -> Self {
+        Self {
+            target,
+            transition: RoomTransition::default(),
+            room: None,
+            slide: None,
+        }
+    }
+
+    /// Use another transition between rooms.
+    pub fn with_transition(mut self, transition: RoomTransition) -> Self {
+        self.transition = transition;
+        self
+    }
+
+    /// The map entity and room index the camera is in.
+    pub fn room(&self) -> Option<(Entity, usize)> {
+        self.room
+    }
+
+    /// Check if the camera is sliding between rooms.
+    pub fn is_sliding(&self) -> bool {
+        self.slide.is_some()
+    }
+}
+
+/// Sent when the target of a [`RoomCamera`] enters another room.
+#[derive(Message, Debug, Clone)]
+pub struct RoomEntered {
+    /// The camera entity.
+    pub camera: Entity,
+    /// The map entity of the room.
+    pub map: Entity,
+    /// Index of the room in the [`MapRooms`] of the map.
+    pub room: usize,
+}
+
+/// System that adds the rooms of newly spawned maps.
+fn insert_map_rooms(
+    mut commands: Commands,
+    maps: Query<(Entity, &SpriteFusionMapMarker), Added<SpriteFusionMapMarker>>,
+    detection: Res<RoomDetection>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (entity, marker) in maps.iter() {
+        let rooms = MapRooms::from_map(&marker.map, &detection, &settings.attribute_prefix);
+        if !rooms.0.is_empty() {
+            commands.entity(entity).insert(rooms);
+        }
+    }
+}
+
+/// System that moves room cameras along with their target.
+fn follow_rooms(
+    time: Res<Time>,
+    mut cameras: Query<(Entity, &mut RoomCamera, &mut Transform, Option<&Projection>)>,
+    targets: Query<&GlobalTransform>,
+    maps: Query<(Entity, &MapRooms, &GlobalTransform), Without<MapPaused>>,
+    paused_maps: Query<(), With<MapPaused>>,
+    mut entered: MessageWriter<RoomEntered>,
+) {
+    for (camera, mut room_camera, mut transform, projection) in cameras.iter_mut() {
+        // Cameras in a room of a paused map stay where they are
+        if room_camera
+            .room
+            .is_some_and(|(map, _)| paused_maps.contains(map))
+        {
+            continue;
+        }
+        let Ok(target) = targets.get(room_camera.target) else {
+            continue;
+        };
+        let position = target.translation().truncate();
+
+        let found = maps.iter().find_map(|(map, rooms, map_transform)| {
+            let local = map_transform
+                .affine()
+                .inverse()
+                .transform_point3(position.extend(0.0))
+                .truncate();
+            let index = rooms.room_at_local(local)?;
+            let bounds = rooms.0[index].bounds;
+            let min = map_transform.transform_point(bounds.min.extend(0.0));
+            let max = map_transform.transform_point(bounds.max.extend(0.0));
+            Some((
+                (map, index),
+                Rect::from_corners(min.truncate(), max.truncate()),
+            ))
+        });
+
+        let current = transform.translation.truncate();
+        let Some((room, bounds)) = found else {
+            room_camera.room = None;
+            room_camera.slide = None;
+            transform.translation = position.extend(transform.translation.z);
+            continue;
+        };
+
+        if room_camera.room != Some(room) {
+            // The first room the camera finds is entered without a transition
+            if room_camera.room.is_some() {
+                if let RoomTransition::Slide { .. } = room_camera.transition {
+                    room_camera.slide = Some((current, 0.0));
+                }
+            }
+            room_camera.room = Some(room);
+            entered.write(RoomEntered {
+                camera,
+                map: room.0,
+                room: room.1,
+            });
+        }
+
+        let half_view = match projection {
+            Some(Projection::Orthographic(ortho)) => ortho.area.half_size(),
+            _ => Vec2::ZERO,
+        };
+        let destination = clamp_to_room(position, bounds, half_view);
+
+        let next = match (room_camera.slide, room_camera.transition) {
+            (Some((from, elapsed)), RoomTransition::Slide { duration, ease }) => {
+                let elapsed = elapsed + time.delta_secs();
+                let progress = if duration > 0.0 {
+                    (elapsed / duration).min(1.0)
+                } else {
+                    1.0
+                };
+                room_camera.slide = (progress < 1.0).then_some((from, elapsed));
+                from.lerp(destination, ease.sample_clamped(progress))
+            }
+            _ => {
+                room_camera.slide = None;
+                destination
+            }
+        };
+        transform.translation = next.extend(transform.translation.z);
+    }
+}
+
+/// Keep a view of `half_view` around `position` within `bounds`, centering it
+/// on axes where the room is smaller than the view.
+fn clamp_to_room(position: Vec2, bounds: Rect, half_view: Vec2) -> Vec2 {
+    let min = bounds.min + half_view;
+    let max = bounds.max - half_view;
+    let center = bounds.center();
+    Vec2::new(
+        if min.x <= max.x {
+            position.x.clamp(min.x, max.x)
+        } else {
+            center.x
+        },
+        if min.y <= max.y {
+            position.y.clamp(min.y, max.y)
+        } else {
+            center.y
+        },
+    )
+}
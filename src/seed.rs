@@ -0,0 +1,74 @@
+//! Stable per-map random seeds.
+//!
+//! Spawned maps get a [`MapSeed`], derived from the content of the map and the
+//! optional [`SpriteFusionSettings::seed`]. The same map always gets the same
+//! seed, so generation seeded from it (loot, decoration scatter, enemy rolls) is
+//! reproducible per level, and changes when the level is edited.
+
+use bevy::prelude::*;
+
+use crate::{
+    plugin::SpriteFusionSettings,
+    types::{SpriteFusionMap, SpriteFusionMapMarker},
+};
+
+pub(crate) struct SpriteFusionSeedPlugin;
+
+impl Plugin for SpriteFusionSeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, insert_map_seeds);
+    }
+}
+
+/// Random seed of a spawned map.
+///
+/// ```rust,ignore
+/// fn roll_loot(chests: Query<&SpawnedFromTile, Added<SpawnedFromTile>>, maps: Query<&MapSeed>) {
+///     for chest in chests.iter() {
+///         let Ok(seed) = maps.get(chest.map) else { continue };
+///         let mut rng = ChaCha8Rng::seed_from_u64(seed.at(chest.x, chest.y));
+///         // ...
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MapSeed(pub u64);
+
+impl MapSeed {
+    /// Derive the seed of a map from its content and an optional user seed.
+    pub fn new(map: &SpriteFusionMap, user_seed: Option<u64>) -> Self {
+        Self(mix(map.content_hash() ^ mix(user_seed.unwrap_or(0))))
+    }
+
+    /// Derive a seed for one randomized pass, so passes don't share a sequence.
+    pub fn derive(self, pass: &str) -> u64 {
+        pass.bytes()
+            .fold(self.0, |seed, byte| mix(seed ^ u64::from(byte)))
+    }
+
+    /// Derive a seed for one tile, in Sprite Fusion coordinates.
+    pub fn at(self, x: i32, y: i32) -> u64 {
+        mix(mix(self.0 ^ x as u32 as u64) ^ ((y as u32 as u64) << 32))
+    }
+}
+
+/// The SplitMix64 finalizer, spreading every input bit over the output.
+fn mix(mut value: u64) -> u64 {
+    value = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+/// System that adds the seed of newly spawned maps.
+fn insert_map_seeds(
+    mut commands: Commands,
+    maps: Query<(Entity, &SpriteFusionMapMarker), Added<SpriteFusionMapMarker>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (entity, marker) in maps.iter() {
+        commands
+            .entity(entity)
+            .insert(MapSeed::new(&marker.map, settings.seed));
+    }
+}
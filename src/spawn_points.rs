@@ -0,0 +1,131 @@
+//! Spawn points read from a conventional layer.
+//!
+//! Player starts and enemy spawns are often painted on a dedicated layer in
+//! Sprite Fusion. Layers named like [`SpawnPointSettings::layers`], `"SpawnPoints"`
+//! by default, aren't drawn: each of their tiles becomes an entity with a
+//! [`SpawnPoint`] component instead, so start positions can be queried by name.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    attributes::{clone_attributes, AttributeLocalizer},
+    convert::map_local_position,
+    plugin::matches_wildcard,
+    types::SpriteFusionMapMarker,
+};
+
+pub(crate) struct SpriteFusionSpawnPointsPlugin;
+
+impl Plugin for SpriteFusionSpawnPointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpawnPointSettings>()
+            .add_systems(Update, spawn_spawn_points);
+    }
+}
+
+/// Settings of spawn point layers.
+#[derive(Resource, Debug, Clone)]
+pub struct SpawnPointSettings {
+    /// Names of spawn point layers, with `*` matching any characters like in
+    /// [`SpriteFusionSettings::skip_layers`](crate::plugin::SpriteFusionSettings::skip_layers).
+    /// Defaults to `["SpawnPoints"]`.
+    pub layers: Vec<String>,
+    /// Attribute naming a spawn point. Defaults to `"name"`.
+    pub name_attribute: String,
+}
+
+impl Default for SpawnPointSettings {
+    fn default() -> Self {
+        Self {
+            layers: vec!["SpawnPoints".to_string()],
+            name_attribute: "name".to_string(),
+        }
+    }
+}
+
+impl SpawnPointSettings {
+    /// Check if a layer is a spawn point layer.
+    pub fn is_spawn_point_layer(&self, name: &str) -> bool {
+        self.layers
+            .iter()
+            .any(|pattern| matches_wildcard(pattern, name))
+    }
+}
+
+/// Component of entities spawned from the tiles of a spawn point layer.
+///
+/// The entity is a child of the map, placed at the tile's center.
+///
+/// ```rust,ignore
+/// fn spawn_player(
+///     mut commands: Commands,
+///     points: Query<&SpawnPoint, Added<SpawnPoint>>,
+///     assets: Res<GameAssets>,
+/// ) {
+///     for point in points.iter().filter(|point| point.name == "player") {
+///         commands.spawn((Player, Sprite::from_image(assets.player.clone()), Transform::from_translation(point.world_pos.extend(1.0))));
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct SpawnPoint {
+    /// Name of the spawn point, from the [`SpawnPointSettings::name_attribute`]
+    /// attribute of the tile, or the tile ID if it has none.
+    pub name: String,
+    /// World-space center of the tile when the map spawned.
+    pub world_pos: Vec2,
+    /// All attributes of the tile.
+    pub attributes: HashMap<String, serde_json::Value>,
+    /// The map the spawn point belongs to.
+    pub map: Entity,
+    /// ID of the tile, e.g. to tell apart spawn points painted with different tiles.
+    pub tile_id: u32,
+    /// X position of the tile, in Sprite Fusion coordinates.
+    pub x: i32,
+    /// Y position of the tile, in Sprite Fusion coordinates.
+    pub y: i32,
+}
+
+/// System that spawns the spawn points of newly spawned maps.
+fn spawn_spawn_points(
+    mut commands: Commands,
+    maps: Query<(Entity, &SpriteFusionMapMarker, &GlobalTransform), Added<SpriteFusionMapMarker>>,
+    settings: Res<SpawnPointSettings>,
+    localizer: Option<Res<AttributeLocalizer>>,
+) {
+    for (map, marker, transform) in maps.iter() {
+        let layers = marker
+            .map
+            .layers
+            .iter()
+            .filter(|layer| settings.is_spawn_point_layer(&layer.name));
+        for tile in layers.flat_map(|layer| &layer.tiles) {
+            let attributes = tile
+                .attributes
+                .as_ref()
+                .map(|attrs| clone_attributes(attrs, localizer.as_deref()))
+                .unwrap_or_default();
+            let name = attributes
+                .get(&settings.name_attribute)
+                .and_then(|name| name.as_str())
+                .map_or_else(|| tile.id.to_string(), str::to_string);
+            let position = map_local_position(&marker.map, tile.x, tile.y);
+            commands.spawn((
+                SpawnPoint {
+                    name,
+                    world_pos: transform.transform_point(position.extend(0.0)).truncate(),
+                    attributes,
+                    map,
+                    tile_id: tile.id,
+                    x: tile.x,
+                    y: tile.y,
+                },
+                Transform::from_translation(position.extend(0.0)),
+                Visibility::default(),
+                ChildOf(map),
+            ));
+        }
+    }
+}
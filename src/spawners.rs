@@ -0,0 +1,158 @@
+//! Entities spawned from attribute-tagged tiles.
+//!
+//! Sprite Fusion has no object layers, so entities are placed with tiles
+//! instead: a tile with an `"entity": "chest"` attribute becomes a standalone
+//! entity with a [`SpawnedFromTile`] component at the tile's position, which
+//! games turn into their own prefabs. The tile itself is removed, unless
+//! [`TileEntitySettings::keep_tiles`] is set.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::{clone_attributes, AttributeLocalizer},
+    convert::map_local_position,
+    geometry::SpriteFusionMapGeometry,
+    tile_index::{update_tile_indices, SpriteFusionTileIndex},
+    types::{SpriteFusionLayerMarker, SpriteFusionMapMarker},
+};
+
+/// Plugin that spawns entities from attribute-tagged tiles, see
+/// [`TileEntitySettings`].
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionSpawnersPlugin;
+
+impl Plugin for SpriteFusionSpawnersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileEntitySettings>()
+            .add_systems(Update, spawn_tile_entities.before(update_tile_indices));
+    }
+}
+
+/// Settings of entities spawned from tiles.
+#[derive(Resource, Debug, Clone)]
+pub struct TileEntitySettings {
+    /// Attribute naming the kind of entity to spawn. Defaults to `"entity"`.
+    pub attribute: String,
+    /// Keep the tiles the entities are spawned from. Defaults to `false`.
+    pub keep_tiles: bool,
+}
+
+impl Default for TileEntitySettings {
+    fn default() -> Self {
+        Self {
+            attribute: "entity".to_string(),
+            keep_tiles: false,
+        }
+    }
+}
+
+/// Component of entities spawned from a tagged tile.
+///
+/// The entity is a child of the map, placed at the tile's center.
+///
+/// ```rust,ignore
+/// fn spawn_prefabs(
+///     mut commands: Commands,
+///     spawned: Query<(Entity, &SpawnedFromTile), Added<SpawnedFromTile>>,
+///     assets: Res<GameAssets>,
+/// ) {
+///     for (entity, spawned) in spawned.iter() {
+///         match spawned.kind.as_str() {
+///             "chest" => commands.entity(entity).insert(Sprite::from_image(assets.chest.clone())),
+///             "enemy" => commands.entity(entity).insert(Enemy::from_attributes(&spawned.attributes)),
+///             _ => {}
+///         }
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone)]
+pub struct SpawnedFromTile {
+    /// Kind of entity, the value of the tile's attribute.
+    pub kind: String,
+    /// All attributes of the tile.
+    pub attributes: HashMap<String, serde_json::Value>,
+    /// The map the tile belongs to.
+    pub map: Entity,
+    /// Index of the tile's layer in the map.
+    pub layer: usize,
+    /// X position of the tile, in Sprite Fusion coordinates.
+    pub x: i32,
+    /// Y position of the tile, in Sprite Fusion coordinates.
+    pub y: i32,
+}
+
+/// System that spawns the tagged tiles of newly spawned maps as entities.
+fn spawn_tile_entities(
+    mut commands: Commands,
+    maps: Query<
+        (
+            Entity,
+            &SpriteFusionMapMarker,
+            &SpriteFusionMapGeometry,
+            &Children,
+        ),
+        Added<SpriteFusionMapMarker>,
+    >,
+    mut layers: Query<(&SpriteFusionLayerMarker, &mut TileStorage, &Transform)>,
+    mut tile_indices: Query<&mut SpriteFusionTileIndex>,
+    settings: Res<TileEntitySettings>,
+    localizer: Option<Res<AttributeLocalizer>>,
+) {
+    for (map, marker, geometry, children) in maps.iter() {
+        let mut spawned_layers: HashMap<usize, Entity> = HashMap::new();
+        for child in children.iter() {
+            if let Ok((layer, ..)) = layers.get(child) {
+                spawned_layers.insert(layer.index, child);
+            }
+        }
+
+        for (index, layer) in marker.map.layers.iter().enumerate() {
+            for tile in &layer.tiles {
+                let Some(attrs) = &tile.attributes else {
+                    continue;
+                };
+                let Some(kind) = attrs.get(&settings.attribute).and_then(|v| v.as_str()) else {
+                    continue;
+                };
+
+                // Layers skipped at spawn have no tilemap, and sit at the map's Z
+                let mut z = 0.0;
+                if let Some((layer_entity, (_, mut storage, transform))) = spawned_layers
+                    .get(&index)
+                    .and_then(|&layer| Some((layer, layers.get_mut(layer).ok()?)))
+                {
+                    z = transform.translation.z;
+                    let pos = geometry.tile_pos(tile.x, tile.y);
+                    if let Some(pos) = pos.filter(|_| !settings.keep_tiles) {
+                        if let Some(tile_entity) = storage.checked_get(&pos) {
+                            storage.remove(&pos);
+                            if let Ok(mut tile_index) = tile_indices.get_mut(map) {
+                                tile_index.set_tile(layer_entity, pos, None);
+                            }
+                            commands.entity(tile_entity).despawn();
+                        }
+                    }
+                }
+
+                let position = map_local_position(&marker.map, tile.x, tile.y);
+                commands.spawn((
+                    SpawnedFromTile {
+                        kind: kind.to_string(),
+                        attributes: clone_attributes(attrs, localizer.as_deref()),
+                        map,
+                        layer: index,
+                        x: tile.x,
+                        y: tile.y,
+                    },
+                    Transform::from_translation(position.extend(z)),
+                    Visibility::default(),
+                    ChildOf(map),
+                ));
+            }
+        }
+    }
+}
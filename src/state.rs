@@ -0,0 +1,190 @@
+//! Savegames of the runtime state of spawned maps.
+//!
+//! [`serialize_runtime_state`] captures what changed on a map since it was
+//! spawned: tile textures, visibility, removed tiles and attribute changes (which
+//! is where switches, doors and other gameplay state authored as attributes
+//! live). The result is a serde structure, to save as RON, JSON or any other
+//! format, and [`apply_runtime_state`] puts it back on a freshly spawned map.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    attributes::{clone_attributes, AttributeLocalizer},
+    convert::{layer_tiles, resolve_duplicates},
+    plugin::{SpriteFusionSettings, TextureIndexOffsets},
+    tile_index::index_tile,
+    types::{SpriteFusionLayerMarker, SpriteFusionMapMarker, TileAttributes},
+};
+
+/// Changes made to a spawned map, see [`serialize_runtime_state`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MapRuntimeState {
+    /// Changed tiles.
+    pub tiles: Vec<TileState>,
+}
+
+impl MapRuntimeState {
+    /// Check if the map is as it was spawned.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+}
+
+/// The state of a changed tile. Fields left to `None` didn't change.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TileState {
+    /// Index of the layer the tile belongs to.
+    pub layer: usize,
+    /// X position of the tile.
+    pub x: u32,
+    /// Y position of the tile.
+    pub y: u32,
+    /// Whether the tile was removed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub removed: bool,
+    /// Current texture index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texture: Option<u32>,
+    /// Current visibility.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visible: Option<bool>,
+    /// Current attributes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl TileState {
+    fn is_changed(&self) -> bool {
+        self.removed
+            || self.texture.is_some()
+            || self.visible.is_some()
+            || self.attributes.is_some()
+    }
+}
+
+/// Capture the changes made to a spawned map since it was spawned.
+///
+/// Only tiles of the map data are compared, tiles added at runtime aren't saved.
+/// Attributes kept in an [`AttributeStore`](crate::attributes::AttributeStore)
+/// can't change, so only [`TileAttributes`] components are compared. Returns
+/// `None` if `map` isn't a spawned map.
+pub fn serialize_runtime_state(map: Entity, world: &World) -> Option<MapRuntimeState> {
+    let marker = world.get::<SpriteFusionMapMarker>(map)?;
+    let settings = world.get_resource::<SpriteFusionSettings>()?;
+    let offsets = world.get::<TextureIndexOffsets>(map);
+    let localizer = world.get_resource::<AttributeLocalizer>();
+    let mut state = MapRuntimeState::default();
+
+    for child in world.get::<Children>(map).into_iter().flatten() {
+        let (Some(layer), Some(storage)) = (
+            world.get::<SpriteFusionLayerMarker>(*child),
+            world.get::<TileStorage>(*child),
+        ) else {
+            continue;
+        };
+        let Some(data) = marker.map.layers.get(layer.index) else {
+            continue;
+        };
+        let texture_offset = offsets.map_or(0, |offsets| offsets.get(&data.name));
+        let mut tiles = layer_tiles(&marker.map, data, settings.tile_origin);
+        // Maps rejected for duplicates aren't spawned, so this can't fail here
+        let _ = resolve_duplicates(&mut tiles, settings.duplicate_tiles);
+
+        for spawned in tiles {
+            let mut tile_state = TileState {
+                layer: layer.index,
+                x: spawned.position.x,
+                y: spawned.position.y,
+                ..default()
+            };
+            let Some(tile) = storage.checked_get(&spawned.position) else {
+                tile_state.removed = true;
+                state.tiles.push(tile_state);
+                continue;
+            };
+
+            let texture = world.get::<TileTextureIndex>(tile).map(|t| t.0);
+            if texture.is_some_and(|t| t != spawned.texture_index.0 + texture_offset) {
+                tile_state.texture = texture;
+            }
+            if let Some(visible) = world.get::<TileVisible>(tile) {
+                tile_state.visible = (!visible.0).then_some(false);
+            }
+            if let Some(attrs) = world.get::<TileAttributes>(tile) {
+                let original = spawned
+                    .tile
+                    .attributes
+                    .as_ref()
+                    .map(|attrs| clone_attributes(attrs, localizer))
+                    .unwrap_or_default();
+                if attrs.0 != original {
+                    tile_state.attributes = Some(attrs.0.clone());
+                }
+            }
+
+            if tile_state.is_changed() {
+                state.tiles.push(tile_state);
+            }
+        }
+    }
+
+    state.tiles.sort_by_key(|tile| (tile.layer, tile.y, tile.x));
+    Some(state)
+}
+
+/// Apply a state captured with [`serialize_runtime_state`] to a spawned map.
+///
+/// Tiles missing from the map are skipped with a warning.
+pub fn apply_runtime_state(world: &mut World, map: Entity, state: &MapRuntimeState) {
+    let layers: HashMap<usize, Entity> = world
+        .get::<Children>(map)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| Some((world.get::<SpriteFusionLayerMarker>(*child)?.index, *child)))
+        .collect();
+
+    for tile_state in &state.tiles {
+        let pos = TilePos {
+            x: tile_state.x,
+            y: tile_state.y,
+        };
+        let tile = layers.get(&tile_state.layer).and_then(|&layer| {
+            world
+                .get::<TileStorage>(layer)?
+                .checked_get(&pos)
+                .map(|tile| (layer, tile))
+        });
+        let Some((layer, tile)) = tile else {
+            if !tile_state.removed {
+                warn!(
+                    "Runtime state references missing tile ({}, {}) on layer {}",
+                    tile_state.x, tile_state.y, tile_state.layer
+                );
+            }
+            continue;
+        };
+
+        if tile_state.removed {
+            if let Some(mut storage) = world.get_mut::<TileStorage>(layer) {
+                storage.remove(&pos);
+            }
+            index_tile(world, layer, pos, None);
+            world.despawn(tile);
+            continue;
+        }
+        let mut tile = world.entity_mut(tile);
+        if let Some(texture) = tile_state.texture {
+            tile.insert(TileTextureIndex(texture));
+        }
+        if let Some(visible) = tile_state.visible {
+            tile.insert(TileVisible(visible));
+        }
+        if let Some(attrs) = &tile_state.attributes {
+            tile.insert(TileAttributes(attrs.clone()));
+        }
+    }
+}
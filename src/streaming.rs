@@ -0,0 +1,371 @@
+//! Chunk streaming for very large maps.
+//!
+//! Open-world maps can have millions of tiles, too many to keep as entities.
+//! Insert [`MapStreaming`] on a map entity before it spawns to only spawn tile
+//! entities in the chunks of each layer near a camera. Chunks farther away are
+//! despawned again; their tiles stay in the map data, with the map in
+//! [`SpriteFusionMapMarker`], and spawn anew when a camera comes back.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    attributes::{AttrMarkerRegistry, AttributeComponentRegistry, PendingTileAttributes},
+    convert::TileOrigin,
+    editing::remap_tile_index,
+    geometry::SpriteFusionMapGeometry,
+    hooks::SpriteFusionHookRegistry,
+    plugin::SpriteFusionSystems,
+    pool::{release_tiles, reserve_tile, TileEntityPool},
+    prepare::{
+        spawn_reserved_tiles, NextTileSpawnIndex, PreparedAttributes, PreparedTile, ReservedTile,
+    },
+    tile_index::SpriteFusionTileIndex,
+    types::{MapPaused, SpriteFusionMapMarker},
+};
+
+pub(crate) struct SpriteFusionStreamingPlugin;
+
+impl Plugin for SpriteFusionStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, stream_chunks.in_set(SpriteFusionSystems::Spawn));
+    }
+}
+
+/// Component streaming the tiles of a map in chunks around cameras.
+///
+/// Every layer is split into chunks of [`chunk_size`](Self::chunk_size) tiles.
+/// Chunks within [`radius`](Self::radius) of a [`Camera`] spawn their tile
+/// entities; chunks more than a chunk beyond the radius despawn them, so a
+/// camera moving back and forth on a chunk edge doesn't respawn it every frame.
+///
+/// Streamed tiles are rebuilt from the map data each time their chunk spawns:
+/// runtime changes to tiles of a despawned chunk are lost, and tile spawn hooks
+/// run again. Systems working on whole layers, like collider merging, only see
+/// the loaded chunks.
+///
+/// ```rust,ignore
+/// commands.spawn((
+///     SpriteFusionBundle {
+///         map: asset_server.load("world.sf.json"),
+///         tileset: asset_server.load("spritesheet.png"),
+///         ..default()
+///     },
+///     MapStreaming::new(1500.0).with_chunk_size(UVec2::splat(64)),
+/// ));
+/// ```
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct MapStreaming {
+    /// Size of the streamed chunks, in tiles. Changes only apply to maps
+    /// spawned afterwards.
+    pub chunk_size: UVec2,
+    /// Distance from cameras within which chunks are spawned, in world units
+    /// along each axis.
+    pub radius: f32,
+}
+
+impl Default for MapStreaming {
+    fn default() -> Self {
+        Self {
+            chunk_size: UVec2::splat(32),
+            radius: 1024.0,
+        }
+    }
+}
+
+impl MapStreaming {
+    /// Stream chunks within `radius` world units of cameras.
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            ..default()
+        }
+    }
+
+    /// Set the size of the streamed chunks, in tiles.
+    pub fn with_chunk_size(mut self, chunk_size: UVec2) -> Self {
+        self.chunk_size = chunk_size.max(UVec2::ONE);
+        self
+    }
+}
+
+/// The tiles of a streamed layer, by chunk.
+pub(crate) struct StreamedLayer {
+    pub(crate) index: usize,
+    pub(crate) tilemap: Entity,
+    /// Tiles of each chunk, with their spawn index.
+    pub(crate) chunks: HashMap<UVec2, Vec<(u32, PreparedTile)>>,
+}
+
+/// Component holding the tiles of a streamed map, and the chunks spawned.
+#[derive(Component)]
+pub(crate) struct StreamedChunks {
+    chunk_size: UVec2,
+    layers: Vec<StreamedLayer>,
+    loaded: HashSet<UVec2>,
+}
+
+impl StreamedChunks {
+    pub(crate) fn new(chunk_size: UVec2, layers: Vec<StreamedLayer>) -> Self {
+        Self {
+            chunk_size,
+            layers,
+            loaded: HashSet::new(),
+        }
+    }
+
+    /// Check if the chunk holding a tile position is spawned.
+    pub(crate) fn is_loaded(&self, tile_pos: TilePos) -> bool {
+        self.loaded.contains(&self.chunk_of(tile_pos))
+    }
+
+    /// Place a tile in the chunk data of a layer, replacing the texture of the
+    /// tiles already at its position.
+    ///
+    /// Returns the spawn index of the tile at the position, taken from
+    /// `next_spawn_index` for new tiles, or `None` if the layer isn't streamed.
+    pub(crate) fn set_tile(
+        &mut self,
+        layer: usize,
+        tile: PreparedTile,
+        next_spawn_index: &mut NextTileSpawnIndex,
+    ) -> Option<u32> {
+        let chunk = self.chunk_of(tile.position());
+        let layer = self.layers.iter_mut().find(|l| l.index == layer)?;
+        let tiles = layer.chunks.entry(chunk).or_default();
+        let mut found = None;
+        for (spawn_index, existing) in tiles.iter_mut() {
+            if existing.position() == tile.position() {
+                existing.texture_index = tile.texture_index;
+                found.get_or_insert(*spawn_index);
+            }
+        }
+        Some(found.unwrap_or_else(|| {
+            let spawn_index = next_spawn_index.take();
+            tiles.push((spawn_index, tile));
+            spawn_index
+        }))
+    }
+
+    /// Replace the attributes of the tiles at a position in the chunk data of a
+    /// layer.
+    pub(crate) fn set_attributes(
+        &mut self,
+        layer: usize,
+        tile_pos: TilePos,
+        attrs: &HashMap<String, serde_json::Value>,
+    ) {
+        let chunk = self.chunk_of(tile_pos);
+        let Some(tiles) = self
+            .layers
+            .iter_mut()
+            .find(|l| l.index == layer)
+            .and_then(|layer| layer.chunks.get_mut(&chunk))
+        else {
+            return;
+        };
+        for (_, tile) in tiles.iter_mut() {
+            if tile.position() == tile_pos {
+                tile.attributes = if attrs.is_empty() {
+                    PreparedAttributes::None
+                } else {
+                    PreparedAttributes::Components(attrs.clone())
+                };
+            }
+        }
+    }
+
+    /// Drop the tiles at a position from the chunk data of a layer, after the
+    /// map data tiles at the sorted indices `removed` were removed.
+    pub(crate) fn remove_tiles(&mut self, layer: usize, tile_pos: TilePos, removed: &[usize]) {
+        let chunk = self.chunk_of(tile_pos);
+        let Some(layer) = self.layers.iter_mut().find(|l| l.index == layer) else {
+            return;
+        };
+        if let Some(tiles) = layer.chunks.get_mut(&chunk) {
+            tiles.retain(|(_, tile)| tile.position() != tile_pos);
+        }
+        // The map data tiles after the removed ones moved down
+        for tiles in layer.chunks.values_mut() {
+            tiles.retain_mut(|(_, tile)| match remap_tile_index(removed, tile.index) {
+                Some(index) => {
+                    tile.index = index;
+                    true
+                }
+                None => false,
+            });
+        }
+    }
+
+    fn chunk_of(&self, tile_pos: TilePos) -> UVec2 {
+        UVec2::new(tile_pos.x, tile_pos.y) / self.chunk_size
+    }
+
+    /// Get the inclusive range of chunks within `radius` world units of
+    /// `world_pos`, if any.
+    fn chunks_near(
+        &self,
+        geometry: &SpriteFusionMapGeometry,
+        world_pos: Vec2,
+        radius: f32,
+    ) -> Option<(UVec2, UVec2)> {
+        let (min, max) = [
+            Vec2::new(-radius, -radius),
+            Vec2::new(radius, -radius),
+            Vec2::new(radius, radius),
+            Vec2::new(-radius, radius),
+        ]
+        .into_iter()
+        .map(|corner| grid_position(geometry, geometry.world_to_local(world_pos + corner)))
+        .fold((Vec2::INFINITY, Vec2::NEG_INFINITY), |(min, max), pos| {
+            (min.min(pos), max.max(pos))
+        });
+        let bounds = Vec2::new(geometry.size.x as f32, geometry.size.y as f32);
+        if max.x < 0.0 || max.y < 0.0 || min.x >= bounds.x || min.y >= bounds.y {
+            return None;
+        }
+        let min = min.floor().max(Vec2::ZERO).as_uvec2();
+        let max = max.floor().min(bounds - 1.0).as_uvec2();
+        Some((min / self.chunk_size, max / self.chunk_size))
+    }
+
+    /// Get the tile positions of a chunk within the map.
+    fn chunk_tiles(&self, chunk: UVec2, size: TilemapSize) -> impl Iterator<Item = TilePos> {
+        let min = chunk * self.chunk_size;
+        let max = (min + self.chunk_size).min(UVec2::new(size.x, size.y));
+        (min.y..max.y).flat_map(move |y| (min.x..max.x).map(move |x| TilePos { x, y }))
+    }
+}
+
+/// Get the continuous tile grid position of a point in the map's local space,
+/// tile `(x, y)` covering `[x, x + 1) × [y, y + 1)`.
+fn grid_position(geometry: &SpriteFusionMapGeometry, local: Vec2) -> Vec2 {
+    let x = local.x / geometry.tile_size + 0.5 + geometry.tile_offset.x as f32;
+    let row = local.y / geometry.tile_size + 0.5;
+    let y = match geometry.tile_origin {
+        TileOrigin::BottomLeft => row,
+        TileOrigin::TopLeft => geometry.size.y as f32 - row,
+    };
+    Vec2::new(x, y)
+}
+
+/// System that spawns the chunks of streamed maps near cameras, and despawns the
+/// chunks far from every camera.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn stream_chunks(
+    mut commands: Commands,
+    mut maps: Query<
+        (
+            Entity,
+            &mut StreamedChunks,
+            &MapStreaming,
+            &SpriteFusionMapGeometry,
+            &SpriteFusionMapMarker,
+            Option<&mut SpriteFusionTileIndex>,
+        ),
+        Without<MapPaused>,
+    >,
+    mut storages: Query<&mut TileStorage>,
+    cameras: Query<&GlobalTransform, With<Camera>>,
+    mut pool: ResMut<TileEntityPool>,
+    mut pending_attributes: ResMut<PendingTileAttributes>,
+    attr_markers: Res<AttrMarkerRegistry>,
+    attr_components: Res<AttributeComponentRegistry>,
+    hooks: Res<SpriteFusionHookRegistry>,
+) {
+    for (map, mut streamed, streaming, geometry, marker, mut index) in maps.iter_mut() {
+        let streamed = &mut *streamed;
+        let margin = streamed.chunk_size.max_element() as f32 * geometry.tile_size;
+        let mut wanted = Vec::new();
+        let mut kept = HashSet::new();
+        for camera in cameras.iter() {
+            let position = camera.translation().truncate();
+            if let Some((min, max)) = streamed.chunks_near(geometry, position, streaming.radius) {
+                for y in min.y..=max.y {
+                    wanted.extend((min.x..=max.x).map(|x| UVec2::new(x, y)));
+                }
+            }
+            let radius = streaming.radius + margin;
+            if let Some((min, max)) = streamed.chunks_near(geometry, position, radius) {
+                for y in min.y..=max.y {
+                    kept.extend((min.x..=max.x).map(|x| UVec2::new(x, y)));
+                }
+            }
+        }
+
+        let mut unloaded: Vec<UVec2> = streamed
+            .loaded
+            .iter()
+            .filter(|chunk| !kept.contains(chunk))
+            .copied()
+            .collect();
+        unloaded.sort_by_key(|chunk| (chunk.y, chunk.x));
+        let mut released = Vec::new();
+        for chunk in unloaded {
+            streamed.loaded.remove(&chunk);
+            for layer in &streamed.layers {
+                let Ok(mut storage) = storages.get_mut(layer.tilemap) else {
+                    continue;
+                };
+                for tile_pos in streamed.chunk_tiles(chunk, geometry.size) {
+                    let Some(tile) = storage.checked_remove(&tile_pos) else {
+                        continue;
+                    };
+                    if let Some(index) = index.as_mut() {
+                        index.set_tile(layer.tilemap, tile_pos, None);
+                    }
+                    released.push(tile);
+                }
+            }
+        }
+        if !released.is_empty() {
+            commands.queue(move |world: &mut World| release_tiles(world, released));
+        }
+
+        let mut pending = Vec::new();
+        for chunk in wanted {
+            if !streamed.loaded.insert(chunk) {
+                continue;
+            }
+            for layer in &streamed.layers {
+                let Some(chunk_tiles) = layer.chunks.get(&chunk) else {
+                    continue;
+                };
+                let Ok(mut storage) = storages.get_mut(layer.tilemap) else {
+                    continue;
+                };
+                let mut tiles: Vec<ReservedTile> = chunk_tiles
+                    .iter()
+                    .map(|(spawn_index, tile)| {
+                        let entity = reserve_tile(&mut commands, &mut pool);
+                        storage.set(&tile.position(), entity);
+                        if let Some(index) = index.as_mut() {
+                            index.set_tile(layer.tilemap, tile.position(), Some(entity));
+                        }
+                        ReservedTile {
+                            tile: tile.clone(),
+                            entity,
+                            spawn_index: *spawn_index,
+                        }
+                    })
+                    .collect();
+                spawn_reserved_tiles(
+                    &mut commands,
+                    &attr_markers,
+                    &attr_components,
+                    &hooks,
+                    &marker.map.layers[layer.index],
+                    layer.index,
+                    layer.tilemap,
+                    &mut tiles,
+                    &mut pending,
+                );
+            }
+        }
+        for (tile, layer, index) in pending {
+            pending_attributes.push(tile, map, layer, index);
+        }
+    }
+}
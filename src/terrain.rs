@@ -0,0 +1,157 @@
+//! Terrain tags and biome regions.
+//!
+//! Tiles tagged with a `"terrain"` attribute, usually through
+//! [`TilesetDefaults`](crate::core::TilesetDefaults) so every water tile is
+//! `"terrain": "water"`, can be queried with [`terrain_at`]. Connected areas of
+//! the same terrain form [`Biome`]s, which spawned maps get in a [`MapBiomes`]
+//! component, e.g. to populate each biome with fitting enemies or resources.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::{
+    attributes::reserved_attribute,
+    plugin::SpriteFusionSettings,
+    types::{SpriteFusionMap, SpriteFusionMapMarker},
+};
+
+/// Attribute giving the terrain of a tile.
+pub const TERRAIN_ATTRIBUTE: &str = "terrain";
+
+/// Plugin that adds the [`MapBiomes`] of spawned maps.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionTerrainPlugin;
+
+impl Plugin for SpriteFusionTerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, insert_biomes);
+    }
+}
+
+/// A connected area of tiles sharing a terrain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Biome {
+    /// The terrain of the area.
+    pub terrain: String,
+    /// Positions of the tiles, in Sprite Fusion coordinates.
+    pub tiles: HashSet<(i32, i32)>,
+}
+
+impl Biome {
+    /// Check if a position, in Sprite Fusion coordinates, lies in the biome.
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.tiles.contains(&(x, y))
+    }
+}
+
+/// Biomes of a spawned map, see [`extract_biomes`].
+#[derive(Component, Debug, Clone, Default)]
+pub struct MapBiomes(pub Vec<Biome>);
+
+impl MapBiomes {
+    /// Iterate over the biomes of a terrain.
+    pub fn of_terrain<'a>(&'a self, terrain: &'a str) -> impl Iterator<Item = &'a Biome> {
+        self.0.iter().filter(move |biome| biome.terrain == terrain)
+    }
+
+    /// Get the biome containing a position, in Sprite Fusion coordinates.
+    pub fn at(&self, x: i32, y: i32) -> Option<&Biome> {
+        self.0.iter().find(|biome| biome.contains(x, y))
+    }
+}
+
+/// Get the terrain at a position, in Sprite Fusion coordinates.
+///
+/// Layers are ordered top to bottom, so the topmost tile with a terrain wins: a
+/// bridge tagged `"wood"` over a `"water"` tile is wood. Attributes are read
+/// with the given
+/// [`SpriteFusionSettings::attribute_prefix`](crate::plugin::SpriteFusionSettings::attribute_prefix).
+pub fn terrain_at<'a>(map: &'a SpriteFusionMap, x: i32, y: i32, prefix: &str) -> Option<&'a str> {
+    let key = reserved_attribute(prefix, TERRAIN_ATTRIBUTE);
+    map.layers
+        .iter()
+        .flat_map(|layer| layer.tiles.iter())
+        .filter(|tile| tile.x == x && tile.y == y)
+        .find_map(|tile| tile_terrain(tile.attributes.as_ref()?, &key))
+}
+
+/// Get the terrain at a position in the local space of a spawned map entity,
+/// see [`terrain_at`].
+pub fn terrain_at_local<'a>(
+    map: &'a SpriteFusionMap,
+    local: Vec2,
+    prefix: &str,
+) -> Option<&'a str> {
+    let tile_size = map.tile_size as f32;
+    let x = (local.x / tile_size).round() as i32;
+    let y = map.map_height as i32 - 1 - (local.y / tile_size).round() as i32;
+    terrain_at(map, x, y, prefix)
+}
+
+fn tile_terrain<'a>(attrs: &'a HashMap<String, serde_json::Value>, key: &str) -> Option<&'a str> {
+    attrs.get(key).and_then(|v| v.as_str())
+}
+
+/// Group connected tiles sharing a terrain into biomes.
+///
+/// Each position takes the terrain of its topmost tagged tile, as in
+/// [`terrain_at`], and tiles touch through their edges. Two separate lakes are
+/// two biomes.
+pub fn extract_biomes(map: &SpriteFusionMap, prefix: &str) -> Vec<Biome> {
+    let key = reserved_attribute(prefix, TERRAIN_ATTRIBUTE);
+    let mut terrains: HashMap<(i32, i32), &str> = HashMap::new();
+    // Walk layers bottom to top, so upper layers overwrite lower ones
+    for tile in map.layers.iter().rev().flat_map(|layer| layer.tiles.iter()) {
+        if let Some(terrain) = tile
+            .attributes
+            .as_ref()
+            .and_then(|attrs| tile_terrain(attrs, &key))
+        {
+            terrains.insert((tile.x, tile.y), terrain);
+        }
+    }
+
+    // Sorted seeds keep biome order stable between runs
+    let mut seeds: Vec<_> = terrains.keys().copied().collect();
+    seeds.sort_by_key(|&(x, y)| (y, x));
+
+    let mut visited = HashSet::new();
+    let mut biomes = Vec::new();
+    for seed in seeds {
+        if !visited.insert(seed) {
+            continue;
+        }
+        let terrain = terrains[&seed];
+        let mut tiles = HashSet::from([seed]);
+        let mut stack = vec![seed];
+        while let Some((x, y)) = stack.pop() {
+            for neighbor in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if terrains.get(&neighbor) == Some(&terrain) && visited.insert(neighbor) {
+                    tiles.insert(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+        }
+        biomes.push(Biome {
+            terrain: terrain.to_string(),
+            tiles,
+        });
+    }
+    biomes
+}
+
+/// System that adds the biomes of newly spawned maps.
+fn insert_biomes(
+    mut commands: Commands,
+    maps: Query<(Entity, &SpriteFusionMapMarker), Added<SpriteFusionMapMarker>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (entity, marker) in maps.iter() {
+        let biomes = extract_biomes(&marker.map, &settings.attribute_prefix);
+        if !biomes.is_empty() {
+            commands.entity(entity).insert(MapBiomes(biomes));
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Lookup of tile entities by layer name and position.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    plugin::SpriteFusionSystems,
+    types::{SpriteFusionLayerMarker, SpriteFusionMapMarker},
+};
+
+pub(crate) struct SpriteFusionTileIndexPlugin;
+
+impl Plugin for SpriteFusionTileIndexPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            update_tile_indices.after(SpriteFusionSystems::Spawn),
+        );
+    }
+}
+
+/// Component of spawned maps finding the tile entity at a position of a layer,
+/// by layer name, in constant time.
+///
+/// Inserted on map entities once they spawn. Tiles placed or removed by the
+/// crate afterwards update their cell: edits of
+/// [`SpriteFusionMapCommands`](crate::editing::SpriteFusionMapCommands),
+/// streamed chunks, replays, spawners and restored runtime state are found too,
+/// but tiles set in a [`TileStorage`] by hand aren't. If a map has several
+/// layers with the same name, the top one, listed first in the map data, is
+/// indexed.
+///
+/// ```rust,ignore
+/// fn ground_under_player(
+///     maps: Query<(&SpriteFusionTileIndex, &SpriteFusionMapGeometry)>,
+///     player: Single<&Transform, With<Player>>,
+/// ) {
+///     for (index, geometry) in maps.iter() {
+///         let Some(pos) = geometry.world_to_tile(player.translation.truncate()) else {
+///             continue;
+///         };
+///         if let Some(tile) = index.get("Ground", pos) {
+///             info!("Standing on {tile}");
+///         }
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Clone, Default)]
+pub struct SpriteFusionTileIndex {
+    size: TilemapSize,
+    layers: HashMap<String, IndexedLayer>,
+}
+
+#[derive(Debug, Clone)]
+struct IndexedLayer {
+    entity: Entity,
+    index: usize,
+    tiles: Vec<Option<Entity>>,
+}
+
+impl SpriteFusionTileIndex {
+    /// Get the tile entity at a position of a layer, if any.
+    pub fn get(&self, layer: &str, tile_pos: TilePos) -> Option<Entity> {
+        if !tile_pos.within_map_bounds(&self.size) {
+            return None;
+        }
+        let layer = self.layers.get(layer)?;
+        let index = (tile_pos.y * self.size.x + tile_pos.x) as usize;
+        layer.tiles.get(index).copied().flatten()
+    }
+
+    /// Get the tilemap entity of a layer.
+    pub fn layer(&self, name: &str) -> Option<Entity> {
+        self.layers.get(name).map(|layer| layer.entity)
+    }
+
+    /// Iterate over the tiles at a position, with the name of their layer.
+    pub fn tiles_at(&self, tile_pos: TilePos) -> impl Iterator<Item = (&str, Entity)> {
+        self.layers
+            .keys()
+            .filter_map(move |name| Some((name.as_str(), self.get(name, tile_pos)?)))
+    }
+
+    /// Update the tile at a position of a layer, if the layer is indexed.
+    pub(crate) fn set_tile(&mut self, layer: Entity, tile_pos: TilePos, tile: Option<Entity>) {
+        if !tile_pos.within_map_bounds(&self.size) {
+            return;
+        }
+        let index = (tile_pos.y * self.size.x + tile_pos.x) as usize;
+        if let Some(slot) = self
+            .layers
+            .values_mut()
+            .find(|indexed| indexed.entity == layer)
+            .and_then(|indexed| indexed.tiles.get_mut(index))
+        {
+            *slot = tile;
+        }
+    }
+
+    /// Index the tiles of a layer, unless a layer above it has its name.
+    fn index_layer(
+        &mut self,
+        entity: Entity,
+        layer: &SpriteFusionLayerMarker,
+        storage: &TileStorage,
+    ) {
+        if self
+            .layers
+            .get(&layer.name)
+            .is_some_and(|indexed| indexed.entity != entity && indexed.index < layer.index)
+        {
+            return;
+        }
+        self.size = storage.size;
+        self.layers.insert(
+            layer.name.clone(),
+            IndexedLayer {
+                entity,
+                index: layer.index,
+                tiles: storage.iter().copied().collect(),
+            },
+        );
+    }
+}
+
+/// Update the [`SpriteFusionTileIndex`] of the map of a layer, after the tile at
+/// a position of the layer's [`TileStorage`] was set or removed.
+pub(crate) fn index_tile(
+    world: &mut World,
+    layer: Entity,
+    tile_pos: TilePos,
+    tile: Option<Entity>,
+) {
+    let Some(map) = world
+        .get::<SpriteFusionLayerMarker>(layer)
+        .map(|layer| layer.map)
+    else {
+        return;
+    };
+    if let Some(mut index) = world.get_mut::<SpriteFusionTileIndex>(map) {
+        index.set_tile(layer, tile_pos, tile);
+    }
+}
+
+/// System that indexes the tiles of newly spawned maps.
+pub(crate) fn update_tile_indices(
+    mut commands: Commands,
+    maps: Query<(
+        Entity,
+        Ref<SpriteFusionMapMarker>,
+        Has<SpriteFusionTileIndex>,
+    )>,
+    layers: Query<(Entity, &SpriteFusionLayerMarker, &TileStorage)>,
+) {
+    for (map, marker, indexed) in maps.iter() {
+        if indexed && !marker.is_added() {
+            continue;
+        }
+        let mut index = SpriteFusionTileIndex::default();
+        let mut map_layers: Vec<_> = layers
+            .iter()
+            .filter(|(_, layer, _)| layer.map == map)
+            .collect();
+        map_layers.sort_by_key(|(_, layer, _)| layer.index);
+        for (entity, layer, storage) in map_layers {
+            index.index_layer(entity, layer, storage);
+        }
+        commands.entity(map).insert(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: TilemapSize = TilemapSize { x: 2, y: 2 };
+
+    fn layer(map: Entity, name: &str, index: usize) -> SpriteFusionLayerMarker {
+        SpriteFusionLayerMarker {
+            map,
+            name: name.to_string(),
+            index,
+            collider: false,
+        }
+    }
+
+    fn storage(tiles: &[(TilePos, Entity)]) -> TileStorage {
+        let mut storage = TileStorage::empty(SIZE);
+        for (tile_pos, tile) in tiles {
+            storage.set(tile_pos, *tile);
+        }
+        storage
+    }
+
+    #[test]
+    fn top_layer_wins_name_clashes() {
+        let mut world = World::new();
+        let [map, top, bottom, ground, a, b, c, d, e] =
+            std::array::from_fn(|_| world.spawn_empty().id());
+        let (origin, corner) = (TilePos::new(0, 0), TilePos::new(1, 1));
+        let layers = [
+            (top, layer(map, "Walls", 0), storage(&[(origin, a)])),
+            (
+                bottom,
+                layer(map, "Walls", 1),
+                storage(&[(origin, b), (corner, c)]),
+            ),
+            (ground, layer(map, "Ground", 2), storage(&[(origin, d)])),
+        ];
+
+        // Whatever the order layers are indexed in
+        for order in [[0, 1, 2], [2, 1, 0]] {
+            let mut index = SpriteFusionTileIndex::default();
+            for i in order {
+                let (entity, layer, storage) = &layers[i];
+                index.index_layer(*entity, layer, storage);
+            }
+            assert_eq!(index.layer("Walls"), Some(top));
+            assert_eq!(index.get("Walls", origin), Some(a));
+            assert_eq!(index.get("Walls", corner), None);
+            let mut at_origin: Vec<_> = index.tiles_at(origin).collect();
+            at_origin.sort();
+            assert_eq!(at_origin, vec![("Ground", d), ("Walls", a)]);
+
+            // Only cells of the indexed layer are updated
+            index.set_tile(bottom, corner, Some(e));
+            assert_eq!(index.get("Walls", corner), None);
+            index.set_tile(top, corner, Some(e));
+            assert_eq!(index.get("Walls", corner), Some(e));
+            index.set_tile(top, origin, None);
+            assert_eq!(index.get("Walls", origin), None);
+            assert_eq!(index.get("Ground", origin), Some(d));
+            // Positions out of the map are ignored
+            index.set_tile(top, TilePos::new(2, 0), Some(e));
+        }
+    }
+}
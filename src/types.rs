@@ -1,81 +1,53 @@
-//! Sprite Fusion map data types.
+//! Sprite Fusion map data types and components.
 //!
-//! These types match the JSON export format from Sprite Fusion.
+//! The map data types match the JSON export format from Sprite Fusion and live in
+//! [`core`](crate::core); they are re-exported here.
 
-use bevy::prelude::*;
-use serde::{Deserialize, Serialize};
+use bevy::{
+    prelude::*,
+    reflect::{ReflectDeserialize, ReflectSerialize},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::HashMap;
 
-/// A complete SpriteFusion map export.
-///
-/// This is the root type that gets deserialized from the SpriteFusion JSON export.
-#[derive(Debug, Clone, Serialize, Deserialize, Asset, TypePath)]
-#[serde(rename_all = "camelCase")]
-pub struct SpriteFusionMap {
-    /// Size of each tile in pixels.
-    pub tile_size: u32,
-    /// Width of the map in tiles.
-    pub map_width: u32,
-    /// Height of the map in tiles.
-    pub map_height: u32,
-    /// All layers in the map, ordered from top to bottom (first layer is on top, last is background).
-    pub layers: Vec<SpriteFusionLayer>,
-}
-
-/// A single layer in a SpriteFusion map.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SpriteFusionLayer {
-    /// Name of the layer.
-    pub name: String,
-    /// Whether this layer should have collision enabled.
-    #[serde(default)]
-    pub collider: bool,
-    /// All tiles in this layer.
-    pub tiles: Vec<SpriteFusionTile>,
-}
-
-/// A single tile in a SpriteFusion layer.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SpriteFusionTile {
-    /// Tile ID referencing the index in the spritesheet.
-    /// This is a string in SpriteFusion's format (e.g., "0", "1").
-    pub id: String,
-    /// X position in tile coordinates.
-    pub x: i32,
-    /// Y position in tile coordinates.
-    pub y: i32,
-    /// Optional custom attributes attached to this tile.
-    #[serde(default)]
-    pub attributes: Option<HashMap<String, serde_json::Value>>,
-}
-
-impl SpriteFusionTile {
-    /// Get the tile ID as a u32.
-    pub fn tile_id(&self) -> u32 {
-        self.id.parse().unwrap_or(0)
-    }
-}
+pub use crate::core::{
+    SpriteFusionAttributes, SpriteFusionLayer, SpriteFusionMap, SpriteFusionTile,
+};
 
 /// Component attached to spawned tilemap entities.
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component, Debug, Clone)]
 pub struct SpriteFusionMapMarker {
     /// The original map data.
     pub map: SpriteFusionMap,
+    /// Offset added to Sprite Fusion coordinates to get tilemap coordinates.
+    ///
+    /// Non-zero when the map has tiles at negative coordinates. Layers are moved back
+    /// by this offset, so world positions match the Sprite Fusion coordinates.
+    pub tile_offset: UVec2,
 }
 
 /// Component attached to layer entities.
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component, Debug, Clone)]
 pub struct SpriteFusionLayerMarker {
+    /// The map entity this layer belongs to.
+    pub map: Entity,
     /// Name of the layer.
     pub name: String,
-    /// Layer index (0 = bottom).
+    /// Layer index (0 = top).
     pub index: usize,
     /// Whether this layer has collision.
     pub collider: bool,
 }
 
 /// Component attached to tiles that have custom attributes.
-#[derive(Component, Debug, Clone)]
+///
+/// Reflected as an opaque value, serialized like the attributes of the Sprite
+/// Fusion export.
+#[derive(Component, Debug, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(opaque, Component, Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct TileAttributes(pub HashMap<String, serde_json::Value>);
 
 impl TileAttributes {
@@ -103,8 +75,68 @@ impl TileAttributes {
     pub fn contains(&self, key: &str) -> bool {
         self.0.contains_key(key)
     }
+
+    /// Get a string attribute through a localization function.
+    ///
+    /// Falls back to the raw value when `localize` returns `None`.
+    ///
+    /// ```rust,ignore
+    /// let text = attrs.localized("text", |key| locale.translate(key));
+    /// ```
+    pub fn localized(
+        &self,
+        key: &str,
+        localize: impl FnOnce(&str) -> Option<String>,
+    ) -> Option<String> {
+        let raw = self.get_str(key)?;
+        Some(localize(raw).unwrap_or_else(|| raw.to_string()))
+    }
+
+    /// Deserialize all attributes into a struct, its fields named after the
+    /// attributes.
+    ///
+    /// Fails if an attribute has the wrong type, or a field without a serde
+    /// default has no attribute. Attributes without a field are ignored.
+    ///
+    /// ```rust,ignore
+    /// #[derive(Deserialize)]
+    /// struct Collectible {
+    ///     name: String,
+    ///     value: i64,
+    /// }
+    ///
+    /// let collectible: Collectible = attrs.deserialize_into()?;
+    /// ```
+    pub fn deserialize_into<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        let object = self
+            .0
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        T::deserialize(serde_json::Value::Object(object))
+    }
 }
 
+/// Stable index of a tile within its map, following the deterministic spawn order.
+///
+/// Layers are spawned by index, and tiles within a layer row by row
+/// (top to bottom, left to right in Sprite Fusion coordinates).
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, Reflect)]
+#[reflect(Component, Debug, Clone, PartialEq, Hash)]
+pub struct TileSpawnIndex(pub u32);
+
+/// Marker component that suspends this crate's per-map systems for one map.
+///
+/// Insert it on a map entity to freeze that map (e.g. an in-game pause or an
+/// inactive background level). Remove it to resume.
+#[derive(Component, Debug, Clone, Copy, Default, Reflect)]
+#[reflect(Component, Debug, Clone, Default)]
+pub struct MapPaused;
+
 /// Marker component for tiles that are on a collider layer.
-#[derive(Component, Debug, Clone, Copy, Default)]
+///
+/// Removed while the tile is hidden with `TileVisible`, and inserted again
+/// when it's shown.
+#[derive(Component, Debug, Clone, Copy, Default, Reflect)]
+#[reflect(Component, Debug, Clone, Default)]
 pub struct Collider;
@@ -0,0 +1,158 @@
+//! HD/SD variants of map tilesets.
+//!
+//! Games shipping both pixel-perfect and high-DPI art register a spritesheet per
+//! pixel density in [`TilesetVariants`], and pick one with the [`TilesetDensity`]
+//! resource. Maps switch to the closest variant at runtime, and their layers are
+//! scaled so a 2x spritesheet covers the same world area as the 1x one.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    plugin::SpriteFusionTilesetHandle,
+    reload::reload_tilesets,
+    types::{SpriteFusionLayerMarker, SpriteFusionMapMarker},
+};
+
+pub(crate) struct SpriteFusionVariantsPlugin;
+
+impl Plugin for SpriteFusionVariantsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TilesetDensity>()
+            .add_systems(Update, apply_tileset_variants.before(reload_tilesets));
+    }
+}
+
+/// Pixel density of the tileset variants used by maps, e.g. `2.0` for HD art.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct TilesetDensity(pub f32);
+
+impl Default for TilesetDensity {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// A spritesheet drawn at some pixel density.
+#[derive(Debug, Clone)]
+pub struct TilesetVariant {
+    /// Pixels per Sprite Fusion pixel: `1.0` for the exported spritesheet, `2.0`
+    /// for one with tiles twice as large.
+    pub density: f32,
+    /// The spritesheet.
+    pub image: Handle<Image>,
+}
+
+/// Spritesheet variants of a map, at several pixel densities.
+///
+/// The map uses the variant closest to [`TilesetDensity`], replacing its
+/// [`SpriteFusionTilesetHandle`].
+///
+/// ```rust,ignore
+/// commands.spawn((
+///     SpriteFusionBundle {
+///         map: asset_server.load("map.json"),
+///         ..default()
+///     },
+///     TilesetVariants::new()
+///         .with_variant(1.0, asset_server.load("spritesheet.png"))
+///         .with_variant(2.0, asset_server.load("spritesheet@2x.png")),
+/// ));
+///
+/// // Later, on a high-DPI display
+/// commands.insert_resource(TilesetDensity(2.0));
+/// ```
+#[derive(Component, Debug, Clone, Default)]
+pub struct TilesetVariants {
+    variants: Vec<TilesetVariant>,
+    active: Option<usize>,
+}
+
+impl TilesetVariants {
+    /// Create an empty set of variants.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a spritesheet drawn at `density`.
+    pub fn with_variant(mut self, density: f32, image: Handle<Image>) -> Self {
+        self.variants.push(TilesetVariant { density, image });
+        self
+    }
+
+    /// The registered variants.
+    pub fn variants(&self) -> &[TilesetVariant] {
+        &self.variants
+    }
+
+    /// The variant currently used by the map.
+    pub fn active(&self) -> Option<&TilesetVariant> {
+        self.variants.get(self.active?)
+    }
+
+    fn closest(&self, density: f32) -> Option<usize> {
+        (0..self.variants.len()).min_by(|&a, &b| {
+            let distance = |index: usize| (self.variants[index].density - density).abs();
+            distance(a).total_cmp(&distance(b))
+        })
+    }
+}
+
+/// System that switches maps to the variant closest to [`TilesetDensity`], and
+/// scales their layers to the variant's density.
+#[allow(clippy::type_complexity)]
+fn apply_tileset_variants(
+    density: Res<TilesetDensity>,
+    mut maps: Query<(
+        Entity,
+        &mut TilesetVariants,
+        &mut SpriteFusionTilesetHandle,
+        Option<&SpriteFusionMapMarker>,
+    )>,
+    new_layers: Query<&SpriteFusionLayerMarker, Added<SpriteFusionLayerMarker>>,
+    mut layers: Query<(
+        &SpriteFusionLayerMarker,
+        &mut TilemapTileSize,
+        &mut TilemapGridSize,
+        &mut Transform,
+    )>,
+) {
+    let mut rescaled = HashMap::new();
+    for (map, mut variants, mut tileset, marker) in maps.iter_mut() {
+        let Some(index) = variants.closest(density.0) else {
+            continue;
+        };
+        let switched = variants.active != Some(index);
+        if switched {
+            variants.active = Some(index);
+            let image = &variants.variants[index].image;
+            if tileset.0 != *image {
+                tileset.0 = image.clone();
+            }
+        }
+        let has_new_layers = new_layers.iter().any(|layer| layer.map == map);
+        if let Some(marker) = marker.filter(|_| switched || has_new_layers) {
+            rescaled.insert(
+                map,
+                (
+                    marker.map.tile_size as f32,
+                    variants.variants[index].density,
+                ),
+            );
+        }
+    }
+
+    for (layer, mut tile_size, mut grid_size, mut transform) in layers.iter_mut() {
+        let Some(&(base, density)) = rescaled.get(&layer.map) else {
+            continue;
+        };
+        // Tiles are `density` times larger in the image, shrink the layer back
+        let size = base * density;
+        *tile_size = TilemapTileSize { x: size, y: size };
+        *grid_size = TilemapGridSize { x: size, y: size };
+        transform.scale.x = transform.scale.x.signum() / density;
+        transform.scale.y = transform.scale.y.signum() / density;
+    }
+}
@@ -0,0 +1,362 @@
+//! Named zones painted in the editor with attribute tiles.
+//!
+//! Contiguous tiles sharing a `"zone"` attribute value form a zone. Spawned maps
+//! get one child entity with a [`MapZone`] per zone, and entities with a
+//! [`ZoneTracker`] get [`ZoneEntered`] and [`ZoneExited`] messages, which is
+//! enough to drive area names, music changes and quest triggers.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use crate::{
+    attributes::reserved_attribute,
+    convert::map_local_position,
+    plugin::SpriteFusionSettings,
+    types::{MapPaused, SpriteFusionMap, SpriteFusionMapMarker},
+};
+
+/// Attribute naming the zone a tile belongs to.
+pub const ZONE_ATTRIBUTE: &str = "zone";
+
+/// Plugin that spawns map zones and tracks entities entering and leaving them.
+///
+/// Part of [`SpriteFusionGameplayPlugins`](crate::plugin::SpriteFusionGameplayPlugins).
+pub struct SpriteFusionZonesPlugin;
+
+impl Plugin for SpriteFusionZonesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ZoneModifierRegistry>()
+            .add_message::<ZoneEntered>()
+            .add_message::<ZoneExited>()
+            .add_systems(
+                Update,
+                (spawn_map_zones, track_zones, apply_zone_modifiers).chain(),
+            );
+    }
+}
+
+/// A zone of a map, attached to a child entity of the map.
+#[derive(Component, Debug, Clone)]
+pub struct MapZone {
+    /// Value of the `"zone"` attribute.
+    pub name: String,
+    /// The map entity this zone belongs to.
+    pub map: Entity,
+    /// Tiles of the zone, in Sprite Fusion coordinates.
+    pub tiles: HashSet<(i32, i32)>,
+    /// Bounds of the zone in the local space of the map entity.
+    pub bounds: Rect,
+    tile_size: f32,
+    map_height: u32,
+}
+
+impl MapZone {
+    /// Check if a position in the local space of the map entity lies in the zone.
+    pub fn contains_local(&self, local: Vec2) -> bool {
+        if !self.bounds.contains(local) {
+            return false;
+        }
+        let x = (local.x / self.tile_size).round() as i32;
+        let y = self.map_height as i32 - 1 - (local.y / self.tile_size).round() as i32;
+        self.tiles.contains(&(x, y))
+    }
+}
+
+/// Group contiguous tiles sharing a `"zone"` attribute value.
+///
+/// Tiles are grouped across layers and touch through their edges. Returns the name
+/// and tiles of each zone; two separate areas with the same name are two zones.
+pub fn extract_zones(map: &SpriteFusionMap, prefix: &str) -> Vec<(String, HashSet<(i32, i32)>)> {
+    let key = reserved_attribute(prefix, ZONE_ATTRIBUTE);
+    let mut names: HashMap<(i32, i32), &str> = HashMap::new();
+    for tile in map.layers.iter().flat_map(|layer| layer.tiles.iter()) {
+        if let Some(name) = tile
+            .attributes
+            .as_ref()
+            .and_then(|attrs| attrs.get(&*key))
+            .and_then(|v| v.as_str())
+        {
+            names.insert((tile.x, tile.y), name);
+        }
+    }
+
+    // Sorted seeds keep zone order stable between runs
+    let mut seeds: Vec<_> = names.keys().copied().collect();
+    seeds.sort_by_key(|&(x, y)| (y, x));
+
+    let mut visited = HashSet::new();
+    let mut zones = Vec::new();
+    for seed in seeds {
+        if !visited.insert(seed) {
+            continue;
+        }
+        let name = names[&seed];
+        let mut tiles = HashSet::from([seed]);
+        let mut stack = vec![seed];
+        while let Some((x, y)) = stack.pop() {
+            for neighbor in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if names.get(&neighbor) == Some(&name) && visited.insert(neighbor) {
+                    tiles.insert(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+        }
+        zones.push((name.to_string(), tiles));
+    }
+    zones
+}
+
+/// Component tracking the zones an entity is in.
+///
+/// Insert it on entities that should get [`ZoneEntered`] and [`ZoneExited`] messages.
+#[derive(Component, Debug, Clone, Default)]
+pub struct ZoneTracker {
+    zones: Vec<Entity>,
+}
+
+impl ZoneTracker {
+    /// The zone entities the entity is currently in.
+    pub fn zones(&self) -> &[Entity] {
+        &self.zones
+    }
+}
+
+/// Sent when a tracked entity enters a zone.
+#[derive(Message, Debug, Clone)]
+pub struct ZoneEntered {
+    /// The tracked entity.
+    pub entity: Entity,
+    /// The zone entity.
+    pub zone: Entity,
+    /// Name of the zone.
+    pub name: String,
+}
+
+/// Sent when a tracked entity leaves a zone.
+#[derive(Message, Debug, Clone)]
+pub struct ZoneExited {
+    /// The tracked entity.
+    pub entity: Entity,
+    /// The zone entity.
+    pub zone: Entity,
+    /// Name of the zone.
+    pub name: String,
+}
+
+/// Extension methods on [`App`] registering gameplay modifiers per zone name.
+pub trait ZoneModifierAppExt {
+    /// Insert `component` on tracked entities while they are inside a zone named
+    /// `zone`, and remove it when they leave.
+    ///
+    /// The crate only manages the component; your own systems give it meaning.
+    ///
+    /// ```rust,ignore
+    /// #[derive(Component, Clone)]
+    /// struct SpeedMultiplier(f32);
+    ///
+    /// #[derive(Component, Clone)]
+    /// struct Burning { damage_per_second: f32 }
+    ///
+    /// app.add_zone_modifier("swamp", SpeedMultiplier(0.5))
+    ///     .add_zone_modifier("lava", Burning { damage_per_second: 10.0 });
+    /// ```
+    fn add_zone_modifier<C: Component + Clone>(&mut self, zone: &str, component: C) -> &mut Self;
+}
+
+impl ZoneModifierAppExt for App {
+    fn add_zone_modifier<C: Component + Clone>(&mut self, zone: &str, component: C) -> &mut Self {
+        self.init_resource::<ZoneModifierRegistry>();
+        self.world_mut()
+            .resource_mut::<ZoneModifierRegistry>()
+            .modifiers
+            .entry(zone.to_string())
+            .or_default()
+            .push(ZoneModifier {
+                insert: Box::new(move |entity| {
+                    entity.insert(component.clone());
+                }),
+                remove: |entity| {
+                    entity.remove::<C>();
+                },
+            });
+        self
+    }
+}
+
+/// Modifiers registered with [`ZoneModifierAppExt::add_zone_modifier`], by zone name.
+#[derive(Resource, Default)]
+struct ZoneModifierRegistry {
+    modifiers: HashMap<String, Vec<ZoneModifier>>,
+}
+
+struct ZoneModifier {
+    insert: Box<dyn Fn(&mut EntityCommands) + Send + Sync>,
+    remove: fn(&mut EntityCommands),
+}
+
+/// System parameter looking up zones by world position.
+#[derive(SystemParam)]
+pub struct Zones<'w, 's> {
+    zones: Query<'w, 's, (Entity, &'static MapZone)>,
+    maps: Query<'w, 's, &'static GlobalTransform, With<SpriteFusionMapMarker>>,
+}
+
+impl Zones<'_, '_> {
+    /// Get the zone at a world position, if any.
+    ///
+    /// When zones of several maps overlap, the first one found is returned.
+    pub fn zone_at(&self, world_pos: Vec2) -> Option<(Entity, &MapZone)> {
+        self.zones_at(world_pos).next()
+    }
+
+    /// Get all zones at a world position.
+    pub fn zones_at(&self, world_pos: Vec2) -> impl Iterator<Item = (Entity, &MapZone)> {
+        self.zones.iter().filter(move |(_, zone)| {
+            self.maps.get(zone.map).is_ok_and(|map_transform| {
+                let local = map_transform
+                    .affine()
+                    .inverse()
+                    .transform_point3(world_pos.extend(0.0));
+                zone.contains_local(local.truncate())
+            })
+        })
+    }
+}
+
+/// System that spawns the zones of newly spawned maps.
+fn spawn_map_zones(
+    mut commands: Commands,
+    maps: Query<(Entity, &SpriteFusionMapMarker), Added<SpriteFusionMapMarker>>,
+    settings: Res<SpriteFusionSettings>,
+) {
+    for (map_entity, marker) in maps.iter() {
+        let map = &marker.map;
+        let tile_size = map.tile_size as f32;
+        for (name, tiles) in extract_zones(map, &settings.attribute_prefix) {
+            let bounds = tiles
+                .iter()
+                .map(|&(x, y)| {
+                    let center = map_local_position(map, x, y);
+                    Rect::from_center_size(center, Vec2::splat(tile_size))
+                })
+                .reduce(|a, b| a.union(b))
+                .unwrap_or_default();
+            commands.spawn((
+                Name::new(format!("Zone {name}")),
+                MapZone {
+                    name,
+                    map: map_entity,
+                    tiles,
+                    bounds,
+                    tile_size,
+                    map_height: map.map_height,
+                },
+                Transform::default(),
+                ChildOf(map_entity),
+            ));
+        }
+    }
+}
+
+/// System that updates the zones of tracked entities.
+fn track_zones(
+    mut trackers: Query<(Entity, &GlobalTransform, &mut ZoneTracker)>,
+    zones: Zones,
+    paused_maps: Query<(), With<MapPaused>>,
+    mut entered: MessageWriter<ZoneEntered>,
+    mut exited: MessageWriter<ZoneExited>,
+) {
+    for (entity, transform, mut tracker) in trackers.iter_mut() {
+        let position = transform.translation().truncate();
+        let is_paused = |zone: Entity| {
+            zones
+                .zones
+                .get(zone)
+                .is_ok_and(|(_, z)| paused_maps.contains(z.map))
+        };
+
+        // Zones of paused maps keep their membership as it is
+        let mut current: Vec<Entity> = tracker
+            .zones
+            .iter()
+            .copied()
+            .filter(|&zone| is_paused(zone))
+            .collect();
+        current.extend(
+            zones
+                .zones_at(position)
+                .filter(|(_, zone)| !paused_maps.contains(zone.map))
+                .map(|(zone, _)| zone),
+        );
+
+        for &zone in current.iter().filter(|z| !tracker.zones.contains(z)) {
+            if let Ok((_, map_zone)) = zones.zones.get(zone) {
+                entered.write(ZoneEntered {
+                    entity,
+                    zone,
+                    name: map_zone.name.clone(),
+                });
+            }
+        }
+        for &zone in tracker.zones.iter().filter(|z| !current.contains(z)) {
+            // Despawned zones are left without a name
+            let name = zones
+                .zones
+                .get(zone)
+                .map(|(_, z)| z.name.clone())
+                .unwrap_or_default();
+            exited.write(ZoneExited { entity, zone, name });
+        }
+        tracker.zones = current;
+    }
+}
+
+/// System that inserts and removes zone modifiers on tracked entities.
+fn apply_zone_modifiers(
+    mut commands: Commands,
+    registry: Res<ZoneModifierRegistry>,
+    trackers: Query<&ZoneTracker>,
+    zones: Query<&MapZone>,
+    mut entered: MessageReader<ZoneEntered>,
+    mut exited: MessageReader<ZoneExited>,
+) {
+    if registry.modifiers.is_empty() {
+        entered.clear();
+        exited.clear();
+        return;
+    }
+
+    for event in exited.read() {
+        let Some(modifiers) = registry.modifiers.get(&event.name) else {
+            continue;
+        };
+        // Keep the modifiers while still inside another zone with the same name
+        let still_inside = trackers.get(event.entity).is_ok_and(|tracker| {
+            tracker
+                .zones()
+                .iter()
+                .filter_map(|&zone| zones.get(zone).ok())
+                .any(|zone| zone.name == event.name)
+        });
+        if still_inside {
+            continue;
+        }
+        if let Ok(mut entity) = commands.get_entity(event.entity) {
+            for modifier in modifiers {
+                (modifier.remove)(&mut entity);
+            }
+        }
+    }
+
+    for event in entered.read() {
+        let Some(modifiers) = registry.modifiers.get(&event.name) else {
+            continue;
+        };
+        if let Ok(mut entity) = commands.get_entity(event.entity) {
+            for modifier in modifiers {
+                (modifier.insert)(&mut entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Colliders of spawned maps following tile edits, visibility and streaming.
+//!
+//! Each test spawns a map in a headless app, changes its tiles one way, and
+//! checks that the `Collider` markers, the `MergedColliders` of the collider
+//! layer and the `CollisionGrid` of the map all agree after every step.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_spritefusion::prelude::*;
+
+mod common;
+
+use common::*;
+
+/// Spawned positions of the walls of [`MAP`], rows flipped to bottom-left.
+const WALLS: [(u32, u32); 5] = [(0, 2), (1, 2), (2, 2), (3, 2), (0, 0)];
+
+fn walls(cells: &[(u32, u32)]) -> HashSet<TilePos> {
+    cells.iter().map(|&(x, y)| pos(x, y)).collect()
+}
+
+/// Check that the colliders of the wall layer cover exactly `expected`.
+fn assert_colliders(app: &mut App, map: Entity, expected: &HashSet<TilePos>) {
+    let world = app.world_mut();
+    let (walls_layer, merged) = world
+        .query::<(Entity, &SpriteFusionLayerMarker, &MergedColliders)>()
+        .iter(world)
+        .find(|(_, layer, _)| layer.map == map && layer.name == "Walls")
+        .map(|(entity, _, merged)| (entity, merged.clone()))
+        .expect("the wall layer has merged colliders");
+
+    let colliders: HashSet<TilePos> = world
+        .query_filtered::<(&TilePos, &TilemapId), With<Collider>>()
+        .iter(world)
+        .filter(|(_, tilemap_id)| tilemap_id.0 == walls_layer)
+        .map(|(tile_pos, _)| *tile_pos)
+        .collect();
+    assert_eq!(&colliders, expected, "tiles with a Collider");
+
+    let mut merged_cells = HashSet::new();
+    for rect in merged.tile_rects() {
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                assert!(merged_cells.insert(pos(x, y)), "merged rects overlap");
+            }
+        }
+    }
+    assert_eq!(&merged_cells, expected, "cells of MergedColliders");
+    assert_eq!(merged.rects.len(), merged.tile_rects().len());
+
+    let grid = world
+        .get::<CollisionGrid>(map)
+        .expect("the map has a collision grid");
+    let size = grid.size();
+    let blocked: HashSet<TilePos> = (0..size.y)
+        .flat_map(|y| (0..size.x).map(move |x| pos(x, y)))
+        .filter(|&tile_pos| grid.is_blocked(tile_pos))
+        .collect();
+    assert_eq!(&blocked, expected, "blocked cells of the CollisionGrid");
+}
+
+fn set_visible(app: &mut App, map: Entity, tile_pos: TilePos, visible: bool) {
+    let tile = tile_entity(app, map, "Walls", tile_pos);
+    app.world_mut()
+        .entity_mut(tile)
+        .insert(TileVisible(visible));
+    app.update();
+}
+
+#[test]
+fn spawned_map() {
+    let mut app = app();
+    let map = spawn_map(&mut app, None);
+    assert_colliders(&mut app, map, &walls(&WALLS));
+}
+
+#[test]
+fn set_and_remove_tiles() {
+    let mut app = app();
+    let map = spawn_map(&mut app, None);
+
+    // A new wall, then one over an existing wall tile
+    edit(&mut app, move |map_commands| {
+        assert!(map_commands.set_tile(map, "Walls", pos(2, 1), 1).is_some());
+    });
+    let mut expected = walls(&WALLS);
+    expected.insert(pos(2, 1));
+    assert_colliders(&mut app, map, &expected);
+    edit(&mut app, move |map_commands| {
+        assert!(map_commands.set_tile(map, "Walls", pos(0, 0), 0).is_some());
+    });
+    assert_colliders(&mut app, map, &expected);
+
+    // Tiles of other layers never collide
+    edit(&mut app, move |map_commands| {
+        assert!(map_commands.set_tile(map, "Ground", pos(3, 0), 0).is_some());
+    });
+    assert_colliders(&mut app, map, &expected);
+
+    edit(&mut app, move |map_commands| {
+        assert!(map_commands.remove_tile(map, "Walls", pos(1, 2)).is_some());
+        assert!(map_commands.remove_tile(map, "Walls", pos(2, 1)).is_some());
+    });
+    expected.remove(&pos(1, 2));
+    expected.remove(&pos(2, 1));
+    assert_colliders(&mut app, map, &expected);
+
+    // Removing a missing tile changes nothing
+    edit(&mut app, move |map_commands| {
+        assert!(map_commands.remove_tile(map, "Walls", pos(1, 2)).is_none());
+    });
+    assert_colliders(&mut app, map, &expected);
+}
+
+#[test]
+fn hide_and_show_tiles() {
+    let mut app = app();
+    let map = spawn_map(&mut app, None);
+
+    set_visible(&mut app, map, pos(0, 0), false);
+    let mut expected = walls(&WALLS);
+    expected.remove(&pos(0, 0));
+    assert_colliders(&mut app, map, &expected);
+
+    set_visible(&mut app, map, pos(2, 2), false);
+    expected.remove(&pos(2, 2));
+    assert_colliders(&mut app, map, &expected);
+
+    set_visible(&mut app, map, pos(0, 0), true);
+    expected.insert(pos(0, 0));
+    assert_colliders(&mut app, map, &expected);
+
+    set_visible(&mut app, map, pos(2, 2), true);
+    assert_colliders(&mut app, map, &walls(&WALLS));
+}
+
+#[test]
+fn stream_chunks_out_and_back() {
+    let mut app = app();
+    let camera = app
+        .world_mut()
+        .spawn((Camera::default(), Transform::from_xyz(24.0, 16.0, 0.0)))
+        .id();
+    let streaming = MapStreaming::new(4.0 * TILE_SIZE).with_chunk_size(UVec2::splat(2));
+    let map = spawn_map(&mut app, Some(streaming));
+    app.update();
+    assert_colliders(&mut app, map, &walls(&WALLS));
+
+    // Far from the camera, every chunk despawns and nothing collides
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(10_000.0, 10_000.0, 0.0));
+    app.update();
+    app.update();
+    assert_colliders(&mut app, map, &HashSet::new());
+
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(24.0, 16.0, 0.0));
+    app.update();
+    app.update();
+    assert_colliders(&mut app, map, &walls(&WALLS));
+
+    // Only the chunks near a camera collide: with a smaller radius, a camera
+    // on the bottom left tile only spawns the 2×2 chunk holding it
+    let streaming = MapStreaming::new(0.25 * TILE_SIZE).with_chunk_size(UVec2::splat(2));
+    app.world_mut().entity_mut(map).insert(streaming);
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(10_000.0, 10_000.0, 0.0));
+    app.update();
+    app.update();
+    assert_colliders(&mut app, map, &HashSet::new());
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(0.0, 0.0, 0.0));
+    app.update();
+    app.update();
+    assert_colliders(&mut app, map, &walls(&[(0, 0)]));
+}
+
+#[test]
+fn paused_map_does_not_stream() {
+    let mut app = app();
+    let camera = app
+        .world_mut()
+        .spawn((Camera::default(), Transform::from_xyz(24.0, 16.0, 0.0)))
+        .id();
+    let streaming = MapStreaming::new(4.0 * TILE_SIZE).with_chunk_size(UVec2::splat(2));
+    let map = spawn_map(&mut app, Some(streaming));
+    app.update();
+    assert_colliders(&mut app, map, &walls(&WALLS));
+
+    // A paused map keeps its chunks while the camera moves away
+    app.world_mut().entity_mut(map).insert(MapPaused);
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(10_000.0, 10_000.0, 0.0));
+    app.update();
+    app.update();
+    assert_colliders(&mut app, map, &walls(&WALLS));
+
+    // And streams them out once resumed
+    app.world_mut().entity_mut(map).remove::<MapPaused>();
+    app.update();
+    app.update();
+    assert_colliders(&mut app, map, &HashSet::new());
+}
+
+#[test]
+fn moving_map_keeps_collision_grid() {
+    let mut app = app();
+    let map = spawn_map(&mut app, None);
+    app.update();
+    let grid_changed = |app: &App| {
+        app.world()
+            .entity(map)
+            .get_change_ticks::<CollisionGrid>()
+            .expect("the map has a collision grid")
+            .changed
+    };
+    let built = grid_changed(&app);
+
+    // Merged colliders follow the layers, the blocked cells stay the same
+    app.world_mut()
+        .entity_mut(map)
+        .insert(Transform::from_xyz(100.0, 50.0, 0.0));
+    app.update();
+    app.update();
+    assert_eq!(grid_changed(&app), built);
+    assert_colliders(&mut app, map, &walls(&WALLS));
+
+    edit(&mut app, move |map_commands| {
+        map_commands.remove_tile(map, "Walls", pos(0, 0));
+    });
+    assert_ne!(grid_changed(&app), built);
+    let mut expected = walls(&WALLS);
+    expected.remove(&pos(0, 0));
+    assert_colliders(&mut app, map, &expected);
+}
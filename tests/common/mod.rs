@@ -0,0 +1,120 @@
+//! Helpers shared by the app tests.
+
+#![allow(dead_code)]
+
+use bevy::{
+    asset::RenderAssetUsages,
+    ecs::system::RunSystemOnce,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_spritefusion::prelude::*;
+
+pub const TILE_SIZE: f32 = 16.0;
+
+/// A 4×3 map with a ground layer and a wall layer, walls along the top row
+/// and at the bottom left corner, in Sprite Fusion coordinates.
+pub const MAP: &str = r#"{
+    "tileSize": 16,
+    "mapWidth": 4,
+    "mapHeight": 3,
+    "layers": [
+        {
+            "name": "Walls",
+            "collider": true,
+            "tiles": [
+                { "id": "1", "x": 0, "y": 0 },
+                { "id": "1", "x": 1, "y": 0 },
+                { "id": "1", "x": 2, "y": 0 },
+                { "id": "1", "x": 3, "y": 0 },
+                { "id": "1", "x": 0, "y": 2 }
+            ]
+        },
+        {
+            "name": "Ground",
+            "collider": false,
+            "tiles": [
+                { "id": "0", "x": 1, "y": 1 },
+                { "id": "0", "x": 2, "y": 2 }
+            ]
+        }
+    ]
+}"#;
+
+pub fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        SpriteFusionPlugin,
+    ))
+    .init_asset::<Image>();
+    app
+}
+
+/// Spawn [`MAP`] and update the app until it's spawned, returning the map entity.
+pub fn spawn_map(app: &mut App, streaming: Option<MapStreaming>) -> Entity {
+    let world = app.world_mut();
+    let map = world
+        .resource_mut::<Assets<SpriteFusionMap>>()
+        .add(SpriteFusionMap::from_json_bytes(MAP.as_bytes()).expect("test map parses"));
+    let tileset = world.resource_mut::<Assets<Image>>().add(Image::new_fill(
+        Extent3d {
+            width: 32,
+            height: 16,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[255; 4],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ));
+    let mut entity = world.spawn(SpriteFusionBundle {
+        map: SpriteFusionMapHandle(map),
+        tileset: SpriteFusionTilesetHandle(tileset),
+        ..default()
+    });
+    if let Some(streaming) = streaming {
+        entity.insert(streaming);
+    }
+    let entity = entity.id();
+
+    // Maps are prepared on a task pool, so they spawn a few frames later
+    for _ in 0..1000 {
+        app.update();
+        if app.world().get::<CollisionGrid>(entity).is_some() {
+            return entity;
+        }
+        std::thread::yield_now();
+    }
+    panic!("the test map didn't spawn");
+}
+
+pub fn pos(x: u32, y: u32) -> TilePos {
+    TilePos { x, y }
+}
+
+pub fn edit(
+    app: &mut App,
+    edit: impl FnOnce(&mut SpriteFusionMapCommands) + Send + Sync + 'static,
+) {
+    let mut edit = Some(edit);
+    app.world_mut()
+        .run_system_once(move |mut map_commands: SpriteFusionMapCommands| {
+            edit.take().expect("editing system runs once")(&mut map_commands)
+        })
+        .expect("editing system runs");
+    app.update();
+}
+
+/// Get the tile entity at a position of a layer.
+pub fn tile_entity(app: &mut App, map: Entity, layer: &'static str, tile_pos: TilePos) -> Entity {
+    app.world_mut()
+        .run_system_once(move |map_commands: SpriteFusionMapCommands| {
+            map_commands.get_tile_entity(map, layer, tile_pos)
+        })
+        .expect("lookup system runs")
+        .expect("the tile exists")
+}
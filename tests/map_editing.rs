@@ -0,0 +1,127 @@
+//! Tiles placed and removed at runtime with `SpriteFusionMapCommands`.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy_spritefusion::prelude::*;
+
+mod common;
+
+use common::*;
+
+fn spawn_index(app: &App, tile: Entity) -> u32 {
+    app.world()
+        .get::<TileSpawnIndex>(tile)
+        .expect("the tile has a spawn index")
+        .0
+}
+
+#[test]
+fn placed_tiles_get_new_spawn_indices() {
+    let mut app = app();
+    let map = spawn_map(&mut app, None);
+
+    // The 7 tiles of the test map use the spawn indices 0 to 6
+    let world = app.world_mut();
+    let indices: HashSet<u32> = world
+        .query::<&TileSpawnIndex>()
+        .iter(world)
+        .map(|index| index.0)
+        .collect();
+    assert_eq!(indices, (0..7).collect());
+
+    edit(&mut app, move |map_commands| {
+        map_commands.set_tile(map, "Walls", pos(2, 1), 1);
+        map_commands.set_tile(map, "Ground", pos(3, 0), 0);
+    });
+    let wall = tile_entity(&mut app, map, "Walls", pos(2, 1));
+    let ground = tile_entity(&mut app, map, "Ground", pos(3, 0));
+    assert_eq!(spawn_index(&app, wall), 7);
+    assert_eq!(spawn_index(&app, ground), 8);
+
+    // Changing a tile keeps its index
+    let corner = tile_entity(&mut app, map, "Walls", pos(0, 0));
+    let corner_index = spawn_index(&app, corner);
+    edit(&mut app, move |map_commands| {
+        map_commands.set_tile(map, "Walls", pos(0, 0), 0);
+    });
+    assert_eq!(spawn_index(&app, corner), corner_index);
+}
+
+#[test]
+fn placed_tiles_keep_their_spawn_index_when_streamed() {
+    let mut app = app();
+    let camera = app
+        .world_mut()
+        .spawn((Camera::default(), Transform::from_xyz(24.0, 16.0, 0.0)))
+        .id();
+    let streaming = MapStreaming::new(4.0 * TILE_SIZE).with_chunk_size(UVec2::splat(2));
+    let map = spawn_map(&mut app, Some(streaming));
+    app.update();
+
+    edit(&mut app, move |map_commands| {
+        map_commands.set_tile(map, "Walls", pos(2, 1), 1);
+    });
+    let wall = tile_entity(&mut app, map, "Walls", pos(2, 1));
+    assert_eq!(spawn_index(&app, wall), 7);
+
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(10_000.0, 10_000.0, 0.0));
+    app.update();
+    app.update();
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(24.0, 16.0, 0.0));
+    app.update();
+    app.update();
+    let wall = tile_entity(&mut app, map, "Walls", pos(2, 1));
+    assert_eq!(spawn_index(&app, wall), 7);
+}
+
+fn indexed(app: &App, map: Entity, layer: &str, tile_pos: TilePos) -> Option<Entity> {
+    app.world()
+        .get::<SpriteFusionTileIndex>(map)
+        .expect("the map has a tile index")
+        .get(layer, tile_pos)
+}
+
+#[test]
+fn tile_index_follows_edits_and_streaming() {
+    let mut app = app();
+    let camera = app
+        .world_mut()
+        .spawn((Camera::default(), Transform::from_xyz(24.0, 16.0, 0.0)))
+        .id();
+    let streaming = MapStreaming::new(4.0 * TILE_SIZE).with_chunk_size(UVec2::splat(2));
+    let map = spawn_map(&mut app, Some(streaming));
+    app.update();
+    let corner = tile_entity(&mut app, map, "Walls", pos(0, 0));
+    assert_eq!(indexed(&app, map, "Walls", pos(0, 0)), Some(corner));
+
+    edit(&mut app, move |map_commands| {
+        map_commands.set_tile(map, "Walls", pos(2, 1), 1);
+        map_commands.remove_tile(map, "Walls", pos(0, 0));
+    });
+    let wall = tile_entity(&mut app, map, "Walls", pos(2, 1));
+    assert_eq!(indexed(&app, map, "Walls", pos(2, 1)), Some(wall));
+    assert_eq!(indexed(&app, map, "Walls", pos(0, 0)), None);
+
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(10_000.0, 10_000.0, 0.0));
+    app.update();
+    app.update();
+    assert_eq!(indexed(&app, map, "Walls", pos(2, 1)), None);
+    assert_eq!(indexed(&app, map, "Ground", pos(1, 1)), None);
+
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(24.0, 16.0, 0.0));
+    app.update();
+    app.update();
+    let wall = tile_entity(&mut app, map, "Walls", pos(2, 1));
+    assert_eq!(indexed(&app, map, "Walls", pos(2, 1)), Some(wall));
+    let ground = tile_entity(&mut app, map, "Ground", pos(1, 1));
+    assert_eq!(indexed(&app, map, "Ground", pos(1, 1)), Some(ground));
+}
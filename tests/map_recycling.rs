@@ -0,0 +1,72 @@
+//! Maps and tile entities recycled through the `TileEntityPool`.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy_spritefusion::prelude::*;
+
+mod common;
+
+use common::*;
+
+/// Hooks recording the maps despawned, with the number of tiles they still had.
+#[derive(Clone, Default)]
+struct DespawnLog(Arc<Mutex<Vec<(Entity, usize)>>>);
+
+impl SpriteFusionHooks for DespawnLog {
+    fn on_map_despawn(&self, world: &mut World, map: Entity) {
+        let tiles = world
+            .query::<(&SpriteFusionLayerMarker, &Children)>()
+            .iter(world)
+            .filter(|(layer, _)| layer.map == map)
+            .map(|(_, tiles)| tiles.len())
+            .sum();
+        self.0.lock().unwrap().push((map, tiles));
+    }
+}
+
+#[test]
+fn recycled_map_runs_despawn_hooks() {
+    let mut app = app();
+    let log = DespawnLog::default();
+    app.add_spritefusion_hooks(log.clone())
+        .insert_resource(TileEntityPool::with_capacity(64));
+    let map = spawn_map(&mut app, None);
+
+    app.world_mut().commands().recycle_map(map);
+    app.update();
+
+    // The hooks run before the 7 tiles of the map go back to the pool
+    assert_eq!(*log.0.lock().unwrap(), vec![(map, 7)]);
+    assert!(app.world().get_entity(map).is_err());
+    let stats = app.world().resource::<TileEntityPool>().stats();
+    assert_eq!(stats.released, 7);
+}
+
+#[test]
+fn attachment_leaves_reused_tile_entity() {
+    let mut app = app();
+    let map = spawn_map(&mut app, None);
+    let tile = tile_entity(&mut app, map, "Walls", pos(0, 0));
+    let spawn_index = *app.world().get::<TileSpawnIndex>(tile).unwrap();
+    let attached = app
+        .world_mut()
+        .commands()
+        .attach_to_tile(tile, Name::new("Torch"));
+    app.update();
+
+    // The same tile moving keeps it
+    app.world_mut().entity_mut(tile).insert(pos(1, 1));
+    app.update();
+    let geometry = app.world().get::<SpriteFusionMapGeometry>(map).unwrap();
+    let expected = geometry.tile_to_local(pos(1, 1));
+    let transform = app.world().get::<Transform>(attached).unwrap();
+    assert_eq!(transform.translation.truncate(), expected);
+
+    // The tile entity reused for another tile, as the pool does, despawns it
+    app.world_mut()
+        .entity_mut(tile)
+        .insert((pos(3, 0), TileSpawnIndex(spawn_index.0 + 100)));
+    app.update();
+    assert!(app.world().get_entity(attached).is_err());
+}
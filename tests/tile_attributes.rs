@@ -0,0 +1,138 @@
+//! Tile attributes changed at runtime reaching the map data and the
+//! subsystems reading it.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_spritefusion::prelude::*;
+use serde_json::json;
+
+mod common;
+
+use common::*;
+
+/// Get the attribute `key` of the map data tile at a spawned position of a layer.
+fn data_attribute(
+    app: &App,
+    map: Entity,
+    layer: &str,
+    tile_pos: TilePos,
+    key: &str,
+) -> Option<serde_json::Value> {
+    let world = app.world();
+    let marker = world.get::<SpriteFusionMapMarker>(map)?;
+    let geometry = world.get::<SpriteFusionMapGeometry>(map)?;
+    let pos = geometry.sprite_fusion_pos(tile_pos);
+    marker
+        .map
+        .layers
+        .iter()
+        .find(|data| data.name == layer)?
+        .tiles
+        .iter()
+        .find(|tile| tile.x == pos.x && tile.y == pos.y)?
+        .attributes
+        .as_ref()?
+        .get(key)
+        .cloned()
+}
+
+fn set_attr(app: &mut App, tile: Entity, key: &'static str, value: serde_json::Value) {
+    app.world_mut().commands().set_attr(tile, key, value);
+    app.update();
+}
+
+#[test]
+fn attribute_survives_streaming_out_and_back() {
+    let mut app = app();
+    let camera = app
+        .world_mut()
+        .spawn((Camera::default(), Transform::from_xyz(24.0, 16.0, 0.0)))
+        .id();
+    let streaming = MapStreaming::new(4.0 * TILE_SIZE).with_chunk_size(UVec2::splat(2));
+    let map = spawn_map(&mut app, Some(streaming));
+    app.update();
+
+    let door = tile_entity(&mut app, map, "Walls", pos(0, 0));
+    set_attr(&mut app, door, "locked", json!(true));
+    let attrs = app
+        .world()
+        .get::<TileAttributes>(door)
+        .expect("door attributes");
+    assert_eq!(attrs.0.get("locked"), Some(&json!(true)));
+    assert!(app.world().get::<Collider>(door).is_some());
+    assert_eq!(
+        data_attribute(&app, map, "Walls", pos(0, 0), "locked"),
+        Some(json!(true))
+    );
+
+    // Streamed out, the change only lives in the map data, and is exported
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(10_000.0, 10_000.0, 0.0));
+    app.update();
+    app.update();
+    let exported = export_map(app.world(), map).expect("the map exports");
+    let walls = exported
+        .layers
+        .iter()
+        .find(|layer| layer.name == "Walls")
+        .expect("the wall layer exports");
+    let exported_door = walls
+        .tiles
+        .iter()
+        .find(|tile| tile.x == 0 && tile.y == 2)
+        .expect("the door exports");
+    assert_eq!(
+        exported_door
+            .attributes
+            .as_ref()
+            .and_then(|attrs| attrs.get("locked")),
+        Some(&json!(true))
+    );
+
+    // Streamed back in, the new tile entity has it again
+    app.world_mut()
+        .entity_mut(camera)
+        .insert(Transform::from_xyz(24.0, 16.0, 0.0));
+    app.update();
+    app.update();
+    let door = tile_entity(&mut app, map, "Walls", pos(0, 0));
+    let attrs = app
+        .world()
+        .get::<TileAttributes>(door)
+        .expect("door attributes");
+    assert_eq!(attrs.0.get("locked"), Some(&json!(true)));
+}
+
+#[test]
+fn move_cost_follows_attribute() {
+    let mut app = app();
+    app.add_plugins(SpriteFusionPathfindingPlugin);
+    let map = spawn_map(&mut app, None);
+    app.update();
+    let costs = |app: &App| {
+        app.world()
+            .get::<MoveCosts>(map)
+            .map_or(1.0, |costs| costs.cost(pos(1, 1)))
+    };
+    // The test map has no move costs at first
+    assert!(app.world().get::<MoveCosts>(map).is_none());
+
+    let mud = tile_entity(&mut app, map, "Ground", pos(1, 1));
+    set_attr(&mut app, mud, MOVE_COST_ATTRIBUTE, json!(3.0));
+    assert_eq!(costs(&app), 3.0);
+    assert_eq!(
+        data_attribute(&app, map, "Ground", pos(1, 1), MOVE_COST_ATTRIBUTE),
+        Some(json!(3.0))
+    );
+
+    app.world_mut()
+        .commands()
+        .remove_attr(mud, MOVE_COST_ATTRIBUTE);
+    app.update();
+    assert_eq!(costs(&app), 1.0);
+    assert_eq!(
+        data_attribute(&app, map, "Ground", pos(1, 1), MOVE_COST_ATTRIBUTE),
+        None
+    );
+}